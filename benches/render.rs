@@ -0,0 +1,122 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use ray_tracer::camera::Camera;
+use ray_tracer::color::Color;
+use ray_tracer::generators::{city, pebble_planet, sphere_grid, terrain};
+use ray_tracer::light::{Light, PointLight};
+use ray_tracer::material::{lighting, Material};
+use ray_tracer::matrix;
+use ray_tracer::ray::Ray;
+use ray_tracer::sphere::sphere;
+use ray_tracer::transformations::{aim, rotation_y, scaling, translation};
+use ray_tracer::tuple::{Point, Vector};
+use ray_tracer::world::{default_world, World};
+use std::f64::consts::PI;
+
+fn bench_matrix(c: &mut Criterion) {
+    let m = matrix!([-5, 2, 6, -8], [1, -5, 1, 8], [7, 7, -6, -7], [1, -3, 7, 4]);
+    c.bench_function("matrix4 inverse", |b| b.iter(|| black_box(&m).inverse()));
+    let a = translation(1.0, 2.0, 3.0) * rotation_y(0.5);
+    let s = scaling(2.0, 2.0, 2.0);
+    c.bench_function("matrix4 multiply", |b| {
+        b.iter(|| black_box(a.clone()) * black_box(s.clone()))
+    });
+}
+
+fn bench_intersection(c: &mut Criterion) {
+    let sphere = sphere().set_transform(translation(0.0, 0.5, 0.0));
+    let ray = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+    c.bench_function("sphere intersect", |b| {
+        b.iter(|| black_box(&sphere).intersect(black_box(ray)).len())
+    });
+
+    let mut world = sphere_grid(10, 2.5);
+    let ray = Ray::new(
+        Point::new(-20.0, 0.3, -20.0),
+        Vector::new(1.0, 0.1, 1.0).normalize(),
+    );
+    c.bench_function("world intersect 1000 spheres", |b| {
+        b.iter(|| world.intersect(black_box(ray)).len())
+    });
+    world.build_sphere_batch();
+    c.bench_function("world intersect 1000 spheres batched", |b| {
+        b.iter(|| world.intersect(black_box(ray)).len())
+    });
+    world.build_bvh();
+    c.bench_function("world intersect 1000 spheres bvh", |b| {
+        b.iter(|| world.intersect(black_box(ray)).len())
+    });
+    c.bench_function("world refit 1000 spheres", |b| {
+        b.iter(|| {
+            world.set_object_transform(0, translation(0.0, black_box(1.0), 0.0));
+            world.refit();
+        })
+    });
+
+    let mut world = pebble_planet(20_000, 1);
+    world.build_bvh();
+    let ray = Ray::new(
+        Point::new(-30.0, 5.0, -30.0),
+        Vector::new(1.0, 0.0, 1.0).normalize(),
+    );
+    c.bench_function("world intersect 20000 scattered bvh", |b| {
+        b.iter(|| world.intersect(black_box(ray)).len())
+    });
+}
+
+fn bench_lighting(c: &mut Criterion) {
+    let m = Material::default();
+    let light: Light =
+        PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+    let point = Point::new(0.0, 0.0, 0.0);
+    let eyev = Vector::new(0.0, 0.0, -1.0);
+    let normalv = Vector::new(0.0, 0.0, -1.0);
+    c.bench_function("lighting", |b| {
+        b.iter(|| lighting(m, &light, black_box(point), eyev, normalv, false))
+    });
+}
+
+fn camera(from: Point, to: Point) -> Camera {
+    let mut camera = Camera::new(64, 48, PI / 3.0);
+    camera.set_transform(aim(from, to));
+    camera
+}
+
+fn bench_render(c: &mut Criterion) {
+    let mut group = c.benchmark_group("render 64x48");
+    group.sample_size(10);
+    let scenes: Vec<(&str, World, Camera)> = vec![
+        (
+            "default world",
+            default_world(),
+            camera(Point::new(0.0, 0.0, -5.0), Point::new(0.0, 0.0, 0.0)),
+        ),
+        (
+            "sphere grid",
+            sphere_grid(6, 2.5),
+            camera(Point::new(10.0, 8.0, -20.0), Point::new(0.0, 0.0, 0.0)),
+        ),
+        (
+            "city",
+            city(8, 1),
+            camera(Point::new(20.0, 25.0, -30.0), Point::new(0.0, 0.0, 0.0)),
+        ),
+        (
+            "terrain",
+            terrain(4, 0.5, 2.0, 20.0, 1),
+            camera(Point::new(0.0, 12.0, -18.0), Point::new(0.0, 0.0, 0.0)),
+        ),
+    ];
+    for (name, world, camera) in &scenes {
+        group.bench_function(*name, |b| b.iter(|| camera.render(world)));
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_matrix,
+    bench_intersection,
+    bench_lighting,
+    bench_render
+);
+criterion_main!(benches);
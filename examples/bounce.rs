@@ -0,0 +1,67 @@
+use ray_tracer::animation::{simulate, write_y4m, Simulation};
+use ray_tracer::camera::Camera;
+use ray_tracer::color::Color;
+use ray_tracer::light::PointLight;
+use ray_tracer::material::Material;
+use ray_tracer::sphere::sphere;
+use ray_tracer::transformations::*;
+use ray_tracer::tuple::{Point, Vector};
+use ray_tracer::world::World;
+use std::f64::consts::PI;
+use std::io;
+
+// cargo run --example bounce | ffmpeg -f yuv4mpegpipe -i - bounce.mp4
+struct Ball {
+    height: f64,
+    velocity: f64,
+}
+
+impl Simulation for Ball {
+    fn step(&mut self, world: &mut World, dt: f64) {
+        self.velocity -= 9.8 * dt;
+        self.height += self.velocity * dt;
+        if self.height < 0.0 {
+            self.height = -self.height;
+            self.velocity = -self.velocity * 0.8;
+        }
+        world.objects[1].update_transform(translation(0.0, self.height + 1.0, 0.0));
+    }
+}
+
+fn main() {
+    let floor = sphere()
+        .set_transform(scaling(10.0, 0.01, 10.0))
+        .set_material(Material {
+            color: Color::new(1.0, 0.9, 0.9),
+            specular: 0.0,
+            ..Default::default()
+        });
+    let ball = sphere()
+        .set_transform(translation(0.0, 3.0, 0.0))
+        .set_material(Material {
+            color: Color::new(0.1, 1.0, 0.5),
+            diffuse: 0.7,
+            specular: 0.3,
+            ..Default::default()
+        });
+
+    let mut world = World::new();
+    world.objects = vec![floor, ball];
+    world
+        .lights
+        .push(PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+
+    let mut camera = Camera::new(320, 240, PI / 3.0);
+    camera.set_transform(view_transform(
+        Point::new(0.0, 1.5, -8.0),
+        Point::new(0.0, 1.5, 0.0),
+        Vector::new(0.0, 1.0, 0.0),
+    ));
+
+    let mut ball = Ball {
+        height: 2.0,
+        velocity: 0.0,
+    };
+    let frames = simulate(&mut world, &camera, &mut ball, 96, 1.0 / 24.0);
+    write_y4m(io::stdout().lock(), frames, 24).expect("failed to write frames");
+}
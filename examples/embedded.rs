@@ -0,0 +1,24 @@
+use ray_tracer::canvas::Canvas;
+use ray_tracer::decal::{Decal, Projector};
+use ray_tracer::scene;
+use ray_tracer::tuple::{Point, Vector};
+use std::f64::consts::PI;
+
+// everything the scene needs is compiled into the binary, nothing is read
+// from disk at runtime
+const SCENE: &[u8] = include_bytes!("assets/scene.yaml");
+const STRIPES: &[u8] = include_bytes!("assets/stripes.ppm");
+
+fn main() {
+    let (mut world, camera) = scene::from_yaml_bytes(SCENE).expect("embedded scene is valid");
+    let stripes = Canvas::from_ppm_bytes(STRIPES).expect("embedded texture is valid");
+    let projector = Projector::new(
+        Point::new(-0.5, 5.0, 0.5),
+        Vector::new(0.0, -1.0, 0.0),
+        PI / 3.0,
+    );
+    world
+        .decals
+        .push(Decal::new(projector, stripes).set_opacity(0.6));
+    println!("{}", camera.render(&world).to_ppm());
+}
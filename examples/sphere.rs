@@ -1,9 +1,9 @@
 use ray_tracer::canvas::Canvas;
 use ray_tracer::color::Color;
-use ray_tracer::light::PointLight;
+use ray_tracer::light::{Light, PointLight};
 use ray_tracer::material::{lighting, Material};
 use ray_tracer::ray::Ray;
-use ray_tracer::sphere::Sphere;
+use ray_tracer::sphere::sphere;
 use ray_tracer::tuple::{Point, Vector};
 
 fn main() {
@@ -15,11 +15,12 @@ fn main() {
     let half = wall_size / 2.0;
 
     let mut canv = Canvas::new(canvas_pixels, canvas_pixels);
-    let s = Sphere::new().set_material(Material {
+    let s = sphere().set_material(Material {
         color: Color::new(1.0, 0.2, 1.0),
         ..Default::default()
     });
-    let light = PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
+    let light: Light =
+        PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
 
     for y in 0..canvas_pixels {
         // top = +half, bottom = -half
@@ -36,7 +37,7 @@ fn main() {
                 let point = r.position(hit.t);
                 let normal = hit.object.normal_at(point);
                 let eye: Vector = -r.direction;
-                let color = lighting(hit.object.material, light, point, eye, normal, false);
+                let color = lighting(hit.object.material, &light, point, eye, normal, false);
                 canv.write_pixel(x, y, color);
             }
         }
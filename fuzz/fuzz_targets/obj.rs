@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// like the scene loader, bad meshes should be an error and never a panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(src) = std::str::from_utf8(data) {
+        let _ = ray_tracer::obj_parser::parse_obj_str(src);
+    }
+});
@@ -0,0 +1,409 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::profile::span;
+use crate::transformations::view_transform;
+use crate::tuple::{Point, Vector};
+use crate::world::World;
+use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::io::{self, Write};
+
+// camera positions evenly spaced on a horizontal circle around center,
+// starting on the -z side like the examples
+pub fn turntable_positions(center: Point, frames: u32, radius: f64) -> Vec<Point> {
+    (0..frames)
+        .map(|i| {
+            let angle = i as f64 / frames as f64 * 2.0 * PI;
+            center + Vector::new(radius * angle.sin(), 0.0, -radius * angle.cos())
+        })
+        .collect()
+}
+
+// orbits the camera around the center of the world bounds, rendering frames
+// lazily so long sequences can be written out one at a time
+pub fn turntable<'a>(
+    world: &'a World,
+    camera: &'a mut Camera,
+    frames: u32,
+    radius: f64,
+) -> impl Iterator<Item = Canvas> + 'a {
+    let center = world.bounds().center();
+    turntable_positions(center, frames, radius)
+        .into_iter()
+        .map(move |from| {
+            camera.set_transform(view_transform(from, center, Vector::new(0.0, 1.0, 0.0)));
+            camera.render(world)
+        })
+}
+
+pub fn render_turntable(
+    world: &World,
+    camera: &mut Camera,
+    frames: u32,
+    radius: f64,
+) -> Vec<Canvas> {
+    turntable(world, camera, frames, radius).collect()
+}
+
+// anything that advances a world through time, e.g. physics
+pub trait Simulation {
+    fn step(&mut self, world: &mut World, dt: f64);
+}
+
+impl<F> Simulation for F
+where
+    F: FnMut(&mut World, f64),
+{
+    fn step(&mut self, world: &mut World, dt: f64) {
+        self(world, dt)
+    }
+}
+
+// moves the ripples on every object along by dt, usable as a simulation
+pub fn advance_waves(world: &mut World, dt: f64) {
+    for object in &mut world.objects {
+        if let Some(waves) = &mut object.waves {
+            waves.time += dt;
+        }
+    }
+}
+
+// renders the current state then advances the simulation by dt, once per frame
+pub fn simulate<'a, S: Simulation>(
+    world: &'a mut World,
+    camera: &'a Camera,
+    simulation: &'a mut S,
+    frames: u32,
+    dt: f64,
+) -> impl Iterator<Item = Canvas> + 'a {
+    (0..frames).map(move |_| {
+        let frame = camera.render(world);
+        simulation.step(world, dt);
+        frame
+    })
+}
+
+// one frame with whatever moves during the camera's shutter blurred. the
+// world is stepped through the exposure by the simulation from the start
+// of the frame, rendered at samples times and the renders summed by how
+// far open the shutter is at each. the world passed in isn't changed
+pub fn render_motion_blurred<S: Simulation>(
+    world: &World,
+    camera: &Camera,
+    simulation: &mut S,
+    samples: u32,
+) -> Canvas {
+    let _span = span!(INFO, "motion_blur", samples);
+    if camera.shutter().is_rolling() {
+        return render_rolling(world, camera, simulation, samples);
+    }
+    let mut world = world.clone();
+    let mut now = 0.0;
+    let mut frame = Canvas::new(camera.hsize() as isize, camera.vsize() as isize);
+    for (t, weight) in camera.shutter().time_samples(samples) {
+        if t > now {
+            simulation.step(&mut world, t - now);
+            now = t;
+        }
+        if weight > 0.0 {
+            let image = camera.render(&world);
+            for (p, c) in frame.pixels.iter_mut().zip(image.pixels) {
+                *p = *p + c * weight;
+            }
+        }
+    }
+    frame
+}
+
+// each row exposed over its own stretch of time, later further down, so
+// fast movers lean and wobble. rows are rendered in time order as the
+// world steps along, all rows read at the same time in one batch
+fn render_rolling<S: Simulation>(
+    world: &World,
+    camera: &Camera,
+    simulation: &mut S,
+    samples: u32,
+) -> Canvas {
+    let shutter = camera.shutter();
+    let (width, height) = (camera.hsize(), camera.vsize());
+    let times = shutter.time_samples(samples);
+    let mut exposures = (0..height)
+        .flat_map(|y| {
+            let offset = shutter.row_offset(y, height);
+            times
+                .iter()
+                .map(move |(t, weight)| (t + offset, y, *weight))
+        })
+        .filter(|(_, _, weight)| *weight > 0.0)
+        .collect::<Vec<_>>();
+    exposures.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut world = world.clone();
+    let mut now = 0.0;
+    let mut frame = Canvas::new(width as isize, height as isize);
+    for batch in exposures.chunk_by(|a, b| a.0 == b.0) {
+        let t = batch[0].0;
+        if t > now {
+            simulation.step(&mut world, t - now);
+            now = t;
+        }
+        // a batch is often a single row, so its pixels are shared out
+        // between threads rather than its rows
+        let pixels = batch
+            .iter()
+            .flat_map(|&(_, y, weight)| (0..width).map(move |x| (x, y, weight)))
+            .collect::<Vec<_>>();
+        let colors = pixels
+            .par_iter()
+            .map(|&(x, y, _)| camera.pixel_color(&world, x, y))
+            .collect::<Vec<_>>();
+        for ((x, y, weight), c) in pixels.into_iter().zip(colors) {
+            let p = &mut frame.pixels[(y * width + x) as usize];
+            *p = *p + c * weight;
+        }
+    }
+    frame
+}
+
+// streams frames as yuv4mpeg2 (4:4:4, bt.601 limited range) so they can be
+// piped straight into ffmpeg with `-f yuv4mpegpipe -i -`
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: isize,
+    height: isize,
+    fps: u32,
+    header_written: bool,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(writer: W, width: isize, height: isize, fps: u32) -> Y4mWriter<W> {
+        Y4mWriter {
+            writer,
+            width,
+            height,
+            fps,
+            header_written: false,
+        }
+    }
+
+    pub fn write_frame(&mut self, frame: &Canvas) -> io::Result<()> {
+        let _span = span!(INFO, "encode", format = "y4m");
+        if frame.width != self.width || frame.height != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame size does not match the stream",
+            ));
+        }
+        if !self.header_written {
+            writeln!(
+                self.writer,
+                "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                self.width, self.height, self.fps
+            )?;
+            self.header_written = true;
+        }
+
+        let n = frame.pixels.len();
+        let mut planes = vec![0u8; n * 3];
+        for (i, p) in frame.pixels.iter().enumerate() {
+            let (r, g, b) = (
+                p.red.clamp(0.0, 1.0),
+                p.green.clamp(0.0, 1.0),
+                p.blue.clamp(0.0, 1.0),
+            );
+            planes[i] = (16.0 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8;
+            planes[n + i] = (128.0 - 37.797 * r - 74.203 * g + 112.0 * b).round() as u8;
+            planes[2 * n + i] = (128.0 + 112.0 * r - 93.786 * g - 18.214 * b).round() as u8;
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&planes)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub fn write_y4m<W: Write>(
+    writer: W,
+    frames: impl IntoIterator<Item = Canvas>,
+    fps: u32,
+) -> io::Result<()> {
+    let mut frames = frames.into_iter().peekable();
+    let (width, height) = match frames.peek() {
+        Some(first) => (first.width, first.height),
+        None => (0, 0),
+    };
+    let mut y4m = Y4mWriter::new(writer, width, height, fps);
+    for frame in frames {
+        y4m.write_frame(&frame)?;
+    }
+    y4m.into_inner().flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::shutter::{Shutter, ShutterCurve};
+    use crate::sphere::sphere;
+    use crate::transformations::translation;
+    use crate::water::Waves;
+    use crate::world::default_world;
+
+    struct Falling {
+        height: f64,
+        velocity: f64,
+    }
+
+    impl Simulation for Falling {
+        fn step(&mut self, world: &mut World, dt: f64) {
+            self.velocity -= 9.8 * dt;
+            self.height += self.velocity * dt;
+            world.objects[0].update_transform(translation(0.0, self.height, 0.0));
+        }
+    }
+
+    #[test]
+    fn simulation_steps_between_frames() {
+        let mut w = default_world();
+        w.objects.truncate(1);
+        let camera = Camera::new(5, 5, PI / 2.0);
+        let mut sim = Falling {
+            height: 0.0,
+            velocity: 0.0,
+        };
+        let frames = simulate(&mut w, &camera, &mut sim, 3, 0.1).count();
+        assert_eq!(frames, 3);
+        assert!(sim.height < 0.0);
+        assert_eq!(w.objects[0].transform, translation(0.0, sim.height, 0.0));
+    }
+
+    #[test]
+    fn motion_blur_follows_the_shutter() {
+        let mut w = default_world();
+        w.objects.truncate(1);
+        let mut camera = Camera::new(11, 11, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        // sliding right by two units a second
+        let slide = || {
+            let mut x = 0.0;
+            move |w: &mut World, dt: f64| {
+                x += 2.0 * dt;
+                w.objects[0].update_transform(translation(x, 0.0, 0.0));
+            }
+        };
+        let plain = camera.render(&w);
+        assert_eq!(render_motion_blurred(&w, &camera, &mut slide(), 8), plain);
+        camera.set_shutter(Shutter::new(0.0, 1.0));
+        let open = render_motion_blurred(&w, &camera, &mut slide(), 8);
+        camera.set_shutter(Shutter::new(0.0, 1.0).with_curve(ShutterCurve::Triangle));
+        let soft = render_motion_blurred(&w, &camera, &mut slide(), 8);
+        assert_ne!(open, plain);
+        assert_ne!(open, soft);
+        // the left edge of the ball is only there at the start, which the
+        // soft shutter lets in less of
+        let left = |c: &Canvas| c.read_pixel(4, 5).unwrap().green;
+        assert!(left(&soft) < left(&open) && left(&open) < left(&plain));
+        assert_eq!(w.objects[0].transform, translation(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rolling_shutters_skew_movers() {
+        let mut w = default_world();
+        w.objects.truncate(1);
+        let mut camera = Camera::new(21, 21, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        camera.set_shutter(Shutter::default().with_readout(1.0));
+        // still scenes come out the same row by row
+        let mut still = |_: &mut World, _: f64| {};
+        assert_eq!(
+            render_motion_blurred(&w, &camera, &mut still, 4),
+            camera.render(&w)
+        );
+        let mut x = 0.0;
+        let mut slide = |w: &mut World, dt: f64| {
+            x += 2.0 * dt;
+            w.objects[0].update_transform(translation(x, 0.0, 0.0));
+        };
+        let image = render_motion_blurred(&w, &camera, &mut slide, 1);
+        // lower rows are read later, when the ball has moved further right
+        let left_edge = |y| (0..21).find(|&x| image.read_pixel(x, y).unwrap().green > 0.0);
+        assert!(left_edge(12).unwrap() > left_edge(8).unwrap());
+    }
+
+    #[test]
+    fn closures_are_simulations() {
+        let mut w = default_world();
+        let camera = Camera::new(5, 5, PI / 2.0);
+        let mut steps = 0;
+        let mut count = |_: &mut World, _: f64| steps += 1;
+        simulate(&mut w, &camera, &mut count, 4, 1.0).for_each(drop);
+        assert_eq!(steps, 4);
+    }
+
+    #[test]
+    fn waves_advance_per_frame() {
+        let mut w = default_world();
+        w.objects[0].waves = Some(Waves::new());
+        let camera = Camera::new(5, 5, PI / 2.0);
+        simulate(&mut w, &camera, &mut advance_waves, 3, 0.5).for_each(drop);
+        assert_eq!(w.objects[0].waves.as_ref().unwrap().time, 1.5);
+        assert!(w.objects[1].waves.is_none());
+    }
+
+    #[test]
+    fn y4m_stream_layout() {
+        let mut frame = Canvas::new(2, 1);
+        frame.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        let mut out = Vec::new();
+        write_y4m(&mut out, vec![frame.clone(), frame], 24).unwrap();
+        let header = b"YUV4MPEG2 W2 H1 F24:1 Ip A1:1 C444\n";
+        assert_eq!(&out[..header.len()], header);
+        let body = &out[header.len()..];
+        assert_eq!(&body[..6], b"FRAME\n");
+        // white then black in each plane
+        assert_eq!(&body[6..12], &[235, 16, 128, 128, 128, 128]);
+        assert_eq!(body.len(), 2 * (6 + 6));
+    }
+
+    #[test]
+    fn y4m_rejects_mismatched_frames() {
+        let mut y4m = Y4mWriter::new(Vec::new(), 2, 2, 30);
+        assert!(y4m.write_frame(&Canvas::new(3, 2)).is_err());
+    }
+
+    #[test]
+    fn positions_orbit_the_center() {
+        let center = Point::new(1.0, 2.0, 3.0);
+        let positions = turntable_positions(center, 4, 5.0);
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[0], Point::new(1.0, 2.0, -2.0));
+        assert_eq!(positions[1], Point::new(6.0, 2.0, 3.0));
+        assert_eq!(positions[2], Point::new(1.0, 2.0, 8.0));
+        assert_eq!(positions[3], Point::new(-4.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn turntable_keeps_scene_centered() {
+        let mut w = default_world();
+        for object in w.objects.iter_mut() {
+            *object = sphere()
+                .set_transform(translation(10.0, 0.0, 0.0))
+                .set_material(object.material);
+        }
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        let frames = render_turntable(&w, &mut camera, 3, 5.0);
+        assert_eq!(frames.len(), 3);
+        for frame in frames {
+            assert_ne!(frame.read_pixel(5, 5).unwrap(), Color::new(0.0, 0.0, 0.0));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::png;
+use crate::profile::span;
+use crate::world::World;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthBuffer {
+    pub width: usize,
+    pub height: usize,
+    // distance along the primary ray, infinite where nothing was hit
+    pub data: Vec<f64>,
+}
+
+impl DepthBuffer {
+    pub fn get(&self, x: usize, y: usize) -> f64 {
+        self.data[y * self.width + x]
+    }
+
+    // portable float map, single channel little endian, rows bottom to top
+    pub fn to_pfm(&self) -> Vec<u8> {
+        let mut out = format!("Pf\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+        for row in self.data.chunks(self.width).rev() {
+            for d in row {
+                out.extend_from_slice(&(*d as f32).to_le_bytes());
+            }
+        }
+        out
+    }
+
+    // 16 bit grayscale png with near mapped to 0 and far (and misses) to 65535
+    pub fn to_png16(&self, near: f64, far: f64) -> Vec<u8> {
+        let samples = self
+            .data
+            .iter()
+            .flat_map(|d| {
+                let n = ((d - near) / (far - near)).clamp(0.0, 1.0);
+                ((n * 65535.0).round() as u16).to_be_bytes()
+            })
+            .collect::<Vec<_>>();
+        png::encode(
+            self.width as u32,
+            self.height as u32,
+            png::ColorType::Gray,
+            16,
+            &samples,
+        )
+    }
+}
+
+impl Canvas {
+    pub fn to_png(&self) -> Vec<u8> {
+        let _span = span!(INFO, "encode", format = "png");
+        let samples = self
+            .pixels
+            .iter()
+            .flat_map(|c| c.to_rgb8())
+            .collect::<Vec<_>>();
+        png::encode(
+            self.width as u32,
+            self.height as u32,
+            png::ColorType::Rgb,
+            8,
+            &samples,
+        )
+    }
+}
+
+impl Camera {
+    pub fn render_depth(&self, world: &World) -> DepthBuffer {
+        let data = self.render_with(|ray| {
+            match world
+                .intersect(ray)
+                .hit_with_tolerance(world.settings.epsilon)
+            {
+                Some(hit) => hit.t * ray.direction.magnitude(),
+                None => f64::INFINITY,
+            }
+        });
+        DepthBuffer {
+            width: self.hsize() as usize,
+            height: self.vsize() as usize,
+            data,
+        }
+    }
+
+    // world space normals encoded as (n + 1) / 2, misses are black
+    pub fn render_normals(&self, world: &World) -> Canvas {
+        let mut canvas = Canvas::new(self.hsize() as isize, self.vsize() as isize);
+        canvas.pixels = self.render_with(|ray| {
+            match world
+                .intersect(ray)
+                .hit_with_tolerance(world.settings.epsilon)
+            {
+                Some(hit) => {
+                    let n = hit.prepare_computations(ray).normal.0;
+                    Color::new((n.x + 1.0) / 2.0, (n.y + 1.0) / 2.0, (n.z + 1.0) / 2.0)
+                }
+                None => Color::new(0.0, 0.0, 0.0),
+            }
+        });
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        camera
+    }
+
+    #[test]
+    fn depth_of_center_pixel() {
+        let depth = camera().render_depth(&default_world());
+        assert!((depth.get(5, 5) - 4.0).abs() < 1e-5);
+        assert_eq!(depth.get(0, 0), f64::INFINITY);
+    }
+
+    #[test]
+    fn normal_of_center_pixel_faces_camera() {
+        let normals = camera().render_normals(&default_world());
+        assert_eq!(normals.read_pixel(5, 5).unwrap(), Color::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn pfm_layout() {
+        let depth = DepthBuffer {
+            width: 2,
+            height: 2,
+            data: vec![1.0, 2.0, 3.0, 4.0],
+        };
+        let pfm = depth.to_pfm();
+        let header = b"Pf\n2 2\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        // bottom row first
+        assert_eq!(&pfm[header.len()..header.len() + 4], &3f32.to_le_bytes());
+        assert_eq!(pfm.len(), header.len() + 16);
+    }
+
+    #[test]
+    fn png16_maps_depth_range() {
+        let depth = DepthBuffer {
+            width: 3,
+            height: 1,
+            data: vec![1.0, 2.0, f64::INFINITY],
+        };
+        let png = depth.to_png16(1.0, 3.0);
+        // first scanline starts after the zlib and stored block headers
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        let row = &png[idat + 2 + 5 + 1..idat + 2 + 5 + 1 + 6];
+        assert_eq!(row, &[0, 0, 0x80, 0x00, 0xff, 0xff]);
+    }
+}
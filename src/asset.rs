@@ -0,0 +1,180 @@
+use crate::canvas::{Canvas, CanvasError};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+// magic at the start of a pack file, see pack
+const PACK_MAGIC: &[u8] = b"RTPACK1\n";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetError {
+    NotFound(String),
+    Io(String, io::ErrorKind),
+    NotUtf8(String),
+    BadPack,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    Directory(PathBuf),
+    Memory(HashMap<String, Vec<u8>>),
+}
+
+// finds assets by a relative name, trying each source in the order they
+// were added. a scene and everything it needs can live in a directory, a
+// single pack file or in memory without the loaders knowing which
+#[derive(Debug, Clone, Default)]
+pub struct AssetResolver {
+    sources: Vec<Source>,
+}
+
+// names are always relative with forward slashes
+fn normalize(name: &str) -> String {
+    name.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+impl AssetResolver {
+    pub fn new() -> AssetResolver {
+        AssetResolver::default()
+    }
+
+    pub fn with_search_path(mut self, directory: impl Into<PathBuf>) -> AssetResolver {
+        self.sources.push(Source::Directory(directory.into()));
+        self
+    }
+
+    pub fn with_asset(mut self, name: &str, bytes: impl Into<Vec<u8>>) -> AssetResolver {
+        if let Some(Source::Memory(assets)) = self.sources.last_mut() {
+            assets.insert(normalize(name), bytes.into());
+        } else {
+            let assets = HashMap::from([(normalize(name), bytes.into())]);
+            self.sources.push(Source::Memory(assets));
+        }
+        self
+    }
+
+    // every asset stored in a pack made by pack
+    pub fn with_pack(mut self, bytes: &[u8]) -> Result<AssetResolver, AssetError> {
+        self.sources.push(Source::Memory(unpack(bytes)?));
+        Ok(self)
+    }
+
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, AssetError> {
+        let key = normalize(name);
+        for source in &self.sources {
+            match source {
+                Source::Directory(dir) => match std::fs::read(dir.join(&key)) {
+                    Ok(bytes) => return Ok(bytes),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(AssetError::Io(key, e.kind())),
+                },
+                Source::Memory(assets) => {
+                    if let Some(bytes) = assets.get(&key) {
+                        return Ok(bytes.clone());
+                    }
+                }
+            }
+        }
+        Err(AssetError::NotFound(key))
+    }
+
+    pub fn read_to_string(&self, name: &str) -> Result<String, AssetError> {
+        String::from_utf8(self.read(name)?).map_err(|_| AssetError::NotUtf8(normalize(name)))
+    }
+
+    pub fn load_ppm(&self, name: &str) -> Result<Canvas, CanvasError> {
+        let bytes = self.read(name).map_err(|_| CanvasError::ReadError)?;
+        Canvas::from_ppm_bytes(&bytes)
+    }
+}
+
+// bundles named assets into one file: the magic, then per asset a u32 name
+// length, the name, a u64 data length and the data, all little endian
+pub fn pack<'a>(assets: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> Vec<u8> {
+    let mut out = PACK_MAGIC.to_vec();
+    for (name, data) in assets {
+        let name = normalize(name);
+        out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+// next n bytes of a pack, failing when it ends early
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, n: u64) -> Result<&'a [u8], AssetError> {
+    let end = usize::try_from(n)
+        .ok()
+        .and_then(|n| pos.checked_add(n))
+        .filter(|&end| end <= bytes.len())
+        .ok_or(AssetError::BadPack)?;
+    let slice = &bytes[*pos..end];
+    *pos = end;
+    Ok(slice)
+}
+
+fn unpack(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>, AssetError> {
+    if !bytes.starts_with(PACK_MAGIC) {
+        return Err(AssetError::BadPack);
+    }
+    let mut pos = PACK_MAGIC.len();
+    let mut assets = HashMap::new();
+    while pos < bytes.len() {
+        let name_len = u32::from_le_bytes(take(bytes, &mut pos, 4)?.try_into().unwrap());
+        let name = String::from_utf8(take(bytes, &mut pos, name_len as u64)?.to_vec())
+            .map_err(|_| AssetError::BadPack)?;
+        let data_len = u64::from_le_bytes(take(bytes, &mut pos, 8)?.try_into().unwrap());
+        assets.insert(name, take(bytes, &mut pos, data_len)?.to_vec());
+    }
+    Ok(assets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn memory_assets_are_found_by_relative_name() {
+        let r = AssetResolver::new()
+            .with_asset("scenes/a.yaml", "- add: light")
+            .with_asset("b.txt", vec![1, 2]);
+        assert_eq!(r.read_to_string("./scenes/a.yaml").unwrap(), "- add: light");
+        assert_eq!(r.read("b.txt").unwrap(), vec![1, 2]);
+        assert!(matches!(r.read("c.txt"), Err(AssetError::NotFound(n)) if n == "c.txt"));
+    }
+
+    #[test]
+    fn earlier_sources_win() {
+        let dir = std::env::temp_dir().join(format!("ray-tracer-assets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("shared.txt"), "from disk").unwrap();
+        let r = AssetResolver::new()
+            .with_search_path(&dir)
+            .with_asset("shared.txt", "from memory")
+            .with_asset("only.txt", "memory only");
+        assert_eq!(r.read_to_string("shared.txt").unwrap(), "from disk");
+        assert_eq!(r.read_to_string("only.txt").unwrap(), "memory only");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn packs_round_trip() {
+        let mut image = Canvas::new(1, 1);
+        image.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        let ppm = image.to_ppm();
+        let bundle = pack([("scene.yaml", &b"[]"[..]), ("tex/red.ppm", ppm.as_bytes())]);
+        let r = AssetResolver::new().with_pack(&bundle).unwrap();
+        assert_eq!(r.read("scene.yaml").unwrap(), b"[]");
+        assert_eq!(r.load_ppm("tex/red.ppm").unwrap(), image);
+        assert!(matches!(
+            AssetResolver::new().with_pack(b"zip"),
+            Err(AssetError::BadPack)
+        ));
+        assert!(matches!(
+            AssetResolver::new().with_pack(&bundle[..bundle.len() - 1]),
+            Err(AssetError::BadPack)
+        ));
+    }
+}
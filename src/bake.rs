@@ -0,0 +1,224 @@
+use crate::canvas::Canvas;
+use crate::color::{Color, BLACK};
+use crate::intersection::Intersection;
+use crate::object::Object;
+use crate::ray::Ray;
+use crate::tuple::{Point, Vector};
+use crate::world::World;
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+// position and normal of the surface under the center of a texel
+fn texel_surface(
+    object: &Object,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> (Point, Vector) {
+    // row 0 is the top of the texture, which is v = 1
+    let u = (x as f64 + 0.5) / width as f64;
+    let v = 1.0 - (y as f64 + 0.5) / height as f64;
+    let point = object.point_at_uv(u, v);
+    (point, object.normal_at(point))
+}
+
+// deterministic cosine weighted directions around the normal, laid out on a
+// fibonacci spiral so the same scene always bakes the same texture
+fn hemisphere_directions(normal: Vector, samples: usize) -> Vec<Vector> {
+    let helper = if normal.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    let golden_angle = PI * (3.0 - 5f64.sqrt());
+
+    (0..samples)
+        .map(|i| {
+            let r = ((i as f64 + 0.5) / samples as f64).sqrt();
+            let phi = i as f64 * golden_angle;
+            let z = (1.0 - r * r).sqrt();
+            (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z).normalize()
+        })
+        .collect()
+}
+
+fn bake<F>(object: &Object, width: usize, height: usize, texel: F) -> Canvas
+where
+    F: Fn(Point, Vector) -> Color + Sync,
+{
+    let mut canvas = Canvas::new(width as isize, height as isize);
+    (0..height)
+        .into_par_iter()
+        .flat_map(|y| (0..width).into_par_iter().map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (point, normal) = texel_surface(object, x, y, width, height);
+            (x, y, texel(point, normal))
+        })
+        .collect::<Vec<_>>()
+        .iter()
+        .for_each(|(x, y, color)| {
+            canvas.write_pixel(*x as isize, *y as isize, *color);
+        });
+    canvas
+}
+
+// fraction of the hemisphere above each texel that is not blocked by other
+// geometry within max_distance, white is fully open
+pub fn bake_ambient_occlusion(
+    world: &World,
+    object: &Object,
+    width: usize,
+    height: usize,
+    samples: usize,
+    max_distance: f64,
+) -> Canvas {
+    bake(object, width, height, |point, normal| {
+        let origin = point + normal * object.surface_bias(world.settings.shadow_bias);
+        let open = hemisphere_directions(normal, samples)
+            .into_iter()
+            .filter(|direction| {
+                let r = Ray::new(origin, *direction);
+                world.intersect_within(r, 0.0, max_distance).is_empty()
+            })
+            .count();
+        let ao = open as f64 / samples as f64;
+        Color::new(ao, ao, ao)
+    })
+}
+
+// full direct lighting of each texel as seen from straight above the surface
+pub fn bake_lighting(world: &World, object: &Object, width: usize, height: usize) -> Canvas {
+    bake(object, width, height, |point, normal| {
+        let ray = Ray::new(point + normal, -normal);
+        let comps = Intersection::new(1.0, object)
+            .prepare_computations_with_bias(ray, object.surface_bias(world.settings.shadow_bias));
+        world.shade_hit(comps)
+    })
+}
+
+// light arriving at each texel, ignoring the material, so engines can
+// multiply it with their own albedo
+pub fn bake_irradiance(world: &World, object: &Object, width: usize, height: usize) -> Canvas {
+    bake(object, width, height, |point, normal| {
+        let over_point = point + normal * object.surface_bias(world.settings.shadow_bias);
+        world
+            .lights
+            .iter()
+            .map(|light| {
+                let positions = light.sample_positions(point);
+                let weight = 1.0 / positions.len() as f64;
+                positions
+                    .into_iter()
+                    .filter(|p| !world.is_shadowed_from(*p, over_point))
+                    .map(|p| {
+                        let lightv = (p - point).normalize();
+                        light.intensity_at(point) * (lightv.dot(normal).max(0.0) * weight)
+                    })
+                    .fold(BLACK, |acc, c| acc + c)
+            })
+            .fold(BLACK, |acc, c| acc + c)
+    })
+}
+
+// one irradiance map per object in world order, spheres are unwrapped
+// to a 2:1 texture so resolution is the height in texels
+pub fn bake_lightmaps(world: &World, resolution: usize) -> Vec<Canvas> {
+    world
+        .objects
+        .iter()
+        .map(|object| bake_irradiance(world, object, resolution * 2, resolution))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+
+    #[test]
+    fn hemisphere_directions_face_the_normal() {
+        let n = Vector::new(0.0, 1.0, 0.0);
+        let dirs = hemisphere_directions(n, 32);
+        assert_eq!(dirs.len(), 32);
+        for d in dirs {
+            assert!(d.dot(n) > 0.0);
+            assert!((d.magnitude() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn unoccluded_sphere_bakes_white() {
+        let mut w = World::new();
+        w.objects.push(sphere());
+        let ao = bake_ambient_occlusion(&w, &w.objects[0], 8, 4, 16, 10.0);
+        assert_eq!(ao.width, 8);
+        assert_eq!(ao.height, 4);
+        for p in ao.pixels {
+            assert_eq!(p, Color::new(1.0, 1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn nearby_geometry_darkens_ambient_occlusion() {
+        let mut w = World::new();
+        w.objects.push(sphere());
+        // a large flattened sphere just below acts as a floor
+        w.objects
+            .push(sphere().set_transform(translation(0.0, -1.1, 0.0) * scaling(10.0, 0.05, 10.0)));
+        let ao = bake_ambient_occlusion(&w, &w.objects[0], 8, 8, 32, 10.0);
+        let top = ao.read_pixel(0, 0).unwrap();
+        let bottom = ao.read_pixel(0, 7).unwrap();
+        assert_eq!(top, Color::new(1.0, 1.0, 1.0));
+        assert!(bottom.red < 0.5);
+    }
+
+    #[test]
+    fn baked_lighting_is_brighter_facing_the_light() {
+        let mut w = World::new();
+        w.objects.push(sphere());
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        let lit = bake_lighting(&w, &w.objects[0], 4, 4);
+        let top = lit.read_pixel(0, 0).unwrap();
+        let bottom = lit.read_pixel(0, 3).unwrap();
+        assert!(top.red > bottom.red);
+        assert_eq!(bottom, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn lightmaps_are_baked_per_object() {
+        let mut w = crate::world::default_world();
+        w.objects[1] = sphere().set_transform(translation(5.0, 0.0, 0.0));
+        let maps = bake_lightmaps(&w, 4);
+        assert_eq!(maps.len(), 2);
+        assert_eq!(maps[0].width, 8);
+        assert_eq!(maps[0].height, 4);
+    }
+
+    #[test]
+    fn irradiance_ignores_material_and_respects_shadows() {
+        let mut w = World::new();
+        w.objects
+            .push(sphere().set_material(crate::material::Material {
+                color: Color::new(1.0, 0.0, 0.0),
+                ..Default::default()
+            }));
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 100.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        let map = bake_irradiance(&w, &w.objects[0], 4, 16);
+        let top = map.read_pixel(0, 0).unwrap();
+        assert!(top.green > 0.9);
+        assert_eq!(map.read_pixel(0, 15).unwrap(), BLACK);
+
+        // a blocker overhead shadows the top of the sphere
+        w.objects
+            .push(sphere().set_transform(translation(0.0, 3.0, 0.0)));
+        let map = bake_irradiance(&w, &w.objects[0], 4, 16);
+        assert_eq!(map.read_pixel(0, 0).unwrap(), BLACK);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::intersection::{Intersection, Intersections};
+use crate::object::{Geometry, Object};
+use crate::ray::Ray;
+use crate::tuple::Point;
+
+const CHUNK: usize = 64;
+
+// struct of arrays copy of the spheres that are still round after their
+// transform (rotation, uniform scale and translation), so a ray can be
+// tested against all of them in tight loops the compiler vectorizes.
+// stretched spheres are left to the generic path
+#[derive(Debug, Clone, Default)]
+pub struct SphereBatch {
+    center_x: Vec<f64>,
+    center_y: Vec<f64>,
+    center_z: Vec<f64>,
+    radius_squared: Vec<f64>,
+    // index into the world's objects for each batched sphere
+    index: Vec<usize>,
+    // objects that need the generic intersection
+    pub generic: Vec<usize>,
+    // the objects it was built over, in order
+    ids: Vec<u32>,
+}
+
+// center and radius when the transform keeps the sphere round
+fn round_sphere(sphere: &Object) -> Option<(Point, f64)> {
+    if sphere.geometry != Geometry::Sphere {
+        return None;
+    }
+    let m = &sphere.transform;
+    let col = |x: usize| [m.get(0, x), m.get(1, x), m.get(2, x)];
+    let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let (c0, c1, c2) = (col(0), col(1), col(2));
+    let s = dot(c0, c0);
+    let tolerance = 1e-9 * s.max(1.0);
+    if m.get(3, 0) != 0.0 || m.get(3, 1) != 0.0 || m.get(3, 2) != 0.0 || m.get(3, 3) != 1.0 {
+        return None;
+    }
+    if (dot(c1, c1) - s).abs() > tolerance
+        || (dot(c2, c2) - s).abs() > tolerance
+        || dot(c0, c1).abs() > tolerance
+        || dot(c0, c2).abs() > tolerance
+        || dot(c1, c2).abs() > tolerance
+    {
+        return None;
+    }
+    let center = Point::new(m.get(0, 3), m.get(1, 3), m.get(2, 3));
+    Some((center, s.sqrt()))
+}
+
+impl SphereBatch {
+    pub fn new(objects: &[Object]) -> SphereBatch {
+        let mut batch = SphereBatch {
+            ids: objects.iter().map(|o| o.id()).collect(),
+            ..Default::default()
+        };
+        for (i, object) in objects.iter().enumerate() {
+            match round_sphere(object) {
+                Some((center, radius)) => {
+                    batch.center_x.push(center.0.x);
+                    batch.center_y.push(center.0.y);
+                    batch.center_z.push(center.0.z);
+                    batch.radius_squared.push(radius * radius);
+                    batch.index.push(i);
+                }
+                None => batch.generic.push(i),
+            }
+        }
+        batch
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    // a batch built from a different object list must not be used.
+    // walks every object, so rays only check object_count
+    pub fn matches(&self, objects: &[Object]) -> bool {
+        self.ids.iter().copied().eq(objects.iter().map(|o| o.id()))
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    // appends the intersections with every batched sphere, unsorted
+    pub fn intersect<'a>(&self, objects: &'a [Object], ray: Ray, out: &mut Intersections<'a>) {
+        let (ox, oy, oz) = (ray.origin.0.x, ray.origin.0.y, ray.origin.0.z);
+        let (dx, dy, dz) = (ray.direction.0.x, ray.direction.0.y, ray.direction.0.z);
+        let a = dx * dx + dy * dy + dz * dz;
+        let mut half_b = [0.0; CHUNK];
+        let mut discriminant = [0.0; CHUNK];
+
+        for start in (0..self.len()).step_by(CHUNK) {
+            let end = (start + CHUNK).min(self.len());
+            let n = end - start;
+            let cx = &self.center_x[start..end];
+            let cy = &self.center_y[start..end];
+            let cz = &self.center_z[start..end];
+            let r2 = &self.radius_squared[start..end];
+            for i in 0..n {
+                let (px, py, pz) = (ox - cx[i], oy - cy[i], oz - cz[i]);
+                let b = dx * px + dy * py + dz * pz;
+                let c = px * px + py * py + pz * pz - r2[i];
+                half_b[i] = b;
+                discriminant[i] = b * b - a * c;
+            }
+            for i in 0..n {
+                if discriminant[i] >= 0.0 {
+                    let root = discriminant[i].sqrt();
+                    let object = &objects[self.index[start + i]];
+                    out.push(Intersection::new((-half_b[i] - root) / a, object));
+                    out.push(Intersection::new((-half_b[i] + root) / a, object));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators::{city, sphere_grid};
+    use crate::sphere::sphere;
+    use crate::transformations::{rotation_x, scaling, translation};
+    use crate::tuple::Vector;
+
+    #[test]
+    fn only_round_spheres_are_batched() {
+        let objects = vec![
+            sphere(),
+            sphere().set_transform(
+                translation(1.0, 2.0, 3.0) * rotation_x(0.3) * scaling(2.0, 2.0, 2.0),
+            ),
+            sphere().set_transform(scaling(1.0, 2.0, 1.0)),
+        ];
+        let batch = SphereBatch::new(&objects);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.generic, vec![2]);
+        assert_eq!(batch.radius_squared[1], 4.0);
+        assert!(batch.matches(&objects));
+        assert!(!batch.matches(&objects[..2]));
+    }
+
+    #[test]
+    fn batch_agrees_with_generic_intersection() {
+        for world in [sphere_grid(4, 2.5), city(3, 1)] {
+            let batch = SphereBatch::new(&world.objects);
+            let rays = [
+                Ray::new(Point::new(-20.0, 0.3, -20.0), Vector::new(1.0, 0.0, 1.0)),
+                Ray::new(Point::new(0.1, 30.0, 0.2), Vector::new(0.0, -1.0, 0.05)),
+                Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.3, 0.4, 0.5)),
+            ];
+            for ray in rays {
+                let mut xs = Intersections::new();
+                batch.intersect(&world.objects, ray, &mut xs);
+                for &i in &batch.generic {
+                    xs.concat(world.objects[i].intersect(ray));
+                }
+                xs.sort();
+                assert_eq!(xs.len(), world.intersect(ray).len());
+                for (a, b) in xs.iter().zip(world.intersect(ray).iter()) {
+                    assert!((a.t - b.t).abs() < 1e-9);
+                    assert_eq!(a.object.id(), b.object.id());
+                }
+            }
+        }
+    }
+}
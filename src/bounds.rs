@@ -0,0 +1,356 @@
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::ray::Ray;
+use crate::tuple::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        BoundingBox::empty()
+    }
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    // inverted infinite box, adding anything to it yields that thing
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.0.x > self.max.0.x || self.min.0.y > self.max.0.y || self.min.0.z > self.max.0.z
+    }
+
+    pub fn add_point(&mut self, p: Point) {
+        self.min = Point::new(
+            self.min.0.x.min(p.0.x),
+            self.min.0.y.min(p.0.y),
+            self.min.0.z.min(p.0.z),
+        );
+        self.max = Point::new(
+            self.max.0.x.max(p.0.x),
+            self.max.0.y.max(p.0.y),
+            self.max.0.z.max(p.0.z),
+        );
+    }
+
+    pub fn merge(&mut self, other: &BoundingBox) {
+        if !other.is_empty() {
+            self.add_point(other.min);
+            self.add_point(other.max);
+        }
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new(
+            (self.min.0.x + self.max.0.x) / 2.0,
+            (self.min.0.y + self.max.0.y) / 2.0,
+            (self.min.0.z + self.max.0.z) / 2.0,
+        )
+    }
+
+    pub fn contains_point(&self, p: Point) -> bool {
+        (self.min.0.x..=self.max.0.x).contains(&p.0.x)
+            && (self.min.0.y..=self.max.0.y).contains(&p.0.y)
+            && (self.min.0.z..=self.max.0.z).contains(&p.0.z)
+    }
+
+    pub fn corners(&self) -> [Point; 8] {
+        let (a, b) = (self.min.0, self.max.0);
+        [
+            Point::new(a.x, a.y, a.z),
+            Point::new(a.x, a.y, b.z),
+            Point::new(a.x, b.y, a.z),
+            Point::new(a.x, b.y, b.z),
+            Point::new(b.x, a.y, a.z),
+            Point::new(b.x, a.y, b.z),
+            Point::new(b.x, b.y, a.z),
+            Point::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // pairs of corners joined by the twelve edges
+    pub fn edges(&self) -> [(Point, Point); 12] {
+        let c = self.corners();
+        // corners differing in exactly one axis, see the bit order above
+        [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ]
+        .map(|(a, b)| (c[a], c[b]))
+    }
+
+    // slab test, true when the ray's line passes through the box in front
+    // of the origin or with the origin inside
+    pub fn intersects(&self, ray: Ray) -> bool {
+        self.intersection(ray).is_some()
+    }
+
+    // entry and exit t of the ray, entry is negative with the origin inside
+    pub fn intersection(&self, ray: Ray) -> Option<(f64, f64)> {
+        let (o, d) = (ray.origin.0, ray.direction.0);
+        let mut t_min = f64::NEG_INFINITY;
+        let mut t_max = f64::INFINITY;
+        for (o, d, min, max) in [
+            (o.x, d.x, self.min.0.x, self.max.0.x),
+            (o.y, d.y, self.min.0.y, self.max.0.y),
+            (o.z, d.z, self.min.0.z, self.max.0.z),
+        ] {
+            let (t0, t1) = ((min - o) / d, (max - o) / d);
+            // a nan from a ray lying on a slab face leaves the range alone
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        if t_min <= t_max && t_max >= 0.0 {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    // tight box around the unit sphere transformed by m. the extent along
+    // each axis is the length of that row of m's linear part, where the
+    // box of the transformed corners grows by up to sqrt(3) under rotation
+    pub fn of_unit_sphere(m: &Matrix4) -> BoundingBox {
+        let center = Point::new(m.get(0, 3), m.get(1, 3), m.get(2, 3));
+        let e = |i: usize| {
+            (0..3)
+                .map(|j| m.get(i, j) * m.get(i, j))
+                .sum::<f64>()
+                .sqrt()
+        };
+        let (x, y, z) = (e(0), e(1), e(2));
+        BoundingBox::new(
+            Point::new(center.0.x - x, center.0.y - y, center.0.z - z),
+            Point::new(center.0.x + x, center.0.y + y, center.0.z + z),
+        )
+    }
+
+    // box around all eight transformed corners
+    pub fn transform(&self, m: &Matrix4) -> BoundingBox {
+        let mut result = BoundingBox::empty();
+        if self.is_empty() {
+            return result;
+        }
+        for corner in self.corners() {
+            result.add_point((m * corner).try_into().unwrap());
+        }
+        result
+    }
+}
+
+// cheaper to test a ray against than a box, and much tighter than the
+// box of an object turned away from the axes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point,
+    pub radius: f64,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point, radius: f64) -> BoundingSphere {
+        BoundingSphere { center, radius }
+    }
+
+    // sphere through the corners of the box, for groups of objects
+    pub fn around(b: &BoundingBox) -> BoundingSphere {
+        if b.is_empty() {
+            return BoundingSphere::new(Point::new(0.0, 0.0, 0.0), f64::NEG_INFINITY);
+        }
+        BoundingSphere::new(b.center(), (b.max - b.min).magnitude() / 2.0)
+    }
+
+    // smallest sphere around the unit sphere transformed by m. its radius
+    // is the largest stretch m applies in any direction, the square root
+    // of the largest eigenvalue of a^t a for the linear part a
+    pub fn of_unit_sphere(m: &Matrix4) -> BoundingSphere {
+        let mut b = [[0.0; 3]; 3];
+        for (i, row) in b.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                *v = (0..3).map(|k| m.get(k, i) * m.get(k, j)).sum();
+            }
+        }
+        let center = Point::new(m.get(0, 3), m.get(1, 3), m.get(2, 3));
+        // a little slack so rounding never makes the sphere too small
+        let radius = largest_eigenvalue(b).sqrt() * (1.0 + 1e-9);
+        BoundingSphere::new(center, radius)
+    }
+
+    // true when the ray's line passes through the sphere in front of the
+    // origin or with the origin inside, same as the box test
+    pub fn intersects(&self, ray: Ray) -> bool {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.dot(ray.direction);
+        let b = oc.dot(ray.direction);
+        let c = oc.dot(oc) - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+        self.radius >= 0.0 && discriminant >= 0.0 && -b + discriminant.sqrt() >= 0.0
+    }
+}
+
+// closed form for symmetric 3x3 matrices
+fn largest_eigenvalue(b: [[f64; 3]; 3]) -> f64 {
+    let off = b[0][1] * b[0][1] + b[0][2] * b[0][2] + b[1][2] * b[1][2];
+    let q = (b[0][0] + b[1][1] + b[2][2]) / 3.0;
+    let p2 = (b[0][0] - q).powi(2) + (b[1][1] - q).powi(2) + (b[2][2] - q).powi(2) + 2.0 * off;
+    if p2 <= 0.0 {
+        return q;
+    }
+    let p = (p2 / 6.0).sqrt();
+    let c = |i: usize, j: usize| (b[i][j] - if i == j { q } else { 0.0 }) / p;
+    let det = c(0, 0) * (c(1, 1) * c(2, 2) - c(1, 2) * c(2, 1))
+        - c(0, 1) * (c(1, 0) * c(2, 2) - c(1, 2) * c(2, 0))
+        + c(0, 2) * (c(1, 0) * c(2, 1) - c(1, 1) * c(2, 0));
+    let phi = (det / 2.0).clamp(-1.0, 1.0).acos() / 3.0;
+    q + 2.0 * p * phi.cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::{rotation_x, rotation_y, rotation_z, scaling, translation};
+    use crate::tuple::Vector;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn empty_box() {
+        let b = BoundingBox::empty();
+        assert!(b.is_empty());
+    }
+
+    #[test]
+    fn adding_points_to_empty_box() {
+        let mut b = BoundingBox::empty();
+        b.add_point(Point::new(-5.0, 2.0, 0.0));
+        b.add_point(Point::new(7.0, 0.0, -3.0));
+        assert_eq!(b.min, Point::new(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Point::new(7.0, 2.0, 0.0));
+        assert_eq!(b.center(), Point::new(1.0, 1.0, -1.5));
+    }
+
+    #[test]
+    fn merging_boxes() {
+        let mut a = BoundingBox::new(Point::new(-5.0, -2.0, 0.0), Point::new(7.0, 4.0, 4.0));
+        let b = BoundingBox::new(Point::new(8.0, -7.0, -2.0), Point::new(14.0, 2.0, 8.0));
+        a.merge(&b);
+        assert_eq!(a.min, Point::new(-5.0, -7.0, -2.0));
+        assert_eq!(a.max, Point::new(14.0, 4.0, 8.0));
+        a.merge(&BoundingBox::empty());
+        assert_eq!(a.max, Point::new(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn box_contains_point() {
+        let b = BoundingBox::new(Point::new(5.0, -2.0, 0.0), Point::new(11.0, 4.0, 7.0));
+        assert!(b.contains_point(Point::new(5.0, -2.0, 0.0)));
+        assert!(b.contains_point(Point::new(8.0, 1.0, 3.0)));
+        assert!(!b.contains_point(Point::new(3.0, 0.0, 3.0)));
+        assert!(!b.contains_point(Point::new(8.0, 1.0, 8.0)));
+    }
+
+    #[test]
+    fn transforming_a_box() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let t = translation(1.0, 0.0, 0.0) * rotation_y(PI / 4.0);
+        let b2 = b.transform(&t);
+        let r = 2f64.sqrt();
+        assert_eq!(b2.min, Point::new(1.0 - r, -1.0, -r));
+        assert_eq!(b2.max, Point::new(1.0 + r, 1.0, r));
+    }
+
+    #[test]
+    fn box_around_turned_sphere_is_tight() {
+        let b = BoundingBox::of_unit_sphere(&(translation(1.0, 2.0, 3.0) * scaling(2.0, 3.0, 4.0)));
+        assert_eq!(b.min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(3.0, 5.0, 7.0));
+        let b = BoundingBox::of_unit_sphere(&rotation_y(PI / 4.0));
+        assert!((b.max.0.x - 1.0).abs() < 1e-9 && (b.max.0.z - 1.0).abs() < 1e-9);
+        // the far tip of a turned needle sets the box exactly
+        let m = rotation_z(PI / 6.0) * scaling(4.0, 0.0, 0.0);
+        let b = BoundingBox::of_unit_sphere(&m);
+        assert!((b.max.0.x - 4.0 * (PI / 6.0).cos()).abs() < 1e-9);
+        assert!((b.max.0.y - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn box_edges_are_axis_aligned() {
+        let b = BoundingBox::new(Point::new(-1.0, -2.0, -3.0), Point::new(1.0, 2.0, 3.0));
+        let mut lengths = b.edges().map(|(p, q)| (q - p).magnitude());
+        lengths.sort_by(f64::total_cmp);
+        assert_eq!(
+            lengths,
+            [2.0, 2.0, 2.0, 2.0, 4.0, 4.0, 4.0, 4.0, 6.0, 6.0, 6.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn ray_against_box() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let ray = |o: Point, d: Vector| Ray::new(o, d);
+        assert!(b.intersects(ray(Point::new(5.0, 0.5, 0.0), Vector::new(-1.0, 0.0, 0.0))));
+        assert!(b.intersects(ray(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0))));
+        assert!(b.intersects(ray(
+            Point::new(-2.0, -2.0, -2.0),
+            Vector::new(1.0, 1.0, 1.0)
+        )));
+        assert!(!b.intersects(ray(Point::new(5.0, 0.5, 0.0), Vector::new(1.0, 0.0, 0.0))));
+        assert!(!b.intersects(ray(Point::new(2.0, 0.0, 2.0), Vector::new(0.0, 0.0, -1.0))));
+        assert!(!b.intersects(ray(Point::new(0.0, 2.0, -2.0), Vector::new(0.0, 1.0, 1.0))));
+        assert_eq!(
+            b.intersection(ray(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 2.0))),
+            Some((2.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn sphere_around_transformed_unit_sphere() {
+        let s = BoundingSphere::of_unit_sphere(&translation(1.0, 2.0, 3.0));
+        assert_eq!(s.center, Point::new(1.0, 2.0, 3.0));
+        assert!((s.radius - 1.0).abs() < 1e-6);
+        let m = translation(0.0, 1.0, 0.0)
+            * rotation_z(0.3)
+            * rotation_x(1.1)
+            * rotation_y(PI / 4.0)
+            * scaling(5.0, 0.2, 0.5);
+        let s = BoundingSphere::of_unit_sphere(&m);
+        assert_eq!(s.center, Point::new(0.0, 1.0, 0.0));
+        assert!((s.radius - 5.0).abs() < 1e-6);
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        assert!(BoundingSphere::around(&b.transform(&m)).radius > s.radius);
+    }
+
+    #[test]
+    fn ray_against_sphere() {
+        let s = BoundingSphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+        let ray = |o: Point, d: Vector| Ray::new(o, d);
+        assert!(s.intersects(ray(Point::new(5.0, 0.5, 0.0), Vector::new(-2.0, 0.0, 0.0))));
+        assert!(s.intersects(ray(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0))));
+        // the corner of the box around it is missed
+        assert!(!s.intersects(ray(Point::new(0.9, 0.9, -5.0), Vector::new(0.0, 0.0, 1.0))));
+        assert!(!s.intersects(ray(Point::new(5.0, 0.5, 0.0), Vector::new(1.0, 0.0, 0.0))));
+        assert!(!BoundingSphere::around(&BoundingBox::empty())
+            .intersects(ray(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0))));
+    }
+}
@@ -0,0 +1,353 @@
+use crate::color::{Color, BLACK};
+use crate::material::Material;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::tuple::Vector;
+use core::f64::consts::PI;
+use core::fmt::Debug;
+
+// one light seen from one surface point, all vectors unit length and the
+// light known to be above the surface. diffuse and specular say which
+// parts of the response the light takes part in, see PointLight
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub intensity: Color,
+    pub lightv: Vector,
+    pub eyev: Vector,
+    pub normalv: Vector,
+    pub diffuse: bool,
+    pub specular: bool,
+}
+
+impl LightSample {
+    pub fn light_dot_normal(&self) -> f64 {
+        self.lightv.dot(self.normalv)
+    }
+}
+
+// how a surface reflects a single light towards the eye. ambient light and
+// shadowing are handled by lighting for every model alike
+pub trait Brdf: Debug + Send + Sync {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color;
+}
+
+fn lambert(material: &Material, sample: &LightSample) -> Color {
+    if !sample.diffuse {
+        return BLACK;
+    }
+    material.color * sample.intensity * material.diffuse * sample.light_dot_normal()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Phong;
+
+impl Brdf for Phong {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        // reflect dot eye represents the cosine of the angle between the
+        // reflection and the eye
+        let reflectv = -sample.lightv.reflect(sample.normalv);
+        let reflect_dot_eye = reflectv.dot(sample.eyev);
+        let specular = if reflect_dot_eye <= 0.0 || !sample.specular {
+            BLACK
+        } else {
+            let factor = reflect_dot_eye.powf(material.shininess);
+            sample.intensity * material.specular * factor
+        };
+        lambert(material, sample) + specular
+    }
+}
+
+// specular from the half vector between light and eye, which falls off
+// more gently than phong's towards grazing angles
+#[derive(Debug, Clone, Copy)]
+pub struct BlinnPhong;
+
+impl Brdf for BlinnPhong {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        let halfv = (sample.lightv + sample.eyev).normalize();
+        let half_dot_normal = halfv.dot(sample.normalv);
+        let specular = if half_dot_normal <= 0.0 || !sample.specular {
+            BLACK
+        } else {
+            // the half vector sits at half the angle of the reflection, four
+            // times the exponent gives about the same highlight size
+            let factor = half_dot_normal.powf(4.0 * material.shininess);
+            sample.intensity * material.specular * factor
+        };
+        lambert(material, sample) + specular
+    }
+}
+
+// diffuse only, for chalk and paper
+#[derive(Debug, Clone, Copy)]
+pub struct Lambert;
+
+impl Brdf for Lambert {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        lambert(material, sample)
+    }
+}
+
+// cook-torrance microfacets with the ggx distribution, smith shadowing and
+// schlick fresnel. uses the material color as albedo and ignores diffuse,
+// specular and shininess. scaled so a white matte surface facing a light
+// reflects its full intensity, the same as phong with diffuse 1
+#[derive(Debug, Clone, Copy)]
+pub struct CookTorrance {
+    pub roughness: f64,
+    pub metallic: f64,
+}
+
+impl Brdf for CookTorrance {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        let n_dot_l = sample.light_dot_normal();
+        let n_dot_v = sample.normalv.dot(sample.eyev).max(1e-4);
+        let halfv = (sample.lightv + sample.eyev).normalize();
+        let n_dot_h = halfv.dot(sample.normalv).max(0.0);
+        let v_dot_h = halfv.dot(sample.eyev).max(0.0);
+
+        let alpha = (self.roughness * self.roughness).max(1e-4);
+        let a2 = alpha * alpha;
+        let d = a2 / (PI * (n_dot_h * n_dot_h * (a2 - 1.0) + 1.0).powi(2));
+        let k = (self.roughness + 1.0).powi(2) / 8.0;
+        let g = n_dot_l / (n_dot_l * (1.0 - k) + k) * n_dot_v / (n_dot_v * (1.0 - k) + k);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let f0 = white * 0.04 * (1.0 - self.metallic) + material.color * self.metallic;
+        let fresnel = f0 + (white - f0) * (1.0 - v_dot_h).powi(5);
+
+        // both terms carry an extra pi for the scaling above
+        let mut c = BLACK;
+        if sample.specular {
+            c = c + fresnel * (PI * d * g / (4.0 * n_dot_l * n_dot_v));
+        }
+        if sample.diffuse {
+            c = c + (white - fresnel) * material.color * (1.0 - self.metallic);
+        }
+        c * sample.intensity * n_dot_l
+    }
+}
+
+// the sheen lobe of disney's principled brdf, a fresnel like rise as the
+// light and eye move apart, as on cloth where fibers catch light at
+// grazing angles. uses the material's sheen and sheen_tint, and is meant
+// to be added to whatever the model gives. counts as diffuse, like in
+// the principled brdf
+#[derive(Debug, Clone, Copy)]
+pub struct Sheen;
+
+impl Brdf for Sheen {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        if !sample.diffuse {
+            return BLACK;
+        }
+        let halfv = (sample.lightv + sample.eyev).normalize();
+        let l_dot_h = halfv.dot(sample.lightv).clamp(0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let tint = white * (1.0 - material.sheen_tint) + material.color * material.sheen_tint;
+        tint * sample.intensity
+            * (material.sheen * (1.0 - l_dot_h).powi(5) * sample.light_dot_normal())
+    }
+}
+
+// which brdf a material uses. kept as an enum so materials stay Copy, with
+// Custom for models defined outside the crate
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ShadingModel {
+    #[default]
+    Phong,
+    BlinnPhong,
+    Lambert,
+    Pbr {
+        roughness: f64,
+        metallic: f64,
+    },
+    Custom(&'static dyn Brdf),
+}
+
+impl PartialEq for ShadingModel {
+    fn eq(&self, other: &Self) -> bool {
+        use ShadingModel::*;
+        match (self, other) {
+            (Phong, Phong) | (BlinnPhong, BlinnPhong) | (Lambert, Lambert) => true,
+            (
+                Pbr {
+                    roughness: r1,
+                    metallic: m1,
+                },
+                Pbr {
+                    roughness: r2,
+                    metallic: m2,
+                },
+            ) => r1 == r2 && m1 == m2,
+            (Custom(a), Custom(b)) => core::ptr::addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl Brdf for ShadingModel {
+    fn shade(&self, material: &Material, sample: &LightSample) -> Color {
+        match *self {
+            ShadingModel::Phong => Phong.shade(material, sample),
+            ShadingModel::BlinnPhong => BlinnPhong.shade(material, sample),
+            ShadingModel::Lambert => Lambert.shade(material, sample),
+            ShadingModel::Pbr {
+                roughness,
+                metallic,
+            } => CookTorrance {
+                roughness,
+                metallic,
+            }
+            .shade(material, sample),
+            ShadingModel::Custom(brdf) => brdf.shade(material, sample),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::material::lighting;
+    use crate::tuple::Point;
+
+    fn facing() -> LightSample {
+        LightSample {
+            intensity: Color::new(1.0, 1.0, 1.0),
+            lightv: Vector::new(0.0, 0.0, -1.0),
+            eyev: Vector::new(0.0, 0.0, -1.0),
+            normalv: Vector::new(0.0, 0.0, -1.0),
+            diffuse: true,
+            specular: true,
+        }
+    }
+
+    #[test]
+    fn models_agree_head_on() {
+        let m = Material::default();
+        // diffuse 0.9 plus the full highlight
+        assert_eq!(Phong.shade(&m, &facing()), Color::new(1.8, 1.8, 1.8));
+        assert_eq!(BlinnPhong.shade(&m, &facing()), Color::new(1.8, 1.8, 1.8));
+        assert_eq!(Lambert.shade(&m, &facing()), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn highlights_peak_in_the_mirror_direction() {
+        let m = Material::default();
+        let s = 2f64.sqrt() / 2.0;
+        let mirror = LightSample {
+            lightv: Vector::new(0.0, s, -s),
+            eyev: Vector::new(0.0, -s, -s),
+            ..facing()
+        };
+        let off = LightSample {
+            eyev: Vector::new(0.0, -0.6, -0.8),
+            ..mirror
+        };
+        for model in [&Phong as &dyn Brdf, &BlinnPhong] {
+            let lambert = Lambert.shade(&m, &mirror).red;
+            assert!((model.shade(&m, &mirror).red - lambert - 0.9).abs() < 1e-9);
+            let off_peak = model.shade(&m, &off).red - lambert;
+            assert!(off_peak > 0.0 && off_peak < 0.9);
+        }
+    }
+
+    #[test]
+    fn samples_can_leave_out_diffuse_or_specular() {
+        let m = Material::default();
+        let no_specular = LightSample {
+            specular: false,
+            ..facing()
+        };
+        let no_diffuse = LightSample {
+            diffuse: false,
+            ..facing()
+        };
+        let pbr = CookTorrance {
+            roughness: 0.3,
+            metallic: 0.0,
+        };
+        for model in [&Phong as &dyn Brdf, &BlinnPhong, &pbr] {
+            let both = model.shade(&m, &facing());
+            let split = model.shade(&m, &no_specular) + model.shade(&m, &no_diffuse);
+            assert_eq!(split, both);
+        }
+        assert_eq!(Phong.shade(&m, &no_specular), Color::new(0.9, 0.9, 0.9));
+        assert_eq!(Lambert.shade(&m, &no_diffuse), BLACK);
+    }
+
+    #[test]
+    fn rough_dielectric_is_close_to_lambert() {
+        let m = Material {
+            color: Color::new(0.5, 0.5, 0.5),
+            ..Default::default()
+        };
+        let pbr = CookTorrance {
+            roughness: 1.0,
+            metallic: 0.0,
+        };
+        let c = pbr.shade(&m, &facing());
+        assert!((c.red - 0.5).abs() < 0.1);
+        // a polished metal has a much stronger highlight head on
+        let metal = CookTorrance {
+            roughness: 0.2,
+            metallic: 1.0,
+        };
+        assert!(metal.shade(&m, &facing()).red > 2.0 * c.red);
+    }
+
+    #[test]
+    fn sheen_grows_towards_grazing_light() {
+        let m = Material {
+            color: Color::new(1.0, 0.0, 0.0),
+            sheen: 1.0,
+            sheen_tint: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(Sheen.shade(&m, &facing()), BLACK);
+        // light skimming the surface from the far side of the eye
+        let grazing = LightSample {
+            lightv: Vector::new(0.0, 0.95, -0.2).normalize(),
+            eyev: Vector::new(0.0, -0.6, -0.8),
+            ..facing()
+        };
+        let c = Sheen.shade(&m, &grazing);
+        assert!(c.red > 0.0 && c.red > c.green);
+        assert_eq!(c.green, c.blue);
+        // added on top of the model by lighting
+        let light = PointLight::new(Point::new(0.0, 9.5, -2.0), Color::new(1.0, 1.0, 1.0)).into();
+        let eyev = Vector::new(0.0, -0.6, -0.8);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let plain = Material { sheen: 0.0, ..m };
+        let with = lighting(m, &light, Point::zero(), eyev, normalv, false);
+        let without = lighting(plain, &light, Point::zero(), eyev, normalv, false);
+        assert!(with.green > without.green);
+    }
+
+    #[derive(Debug)]
+    struct Flat;
+
+    impl Brdf for Flat {
+        fn shade(&self, material: &Material, _: &LightSample) -> Color {
+            material.color
+        }
+    }
+
+    static FLAT: Flat = Flat;
+
+    #[test]
+    fn custom_models_plug_into_lighting() {
+        let m = Material {
+            color: Color::new(0.2, 0.4, 0.6),
+            ambient: 0.0,
+            model: ShadingModel::Custom(&FLAT),
+            ..Default::default()
+        };
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let c = lighting(m, &light, Point::zero(), eyev, eyev, false);
+        assert_eq!(c, Color::new(0.2, 0.4, 0.6));
+        assert_eq!(m.model, ShadingModel::Custom(&FLAT));
+        assert_ne!(m.model, ShadingModel::Phong);
+    }
+}
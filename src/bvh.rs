@@ -0,0 +1,321 @@
+use crate::bounds::{BoundingBox, BoundingSphere};
+use crate::intersection::Intersections;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
+use crate::ray::Ray;
+
+// objects per leaf before a node is split
+const LEAF_SIZE: usize = 4;
+
+#[derive(Debug, Clone)]
+enum Node {
+    // objects order[start..end]
+    Leaf {
+        bounds: BoundingBox,
+        start: usize,
+        end: usize,
+    },
+    Inner {
+        bounds: BoundingBox,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounds(&self) -> &BoundingBox {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Inner { bounds, .. } => bounds,
+        }
+    }
+}
+
+// bounding volume hierarchy over a world's objects. children are always
+// stored after their parent, so bounds can be refit in one backwards pass
+// when objects move without changing the tree itself
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+    // per object, tested before the object itself in the leaves. rotated
+    // and stretched objects fill little of their box, and this rejects
+    // most rays the box lets through without transforming the ray
+    spheres: Vec<BoundingSphere>,
+    // the objects it was built over, in order
+    ids: Vec<u32>,
+}
+
+impl Bvh {
+    pub fn new(objects: &[Object]) -> Bvh {
+        let bounds = objects.iter().map(|o| o.bounds()).collect::<Vec<_>>();
+        let mut bvh = Bvh {
+            nodes: vec![],
+            order: (0..objects.len()).collect(),
+            spheres: objects.iter().map(|o| o.bounding_sphere()).collect(),
+            ids: objects.iter().map(|o| o.id()).collect(),
+        };
+        if !objects.is_empty() {
+            bvh.split(&bounds, 0, objects.len());
+        }
+        bvh
+    }
+
+    // adds the node for order[start..end] and returns its index
+    fn split(&mut self, bounds: &[BoundingBox], start: usize, end: usize) -> usize {
+        let mut node_bounds = BoundingBox::empty();
+        for &i in &self.order[start..end] {
+            node_bounds.merge(&bounds[i]);
+        }
+        let index = self.nodes.len();
+        self.nodes.push(Node::Leaf {
+            bounds: node_bounds,
+            start,
+            end,
+        });
+        if end - start <= LEAF_SIZE {
+            return index;
+        }
+
+        // median split along the axis where the centers spread the most
+        let mut centers = BoundingBox::empty();
+        for &i in &self.order[start..end] {
+            centers.add_point(bounds[i].center());
+        }
+        let extent = centers.max.0 - centers.min.0;
+        let axis = |b: &BoundingBox| {
+            let c = b.center().0;
+            if extent.x >= extent.y && extent.x >= extent.z {
+                c.x
+            } else if extent.y >= extent.z {
+                c.y
+            } else {
+                c.z
+            }
+        };
+        self.order[start..end].sort_by(|&a, &b| axis(&bounds[a]).total_cmp(&axis(&bounds[b])));
+
+        let mid = (start + end) / 2;
+        let left = self.split(bounds, start, mid);
+        let right = self.split(bounds, mid, end);
+        self.nodes[index] = Node::Inner {
+            bounds: node_bounds,
+            left,
+            right,
+        };
+        index
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.nodes
+            .first()
+            .map_or(BoundingBox::empty(), |n| *n.bounds())
+    }
+
+    // every node's box with its depth below the root, parents first
+    pub fn node_bounds(&self) -> Vec<(BoundingBox, usize)> {
+        let mut depth = vec![0; self.nodes.len()];
+        let mut out = Vec::with_capacity(self.nodes.len());
+        for (n, node) in self.nodes.iter().enumerate() {
+            if let Node::Inner { left, right, .. } = *node {
+                depth[left] = depth[n] + 1;
+                depth[right] = depth[n] + 1;
+            }
+            out.push((*node.bounds(), depth[n]));
+        }
+        out
+    }
+
+    // a hierarchy built from a different object list must not be used.
+    // walks every object, so rays only check object_count
+    pub fn matches(&self, objects: &[Object]) -> bool {
+        self.ids.iter().copied().eq(objects.iter().map(|o| o.id()))
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    // recomputes every node's bounds for objects that moved, keeping the
+    // tree shape. much cheaper than a rebuild but the tree gets looser the
+    // further objects drift from where they were at build time
+    pub fn refit(&mut self, objects: &[Object]) {
+        self.spheres = objects.iter().map(|o| o.bounding_sphere()).collect();
+        for n in (0..self.nodes.len()).rev() {
+            let bounds = match self.nodes[n] {
+                Node::Leaf { start, end, .. } => {
+                    let mut b = BoundingBox::empty();
+                    for &i in &self.order[start..end] {
+                        b.merge(&objects[i].bounds());
+                    }
+                    b
+                }
+                Node::Inner { left, right, .. } => {
+                    let mut b = *self.nodes[left].bounds();
+                    b.merge(self.nodes[right].bounds());
+                    b
+                }
+            };
+            match &mut self.nodes[n] {
+                Node::Leaf { bounds: b, .. } | Node::Inner { bounds: b, .. } => *b = bounds,
+            }
+        }
+    }
+
+    // appends the intersections with every object whose leaf the ray
+    // reaches, unsorted
+    pub fn intersect<'a>(&self, objects: &'a [Object], ray: Ray, out: &mut Intersections<'a>) {
+        self.intersect_within(objects, ray, f64::NEG_INFINITY, f64::INFINITY, out);
+    }
+
+    // same for t_min <= t <= t_max, nodes the ray only passes through
+    // outside the range are skipped
+    pub fn intersect_within<'a>(
+        &self,
+        objects: &'a [Object],
+        ray: Ray,
+        t_min: f64,
+        t_max: f64,
+        out: &mut Intersections<'a>,
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let bounded = t_min.is_finite() || t_max.is_finite();
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            match node.bounds().intersection(ray) {
+                Some((enter, exit)) if enter <= t_max && exit >= t_min => {}
+                _ => continue,
+            }
+            match *node {
+                Node::Leaf { start, end, .. } => {
+                    for &i in &self.order[start..end] {
+                        if !self.spheres[i].intersects(ray) {
+                            continue;
+                        }
+                        if bounded {
+                            out.concat(objects[i].intersect_within(ray, t_min, t_max));
+                        } else {
+                            out.concat(objects[i].intersect(ray));
+                        }
+                    }
+                }
+                Node::Inner { left, right, .. } => {
+                    stack.push(right);
+                    stack.push(left);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::sphere;
+    use crate::transformations::{rotation_z, scaling, translation};
+    use crate::tuple::{Point, Vector};
+
+    fn row_of_spheres(n: usize) -> Vec<Object> {
+        (0..n)
+            .map(|i| sphere().set_transform(translation(i as f64 * 3.0, 0.0, 0.0)))
+            .collect()
+    }
+
+    fn hits(objects: &[Object], bvh: &Bvh, ray: Ray) -> Vec<(f64, u32)> {
+        let mut xs = Intersections::new();
+        bvh.intersect(objects, ray, &mut xs);
+        xs.sort();
+        xs.into_iter().map(|i| (i.t, i.object.id())).collect()
+    }
+
+    #[test]
+    fn building_splits_into_small_leaves() {
+        let objects = row_of_spheres(20);
+        let bvh = Bvh::new(&objects);
+        assert!(bvh.node_count() > 1);
+        assert_eq!(bvh.bounds().min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(bvh.bounds().max, Point::new(58.0, 1.0, 1.0));
+        assert!(Bvh::new(&[]).bounds().is_empty());
+        let nodes = bvh.node_bounds();
+        assert_eq!(nodes.len(), bvh.node_count());
+        assert_eq!(nodes[0], (bvh.bounds(), 0));
+        assert!(nodes.iter().skip(1).all(|(_, depth)| *depth > 0));
+    }
+
+    #[test]
+    fn intersecting_only_visits_hit_objects() {
+        let objects = row_of_spheres(20);
+        let bvh = Bvh::new(&objects);
+        let ray = Ray::new(Point::new(30.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            hits(&objects, &bvh, ray),
+            vec![(4.0, objects[10].id()), (6.0, objects[10].id())]
+        );
+        let miss = Ray::new(Point::new(30.0, 5.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(hits(&objects, &bvh, miss).is_empty());
+    }
+
+    #[test]
+    fn intersecting_within_a_range() {
+        let objects = row_of_spheres(20);
+        let bvh = Bvh::new(&objects);
+        // along the row, through the first few spheres only
+        let ray = Ray::new(Point::new(-5.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let mut xs = Intersections::new();
+        bvh.intersect_within(&objects, ray, 0.0, 9.5, &mut xs);
+        xs.sort();
+        let ts = xs.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(ts, vec![4.0, 6.0, 7.0, 9.0]);
+    }
+
+    #[test]
+    fn refit_follows_moved_objects() {
+        let mut objects = row_of_spheres(20);
+        let mut bvh = Bvh::new(&objects);
+        objects[10].update_transform(translation(30.0, 10.0, 0.0) * scaling(2.0, 2.0, 2.0));
+        let ray = Ray::new(Point::new(30.0, 10.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(hits(&objects, &bvh, ray).is_empty());
+        bvh.refit(&objects);
+        assert_eq!(bvh.bounds().max, Point::new(58.0, 12.0, 2.0));
+        assert_eq!(
+            hits(&objects, &bvh, ray),
+            vec![(3.0, objects[10].id()), (7.0, objects[10].id())]
+        );
+    }
+
+    #[test]
+    fn sphere_check_keeps_hits_of_turned_objects() {
+        let objects: Vec<_> = (0..10)
+            .map(|i| {
+                sphere().set_transform(
+                    translation(i as f64 * 3.0, 0.0, 0.0)
+                        * rotation_z(0.7)
+                        * scaling(1.5, 0.1, 0.1),
+                )
+            })
+            .collect();
+        let bvh = Bvh::new(&objects);
+        for k in 0..60 {
+            let x = k as f64 * 0.5 - 1.0;
+            let ray = Ray::new(Point::new(x, 0.3, -5.0), Vector::new(0.0, 0.0, 1.0));
+            let mut expected = objects
+                .iter()
+                .flat_map(|o| {
+                    o.intersect(ray)
+                        .into_iter()
+                        .map(|i| (i.t, i.object.id()))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+            assert_eq!(hits(&objects, &bvh, ray), expected);
+        }
+    }
+}
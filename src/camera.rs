@@ -1,15 +1,21 @@
+use crate::bounds::BoundingBox;
 use crate::canvas::Canvas;
+use crate::color::{Color, BLACK};
 use crate::matrix::Matrix4;
+use crate::plate::Plate;
+use crate::profile::span;
 use crate::ray::Ray;
-use crate::tuple::Point;
+use crate::sampling::Sampling;
+use crate::shutter::Shutter;
+use crate::transformations::{self, Transformable};
+use crate::tuple::{Point, Vector};
 use crate::world::World;
 use rayon::prelude::*;
 
+#[derive(Debug)]
 pub struct Camera {
     hsize: u32,
     vsize: u32,
-
-    #[allow(dead_code)]
     field_of_view: f64,
 
     half_width: f64,
@@ -17,6 +23,133 @@ pub struct Camera {
     pixel_size: f64,
     transform: Matrix4,
     inv_transform: Matrix4,
+    ray_cache: Option<RayCache>,
+    background: Option<Plate>,
+    // rays per pixel, see set_samples
+    samples: u32,
+    sampling: Sampling,
+    shutter: Shutter,
+}
+
+// primary ray directions for every pixel center, unnormalized so subpixel
+// jitter can be added on top with the per pixel steps
+#[derive(Debug)]
+struct RayCache {
+    origin: Point,
+    directions: Vec<Vector>,
+    step_x: Vector,
+    step_y: Vector,
+}
+
+// objects split by whether their bounds can show up in the camera's view,
+// as indices into the world's objects
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Culling {
+    pub visible: Vec<usize>,
+    pub culled: Vec<usize>,
+}
+
+impl Culling {
+    pub fn culled_fraction(&self) -> f64 {
+        let total = self.visible.len() + self.culled.len();
+        if total == 0 {
+            0.0
+        } else {
+            self.culled.len() as f64 / total as f64
+        }
+    }
+}
+
+// a block of pixels, x and y are its top left corner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // the part of the rect inside an image of the given size
+    pub fn clipped(&self, width: u32, height: u32) -> Rect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Rect {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+// part of the image in normalized coordinates, 0 0 is the top left corner
+// and 1 1 the bottom right, so the same window works at any resolution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropWindow {
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+    // extra pixels rendered on every side, for filters reading past the
+    // edge of the window
+    pub overscan: u32,
+}
+
+impl CropWindow {
+    pub fn new(x_min: f64, y_min: f64, x_max: f64, y_max: f64) -> CropWindow {
+        CropWindow {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+            overscan: 0,
+        }
+    }
+
+    pub fn with_overscan(mut self, overscan: u32) -> CropWindow {
+        self.overscan = overscan;
+        self
+    }
+
+    // every pixel the window touches in an image of the given size,
+    // overscan included and cut off at the image's edges
+    pub fn to_rect(&self, width: u32, height: u32) -> Rect {
+        let span = |min: f64, max: f64, size: u32| {
+            let start = (min.clamp(0.0, 1.0) * size as f64).floor() as u32;
+            let end = (max.clamp(0.0, 1.0) * size as f64).ceil() as u32;
+            let end = (end + self.overscan).min(size);
+            let start = start.saturating_sub(self.overscan).min(end);
+            (start, end - start)
+        };
+        let (x, width) = span(self.x_min, self.x_max, width);
+        let (y, height) = span(self.y_min, self.y_max, height);
+        Rect::new(x, y, width, height)
+    }
+}
+
+// moves the camera itself through the world, the view transform is the
+// inverse of the camera placement so m is undone on the right
+impl Transformable for Camera {
+    fn transformed(mut self, m: &Matrix4) -> Camera {
+        let inv = m.inverse().expect("Fail to inverse camera matrix");
+        let transform = self.transform.clone() * inv;
+        self.set_transform(transform);
+        self
+    }
 }
 
 impl Camera {
@@ -39,53 +172,348 @@ impl Camera {
             pixel_size,
             transform: Matrix4::identity(4),
             inv_transform: Matrix4::identity(4),
+            ray_cache: None,
+            background: None,
+            samples: 1,
+            sampling: Sampling::default(),
+            shutter: Shutter::default(),
         }
     }
 
     pub fn set_transform(&mut self, transform: Matrix4) {
         self.inv_transform = transform.inverse().expect("Fail to inverse camera matrix");
         self.transform = transform;
+        if self.ray_cache.is_some() {
+            self.ray_cache = Some(self.build_ray_cache());
+        }
     }
 
-    pub fn ray_for_pixel(&self, x: u32, y: u32) -> Ray {
-        let xoffset = (x as f64 + 0.5) * self.pixel_size;
-        let yoffset = (y as f64 + 0.5) * self.pixel_size;
+    // keeps every primary ray direction around between frames, useful for
+    // animations with a static camera. the cache is rebuilt whenever the
+    // transform changes
+    pub fn set_ray_cache(&mut self, enabled: bool) {
+        self.ray_cache = if enabled {
+            Some(self.build_ray_cache())
+        } else {
+            None
+        };
+    }
+
+    pub fn has_ray_cache(&self) -> bool {
+        self.ray_cache.is_some()
+    }
+
+    // a photograph behind the render, shown where primary rays miss and
+    // through shadow catchers
+    pub fn set_background(&mut self, plate: Option<Plate>) {
+        self.background = plate;
+    }
+
+    pub fn background(&self) -> Option<&Plate> {
+        self.background.as_ref()
+    }
 
+    // anti aliasing, render averages this many rays per pixel jittered
+    // across it instead of one through its center. the jitter is the same
+    // from render to render
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples.max(1);
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    // where the anti aliasing jitter comes from. motion blur times are
+    // spread evenly over the shutter and take nothing from it
+    pub fn set_sampling(&mut self, sampling: Sampling) {
+        self.sampling = sampling;
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
+    // when the exposure starts and ends, for motion blur. renders of a
+    // single world don't move so only render_motion_blurred looks at it
+    pub fn set_shutter(&mut self, shutter: Shutter) {
+        self.shutter = shutter;
+    }
+
+    pub fn shutter(&self) -> Shutter {
+        self.shutter
+    }
+
+    fn build_ray_cache(&self) -> RayCache {
+        let _span = span!(INFO, "build_ray_cache");
+        let origin = self.position();
+        let directions = (0..self.vsize)
+            .flat_map(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixel_direction(origin, x as f64 + 0.5, y as f64 + 0.5))
+            .collect();
+        let step = |x: f64, y: f64| Vector::try_from(&self.inv_transform * Vector::new(x, y, 0.0));
+        RayCache {
+            origin,
+            directions,
+            step_x: step(-self.pixel_size, 0.0).unwrap(),
+            step_y: step(0.0, -self.pixel_size).unwrap(),
+        }
+    }
+
+    // direction from origin through the continuous pixel coordinate (px, py)
+    fn pixel_direction(&self, origin: Point, px: f64, py: f64) -> Vector {
         // untransformed coordinates in world space
-        let world_x = self.half_width - xoffset;
-        let world_y = self.half_height - yoffset;
+        let world_x = self.half_width - px * self.pixel_size;
+        let world_y = self.half_height - py * self.pixel_size;
 
-        // camera matrix to transform the canvas point and origin
-        // canvas at z = -1
+        // camera matrix to transform the canvas point, canvas at z = -1
         let pixel =
             Point::try_from(&self.inv_transform * Point::new(world_x, world_y, -1.0)).unwrap();
-        let origin = Point::try_from(&self.inv_transform * Point::new(0.0, 0.0, 0.0)).unwrap();
-        let direction = (pixel - origin).normalize();
-        Ray::new(origin, direction)
+        pixel - origin
     }
 
-    pub fn render(&self, world: &World) -> Canvas {
-        let mut image = Canvas::new(self.hsize as isize, self.vsize as isize);
+    // keeps the camera where it is and turns it to face target
+    pub fn aim_at(&mut self, target: Point) {
+        self.set_transform(transformations::aim(self.position(), target));
+    }
+
+    pub fn ray_for_pixel(&self, x: u32, y: u32) -> Ray {
+        self.ray_for_subpixel(x, y, 0.0, 0.0)
+    }
+
+    // ray through pixel (x, y) moved by (dx, dy) pixels from its center,
+    // for jittered sampling
+    pub fn ray_for_subpixel(&self, x: u32, y: u32, dx: f64, dy: f64) -> Ray {
+        if let Some(cache) = &self.ray_cache {
+            let center = cache.directions[(y * self.hsize + x) as usize];
+            let direction = if dx == 0.0 && dy == 0.0 {
+                center
+            } else {
+                center + cache.step_x * dx + cache.step_y * dy
+            };
+            return Ray::new(cache.origin, direction.normalize());
+        }
+        let origin = self.position();
+        let direction = self.pixel_direction(origin, x as f64 + 0.5 + dx, y as f64 + 0.5 + dy);
+        Ray::new(origin, direction.normalize())
+    }
+
+    // inverse of ray_for_pixel, continuous pixel coordinates of a world point
+    // where pixel centers sit at +0.5, None when the point is behind the camera
+    pub fn project(&self, point: Point) -> Option<(f64, f64)> {
+        let p = &self.transform * point;
+        if p.z > -crate::tuple::EPSILON {
+            return None;
+        }
+        let sx = p.x / -p.z;
+        let sy = p.y / -p.z;
+        Some((
+            (self.half_width - sx) / self.pixel_size,
+            (self.half_height - sy) / self.pixel_size,
+        ))
+    }
+
+    pub fn position(&self) -> Point {
+        Point::try_from(&self.inv_transform * Point::new(0.0, 0.0, 0.0)).unwrap()
+    }
+
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.transform
+    }
+
+    pub fn hsize(&self) -> u32 {
+        self.hsize
+    }
 
-        (0..self.vsize)
+    pub fn vsize(&self) -> u32 {
+        self.vsize
+    }
+
+    // evaluates f for the ray through every pixel, results are row major
+    pub fn render_with<T, F>(&self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(Ray) -> T + Sync,
+    {
+        self.render_pixels_with(|x, y| f(self.ray_for_pixel(x, y)))
+    }
+
+    // same for callers making their own rays, e.g. to jitter them
+    pub fn render_pixels_with<T, F>(&self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(u32, u32) -> T + Sync,
+    {
+        self.render_region_with(Rect::new(0, 0, self.hsize, self.vsize), f)
+    }
+
+    // f for the pixels of one block, row major within it. the block is
+    // clipped to the image first. results are in pixel order whichever
+    // thread made them, so as long as f only depends on its pixel the
+    // output is the same for any number of threads
+    pub fn render_region_with<T, F>(&self, rect: Rect, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(u32, u32) -> T + Sync,
+    {
+        let rect = rect.clipped(self.hsize, self.vsize);
+        let _span = span!(INFO, "render", width = rect.width, height = rect.height);
+        (rect.y..rect.y + rect.height)
             .into_par_iter()
-            .flat_map(|y| (0..self.hsize).into_par_iter().map(move |x| (x, y)))
-            .map(|(x, y)| (x, y, self.ray_for_pixel(x, y)))
-            .map(|(x, y, ray)| (x, y, world.color_at(ray)))
-            .collect::<Vec<_>>()
-            .iter()
-            .for_each(|(x, y, color)| {
-                //image.pixels[(y * image.width as u32 + x) as usize] = color;
-                image.write_pixel(*x as isize, *y as isize, *color);
-            });
+            .flat_map_iter(|y| {
+                let _span = span!(DEBUG, "render_row", y);
+                (rect.x..rect.x + rect.width)
+                    .map(|x| f(x, y))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // renders only the pixels in rect over an earlier render of the same
+    // view, e.g. after changing a material that only shows up there
+    pub fn refresh_region(&self, world: &World, canvas: &mut Canvas, rect: Rect) {
+        assert_eq!(
+            (canvas.width, canvas.height),
+            (self.hsize as isize, self.vsize as isize)
+        );
+        let rect = rect.clipped(self.hsize, self.vsize);
+        let colors = self.render_region_with(rect, |x, y| self.pixel_color(world, x, y));
+        for (i, color) in colors.into_iter().enumerate() {
+            let x = rect.x + i as u32 % rect.width;
+            let y = rect.y + i as u32 / rect.width;
+            canvas.write_pixel(x as isize, y as isize, color);
+        }
+    }
+
+    // renders a crop window into its place in a full size canvas, as when
+    // only a damaged part of a frame is rendered again. returns the
+    // pixels that were written
+    pub fn render_crop(&self, world: &World, canvas: &mut Canvas, window: CropWindow) -> Rect {
+        let rect = window.to_rect(self.hsize, self.vsize);
+        self.refresh_region(world, canvas, rect);
+        rect
+    }
+
+    // true when a world space box lies completely outside one of the four
+    // side planes of the view pyramid. conservative, a box that is only
+    // outside the frustum as a whole may still be reported as visible
+    pub fn outside_frustum(&self, bounds: &BoundingBox) -> bool {
+        if bounds.is_empty() {
+            return true;
+        }
+        let corners = bounds.corners().map(|c| &self.transform * c);
+        // camera space looks down -z, a point is inside while
+        // |x| <= half_width * -z and |y| <= half_height * -z
+        let outside =
+            |f: &dyn Fn(f64, f64, f64) -> f64| corners.iter().all(|c| f(c.x, c.y, c.z) > 0.0);
+        outside(&|x, _, z| x + self.half_width * z)
+            || outside(&|x, _, z| -x + self.half_width * z)
+            || outside(&|_, y, z| y + self.half_height * z)
+            || outside(&|_, y, z| -y + self.half_height * z)
+    }
+
+    pub fn cull(&self, world: &World) -> Culling {
+        let _span = span!(INFO, "cull", objects = world.objects.len());
+        let mut culling = Culling::default();
+        for (i, object) in world.objects.iter().enumerate() {
+            if self.outside_frustum(&object.bounds()) {
+                culling.culled.push(i);
+            } else {
+                culling.visible.push(i);
+            }
+        }
+        culling
+    }
+
+    // renders with primary rays only tested against objects in view,
+    // shadows and portals still see the whole world. pays off when most of
+    // the scene is off screen
+    pub fn render_culled(&self, world: &World) -> (Canvas, Culling) {
+        let culling = self.cull(world);
+        let mut image = Canvas::new(self.hsize as isize, self.vsize as isize);
+        image.pixels = self
+            .render_pixels_with(|x, y| self.pixel_color_among(world, Some(&culling.visible), x, y));
+        (image, culling)
+    }
 
+    pub fn render(&self, world: &World) -> Canvas {
+        if let Some(budget) = world.settings.time_budget {
+            return self.render_for(world, budget);
+        }
+        let mut image = Canvas::new(self.hsize as isize, self.vsize as isize);
+        image.pixels = self.render_pixels_with(|x, y| self.pixel_color(world, x, y));
         image
     }
+
+    // the world through one pixel, laid over the background if there is
+    // one. with several samples their rays are jittered across the pixel
+    // and averaged
+    pub(crate) fn pixel_color(&self, world: &World, x: u32, y: u32) -> Color {
+        self.pixel_color_among(world, None, x, y)
+    }
+
+    // same with the primary rays only tested against the objects at the
+    // given indices, see World::color_at_among
+    pub(crate) fn pixel_color_among(
+        &self,
+        world: &World,
+        objects: Option<&[usize]>,
+        x: u32,
+        y: u32,
+    ) -> Color {
+        if self.samples <= 1 {
+            return self.subpixel_color_among(world, objects, x, y, 0.0, 0.0);
+        }
+        let sum = (0..self.samples).fold(BLACK, |sum, i| {
+            let dx = self.sampling.sample(0, x, y, i * 2) - 0.5;
+            let dy = self.sampling.sample(0, x, y, i * 2 + 1) - 0.5;
+            sum + self.subpixel_color_among(world, objects, x, y, dx, dy)
+        });
+        sum * (1.0 / self.samples as f64)
+    }
+
+    // same through a point (dx, dy) pixels off the pixel's center
+    pub(crate) fn subpixel_color(&self, world: &World, x: u32, y: u32, dx: f64, dy: f64) -> Color {
+        self.subpixel_color_among(world, None, x, y, dx, dy)
+    }
+
+    fn subpixel_color_among(
+        &self,
+        world: &World,
+        objects: Option<&[usize]>,
+        x: u32,
+        y: u32,
+        dx: f64,
+        dy: f64,
+    ) -> Color {
+        let ray = self.ray_for_subpixel(x, y, dx, dy);
+        match &self.background {
+            None => match objects {
+                None => world.color_at(ray),
+                Some(objects) => world.color_at_among(ray, objects),
+            },
+            Some(plate) => {
+                let (color, alpha) = match objects {
+                    None => world.color_alpha_at(ray),
+                    Some(objects) => world.color_alpha_at_among(ray, objects),
+                };
+                let (px, py) = (x as f64 + 0.5 + dx, y as f64 + 0.5 + dy);
+                let behind = plate.sample(px, py, self.hsize, self.vsize);
+                color + behind * (1.0 - alpha)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::color::Color;
+    use crate::material::Material;
+    use crate::sphere::sphere;
     use crate::transformations;
     use crate::tuple::Vector;
     use crate::world::default_world;
@@ -146,6 +574,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn projecting_a_point_inverts_ray_for_pixel() {
+        let mut camera = Camera::new(201, 101, PI / 2.0);
+        camera.set_transform(
+            transformations::rotation_y(PI / 4.0) * transformations::translation(0.0, -2.0, 5.0),
+        );
+        let r = camera.ray_for_pixel(30, 70);
+        let (x, y) = camera.project(r.position(3.0)).unwrap();
+        assert!((x - 30.5).abs() < 1e-6);
+        assert!((y - 70.5).abs() < 1e-6);
+        assert_eq!(camera.project(r.position(-3.0)), None);
+    }
+
     #[test]
     fn rendering_a_world_with_camera() {
         let world = default_world();
@@ -160,4 +601,245 @@ mod tests {
             Color::new(0.38066, 0.47583, 0.2855)
         );
     }
+
+    #[test]
+    fn transforming_a_camera_moves_it_through_the_world() {
+        let camera = Camera::new(11, 11, PI / 2.0)
+            .rotated_y(PI)
+            .translated(0.0, 0.0, -5.0);
+        assert_eq!(camera.position(), Point::new(0.0, 0.0, -5.0));
+        // turned around to look down +z at the origin
+        let r = camera.ray_for_pixel(5, 5);
+        assert_eq!(r.direction, Vector::new(0.0, 0.0, 1.0));
+        let image = camera.render(&default_world());
+        assert_eq!(
+            image.read_pixel(5, 5).unwrap(),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+    }
+
+    #[test]
+    fn aiming_a_camera_keeps_its_position() {
+        let mut camera = Camera::new(11, 11, PI / 2.0).translated(3.0, 4.0, -5.0);
+        camera.aim_at(Point::new(3.0, 4.0, 10.0));
+        assert_eq!(camera.position(), Point::new(3.0, 4.0, -5.0));
+        assert_eq!(
+            camera.ray_for_pixel(5, 5).direction,
+            Vector::new(0.0, 0.0, 1.0)
+        );
+        camera.aim_at(Point::new(3.0, -10.0, -5.0));
+        assert_eq!(
+            camera.ray_for_pixel(5, 5).direction,
+            Vector::new(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn cached_rays_match_computed_rays() {
+        let mut camera = Camera::new(21, 11, PI / 3.0);
+        camera.set_transform(
+            transformations::rotation_y(PI / 4.0) * transformations::translation(0.0, -2.0, 5.0),
+        );
+        let expected = camera.ray_for_pixel(7, 3);
+        let jittered = camera.ray_for_subpixel(7, 3, 0.25, -0.4);
+        camera.set_ray_cache(true);
+        assert!(camera.has_ray_cache());
+        assert_eq!(camera.ray_for_pixel(7, 3), expected);
+        assert_eq!(camera.ray_for_subpixel(7, 3, 0.25, -0.4), jittered);
+        assert_eq!(
+            camera.ray_for_subpixel(6, 3, 1.0, 0.0),
+            camera.ray_for_pixel(7, 3)
+        );
+    }
+
+    #[test]
+    fn ray_cache_follows_the_transform() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.set_ray_cache(true);
+        camera.set_transform(transformations::translation(0.0, 0.0, 5.0));
+        assert_eq!(
+            camera.ray_for_pixel(5, 5).origin,
+            Point::new(0.0, 0.0, -5.0)
+        );
+        camera.set_ray_cache(false);
+        assert!(!camera.has_ray_cache());
+        assert_eq!(
+            camera.ray_for_pixel(5, 5).origin,
+            Point::new(0.0, 0.0, -5.0)
+        );
+    }
+
+    #[test]
+    fn culling_objects_outside_the_view() {
+        let mut world = default_world();
+        let mut far_off = |x: f64, y: f64, z: f64| {
+            world
+                .objects
+                .push(sphere().set_transform(transformations::translation(x, y, z)))
+        };
+        far_off(20.0, 0.0, 0.0);
+        far_off(0.0, -30.0, 5.0);
+        far_off(0.0, 0.0, -20.0);
+        // straddles the edge of the view
+        far_off(5.5, 0.0, 0.0);
+        let mut camera = Camera::new(40, 20, PI / 2.0);
+        camera.set_transform(transformations::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let culling = camera.cull(&world);
+        assert_eq!(culling.visible, vec![0, 1, 5]);
+        assert_eq!(culling.culled, vec![2, 3, 4]);
+        assert_eq!(culling.culled_fraction(), 0.5);
+        assert!(camera.outside_frustum(&BoundingBox::empty()));
+    }
+
+    #[test]
+    fn culled_render_matches_full_render() {
+        let mut world = default_world();
+        // off screen, but still shadows the visible spheres
+        world.objects.push(
+            sphere()
+                .set_transform(transformations::translation(-9.0, 9.0, -9.0))
+                .transformed(&transformations::scaling(0.5, 0.5, 0.5)),
+        );
+        let mut camera = Camera::new(21, 21, PI / 4.0);
+        camera.set_transform(transformations::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let (image, culling) = camera.render_culled(&world);
+        assert_eq!(culling.culled, vec![2]);
+        assert_eq!(image, camera.render(&world));
+        // with a plate behind it and several samples per pixel
+        let mut plate = Canvas::new(1, 1);
+        plate.write_pixel(0, 0, Color::new(0.0, 0.0, 1.0));
+        camera.set_background(Some(Plate::new(plate)));
+        camera.set_samples(4);
+        let (image, _) = camera.render_culled(&world);
+        assert_eq!(image, camera.render(&world));
+        assert_eq!(image.read_pixel(0, 10).unwrap(), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn refreshing_a_region() {
+        let mut world = default_world();
+        let mut camera = Camera::new(11, 11, PI / 4.0);
+        camera.set_transform(transformations::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let mut image = camera.render(&world);
+        world.objects[0].material.color = Color::new(0.1, 0.1, 0.9);
+        let fresh = camera.render(&world);
+        let rect = Rect::new(3, 3, 3, 3);
+        camera.refresh_region(&world, &mut image, rect);
+        for y in 3..6 {
+            for x in 3..6 {
+                assert_eq!(
+                    image.read_pixel(x, y).unwrap(),
+                    fresh.read_pixel(x, y).unwrap()
+                );
+            }
+        }
+        // the rest is still the old render, so the images differ
+        assert_ne!(image, fresh);
+        // rects reaching past the image are cut off
+        assert_eq!(Rect::new(8, 9, 5, 5).clipped(11, 11), Rect::new(8, 9, 3, 2));
+        camera.refresh_region(&world, &mut image, Rect::new(0, 0, 20, 20));
+        assert_eq!(image, fresh);
+    }
+
+    #[test]
+    fn crop_windows_in_normalized_coordinates() {
+        let window = CropWindow::new(0.25, 0.5, 0.5, 1.0);
+        assert_eq!(window.to_rect(100, 50), Rect::new(25, 25, 25, 25));
+        // partly covered pixels are rendered whole
+        assert_eq!(window.to_rect(10, 5), Rect::new(2, 2, 3, 3));
+        // overscan grows the window until it meets the image's edges
+        let window = window.with_overscan(2);
+        assert_eq!(window.to_rect(100, 50), Rect::new(23, 23, 29, 27));
+        assert_eq!(
+            CropWindow::new(-1.0, 0.0, 0.0, 2.0).to_rect(10, 10),
+            Rect::new(0, 0, 0, 10)
+        );
+    }
+
+    #[test]
+    fn crops_land_in_place_in_the_full_canvas() {
+        let world = default_world();
+        let mut camera = Camera::new(12, 12, PI / 4.0);
+        camera.set_transform(transformations::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let full = camera.render(&world);
+        let mut image = Canvas::new(12, 12);
+        let window = CropWindow::new(0.5, 0.25, 0.75, 0.5).with_overscan(1);
+        let rect = camera.render_crop(&world, &mut image, window);
+        assert_eq!(rect, Rect::new(5, 2, 5, 5));
+        for y in 0..12 {
+            for x in 0..12 {
+                let inside = (5..10).contains(&x) && (2..7).contains(&y);
+                let expected = if inside {
+                    full.read_pixel(x, y).unwrap()
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                };
+                assert_eq!(image.read_pixel(x, y).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn jittered_samples_smooth_edges() {
+        // a flat white disc on black
+        let mut world = World::new();
+        world.lights = default_world().lights;
+        world.objects.push(sphere().set_material(Material {
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        }));
+        let mut camera = Camera::new(16, 16, PI / 4.0);
+        camera.set_transform(transformations::view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let gray = |image: &Canvas| {
+            image
+                .pixels
+                .iter()
+                .filter(|c| c.red > 0.01 && c.red < 0.99)
+                .count()
+        };
+        let aliased = camera.render(&world);
+        assert_eq!(gray(&aliased), 0);
+        camera.set_samples(16);
+        let smooth = camera.render(&world);
+        assert!(gray(&smooth) > 8);
+        // the middle and corners are as before, and the jitter repeats
+        assert_eq!(
+            smooth.read_pixel(8, 8).unwrap(),
+            aliased.read_pixel(8, 8).unwrap()
+        );
+        assert_eq!(
+            smooth.read_pixel(0, 0).unwrap(),
+            aliased.read_pixel(0, 0).unwrap()
+        );
+        assert_eq!(camera.render(&world), smooth);
+        // blue noise jitter smooths the edges just the same
+        camera.set_sampling(Sampling::BlueNoise);
+        let blue = camera.render(&world);
+        assert!(gray(&blue) > 8);
+        assert_ne!(blue, smooth);
+        camera.set_samples(0);
+        assert_eq!(camera.render(&world), aliased);
+    }
 }
@@ -1,7 +1,16 @@
 use crate::color::Color;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::profile::span;
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
 use rayon::prelude::*;
 
-#[derive(Debug, Clone)]
+// read-only image shared between decals, gobos and render threads, cloning
+// one only bumps a reference count
+pub type Texture = Arc<Canvas>;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     pub width: isize,
     pub height: isize,
@@ -12,6 +21,7 @@ pub struct Canvas {
 pub enum CanvasError {
     ReadError,
     WriteError,
+    SizeMismatch,
 }
 
 fn line_wrap(s: String) -> String {
@@ -29,13 +39,12 @@ fn line_wrap(s: String) -> String {
 impl Canvas {
     pub fn new(width: isize, height: isize) -> Canvas {
         let pixels: Vec<Color> = (0..(width * height))
-            .into_iter()
             .map(|_| Color::new(0., 0., 0.))
             .collect();
         Canvas {
             width,
             height,
-            pixels: pixels,
+            pixels,
         }
     }
 
@@ -51,13 +60,37 @@ impl Canvas {
         Ok(self.pixels[(y * self.width + x) as usize])
     }
 
+    // copies other onto this canvas with its top left corner at (x, y)
+    pub fn blit(&mut self, other: &Canvas, x: isize, y: isize) -> &mut Canvas {
+        for oy in 0..other.height {
+            for ox in 0..other.width {
+                let color = other.read_pixel(ox, oy).unwrap();
+                self.write_pixel(x + ox, y + oy, color);
+            }
+        }
+        self
+    }
+
+    pub fn fill(&mut self, color: Color) -> &mut Canvas {
+        self.pixels.iter_mut().for_each(|p| *p = color);
+        self
+    }
+
+    // packed 8 bit rgb rows with no padding, for `-f rawvideo -pix_fmt rgb24`
+    pub fn to_rgb24(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|c| c.to_rgb8()).collect()
+    }
+
     pub fn to_ppm(&self) -> String {
+        let _span = span!(INFO, "encode", format = "ppm");
         let header = format!("P3\n{} {}\n255", self.width, self.height);
-        let body = (0..self.height)
-            .into_par_iter()
+        #[cfg(feature = "std")]
+        let rows = (0..self.height).into_par_iter();
+        #[cfg(not(feature = "std"))]
+        let rows = 0..self.height;
+        let body = rows
             .map(|y| {
                 (0..self.width)
-                    .into_par_iter()
                     .map(|x| self.read_pixel(x, y).unwrap())
                     .map(|p| p.to_string())
                     .collect::<Vec<_>>()
@@ -68,6 +101,99 @@ impl Canvas {
             .join("\n");
         format!("{}\n{}\n", header, body)
     }
+
+    // reads plain (P3) ppm files such as the ones written by to_ppm
+    pub fn from_ppm(ppm: &str) -> Result<Canvas, CanvasError> {
+        let mut tokens = ppm
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or(""))
+            .flat_map(|l| l.split_whitespace());
+        if tokens.next() != Some("P3") {
+            return Err(CanvasError::ReadError);
+        }
+        let tokens = tokens.collect::<Vec<_>>();
+        let mut numbers = tokens
+            .iter()
+            .map(|t| t.parse::<f64>().map_err(|_| CanvasError::ReadError));
+        let mut next = move || numbers.next().unwrap_or(Err(CanvasError::ReadError));
+        let width = next()?;
+        let height = next()?;
+        let max = next()?;
+        // the header must agree with the samples that follow, which also
+        // keeps a bogus header from allocating a huge canvas
+        if width < 0.0
+            || height < 0.0
+            || max <= 0.0
+            || width * height * 3.0 + 3.0 != tokens.len() as f64
+        {
+            return Err(CanvasError::ReadError);
+        }
+        let mut canvas = Canvas::new(width as isize, height as isize);
+        for pixel in canvas.pixels.iter_mut() {
+            *pixel = Color::new(next()? / max, next()? / max, next()? / max);
+        }
+        Ok(canvas)
+    }
+
+    // reads plain P3 or binary P6 data, e.g. an image embedded with
+    // include_bytes!
+    pub fn from_ppm_bytes(bytes: &[u8]) -> Result<Canvas, CanvasError> {
+        if !bytes.starts_with(b"P6") {
+            let text = core::str::from_utf8(bytes).map_err(|_| CanvasError::ReadError)?;
+            return Canvas::from_ppm(text);
+        }
+        // three header numbers separated by whitespace and comments, then a
+        // single whitespace byte before the samples
+        let mut pos = 2;
+        let mut header = [0usize; 3];
+        for value in header.iter_mut() {
+            loop {
+                match bytes.get(pos) {
+                    Some(b'#') => {
+                        while bytes.get(pos).is_some_and(|&b| b != b'\n') {
+                            pos += 1;
+                        }
+                    }
+                    Some(b) if b.is_ascii_whitespace() => pos += 1,
+                    _ => break,
+                }
+            }
+            let start = pos;
+            while bytes.get(pos).is_some_and(|b| b.is_ascii_digit()) {
+                pos += 1;
+            }
+            *value = core::str::from_utf8(&bytes[start..pos])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or(CanvasError::ReadError)?;
+        }
+        let [width, height, max] = header;
+        if !bytes.get(pos).is_some_and(|b| b.is_ascii_whitespace()) || max == 0 || max > 65535 {
+            return Err(CanvasError::ReadError);
+        }
+        let samples = &bytes[pos + 1..];
+        let size = if max < 256 { 1 } else { 2 };
+        let expected = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3 * size))
+            .ok_or(CanvasError::ReadError)?;
+        if samples.len() != expected {
+            return Err(CanvasError::ReadError);
+        }
+        let mut canvas = Canvas::new(width as isize, height as isize);
+        // samples wider than a byte are big endian
+        let sample = |s: &[u8]| match s {
+            [b] => *b as f64 / max as f64,
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]) as f64 / max as f64,
+            _ => 0.0,
+        };
+        for (pixel, rgb) in canvas.pixels.iter_mut().zip(samples.chunks(3 * size)) {
+            let (r, gb) = rgb.split_at(size);
+            let (g, b) = gb.split_at(size);
+            *pixel = Color::new(sample(r), sample(g), sample(b));
+        }
+        Ok(canvas)
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +216,23 @@ mod tests {
         assert_eq!(c.read_pixel(2, 3).expect("failed to read pixel"), red);
     }
     #[test]
+    fn blit_canvas_clips_to_bounds() {
+        let red = Color::new(1., 0., 0.);
+        let mut small = Canvas::new(2, 2);
+        small.fill(red);
+        let mut c = Canvas::new(3, 3);
+        c.blit(&small, 2, -1);
+        assert_eq!(c.read_pixel(2, 0).unwrap(), red);
+        assert_eq!(c.read_pixel(2, 1).unwrap(), Color::new(0., 0., 0.));
+        assert_eq!(c.read_pixel(1, 0).unwrap(), Color::new(0., 0., 0.));
+    }
+    #[test]
+    fn raw_rgb24_bytes() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        assert_eq!(c.to_rgb24(), vec![0, 0, 0, 255, 128, 0]);
+    }
+    #[test]
     fn create_ppm_header() {
         let c1 = Color::new(1.5, 0.0, 0.0);
         let c2 = Color::new(0.0, 0.5, 0.0);
@@ -151,4 +294,47 @@ mod tests {
         let ppm = c.to_ppm();
         assert_eq!(ppm.chars().last().unwrap(), '\n');
     }
+    #[test]
+    fn read_ppm_back() {
+        let mut c = Canvas::new(10, 2);
+        c.write_pixel(3, 1, Color::new(1.0, 0.8, 0.6));
+        let read = Canvas::from_ppm(&c.to_ppm()).unwrap();
+        assert_eq!((read.width, read.height), (10, 2));
+        assert_eq!(read.to_rgb24(), c.to_rgb24());
+        let commented = "P3\n# made by hand\n1 1\n10\n10 5 0\n";
+        assert_eq!(
+            Canvas::from_ppm(commented)
+                .unwrap()
+                .read_pixel(0, 0)
+                .unwrap(),
+            Color::new(1.0, 0.5, 0.0)
+        );
+        assert!(Canvas::from_ppm("P3\n2 2\n255\n1 2 3\n").is_err());
+        assert!(Canvas::from_ppm("P6\n1 1\n255\n").is_err());
+        assert!(Canvas::from_ppm("P3\n100000 100000\n255\n0 0 0\n").is_err());
+        assert!(Canvas::from_ppm("P3\n-1 -1\n255\n0 0 0\n").is_err());
+        assert!(Canvas::from_ppm("P3\n1 1\n0\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn reading_binary_ppm_bytes() {
+        let mut expected = Canvas::new(2, 1);
+        expected.write_pixel(0, 0, Color::new(1.0, 0.0, 0.2));
+        expected.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+        let mut p6 = b"P6 # made by hand\n2 1\n255\n".to_vec();
+        p6.extend_from_slice(&[255, 0, 51, 0, 255, 0]);
+        assert_eq!(Canvas::from_ppm_bytes(&p6).unwrap(), expected);
+        let wide = [&b"P6\n1 1 65535\n"[..], &[255, 255, 0, 0, 51, 51]].concat();
+        assert_eq!(
+            Canvas::from_ppm_bytes(&wide).unwrap().pixels[0],
+            Color::new(1.0, 0.0, 0.2)
+        );
+        assert_eq!(
+            Canvas::from_ppm_bytes(expected.to_ppm().as_bytes()).unwrap(),
+            expected
+        );
+        assert!(Canvas::from_ppm_bytes(&p6[..p6.len() - 1]).is_err());
+        assert!(Canvas::from_ppm_bytes(b"P6\n99999999999 99999999999\n255\n").is_err());
+        assert!(Canvas::from_ppm_bytes(&[0xff, 0xfe]).is_err());
+    }
 }
@@ -7,18 +7,14 @@ pub struct Color {
 
 pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
 
+fn to255(f: f64) -> u8 {
+    (f * 256.).clamp(0., 255.) as u8
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fn to255(f: f64) -> u32 {
-            (f * 256.).clamp(0., 255.) as u32
-        }
-        write!(
-            f,
-            "{} {} {}",
-            to255(self.red),
-            to255(self.green),
-            to255(self.blue)
-        )
+        let [r, g, b] = self.to_rgb8();
+        write!(f, "{} {} {}", r, g, b)
     }
 }
 
@@ -26,20 +22,33 @@ impl Color {
     pub const fn new(red: f64, green: f64, blue: f64) -> Color {
         Color { red, green, blue }
     }
+
+    // relative luminance using the rec. 709 weights
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [to255(self.red), to255(self.green), to255(self.blue)]
+    }
+
+    pub fn approx_eq(&self, other: &Color, epsilon: f64) -> bool {
+        (self.red - other.red).abs() < epsilon
+            && (self.green - other.green).abs() < epsilon
+            && (self.blue - other.blue).abs() < epsilon
+    }
 }
 
 const EPSILON: f64 = 1e-5;
 
 impl PartialEq for Color {
     fn eq(&self, other: &Color) -> bool {
-        (self.red - other.red).abs() < EPSILON
-            && (self.green - other.green).abs() < EPSILON
-            && (self.blue - other.blue).abs() < EPSILON
+        self.approx_eq(other, EPSILON)
     }
 }
 
 use core::fmt;
-use std::ops::{Add, Mul, Sub};
+use core::ops::{Add, Mul, Sub};
 impl Add for Color {
     type Output = Self;
     fn add(self, other: Color) -> Color {
@@ -101,6 +110,11 @@ mod tests {
         assert_eq!(c * 2., Color::new(0.4, 0.6, 0.8));
     }
     #[test]
+    fn luminance_of_colors() {
+        assert!((Color::new(1.0, 1.0, 1.0).luminance() - 1.0).abs() < 1e-12);
+        assert_eq!(Color::new(0.0, 1.0, 0.0).luminance(), 0.7152);
+    }
+    #[test]
     fn mul_color_by_color() {
         let c1 = Color::new(1.0, 0.2, 0.4);
         let c2 = Color::new(0.9, 1.0, 0.1);
@@ -0,0 +1,221 @@
+use crate::canvas::{Canvas, CanvasError};
+use crate::color::Color;
+
+// per pixel differences between two renders of the same size
+#[derive(Debug, Clone)]
+pub struct CanvasDiff {
+    // absolute difference of each channel
+    pub error: Canvas,
+    pub max_error: f64,
+    // pixels differing by more than one 8 bit step in any channel
+    pub differing_pixels: usize,
+}
+
+impl CanvasDiff {
+    pub fn is_identical(&self) -> bool {
+        self.differing_pixels == 0
+    }
+}
+
+pub fn diff_canvases(a: &Canvas, b: &Canvas) -> Result<CanvasDiff, CanvasError> {
+    same_size(a, b)?;
+    let mut error = Canvas::new(a.width, a.height);
+    let mut max_error: f64 = 0.0;
+    let mut differing_pixels = 0;
+    for (i, (ca, cb)) in a.pixels.iter().zip(&b.pixels).enumerate() {
+        let e = Color::new(
+            (ca.red - cb.red).abs(),
+            (ca.green - cb.green).abs(),
+            (ca.blue - cb.blue).abs(),
+        );
+        let worst = e.red.max(e.green).max(e.blue);
+        if worst > 1.0 / 255.0 {
+            differing_pixels += 1;
+        }
+        max_error = max_error.max(worst);
+        error.pixels[i] = e;
+    }
+    Ok(CanvasDiff {
+        error,
+        max_error,
+        differing_pixels,
+    })
+}
+
+fn same_size(a: &Canvas, b: &Canvas) -> Result<(), CanvasError> {
+    if a.width != b.width || a.height != b.height {
+        Err(CanvasError::SizeMismatch)
+    } else {
+        Ok(())
+    }
+}
+
+// root mean square error of each channel
+pub fn rmse(a: &Canvas, b: &Canvas) -> Result<Color, CanvasError> {
+    same_size(a, b)?;
+    let n = a.pixels.len().max(1) as f64;
+    let sum = a
+        .pixels
+        .iter()
+        .zip(&b.pixels)
+        .map(|(ca, cb)| {
+            let d = *ca - *cb;
+            d * d
+        })
+        .fold(Color::new(0.0, 0.0, 0.0), |acc, c| acc + c);
+    Ok(Color::new(
+        (sum.red / n).sqrt(),
+        (sum.green / n).sqrt(),
+        (sum.blue / n).sqrt(),
+    ))
+}
+
+const SSIM_WINDOW: isize = 8;
+
+// mean structural similarity of the luminance over 8x8 windows, 1 for
+// identical images and dropping towards 0 as structure differs
+pub fn ssim(a: &Canvas, b: &Canvas) -> Result<f64, CanvasError> {
+    same_size(a, b)?;
+    const C1: f64 = 0.01 * 0.01;
+    const C2: f64 = 0.03 * 0.03;
+    let luma =
+        |c: &Canvas, x: isize, y: isize| c.read_pixel(x, y).unwrap().luminance().clamp(0.0, 1.0);
+    let mut total = 0.0;
+    let mut windows = 0;
+    for wy in (0..a.height).step_by(SSIM_WINDOW as usize) {
+        for wx in (0..a.width).step_by(SSIM_WINDOW as usize) {
+            let coords = (wy..(wy + SSIM_WINDOW).min(a.height))
+                .flat_map(|y| (wx..(wx + SSIM_WINDOW).min(a.width)).map(move |x| (x, y)))
+                .collect::<Vec<_>>();
+            let n = coords.len() as f64;
+            let la = coords
+                .iter()
+                .map(|&(x, y)| luma(a, x, y))
+                .collect::<Vec<_>>();
+            let lb = coords
+                .iter()
+                .map(|&(x, y)| luma(b, x, y))
+                .collect::<Vec<_>>();
+            let mean_a = la.iter().sum::<f64>() / n;
+            let mean_b = lb.iter().sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+            for (pa, pb) in la.iter().zip(&lb) {
+                var_a += (pa - mean_a) * (pa - mean_a);
+                var_b += (pb - mean_b) * (pb - mean_b);
+                cov += (pa - mean_a) * (pb - mean_b);
+            }
+            var_a /= n;
+            var_b /= n;
+            cov /= n;
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    Ok(if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    // the default world as rendered when the image was checked in. after
+    // a change meant to alter how it looks, write camera.render's ppm over
+    // src/golden/default_world.ppm
+    #[test]
+    fn default_world_matches_the_golden_image() {
+        let golden = Canvas::from_ppm(include_str!("golden/default_world.ppm")).unwrap();
+        let mut camera = Camera::new(40, 30, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.5, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let image = camera.render(&default_world());
+        assert!(ssim(&image, &golden).unwrap() > 0.99);
+        let error = rmse(&image, &golden).unwrap();
+        assert!(error.red.max(error.green).max(error.blue) < 2.0 / 255.0);
+    }
+
+    #[test]
+    fn identical_canvases_have_no_error() {
+        let mut a = Canvas::new(3, 2);
+        a.fill(Color::new(0.2, 0.4, 0.6));
+        let d = diff_canvases(&a, &a.clone()).unwrap();
+        assert!(d.is_identical());
+        assert_eq!(d.max_error, 0.0);
+    }
+
+    #[test]
+    fn error_image_holds_channel_differences() {
+        let a = Canvas::new(2, 2);
+        let mut b = Canvas::new(2, 2);
+        b.write_pixel(1, 0, Color::new(0.5, -0.25, 0.001));
+        let d = diff_canvases(&a, &b).unwrap();
+        assert_eq!(d.differing_pixels, 1);
+        assert_eq!(d.max_error, 0.5);
+        assert_eq!(
+            d.error.read_pixel(1, 0).unwrap(),
+            Color::new(0.5, 0.25, 0.001)
+        );
+        assert_eq!(d.error.read_pixel(0, 0).unwrap(), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn sizes_must_match() {
+        assert!(matches!(
+            diff_canvases(&Canvas::new(2, 2), &Canvas::new(2, 3)),
+            Err(CanvasError::SizeMismatch)
+        ));
+    }
+
+    fn checker(size: isize) -> Canvas {
+        let mut c = Canvas::new(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let v = if (x + y) % 2 == 0 { 1.0 } else { 0.0 };
+                c.write_pixel(x, y, Color::new(v, v, v));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn rmse_per_channel() {
+        let a = Canvas::new(2, 2);
+        let mut b = Canvas::new(2, 2);
+        b.fill(Color::new(0.5, 0.0, 0.0));
+        b.write_pixel(0, 0, Color::new(0.5, 1.0, 0.0));
+        assert_eq!(rmse(&a, &b).unwrap(), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(rmse(&a, &a).unwrap(), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn ssim_of_identical_images_is_one() {
+        let c = checker(20);
+        assert!((ssim(&c, &c).unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn ssim_drops_with_lost_structure() {
+        let c = checker(16);
+        let mut grey = Canvas::new(16, 16);
+        grey.fill(Color::new(0.5, 0.5, 0.5));
+        let mut slightly_off = c.clone();
+        slightly_off.write_pixel(3, 3, Color::new(0.9, 0.9, 0.9));
+        let near = ssim(&c, &slightly_off).unwrap();
+        let far = ssim(&c, &grey).unwrap();
+        assert!(near > 0.9 && near < 1.0);
+        assert!(far < 0.1);
+        assert!(ssim(&c, &Canvas::new(16, 15)).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::font;
+use crate::world::World;
+
+const PADDING: usize = 4;
+const LABEL_SCALE: usize = 1;
+
+pub type Variation<'a> = (&'a str, &'a dyn Fn(&mut World));
+
+// renders a fresh world from build for every variation after applying it,
+// so variations never leak into each other
+pub fn render_variations(
+    build: impl Fn() -> World,
+    camera: &Camera,
+    variations: &[Variation],
+) -> Vec<(String, Canvas)> {
+    variations
+        .iter()
+        .map(|(label, apply)| {
+            let mut world = build();
+            apply(&mut world);
+            (label.to_string(), camera.render(&world))
+        })
+        .collect()
+}
+
+// lays the tiles out in a grid with each label written underneath its image
+pub fn contact_sheet(tiles: &[(String, Canvas)], columns: usize, background: Color) -> Canvas {
+    let columns = columns.clamp(1, tiles.len().max(1));
+    let rows = tiles.len().div_ceil(columns);
+    let label_height = font::GLYPH_HEIGHT * LABEL_SCALE + PADDING;
+    let cell_width = tiles
+        .iter()
+        .map(|(label, c)| (c.width as usize).max(font::text_width(label, LABEL_SCALE)))
+        .max()
+        .unwrap_or(0)
+        + PADDING;
+    let cell_height = tiles
+        .iter()
+        .map(|(_, c)| c.height as usize)
+        .max()
+        .unwrap_or(0)
+        + label_height
+        + PADDING;
+
+    let mut sheet = Canvas::new(
+        (columns * cell_width + PADDING) as isize,
+        (rows * cell_height + PADDING) as isize,
+    );
+    sheet.fill(background);
+
+    let text_color = if background.red + background.green + background.blue > 1.5 {
+        Color::new(0.0, 0.0, 0.0)
+    } else {
+        Color::new(1.0, 1.0, 1.0)
+    };
+
+    for (i, (label, tile)) in tiles.iter().enumerate() {
+        let x = (PADDING + (i % columns) * cell_width) as isize;
+        let y = (PADDING + (i / columns) * cell_height) as isize;
+        sheet.blit(tile, x, y);
+        let label_y = y + tile.height + (PADDING / 2) as isize;
+        font::draw_text(&mut sheet, x, label_y, label, text_color, LABEL_SCALE);
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn sheet_dimensions() {
+        let tiles = vec![
+            ("a".to_string(), Canvas::new(10, 10)),
+            ("b".to_string(), Canvas::new(10, 10)),
+            ("c".to_string(), Canvas::new(10, 10)),
+        ];
+        let sheet = contact_sheet(&tiles, 2, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(sheet.width, (2 * (10 + PADDING) + PADDING) as isize);
+        assert_eq!(
+            sheet.height,
+            (2 * (10 + font::GLYPH_HEIGHT + 2 * PADDING) + PADDING) as isize
+        );
+    }
+
+    #[test]
+    fn tiles_are_copied_into_cells() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut tile = Canvas::new(4, 4);
+        tile.fill(red);
+        let tiles = vec![("".to_string(), Canvas::new(4, 4)), ("".to_string(), tile)];
+        let sheet = contact_sheet(&tiles, 2, Color::new(0.0, 0.0, 0.0));
+        let x = (PADDING + 4 + PADDING) as isize;
+        assert_eq!(sheet.read_pixel(x, PADDING as isize).unwrap(), red);
+    }
+
+    #[test]
+    fn variations_do_not_accumulate() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let red = |w: &mut World| w.objects[0].material.color = Color::new(1.0, 0.0, 0.0);
+        let none = |_: &mut World| {};
+        let renders =
+            render_variations(default_world, &camera, &[("red", &red), ("default", &none)]);
+        assert_eq!(renders.len(), 2);
+        assert_eq!(renders[0].0, "red");
+        assert_eq!(
+            renders[1].1.read_pixel(5, 5).unwrap(),
+            Color::new(0.38066, 0.47583, 0.2855)
+        );
+        assert_ne!(
+            renders[0].1.read_pixel(5, 5).unwrap(),
+            renders[1].1.read_pixel(5, 5).unwrap()
+        );
+    }
+}
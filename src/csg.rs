@@ -0,0 +1,323 @@
+use crate::bounds::BoundingBox;
+use crate::group::Group;
+use crate::intersection::{Intersection, Intersections};
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
+use crate::ray::Ray;
+use crate::transformations::Transformable;
+use crate::tuple::Point;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOp {
+    // everything inside either side
+    Union,
+    // only what is inside both
+    Intersection,
+    // the left side with the right carved out of it
+    Difference,
+}
+
+// one side of a csg, operands have to be closed for inside and outside
+// to make sense, meshes included
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsgOperand {
+    Object(Box<Object>),
+    // a loaded model or any other collection, see obj_parser
+    Group(Group),
+    Csg(Box<Csg>),
+}
+
+impl CsgOperand {
+    pub fn bounds(&self) -> BoundingBox {
+        match self {
+            CsgOperand::Object(o) => o.bounds(),
+            CsgOperand::Group(g) => g.bounds(),
+            CsgOperand::Csg(c) => c.bounds(),
+        }
+    }
+
+    pub fn objects(&self) -> Vec<&Object> {
+        match self {
+            CsgOperand::Object(o) => vec![o],
+            CsgOperand::Group(g) => g.objects(),
+            CsgOperand::Csg(c) => c.objects(),
+        }
+    }
+
+    pub fn objects_mut(&mut self) -> Vec<&mut Object> {
+        match self {
+            CsgOperand::Object(o) => vec![&mut **o],
+            CsgOperand::Group(g) => g.objects_mut(),
+            CsgOperand::Csg(c) => c.objects_mut(),
+        }
+    }
+
+    fn intersect<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        match self {
+            CsgOperand::Object(o) => out.concat(o.intersect(ray)),
+            CsgOperand::Group(g) => g.intersect(ray, out),
+            CsgOperand::Csg(c) => c.intersect(ray, out),
+        }
+    }
+}
+
+impl From<Object> for CsgOperand {
+    fn from(object: Object) -> CsgOperand {
+        CsgOperand::Object(Box::new(object))
+    }
+}
+
+impl From<Group> for CsgOperand {
+    fn from(group: Group) -> CsgOperand {
+        CsgOperand::Group(group)
+    }
+}
+
+impl From<Csg> for CsgOperand {
+    fn from(csg: Csg) -> CsgOperand {
+        CsgOperand::Csg(Box::new(csg))
+    }
+}
+
+impl Transformable for CsgOperand {
+    fn transformed(self, m: &Matrix4) -> CsgOperand {
+        match self {
+            CsgOperand::Object(o) => CsgOperand::Object(Box::new(o.transformed(m))),
+            CsgOperand::Group(g) => CsgOperand::Group(g.transformed(m)),
+            CsgOperand::Csg(c) => CsgOperand::Csg(Box::new(c.transformed(m))),
+        }
+    }
+}
+
+// constructive solid geometry, a shape made by combining two others.
+// hits are with the objects of the operands, so those keep their own
+// materials
+#[derive(Debug, Clone, PartialEq)]
+pub struct Csg {
+    pub op: CsgOp,
+    left: CsgOperand,
+    right: CsgOperand,
+    bounds: BoundingBox,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: impl Into<CsgOperand>, right: impl Into<CsgOperand>) -> Csg {
+        let (left, right) = (left.into(), right.into());
+        let (lb, rb) = (left.bounds(), right.bounds());
+        let bounds = match op {
+            CsgOp::Union => {
+                let mut b = lb;
+                b.merge(&rb);
+                b
+            }
+            CsgOp::Intersection => overlap(&lb, &rb),
+            CsgOp::Difference => lb,
+        };
+        Csg {
+            op,
+            left,
+            right,
+            bounds,
+        }
+    }
+
+    pub fn left(&self) -> &CsgOperand {
+        &self.left
+    }
+
+    pub fn right(&self) -> &CsgOperand {
+        &self.right
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    // every object of both operands
+    pub fn objects(&self) -> Vec<&Object> {
+        let mut out = self.left.objects();
+        out.extend(self.right.objects());
+        out
+    }
+
+    // see Group::objects_mut
+    pub fn objects_mut(&mut self) -> Vec<&mut Object> {
+        let mut out = self.left.objects_mut();
+        out.extend(self.right.objects_mut());
+        out
+    }
+
+    // whether a hit on one side is on the surface of the result, given
+    // whether the ray is inside the left and right operands at that point
+    fn allowed(&self, left_hit: bool, in_left: bool, in_right: bool) -> bool {
+        match self.op {
+            CsgOp::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+            CsgOp::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+            CsgOp::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+        }
+    }
+
+    // appends the intersections with the surface of the result, unsorted.
+    // a side the ray misses the bounds of is never intersected, and when
+    // the result can't have hits without it the other side isn't either.
+    // as the ray is then outside of that side along its whole length,
+    // whatever would be kept of the other side is kept as is
+    pub fn intersect<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        if !self.bounds.intersects(ray) {
+            return;
+        }
+        let left_in = self.left.bounds().intersects(ray);
+        let right_in = self.right.bounds().intersects(ray);
+        match (left_in, right_in) {
+            (false, false) => return,
+            (false, true) => {
+                if self.op == CsgOp::Union {
+                    self.right.intersect(ray, out);
+                }
+                return;
+            }
+            (true, false) => {
+                if self.op != CsgOp::Intersection {
+                    self.left.intersect(ray, out);
+                }
+                return;
+            }
+            (true, true) => {}
+        }
+        let (mut left, mut right) = (Intersections::new(), Intersections::new());
+        self.left.intersect(ray, &mut left);
+        if left.is_empty() && self.op != CsgOp::Union {
+            return;
+        }
+        self.right.intersect(ray, &mut right);
+        let mut all = left
+            .into_iter()
+            .map(|i| (i, true))
+            .chain(right.into_iter().map(|i| (i, false)))
+            .collect::<Vec<(Intersection, bool)>>();
+        all.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+        // every crossing of a closed surface flips between inside and out
+        let (mut in_left, mut in_right) = (false, false);
+        for (i, left_hit) in all {
+            if self.allowed(left_hit, in_left, in_right) {
+                out.push(i);
+            }
+            if left_hit {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+        }
+    }
+}
+
+impl Transformable for Csg {
+    fn transformed(self, m: &Matrix4) -> Csg {
+        Csg::new(self.op, self.left.transformed(m), self.right.transformed(m))
+    }
+}
+
+// the box both boxes cover, empty when they don't meet
+fn overlap(a: &BoundingBox, b: &BoundingBox) -> BoundingBox {
+    let min = Point::new(
+        a.min.0.x.max(b.min.0.x),
+        a.min.0.y.max(b.min.0.y),
+        a.min.0.z.max(b.min.0.z),
+    );
+    let max = Point::new(
+        a.max.0.x.min(b.max.0.x),
+        a.max.0.y.min(b.max.0.y),
+        a.max.0.z.min(b.max.0.z),
+    );
+    let b = BoundingBox::new(min, max);
+    if b.is_empty() {
+        BoundingBox::empty()
+    } else {
+        b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj_parser::parse_obj_str;
+    use crate::sphere::sphere;
+    use crate::transformations::translation;
+    use crate::tuple::Vector;
+
+    fn ts(csg: &Csg, ray: Ray) -> Vec<f64> {
+        let mut xs = Intersections::new();
+        csg.intersect(ray, &mut xs);
+        xs.sort();
+        xs.iter().map(|i| (i.t * 1e9).round() / 1e9).collect()
+    }
+
+    fn along_x(y: f64, z: f64) -> Ray {
+        Ray::new(Point::new(-5.0, y, z), Vector::new(1.0, 0.0, 0.0))
+    }
+
+    // two unit spheres overlapping between x = -0.5 and x = 0.5
+    fn pair(op: CsgOp) -> Csg {
+        Csg::new(
+            op,
+            sphere().set_transform(translation(-0.5, 0.0, 0.0)),
+            sphere().set_transform(translation(0.5, 0.0, 0.0)),
+        )
+    }
+
+    #[test]
+    fn operations_keep_the_right_surfaces() {
+        assert_eq!(ts(&pair(CsgOp::Union), along_x(0.0, 0.0)), vec![3.5, 6.5]);
+        assert_eq!(
+            ts(&pair(CsgOp::Intersection), along_x(0.0, 0.0)),
+            vec![4.5, 5.5]
+        );
+        assert_eq!(
+            ts(&pair(CsgOp::Difference), along_x(0.0, 0.0)),
+            vec![3.5, 4.5]
+        );
+        let b = pair(CsgOp::Intersection).bounds();
+        assert_eq!(b.min, Point::new(-0.5, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(0.5, 1.0, 1.0));
+    }
+
+    #[test]
+    fn rays_missing_a_side_skip_it() {
+        // a ray passing by the left sphere's box, through the right's
+        let left = sphere().set_transform(translation(-3.0, 0.0, 0.0));
+        let right = sphere().set_transform(translation(3.0, 0.0, 0.0));
+        let down = Ray::new(Point::new(3.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let csg = |op| Csg::new(op, left.clone(), right.clone());
+        assert_eq!(ts(&csg(CsgOp::Union), down), vec![4.0, 6.0]);
+        assert!(ts(&csg(CsgOp::Intersection), down).is_empty());
+        assert!(ts(&csg(CsgOp::Difference), down).is_empty());
+        let down = Ray::new(Point::new(-3.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(ts(&csg(CsgOp::Difference), down), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn meshes_and_nested_csgs_as_operands() {
+        // a closed two unit cube of triangles with a sphere bitten out of
+        // its +x face. the rays keep off the diagonals splitting the faces
+        let cube = parse_obj_str(
+            "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+             v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+             f 1 2 3 4\nf 5 8 7 6\nf 1 5 6 2\nf 4 3 7 8\nf 1 4 8 5\nf 2 6 7 3\n",
+        )
+        .unwrap()
+        .to_group();
+        let bite = sphere().set_transform(translation(1.0, 0.0, 0.0));
+        let bitten = Csg::new(CsgOp::Difference, cube, bite);
+        assert_eq!(ts(&bitten, along_x(0.0, 0.6)), vec![4.0, 5.2]);
+        assert_eq!(ts(&bitten, along_x(0.9, 0.5)), vec![4.0, 6.0]);
+        let moved = bitten.clone().transformed(&translation(0.0, 10.0, 0.0));
+        assert_eq!(ts(&moved, along_x(10.0, 0.6)), vec![4.0, 5.2]);
+        // and the result combines again
+        let ball = sphere().set_transform(translation(3.0, 0.0, 0.0));
+        let both = Csg::new(CsgOp::Union, bitten, ball);
+        assert_eq!(ts(&both, along_x(0.0, 0.6)), vec![4.0, 5.2, 7.2, 8.8]);
+        assert_eq!(both.objects().len(), 14);
+    }
+}
@@ -0,0 +1,200 @@
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::{Geometry, Object};
+use crate::ray::Ray;
+use crate::shape::{LocalHit, LocalHits};
+use crate::tuple::{Point, Vector, EPSILON};
+
+// a unit radius cylinder around the y axis, cut off below `minimum` and
+// above `maximum` (both exclusive) and capped there when closed
+
+pub fn cylinder(minimum: f64, maximum: f64, closed: bool) -> Object {
+    Object::new(Geometry::Cylinder {
+        minimum,
+        maximum,
+        closed,
+    })
+}
+
+// whether the ray at t is within the unit radius of the axis
+fn within_cap(ray: &Ray, t: f64) -> bool {
+    let x = ray.origin.0.x + t * ray.direction.0.x;
+    let z = ray.origin.0.z + t * ray.direction.0.z;
+    x * x + z * z <= 1.0
+}
+
+pub(crate) fn local_intersect(ray: Ray, minimum: f64, maximum: f64, closed: bool) -> LocalHits {
+    let mut xs = LocalHits::new();
+    let (o, d) = (ray.origin.0, ray.direction.0);
+
+    let a = d.x * d.x + d.z * d.z;
+    // parallel to the axis there are no wall hits, only caps
+    if a.abs() > EPSILON * EPSILON {
+        let b = 2.0 * o.x * d.x + 2.0 * o.z * d.z;
+        let c = o.x * o.x + o.z * o.z - 1.0;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return xs;
+        }
+        let root = discriminant.sqrt();
+        let mut t0 = (-b - root) / (2.0 * a);
+        let mut t1 = (-b + root) / (2.0 * a);
+        if t0 > t1 {
+            core::mem::swap(&mut t0, &mut t1);
+        }
+        for t in [t0, t1] {
+            let y = o.y + t * d.y;
+            if minimum < y && y < maximum {
+                xs.push(LocalHit::new(t));
+            }
+        }
+    }
+
+    if closed && d.y.abs() > EPSILON * EPSILON {
+        for cap in [minimum, maximum] {
+            let t = (cap - o.y) / d.y;
+            if cap.is_finite() && within_cap(&ray, t) {
+                xs.push(LocalHit::new(t));
+            }
+        }
+    }
+    xs
+}
+
+pub(crate) fn local_normal_at(p: Point, minimum: f64, maximum: f64) -> Vector {
+    let (x, y, z) = (p.0.x, p.0.y, p.0.z);
+    let dist = x * x + z * z;
+    if dist < 1.0 && y >= maximum - EPSILON {
+        Vector::new(0.0, 1.0, 0.0)
+    } else if dist < 1.0 && y <= minimum + EPSILON {
+        Vector::new(0.0, -1.0, 0.0)
+    } else {
+        Vector::new(x, 0.0, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::{scaling, translation, Transformable};
+
+    fn ts(c: &Object, origin: Point, direction: Vector) -> Vec<f64> {
+        let mut xs = c.intersect(Ray::new(origin, direction.normalize()));
+        xs.sort();
+        xs.iter().map(|i| i.t).collect()
+    }
+
+    #[test]
+    fn ray_misses_a_cylinder() {
+        let c = cylinder(f64::NEG_INFINITY, f64::INFINITY, false);
+        for (origin, direction) in [
+            (Point::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 0.0, -5.0), Vector::new(1.0, 1.0, 1.0)),
+        ] {
+            assert!(ts(&c, origin, direction).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_a_cylinder() {
+        let c = cylinder(f64::NEG_INFINITY, f64::INFINITY, false);
+        let cases = [
+            (
+                Point::new(1.0, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+            (
+                Point::new(0.0, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(0.5, 0.0, -5.0),
+                Vector::new(0.1, 1.0, 1.0),
+                6.80798,
+                7.08872,
+            ),
+        ];
+        for (origin, direction, t0, t1) in cases {
+            let xs = ts(&c, origin, direction);
+            assert_eq!(xs.len(), 2);
+            assert!((xs[0] - t0).abs() < EPSILON);
+            assert!((xs[1] - t1).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn normal_on_a_cylinder() {
+        for (p, n) in [
+            (Point::new(1.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+            (Point::new(0.0, 5.0, -1.0), Vector::new(0.0, 0.0, -1.0)),
+            (Point::new(0.0, -2.0, 1.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(-1.0, 1.0, 0.0), Vector::new(-1.0, 0.0, 0.0)),
+        ] {
+            assert_eq!(local_normal_at(p, f64::NEG_INFINITY, f64::INFINITY), n);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_truncated_cylinder() {
+        let c = cylinder(1.0, 2.0, false);
+        let cases = [
+            (Point::new(0.0, 1.5, 0.0), Vector::new(0.1, 1.0, 0.0), 0),
+            (Point::new(0.0, 3.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0), 0),
+            (Point::new(0.0, 1.5, -2.0), Vector::new(0.0, 0.0, 1.0), 2),
+        ];
+        for (origin, direction, count) in cases {
+            assert_eq!(ts(&c, origin, direction).len(), count);
+        }
+    }
+
+    #[test]
+    fn intersecting_the_caps_of_a_closed_cylinder() {
+        let c = cylinder(1.0, 2.0, true);
+        let cases = [
+            (Point::new(0.0, 3.0, 0.0), Vector::new(0.0, -1.0, 0.0), 2),
+            (Point::new(0.0, 3.0, -2.0), Vector::new(0.0, -1.0, 2.0), 2),
+            (Point::new(0.0, 4.0, -2.0), Vector::new(0.0, -1.0, 1.0), 2),
+            (Point::new(0.0, 0.0, -2.0), Vector::new(0.0, 1.0, 2.0), 2),
+            (Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 1.0), 2),
+        ];
+        for (origin, direction, count) in cases {
+            assert_eq!(ts(&c, origin, direction).len(), count);
+        }
+    }
+
+    #[test]
+    fn normal_on_the_end_caps() {
+        for (p, n) in [
+            (Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.5, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.0, 1.0, 0.5), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.0, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.5, 2.0, 0.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.0, 2.0, 0.5), Vector::new(0.0, 1.0, 0.0)),
+        ] {
+            assert_eq!(local_normal_at(p, 1.0, 2.0), n);
+        }
+    }
+
+    #[test]
+    fn bounds_of_cylinders() {
+        let post = cylinder(0.0, 3.0, true)
+            .transformed(&(translation(1.0, 0.0, 0.0) * scaling(0.5, 1.0, 0.5)));
+        let b = post.bounds();
+        assert_eq!(b.min, Point::new(0.5, 0.0, -0.5));
+        assert_eq!(b.max, Point::new(1.5, 3.0, 0.5));
+        let n = post.normal_at(Point::new(1.5, 1.0, 0.0));
+        assert_eq!(n, Vector::new(1.0, 0.0, 0.0));
+        let endless = cylinder(f64::NEG_INFINITY, f64::INFINITY, false).bounds();
+        assert_eq!(endless.min.0.y, f64::NEG_INFINITY);
+        assert_eq!(endless.max.0.x, f64::INFINITY);
+    }
+}
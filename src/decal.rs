@@ -0,0 +1,172 @@
+use crate::canvas::Texture;
+use crate::color::Color;
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::transformations;
+use crate::tuple::{Point, Vector};
+
+// a virtual slide projector with a square frustum
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projector {
+    pub position: Point,
+    transform: Matrix4,
+    half_view: f64,
+}
+
+impl Projector {
+    pub fn new(position: Point, direction: Vector, field_of_view: f64) -> Projector {
+        let target = Point::try_from(position.0 + direction.0).unwrap();
+        Projector {
+            position,
+            transform: transformations::aim(position, target),
+            half_view: (field_of_view / 2.0).tan(),
+        }
+    }
+
+    // texture coordinates of point within the frustum, u grows to the right
+    // and v downwards as seen from the projector, same as canvas pixels
+    pub fn project(&self, point: Point) -> Option<(f64, f64)> {
+        let p = &self.transform * point;
+        if p.z >= 0.0 {
+            return None;
+        }
+        let u = (1.0 - p.x / (-p.z * self.half_view)) / 2.0;
+        let v = (1.0 - p.y / (-p.z * self.half_view)) / 2.0;
+        if (0.0..1.0).contains(&u) && (0.0..1.0).contains(&v) {
+            Some((u, v))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Decal {
+    pub projector: Projector,
+    pub image: Texture,
+    pub opacity: f64,
+}
+
+impl Decal {
+    // takes either a canvas or a texture already shared with other decals
+    pub fn new(projector: Projector, image: impl Into<Texture>) -> Decal {
+        Decal {
+            projector,
+            image: image.into(),
+            opacity: 1.0,
+        }
+    }
+
+    pub fn set_opacity(mut self, opacity: f64) -> Decal {
+        self.opacity = opacity;
+        self
+    }
+
+    // image color landing on a surface point, surfaces facing away from the
+    // projector are not painted
+    pub fn color_at(&self, point: Point, normal: Vector) -> Option<Color> {
+        if (self.projector.position - point).dot(normal) <= 0.0 {
+            return None;
+        }
+        let (u, v) = self.projector.project(point)?;
+        let x = (u * self.image.width as f64) as isize;
+        let y = (v * self.image.height as f64) as isize;
+        self.image.read_pixel(x, y).ok()
+    }
+
+    // blends the decal over a base surface color
+    pub fn apply(&self, base: Color, point: Point, normal: Vector) -> Color {
+        match self.color_at(point, normal) {
+            Some(c) => base * (1.0 - self.opacity) + c * self.opacity,
+            None => base,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+    use static_assertions::assert_impl_all;
+    use std::f64::consts::PI;
+
+    fn quadrants() -> Canvas {
+        let mut image = Canvas::new(2, 2);
+        image.write_pixel(0, 0, Color::new(1.0, 0.0, 0.0));
+        image.write_pixel(1, 0, Color::new(0.0, 1.0, 0.0));
+        image.write_pixel(0, 1, Color::new(0.0, 0.0, 1.0));
+        image.write_pixel(1, 1, Color::new(1.0, 1.0, 1.0));
+        image
+    }
+
+    #[test]
+    fn projecting_points_into_the_frustum() {
+        let p = Projector::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 2.0,
+        );
+        assert_eq!(p.project(Point::new(0.0, 0.0, 0.0)), Some((0.5, 0.5)));
+        let (u, v) = p.project(Point::new(-2.5, 2.5, 0.0)).unwrap();
+        assert!((u - 0.25).abs() < 1e-9 && (v - 0.25).abs() < 1e-9);
+        assert_eq!(p.project(Point::new(6.0, 0.0, 0.0)), None);
+        assert_eq!(p.project(Point::new(0.0, 0.0, -6.0)), None);
+    }
+
+    #[test]
+    fn decal_paints_only_facing_surfaces() {
+        let p = Projector::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 2.0,
+        );
+        let decal = Decal::new(p, quadrants());
+        let front = Vector::new(0.0, 0.0, -1.0);
+        assert_eq!(
+            decal.color_at(Point::new(-1.0, 1.0, 0.0), front),
+            Some(Color::new(1.0, 0.0, 0.0))
+        );
+        assert_eq!(
+            decal.color_at(Point::new(1.0, -1.0, 0.0), front),
+            Some(Color::new(1.0, 1.0, 1.0))
+        );
+        assert_eq!(decal.color_at(Point::new(-1.0, 1.0, 0.0), -front), None);
+    }
+
+    #[test]
+    fn decal_blends_with_opacity() {
+        let p = Projector::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 2.0,
+        );
+        let decal = Decal::new(p, quadrants()).set_opacity(0.25);
+        let base = Color::new(0.0, 0.0, 0.0);
+        let front = Vector::new(0.0, 0.0, -1.0);
+        assert_eq!(
+            decal.apply(base, Point::new(-1.0, 1.0, 0.0), front),
+            Color::new(0.25, 0.0, 0.0)
+        );
+        assert_eq!(decal.apply(base, Point::new(0.0, 0.0, 10.0), -front), base);
+    }
+
+    #[test]
+    fn decals_share_one_texture() {
+        let p = Projector::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 2.0,
+        );
+        let image = Texture::new(quadrants());
+        let a = Decal::new(p.clone(), image.clone());
+        let b = a.clone().set_opacity(0.5);
+        let c = Decal::new(p, image.clone());
+        assert!(Texture::ptr_eq(&a.image, &b.image));
+        assert!(Texture::ptr_eq(&a.image, &c.image));
+        assert_eq!(Texture::strong_count(&image), 4);
+    }
+
+    assert_impl_all!(Projector: Send, Sync);
+    assert_impl_all!(Decal: Send, Sync);
+}
@@ -0,0 +1,336 @@
+use crate::matrix::Matrix;
+use crate::tuple::Tuple;
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+use std::ops::{Add, Mul, Neg, Sub};
+
+// exact counterparts of the f64 matrix and tuple types. every finite f64 is
+// a rational so values convert without loss, and identities like
+// m * m.inverse() == I hold exactly instead of within EPSILON
+
+pub type Rational = BigRational;
+
+fn exact(x: f64) -> Rational {
+    Rational::from_float(x).expect("Cannot convert non finite value to a rational")
+}
+
+fn approx(x: &Rational) -> f64 {
+    // numerator and denominator can overflow f64 on their own, so divide
+    // the rounded parts and let the big integers cancel first
+    let digits = 1u64 << 53;
+    let scaled = (x * Rational::from_integer(digits.into()))
+        .round()
+        .to_integer();
+    scaled.to_string().parse::<f64>().unwrap() / digits as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactMatrix<const W: usize, const H: usize> {
+    data: Vec<Vec<Rational>>,
+}
+
+pub type ExactMatrix4 = ExactMatrix<4, 4>;
+
+impl<const W: usize, const H: usize> ExactMatrix<W, H> {
+    pub fn from_integers(data: [[i64; W]; H]) -> ExactMatrix<W, H> {
+        ExactMatrix {
+            data: data
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|&x| Rational::from_integer(x.into()))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_f64(m: &Matrix<W, H>) -> ExactMatrix<W, H> {
+        ExactMatrix {
+            data: (0..H)
+                .map(|y| (0..W).map(|x| exact(m.get(y, x))).collect())
+                .collect(),
+        }
+    }
+
+    pub fn to_f64(&self) -> Matrix<W, H> {
+        Matrix::new(std::array::from_fn(|y| {
+            std::array::from_fn(|x| approx(&self.data[y][x]))
+        }))
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> &Rational {
+        &self.data[y][x]
+    }
+
+    pub fn transpose(&self) -> ExactMatrix<H, W> {
+        ExactMatrix {
+            data: (0..W)
+                .map(|x| (0..H).map(|y| self.data[y][x].clone()).collect())
+                .collect(),
+        }
+    }
+}
+
+impl<const D: usize> ExactMatrix<D, D> {
+    pub fn identity() -> ExactMatrix<D, D> {
+        ExactMatrix {
+            data: (0..D)
+                .map(|y| {
+                    (0..D)
+                        .map(|x| {
+                            if x == y {
+                                Rational::one()
+                            } else {
+                                Rational::zero()
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    // gauss-jordan elimination on [self | I], returns the determinant and
+    // the inverse when it exists
+    fn eliminate(&self) -> (Rational, Option<ExactMatrix<D, D>>) {
+        let mut a = self.data.clone();
+        let mut inv = ExactMatrix::<D, D>::identity().data;
+        let mut det = Rational::one();
+        for col in 0..D {
+            let pivot = match (col..D).find(|&r| !a[r][col].is_zero()) {
+                Some(p) => p,
+                None => return (Rational::zero(), None),
+            };
+            if pivot != col {
+                a.swap(pivot, col);
+                inv.swap(pivot, col);
+                det = -det;
+            }
+            let p = a[col][col].clone();
+            det *= &p;
+            for x in 0..D {
+                a[col][x] = &a[col][x] / &p;
+                inv[col][x] = &inv[col][x] / &p;
+            }
+            for row in 0..D {
+                if row == col || a[row][col].is_zero() {
+                    continue;
+                }
+                let factor = a[row][col].clone();
+                for x in 0..D {
+                    let da = &factor * &a[col][x];
+                    let di = &factor * &inv[col][x];
+                    a[row][x] -= da;
+                    inv[row][x] -= di;
+                }
+            }
+        }
+        (det, Some(ExactMatrix { data: inv }))
+    }
+
+    pub fn determinant(&self) -> Rational {
+        self.eliminate().0
+    }
+
+    pub fn inverse(&self) -> Option<ExactMatrix<D, D>> {
+        self.eliminate().1
+    }
+}
+
+impl<const W: usize, const H: usize, const L: usize> Mul<&ExactMatrix<L, W>>
+    for &ExactMatrix<W, H>
+{
+    type Output = ExactMatrix<L, H>;
+    fn mul(self, rhs: &ExactMatrix<L, W>) -> ExactMatrix<L, H> {
+        ExactMatrix {
+            data: (0..H)
+                .map(|y| {
+                    (0..L)
+                        .map(|x| {
+                            (0..W).fold(Rational::zero(), |acc, k| {
+                                acc + &self.data[y][k] * &rhs.data[k][x]
+                            })
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Mul<&ExactTuple> for &ExactMatrix4 {
+    type Output = ExactTuple;
+    fn mul(self, t: &ExactTuple) -> ExactTuple {
+        let row = |y: usize| {
+            &self.data[y][0] * &t.x
+                + &self.data[y][1] * &t.y
+                + &self.data[y][2] * &t.z
+                + &self.data[y][3] * &t.w
+        };
+        ExactTuple {
+            x: row(0),
+            y: row(1),
+            z: row(2),
+            w: row(3),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactTuple {
+    pub x: Rational,
+    pub y: Rational,
+    pub z: Rational,
+    pub w: Rational,
+}
+
+impl ExactTuple {
+    pub fn from_f64(t: Tuple) -> ExactTuple {
+        ExactTuple {
+            x: exact(t.x),
+            y: exact(t.y),
+            z: exact(t.z),
+            w: exact(t.w),
+        }
+    }
+
+    pub fn to_f64(&self) -> Tuple {
+        Tuple::new(
+            approx(&self.x),
+            approx(&self.y),
+            approx(&self.z),
+            approx(&self.w),
+        )
+    }
+
+    pub fn dot(&self, other: &ExactTuple) -> Rational {
+        &self.x * &other.x + &self.y * &other.y + &self.z * &other.z + &self.w * &other.w
+    }
+
+    pub fn cross(&self, other: &ExactTuple) -> ExactTuple {
+        ExactTuple {
+            x: &self.y * &other.z - &self.z * &other.y,
+            y: &self.z * &other.x - &self.x * &other.z,
+            z: &self.x * &other.y - &self.y * &other.x,
+            w: Rational::zero(),
+        }
+    }
+
+    // squared length, the length itself is usually irrational
+    pub fn magnitude_squared(&self) -> Rational {
+        self.dot(self)
+    }
+}
+
+impl Add for &ExactTuple {
+    type Output = ExactTuple;
+    fn add(self, o: &ExactTuple) -> ExactTuple {
+        ExactTuple {
+            x: &self.x + &o.x,
+            y: &self.y + &o.y,
+            z: &self.z + &o.z,
+            w: &self.w + &o.w,
+        }
+    }
+}
+
+impl Sub for &ExactTuple {
+    type Output = ExactTuple;
+    fn sub(self, o: &ExactTuple) -> ExactTuple {
+        ExactTuple {
+            x: &self.x - &o.x,
+            y: &self.y - &o.y,
+            z: &self.z - &o.z,
+            w: &self.w - &o.w,
+        }
+    }
+}
+
+impl Neg for &ExactTuple {
+    type Output = ExactTuple;
+    fn neg(self) -> ExactTuple {
+        ExactTuple {
+            x: -&self.x,
+            y: -&self.y,
+            z: -&self.z,
+            w: -&self.w,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::Matrix4;
+    use crate::transformations::{rotation_x, scaling, shearing, translation};
+    use num_traits::Signed;
+
+    fn a() -> ExactMatrix4 {
+        ExactMatrix::from_integers([[-5, 2, 6, -8], [1, -5, 1, 8], [7, 7, -6, -7], [1, -3, 7, 4]])
+    }
+
+    fn b() -> ExactMatrix4 {
+        ExactMatrix::from_f64(
+            &(translation(0.5, -3.0, 2.25) * rotation_x(0.3) * scaling(2.0, 0.1, 3.0)),
+        )
+    }
+
+    #[test]
+    fn inverse_is_exact() {
+        for m in [a(), b()] {
+            let inv = m.inverse().unwrap();
+            assert_eq!(&m * &inv, ExactMatrix::identity());
+            assert_eq!(&inv * &m, ExactMatrix::identity());
+        }
+        assert_eq!(a().determinant(), Rational::from_integer(532.into()));
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        let m =
+            ExactMatrix::from_integers([[1, 2, 3, 4], [2, 4, 6, 8], [0, 0, 1, 0], [0, 0, 0, 1]]);
+        assert!(m.inverse().is_none());
+        assert!(m.determinant().is_zero());
+    }
+
+    #[test]
+    fn product_identities_hold_exactly() {
+        let c = ExactMatrix::from_f64(&shearing(1.0, 0.0, 0.5, 0.0, 0.0, 0.25));
+        assert_eq!(&(&a() * &b()) * &c, &a() * &(&b() * &c));
+        assert_eq!(
+            (&a() * &b()).transpose(),
+            &b().transpose() * &a().transpose()
+        );
+        assert_eq!(
+            (&a() * &b()).determinant(),
+            a().determinant() * b().determinant()
+        );
+    }
+
+    #[test]
+    fn f64_inverse_is_close_to_exact() {
+        let m = translation(0.5, -3.0, 2.25) * rotation_x(0.3) * scaling(2.0, 0.1, 3.0);
+        let exact = ExactMatrix::from_f64(&m).inverse().unwrap().to_f64();
+        assert_eq!(m.inverse().unwrap(), exact);
+        assert_eq!(
+            ExactMatrix::from_f64(&Matrix4::identity(4)).to_f64(),
+            Matrix4::identity(4)
+        );
+    }
+
+    #[test]
+    fn tuple_algebra() {
+        let x = ExactTuple::from_f64(Tuple::new(1.0, 0.0, 0.0, 0.0));
+        let y = ExactTuple::from_f64(Tuple::new(0.0, 1.0, 0.0, 0.0));
+        let z = x.cross(&y);
+        assert_eq!(z.to_f64(), Tuple::new(0.0, 0.0, 1.0, 0.0));
+        assert!(z.dot(&x).is_zero());
+        let p = ExactTuple::from_f64(Tuple::new(0.1, 0.2, 0.3, 1.0));
+        let m = b();
+        let back = &m.inverse().unwrap() * &(&m * &p);
+        assert_eq!(back, p);
+        assert_eq!((&(&p + &x) - &x), p);
+        assert!(!(-&p).magnitude_squared().is_negative());
+    }
+}
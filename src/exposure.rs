@@ -0,0 +1,181 @@
+use crate::canvas::Canvas;
+use crate::progressive::Progressive;
+use crate::tonemap::ToneMapper;
+
+// pixels at or beyond the displayable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipStats {
+    // any channel at or above 1.0
+    pub highlights: usize,
+    // every channel below a single 8 bit step
+    pub shadows: usize,
+    pub total: usize,
+}
+
+impl ClipStats {
+    pub fn highlight_fraction(&self) -> f64 {
+        self.highlights as f64 / self.total.max(1) as f64
+    }
+
+    pub fn shadow_fraction(&self) -> f64 {
+        self.shadows as f64 / self.total.max(1) as f64
+    }
+}
+
+// middle grey the average luminance is mapped to
+pub const KEY_VALUE: f64 = 0.18;
+
+impl Canvas {
+    // bins evenly split 0..max, values above max land in the last bin
+    pub fn luminance_histogram(&self, bins: usize, max: f64) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+        if bins == 0 {
+            return histogram;
+        }
+        for pixel in &self.pixels {
+            let t = (pixel.luminance() / max).max(0.0);
+            let bin = ((t * bins as f64) as usize).min(bins - 1);
+            histogram[bin] += 1;
+        }
+        histogram
+    }
+
+    pub fn clip_stats(&self) -> ClipStats {
+        let mut stats = ClipStats {
+            highlights: 0,
+            shadows: 0,
+            total: self.pixels.len(),
+        };
+        for p in &self.pixels {
+            if p.red >= 1.0 || p.green >= 1.0 || p.blue >= 1.0 {
+                stats.highlights += 1;
+            } else if p.red < 1.0 / 255.0 && p.green < 1.0 / 255.0 && p.blue < 1.0 / 255.0 {
+                stats.shadows += 1;
+            }
+        }
+        stats
+    }
+
+    // exposure multiplier bringing the log average luminance to KEY_VALUE,
+    // black pixels are left out so a dark background does not blow out the
+    // subject
+    pub fn suggest_exposure(&self) -> f64 {
+        let (sum, count) = self
+            .pixels
+            .iter()
+            .map(|p| p.luminance())
+            .filter(|l| *l > 1e-4)
+            .fold((0.0, 0), |(sum, count), l| (sum + l.ln(), count + 1));
+        if count == 0 {
+            return 1.0;
+        }
+        KEY_VALUE / (sum / count as f64).exp()
+    }
+
+    pub fn apply_exposure(&mut self, exposure: f64) -> &mut Canvas {
+        for p in self.pixels.iter_mut() {
+            *p = *p * exposure;
+        }
+        self
+    }
+
+    // one displayable image per exposure step of an hdr render, each stop
+    // in ev doubles or halves the light before tone mapping, so -2, 0, 2
+    // gives a quarter, plain and four times as bright
+    pub fn bracketed(&self, stops: &[f64], tone: ToneMapper) -> Vec<Canvas> {
+        stops
+            .iter()
+            .map(|ev| {
+                let mut c = self.clone();
+                c.apply_exposure(2f64.powf(*ev));
+                c.tone_mapped(tone)
+            })
+            .collect()
+    }
+}
+
+impl Progressive {
+    // the bracketed set from everything accumulated so far, mapped with
+    // the renderer's own tone mapper
+    pub fn bracketed(&self, stops: &[f64]) -> Vec<Canvas> {
+        self.hdr().bracketed(stops, self.tone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::world::default_world;
+
+    #[test]
+    fn histogram_counts_luminance() {
+        let mut c = Canvas::new(4, 1);
+        c.write_pixel(1, 0, Color::new(0.3, 0.3, 0.3));
+        c.write_pixel(2, 0, Color::new(0.9, 0.9, 0.9));
+        c.write_pixel(3, 0, Color::new(5.0, 5.0, 5.0));
+        assert_eq!(c.luminance_histogram(4, 1.0), vec![1, 1, 0, 2]);
+    }
+
+    #[test]
+    fn clipped_pixels_are_counted() {
+        let mut c = Canvas::new(4, 1);
+        c.write_pixel(0, 0, Color::new(1.2, 0.0, 0.0));
+        c.write_pixel(1, 0, Color::new(0.5, 0.5, 0.5));
+        c.write_pixel(2, 0, Color::new(0.001, 0.0, 0.0));
+        let stats = c.clip_stats();
+        assert_eq!(
+            stats,
+            ClipStats {
+                highlights: 1,
+                shadows: 2,
+                total: 4
+            }
+        );
+        assert_eq!(stats.highlight_fraction(), 0.25);
+    }
+
+    #[test]
+    fn exposure_maps_average_to_middle_grey() {
+        let mut c = Canvas::new(2, 2);
+        c.fill(Color::new(0.72, 0.72, 0.72));
+        c.write_pixel(0, 0, Color::new(0.0, 0.0, 0.0));
+        let exposure = c.suggest_exposure();
+        assert!((exposure - 0.25).abs() < 1e-9);
+        c.apply_exposure(exposure);
+        assert_eq!(c.read_pixel(1, 1).unwrap(), Color::new(0.18, 0.18, 0.18));
+        assert_eq!(Canvas::new(2, 2).suggest_exposure(), 1.0);
+    }
+
+    #[test]
+    fn bracketing_one_render() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(0.2, 0.2, 0.2));
+        c.write_pixel(1, 0, Color::new(2.0, 2.0, 2.0));
+        let set = c.bracketed(&[-2.0, 0.0, 2.0], ToneMapper::Clamp);
+        assert_eq!(set.len(), 3);
+        assert_eq!(
+            set[0].read_pixel(0, 0).unwrap(),
+            Color::new(0.05, 0.05, 0.05)
+        );
+        assert_eq!(set[0].read_pixel(1, 0).unwrap(), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(set[1].read_pixel(1, 0).unwrap(), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(set[2].read_pixel(0, 0).unwrap(), Color::new(0.8, 0.8, 0.8));
+        // the hdr render itself is left alone
+        assert_eq!(c.read_pixel(1, 0).unwrap(), Color::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn bracketing_a_progressive_render() {
+        let camera = Camera::new(4, 4, std::f64::consts::PI / 3.0);
+        let mut progressive = Progressive::new(&camera, ToneMapper::Reinhard);
+        progressive.render_pass(&camera, &default_world());
+        let set = progressive.bracketed(&[0.0, 1.0]);
+        assert_eq!(set[0], progressive.image());
+        assert_eq!(
+            set[1],
+            progressive.hdr().bracketed(&[1.0], ToneMapper::Reinhard)[0]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::material::Material;
+use crate::matrix::Matrix4;
+use crate::object::Object;
+use crate::slab::slab;
+use crate::transformations::{translation, Transformable};
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+// classic 5x7 character rom, each row uses the low 5 bits with the
+// leftmost column in bit 4
+const GLYPHS: [(char, [u8; 7]); 54] = [
+    ('A', [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('#', [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('\'', [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+];
+
+// unknown characters render as a filled box, lowercase uses the capitals
+pub fn glyph(c: char) -> [u8; 7] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(g, _)| *g == c)
+        .map(|(_, rows)| *rows)
+        .unwrap_or([0x1f; 7])
+}
+
+pub fn glyph_pixel(c: char, x: usize, y: usize) -> bool {
+    x < GLYPH_WIDTH && y < GLYPH_HEIGHT && glyph(c)[y] & (0x10 >> x) != 0
+}
+
+// width in pixels of a line of text, glyphs are separated by one column
+pub fn text_width(text: &str, scale: usize) -> usize {
+    let n = text.chars().count();
+    if n == 0 {
+        0
+    } else {
+        (n * (GLYPH_WIDTH + 1) - 1) * scale
+    }
+}
+
+pub fn draw_text(canvas: &mut Canvas, x: isize, y: isize, text: &str, color: Color, scale: usize) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + (i * (GLYPH_WIDTH + 1) * scale) as isize;
+        for gy in 0..GLYPH_HEIGHT * scale {
+            for gx in 0..GLYPH_WIDTH * scale {
+                if glyph_pixel(c, gx / scale, gy / scale) {
+                    canvas.write_pixel(left + gx as isize, y + gy as isize, color);
+                }
+            }
+        }
+    }
+}
+
+// the boxes making up one character of 3d text
+#[derive(Debug, Clone)]
+pub struct TextGlyph {
+    pub c: char,
+    pub objects: Vec<Object>,
+}
+
+impl Transformable for TextGlyph {
+    fn transformed(self, m: &Matrix4) -> TextGlyph {
+        TextGlyph {
+            c: self.c,
+            objects: self.objects.into_iter().map(|o| o.transformed(m)).collect(),
+        }
+    }
+}
+
+// text as slabs, one unit per font pixel. it stands on the x axis reading
+// towards +x from the origin and is extruded `depth` towards +z, away from
+// a camera looking down +z. runs of pixels in a row share one slab
+pub fn text_geometry(text: &str, depth: f64, material: Material) -> Vec<TextGlyph> {
+    text.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let left = (i * (GLYPH_WIDTH + 1)) as f64;
+            let mut objects = vec![];
+            for y in 0..GLYPH_HEIGHT {
+                let mut x = 0;
+                while x < GLYPH_WIDTH {
+                    if !glyph_pixel(c, x, y) {
+                        x += 1;
+                        continue;
+                    }
+                    let start = x;
+                    while x < GLYPH_WIDTH && glyph_pixel(c, x, y) {
+                        x += 1;
+                    }
+                    let width = (x - start) as f64;
+                    objects.push(
+                        slab(width, 1.0, depth)
+                            .transformed(&translation(
+                                left + start as f64 + width / 2.0,
+                                (GLYPH_HEIGHT - y) as f64 - 0.5,
+                                depth / 2.0,
+                            ))
+                            .set_material(material),
+                    );
+                }
+            }
+            TextGlyph { c, objects }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuple::Point;
+
+    #[test]
+    fn glyph_lookup_is_case_insensitive() {
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(glyph('~'), [0x1f; 7]);
+    }
+
+    #[test]
+    fn glyph_pixels() {
+        // the top row of T is solid, the stem is centered
+        assert!((0..5).all(|x| glyph_pixel('T', x, 0)));
+        assert!(glyph_pixel('T', 2, 6));
+        assert!(!glyph_pixel('T', 0, 6));
+        assert!(!glyph_pixel('T', 5, 0));
+    }
+
+    #[test]
+    fn drawing_text() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut c = Canvas::new(20, 10);
+        draw_text(&mut c, 1, 1, "I-", white, 1);
+        assert_eq!(c.read_pixel(2, 1).unwrap(), white);
+        assert_eq!(c.read_pixel(1, 2).unwrap(), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(c.read_pixel(7, 4).unwrap(), white);
+        assert_eq!(text_width("I-", 2), 22);
+    }
+
+    #[test]
+    fn extruded_text() {
+        let glyphs = text_geometry("I T", 0.5, Material::default());
+        assert_eq!(glyphs.len(), 3);
+        // the serifs are one slab each
+        assert_eq!(glyphs[0].objects.len(), 7);
+        assert!(glyphs[1].objects.is_empty());
+        let top = glyphs[0].objects[0].bounds();
+        assert_eq!(top.min, Point::new(1.0, 6.0, 0.0));
+        assert_eq!(top.max, Point::new(4.0, 7.0, 0.5));
+        let bar = glyphs[2]
+            .clone()
+            .transformed(&translation(0.0, 1.0, 0.0))
+            .objects[0]
+            .bounds();
+        assert_eq!(bar.min, Point::new(12.0, 7.0, 0.0));
+        assert_eq!(bar.max, Point::new(17.0, 8.0, 0.5));
+    }
+}
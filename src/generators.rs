@@ -0,0 +1,328 @@
+use crate::color::Color;
+use crate::light::PointLight;
+use crate::material::Material;
+use crate::object::Object;
+use crate::rng::Rng;
+use crate::slab::slab;
+use crate::sphere::sphere;
+use crate::transformations::{rotation_from_to, rotation_y, scaling, translation, Transformable};
+use crate::tuple::{Point, Vector};
+use crate::world::World;
+use std::f64::consts::PI;
+
+// large procedural scenes for benchmarking, all generators are
+// deterministic for a given seed. buildings and terrain cells are slabs
+
+fn lit_world() -> World {
+    let mut w = World::new();
+    w.lights
+        .push(PointLight::new(Point::new(-50.0, 100.0, -50.0), Color::new(1.0, 1.0, 1.0)).into());
+    w
+}
+
+// n x n x n unit spheres centered around the origin
+pub fn sphere_grid(n: usize, spacing: f64) -> World {
+    let mut w = lit_world();
+    let offset = (n as f64 - 1.0) * spacing / 2.0;
+    for x in 0..n {
+        for y in 0..n {
+            for z in 0..n {
+                let material = Material {
+                    color: Color::new(
+                        (x as f64 + 0.5) / n as f64,
+                        (y as f64 + 0.5) / n as f64,
+                        (z as f64 + 0.5) / n as f64,
+                    ),
+                    ..Default::default()
+                };
+                w.objects.push(
+                    sphere()
+                        .set_transform(translation(
+                            x as f64 * spacing - offset,
+                            y as f64 * spacing - offset,
+                            z as f64 * spacing - offset,
+                        ))
+                        .set_material(material),
+                );
+            }
+        }
+    }
+    w
+}
+
+// blocks x blocks grid of buildings with random footprint and height,
+// standing on the y = 0 plane
+pub fn city(blocks: usize, seed: u64) -> World {
+    let mut rng = Rng::new(seed);
+    let mut w = lit_world();
+    let offset = (blocks as f64 - 1.0) * 2.0;
+    for i in 0..blocks {
+        for j in 0..blocks {
+            let height = rng.range(1.0, 10.0);
+            let width = rng.range(0.5, 1.5);
+            let depth = rng.range(0.5, 1.5);
+            let grey = rng.range(0.4, 0.9);
+            w.objects.push(
+                slab(width * 2.0, height, depth * 2.0)
+                    .transformed(&translation(
+                        i as f64 * 4.0 - offset,
+                        height / 2.0,
+                        j as f64 * 4.0 - offset,
+                    ))
+                    .set_material(Material {
+                        color: Color::new(grey, grey, grey),
+                        ..Default::default()
+                    }),
+            );
+        }
+    }
+    w
+}
+
+// diamond-square heights on a (2^detail + 1)^2 grid, roughly in -1..1
+pub fn fractal_heights(detail: u32, roughness: f64, seed: u64) -> Vec<Vec<f64>> {
+    let size = (1usize << detail) + 1;
+    let mut rng = Rng::new(seed);
+    let mut h = vec![vec![0.0; size]; size];
+    for (y, x) in [(0, 0), (0, size - 1), (size - 1, 0), (size - 1, size - 1)] {
+        h[y][x] = rng.range(-1.0, 1.0);
+    }
+    let mut step = size - 1;
+    let mut amplitude = 1.0;
+    while step > 1 {
+        let half = step / 2;
+        for y in (half..size).step_by(step) {
+            for x in (half..size).step_by(step) {
+                let avg = (h[y - half][x - half]
+                    + h[y - half][x + half]
+                    + h[y + half][x - half]
+                    + h[y + half][x + half])
+                    / 4.0;
+                h[y][x] = avg + rng.range(-amplitude, amplitude);
+            }
+        }
+        for y in (0..size).step_by(half) {
+            let start = if (y / half).is_multiple_of(2) {
+                half
+            } else {
+                0
+            };
+            for x in (start..size).step_by(step) {
+                let mut sum = 0.0;
+                let mut count = 0.0;
+                if y >= half {
+                    sum += h[y - half][x];
+                    count += 1.0;
+                }
+                if y + half < size {
+                    sum += h[y + half][x];
+                    count += 1.0;
+                }
+                if x >= half {
+                    sum += h[y][x - half];
+                    count += 1.0;
+                }
+                if x + half < size {
+                    sum += h[y][x + half];
+                    count += 1.0;
+                }
+                h[y][x] = sum / count + rng.range(-amplitude, amplitude);
+            }
+        }
+        step = half;
+        amplitude *= roughness;
+    }
+    h
+}
+
+// terrain as one box column per heightfield cell spanning `extent`
+// units in x and z
+pub fn terrain(detail: u32, roughness: f64, height: f64, extent: f64, seed: u64) -> World {
+    let heights = fractal_heights(detail, roughness, seed);
+    let size = heights.len();
+    let cell = extent / size as f64;
+    // columns start a cell below the lowest one, so neighbors meet
+    // without gaps between them
+    let lowest = heights
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::INFINITY, f64::min);
+    let bottom = lowest * height - cell;
+    let mut w = lit_world();
+    for (j, row) in heights.iter().enumerate() {
+        for (i, h) in row.iter().enumerate() {
+            let y = h * height;
+            let t = (h + 1.0) / 2.0;
+            w.objects.push(
+                slab(cell, y - bottom, cell)
+                    .transformed(&translation(
+                        (i as f64 + 0.5) * cell - extent / 2.0,
+                        (y + bottom) / 2.0,
+                        (j as f64 + 0.5) * cell - extent / 2.0,
+                    ))
+                    .set_material(Material {
+                        color: Color::new(0.3 + 0.5 * t, 0.5 + 0.3 * t, 0.2 + 0.6 * t),
+                        specular: 0.0,
+                        ..Default::default()
+                    }),
+            );
+        }
+    }
+    w
+}
+
+// copies of `instance` spread over the surface of `surface`, each stood
+// upright on it along the normal, spun about the normal at random and
+// scaled by a random factor in `scale`. points are uniform over the
+// untransformed sphere, so stretched surfaces get denser at their ends
+pub fn scatter(
+    instance: &Object,
+    surface: &Object,
+    count: usize,
+    scale: (f64, f64),
+    seed: u64,
+) -> Vec<Object> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| {
+            let y = rng.range(-1.0, 1.0);
+            let angle = rng.range(0.0, 2.0 * PI);
+            let r = (1.0 - y * y).sqrt();
+            let p = Point::new(r * angle.cos(), y, r * angle.sin());
+            let point = Point::try_from(&surface.transform * p).unwrap();
+            let normal = surface.normal_at(point);
+            let s = rng.range(scale.0, scale.1);
+            let spin = rng.range(0.0, 2.0 * PI);
+            instance.clone().set_transform(
+                translation(point.0.x, point.0.y, point.0.z)
+                    * rotation_from_to(Vector::new(0.0, 1.0, 0.0), normal)
+                    * rotation_y(spin)
+                    * scaling(s, s, s)
+                    * instance.transform.clone(),
+            )
+        })
+        .collect()
+}
+
+// a planet covered in `count` flat pebbles of random size and colour
+pub fn pebble_planet(count: usize, seed: u64) -> World {
+    let mut w = lit_world();
+    let planet = sphere().set_transform(scaling(10.0, 10.0, 10.0));
+    let pebble = sphere().set_transform(scaling(0.3, 0.1, 0.2));
+    let mut rng = Rng::new(seed ^ 0x5eed);
+    w.objects.extend(
+        scatter(&pebble, &planet, count, (0.5, 1.5), seed)
+            .into_iter()
+            .map(|p| {
+                let grey = rng.range(0.3, 0.8);
+                p.set_material(Material {
+                    color: Color::new(grey, grey * 0.9, grey * 0.8),
+                    ..Default::default()
+                })
+            }),
+    );
+    w.objects.push(planet);
+    w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::Geometry;
+    use crate::ray::Ray;
+    use crate::sphere::sphere;
+
+    #[test]
+    fn sphere_grid_is_centered() {
+        let w = sphere_grid(3, 2.5);
+        assert_eq!(w.objects.len(), 27);
+        let b = w.bounds();
+        assert_eq!(b.min, Point::new(-3.5, -3.5, -3.5));
+        assert_eq!(b.max, Point::new(3.5, 3.5, 3.5));
+    }
+
+    #[test]
+    fn city_is_deterministic_per_seed() {
+        let a = city(4, 3);
+        let b = city(4, 3);
+        let c = city(4, 4);
+        assert_eq!(a.objects.len(), 16);
+        assert!(a
+            .objects
+            .iter()
+            .zip(&b.objects)
+            .all(|(x, y)| x.transform == y.transform));
+        assert!(a
+            .objects
+            .iter()
+            .zip(&c.objects)
+            .any(|(x, y)| x.transform != y.transform));
+        assert!(a.bounds().min.0.y.abs() < 1e-9);
+        assert!(a
+            .objects
+            .iter()
+            .all(|o| matches!(o.geometry, Geometry::Slab)));
+    }
+
+    #[test]
+    fn fractal_heights_fill_the_grid() {
+        let h = fractal_heights(3, 0.5, 1);
+        assert_eq!(h.len(), 9);
+        assert!(h.iter().all(|row| row.len() == 9));
+        assert!(h.iter().flatten().all(|v| v.abs() < 3.0));
+        let t = terrain(2, 0.5, 2.0, 10.0, 1);
+        assert_eq!(t.objects.len(), 25);
+        // every column stands on the same ground
+        let bottom = t.bounds().min.0.y;
+        assert!(t
+            .objects
+            .iter()
+            .all(|o| (o.bounds().min.0.y - bottom).abs() < 1e-9));
+    }
+
+    #[test]
+    fn scattered_copies_stand_on_the_surface() {
+        let surface = sphere().set_transform(translation(1.0, 2.0, 3.0) * scaling(4.0, 4.0, 4.0));
+        let instance = sphere().set_transform(translation(0.0, 1.0, 0.0));
+        let copies = scatter(&instance, &surface, 50, (0.5, 2.0), 9);
+        assert_eq!(copies.len(), 50);
+        let center = Point::new(1.0, 2.0, 3.0);
+        for copy in &copies {
+            // the copy was lifted by its own radius along the normal
+            let c = Point::try_from(&copy.transform * Point::new(0.0, 0.0, 0.0)).unwrap();
+            let size = (&copy.transform * Vector::new(1.0, 0.0, 0.0)).magnitude();
+            assert!((0.5 - 1e-9..2.0 + 1e-9).contains(&size));
+            assert!(((c - center).magnitude() - 4.0 - size).abs() < 1e-6);
+            assert_ne!(copy.id(), instance.id());
+        }
+        let again = scatter(&instance, &surface, 50, (0.5, 2.0), 9);
+        assert!(copies
+            .iter()
+            .zip(&again)
+            .all(|(a, b)| a.transform == b.transform));
+    }
+
+    #[test]
+    fn pebble_planet_bvh_matches_brute_force() {
+        let mut w = pebble_planet(500, 2);
+        assert_eq!(w.objects.len(), 501);
+        let rays: Vec<_> = (0..20)
+            .map(|i| {
+                let a = i as f64 * 0.3;
+                Ray::new(
+                    Point::new(20.0 * a.cos(), 3.0, 20.0 * a.sin()),
+                    Vector::new(-a.cos(), -0.1, -a.sin()).normalize(),
+                )
+            })
+            .collect();
+        let brute: Vec<_> = rays
+            .iter()
+            .map(|r| w.intersect(*r).hit().map(|h| h.t))
+            .collect();
+        w.build_bvh();
+        for (r, t) in rays.iter().zip(brute) {
+            assert_eq!(w.intersect(*r).hit().map(|h| h.t), t);
+        }
+    }
+}
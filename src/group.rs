@@ -0,0 +1,343 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersections;
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
+use crate::ray::Ray;
+use crate::transformations::Transformable;
+
+// a named collection of objects and nested groups, such as the parts of
+// a loaded model. transforming a group bakes the transform into every
+// object it holds. the bounds are kept up to date as things are added so
+// rays can skip whole groups, see divide for splitting up big ones
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Group {
+    pub name: String,
+    children: Vec<Object>,
+    groups: Vec<Group>,
+    bounds: BoundingBox,
+}
+
+impl Group {
+    pub fn new(name: &str) -> Group {
+        Group {
+            name: name.into(),
+            ..Group::default()
+        }
+    }
+
+    pub fn add_child(mut self, child: Object) -> Group {
+        self.push_child(child);
+        self
+    }
+
+    pub fn add_group(mut self, group: Group) -> Group {
+        self.push_group(group);
+        self
+    }
+
+    pub fn push_child(&mut self, child: Object) {
+        self.bounds.merge(&child.bounds());
+        self.children.push(child);
+    }
+
+    pub fn push_group(&mut self, group: Group) {
+        self.bounds.merge(&group.bounds);
+        self.groups.push(group);
+    }
+
+    pub fn children(&self) -> &[Object] {
+        &self.children
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.groups.iter().all(|g| g.is_empty())
+    }
+
+    // the first group with this name, this one included, depth first
+    pub fn find(&self, name: &str) -> Option<&Group> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.groups.iter().find_map(|g| g.find(name))
+    }
+
+    // every object in the group and below it
+    pub fn objects(&self) -> Vec<&Object> {
+        let mut out = self.children.iter().collect::<Vec<_>>();
+        for group in &self.groups {
+            out.extend(group.objects());
+        }
+        out
+    }
+
+    // for changing materials. moving objects through it leaves the cached
+    // bounds stale, so the group would cull rays that now hit them. build
+    // the group again instead. the same goes for csgs and lod groups
+    pub fn objects_mut(&mut self) -> Vec<&mut Object> {
+        let mut out = self.children.iter_mut().collect::<Vec<_>>();
+        for group in self.groups.iter_mut() {
+            out.extend(group.objects_mut());
+        }
+        out
+    }
+
+    pub fn into_objects(self) -> Vec<Object> {
+        let mut out = self.children;
+        for group in self.groups {
+            out.extend(group.into_objects());
+        }
+        out
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    // turns the group into a hierarchy rays can descend in a few steps.
+    // while a group holds at least threshold objects of its own, the ones
+    // fitting entirely in either half of its bounds, cut across the
+    // longest side, move into a new sub group for that half. objects
+    // straddling the cut stay where they are
+    pub fn divide(mut self, threshold: usize) -> Group {
+        if threshold <= self.children.len() {
+            let (left, right) = self.partition_children();
+            for half in [left, right] {
+                if !half.is_empty() {
+                    let mut sub = Group::default();
+                    for child in half {
+                        sub.push_child(child);
+                    }
+                    self.groups.push(sub);
+                }
+            }
+        }
+        self.groups = self
+            .groups
+            .into_iter()
+            .map(|g| g.divide(threshold))
+            .collect();
+        self
+    }
+
+    // takes out the children fitting in the two halves of the bounds.
+    // when everything would land in one half, which happens for objects
+    // without size, nothing is moved so divide can't go on forever
+    fn partition_children(&mut self) -> (Vec<Object>, Vec<Object>) {
+        let mut own = BoundingBox::empty();
+        for child in &self.children {
+            own.merge(&child.bounds());
+        }
+        let (left_box, right_box) = split_bounds(&own);
+        let fits = |b: &BoundingBox, c: &Object| {
+            let cb = c.bounds();
+            b.contains_point(cb.min) && b.contains_point(cb.max)
+        };
+        let (mut left, mut right, mut rest) = (vec![], vec![], vec![]);
+        for child in self.children.drain(..) {
+            if fits(&left_box, &child) {
+                left.push(child);
+            } else if fits(&right_box, &child) {
+                right.push(child);
+            } else {
+                rest.push(child);
+            }
+        }
+        if rest.is_empty() && (left.is_empty() || right.is_empty()) {
+            left.append(&mut right);
+            self.children = left;
+            return (vec![], vec![]);
+        }
+        self.children = rest;
+        (left, right)
+    }
+
+    // appends the intersections with everything in the group, unsorted.
+    // rays missing the group's bounds skip its objects
+    pub fn intersect<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        if !self.bounds.intersects(ray) {
+            return;
+        }
+        for object in &self.children {
+            out.concat(object.intersect(ray));
+        }
+        for group in &self.groups {
+            group.intersect(ray, out);
+        }
+    }
+}
+
+// the two halves of a box cut across its longest side
+fn split_bounds(b: &BoundingBox) -> (BoundingBox, BoundingBox) {
+    let d = b.max - b.min;
+    let (mut left_max, mut right_min) = (b.max, b.min);
+    if d.0.x >= d.0.y && d.0.x >= d.0.z {
+        let x = b.min.0.x + d.0.x / 2.0;
+        left_max.0.x = x;
+        right_min.0.x = x;
+    } else if d.0.y >= d.0.z {
+        let y = b.min.0.y + d.0.y / 2.0;
+        left_max.0.y = y;
+        right_min.0.y = y;
+    } else {
+        let z = b.min.0.z + d.0.z / 2.0;
+        left_max.0.z = z;
+        right_min.0.z = z;
+    }
+    (
+        BoundingBox::new(b.min, left_max),
+        BoundingBox::new(right_min, b.max),
+    )
+}
+
+impl Transformable for Group {
+    fn transformed(self, m: &Matrix4) -> Group {
+        let mut group = Group::new(&self.name);
+        for child in self.children {
+            group.push_child(child.transformed(m));
+        }
+        for sub in self.groups {
+            group.push_group(sub.transformed(m));
+        }
+        group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+    use crate::tuple::{Point, Vector};
+
+    fn pair() -> Group {
+        Group::new("pair")
+            .add_child(sphere().set_transform(translation(-2.0, 0.0, 0.0)))
+            .add_group(
+                Group::new("right").add_child(sphere().set_transform(translation(2.0, 0.0, 0.0))),
+            )
+    }
+
+    #[test]
+    fn groups_hold_objects_and_groups() {
+        let g = pair();
+        assert!(Group::new("empty").is_empty());
+        assert!(!g.is_empty());
+        assert_eq!(g.objects().len(), 2);
+        assert_eq!(g.find("right").unwrap().children().len(), 1);
+        assert!(g.find("left").is_none());
+        let b = g.bounds();
+        assert_eq!(b.min, Point::new(-3.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(3.0, 1.0, 1.0));
+        assert_eq!(g.into_objects().len(), 2);
+    }
+
+    #[test]
+    fn transforming_a_group_moves_everything_in_it() {
+        let g = pair().transformed(&scaling(2.0, 2.0, 2.0));
+        let b = g.bounds();
+        assert_eq!(b.min, Point::new(-6.0, -2.0, -2.0));
+        assert_eq!(b.max, Point::new(6.0, 2.0, 2.0));
+        assert_eq!(g.find("right").unwrap().bounds().min.0.x, 2.0);
+    }
+
+    #[test]
+    fn intersecting_a_group() {
+        let g = pair();
+        let mut xs = Intersections::new();
+        g.intersect(
+            Ray::new(Point::new(2.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            &mut xs,
+        );
+        assert_eq!(xs.len(), 2);
+        let mut xs = Intersections::new();
+        g.intersect(
+            Ray::new(Point::new(0.0, 5.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            &mut xs,
+        );
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn splitting_bounds_along_the_longest_side() {
+        let b = BoundingBox::new(Point::new(-1.0, -2.0, -3.0), Point::new(9.0, 5.5, 3.0));
+        let (left, right) = split_bounds(&b);
+        assert_eq!(left.max, Point::new(4.0, 5.5, 3.0));
+        assert_eq!(right.min, Point::new(4.0, -2.0, -3.0));
+        let b = BoundingBox::new(Point::new(-1.0, -2.0, -3.0), Point::new(5.0, 3.0, 7.0));
+        let (left, right) = split_bounds(&b);
+        assert_eq!(left.max, Point::new(5.0, 3.0, 2.0));
+        assert_eq!(right.min, Point::new(-1.0, -2.0, 2.0));
+    }
+
+    fn at(x: f64, y: f64, z: f64) -> Object {
+        sphere().set_transform(translation(x, y, z))
+    }
+
+    #[test]
+    fn dividing_a_group() {
+        // the book's example, two spheres on either side and one across
+        // the middle
+        let g = Group::new("g")
+            .add_child(at(-2.0, -2.0, 0.0))
+            .add_child(at(-2.0, 2.0, 0.0))
+            .add_child(sphere().set_transform(scaling(4.0, 4.0, 4.0)))
+            .divide(1);
+        assert_eq!(g.children().len(), 1);
+        assert_eq!(g.groups().len(), 1);
+        let sub = &g.groups()[0];
+        assert_eq!(sub.groups().len(), 2);
+        assert_eq!(sub.groups()[0].children().len(), 1);
+        assert_eq!(sub.groups()[1].children().len(), 1);
+
+        // small groups are left alone but their sub groups aren't
+        let g = Group::new("g")
+            .add_child(at(-2.0, 0.0, 0.0))
+            .add_child(at(2.0, 1.0, 0.0))
+            .add_child(at(2.0, -1.0, 0.0))
+            .add_group(
+                Group::new("inner")
+                    .add_child(at(-2.0, 0.0, 0.0))
+                    .add_child(at(-2.0, 2.0, 0.0))
+                    .add_child(at(2.0, 1.0, 0.0))
+                    .add_child(at(2.0, -1.0, 0.0)),
+            )
+            .divide(4);
+        assert_eq!(g.children().len(), 3);
+        let inner = g.find("inner").unwrap();
+        assert!(inner.children().is_empty());
+        assert_eq!(inner.groups().len(), 2);
+    }
+
+    #[test]
+    fn dividing_keeps_what_rays_hit() {
+        let mut g = Group::new("grid");
+        for i in 0..50 {
+            g.push_child(at((i % 10) as f64 * 3.0, (i / 10) as f64 * 3.0, 0.0));
+        }
+        let divided = g.clone().divide(4);
+        assert_eq!(divided.objects().len(), 50);
+        assert_eq!(divided.bounds(), g.bounds());
+        for x in 0..10 {
+            let r = Ray::new(
+                Point::new(x as f64 * 3.0, 6.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+            );
+            let (mut a, mut b) = (Intersections::new(), Intersections::new());
+            g.intersect(r, &mut a);
+            divided.intersect(r, &mut b);
+            a.sort();
+            b.sort();
+            assert_eq!(a.len(), 2);
+            assert_eq!(
+                a.into_iter().map(|i| i.t).collect::<Vec<_>>(),
+                b.into_iter().map(|i| i.t).collect::<Vec<_>>()
+            );
+        }
+    }
+}
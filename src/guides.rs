@@ -0,0 +1,147 @@
+use crate::bounds::BoundingBox;
+use crate::color::Color;
+use crate::material::Material;
+use crate::matrix::Matrix4;
+use crate::object::Object;
+use crate::slab::slab;
+use crate::sphere::sphere;
+use crate::transformations::{rotation_y, rotation_z, scaling, translation, Transformable};
+use crate::tuple::Point;
+use crate::world::World;
+use std::f64::consts::FRAC_PI_2;
+
+// orientation aids for composing scenes: colored axis arrows at the
+// origin and a grid on the ground. they are flat colored so they read the
+// same under any lighting, but they do cast shadows like anything else
+
+fn flat(color: Color) -> Material {
+    Material {
+        color,
+        ambient: 1.0,
+        diffuse: 0.0,
+        specular: 0.0,
+        ..Default::default()
+    }
+}
+
+// an arrow along +x turned into place, a thin shaft with a stretched
+// sphere for the head
+fn arrow(length: f64, turn: &Matrix4, color: Color) -> [Object; 2] {
+    let thickness = length / 40.0;
+    let head = length / 10.0;
+    let shaft = slab(length - head, thickness, thickness)
+        .transformed(&(turn.clone() * translation((length - head) / 2.0, 0.0, 0.0)));
+    let tip = sphere().set_transform(
+        turn.clone()
+            * translation(length - head, 0.0, 0.0)
+            * scaling(head, thickness * 2.0, thickness * 2.0),
+    );
+    [shaft, tip].map(|part| part.set_material(flat(color)))
+}
+
+// red x, green y and blue z arrows of the given length
+pub fn axis_arrows(length: f64) -> Vec<Object> {
+    [
+        (Matrix4::identity(4), Color::new(1.0, 0.0, 0.0)),
+        (rotation_z(FRAC_PI_2), Color::new(0.0, 1.0, 0.0)),
+        (rotation_y(-FRAC_PI_2), Color::new(0.0, 0.0, 1.0)),
+    ]
+    .iter()
+    .flat_map(|(turn, color)| arrow(length, turn, *color))
+    .collect()
+}
+
+// lines every `spacing` units on the y = 0 plane out to `extent` in each
+// direction, made of thin slabs sunk halfway into the ground
+pub fn ground_grid(extent: f64, spacing: f64) -> Vec<Object> {
+    let lines = (extent / spacing).floor() as i64;
+    let width = spacing / 50.0;
+    let material = flat(Color::new(0.5, 0.5, 0.5));
+    (-lines..=lines)
+        .flat_map(|i| {
+            let at = i as f64 * spacing;
+            [
+                slab(2.0 * extent, width, width).transformed(&translation(0.0, 0.0, at)),
+                slab(width, width, 2.0 * extent).transformed(&translation(at, 0.0, 0.0)),
+            ]
+        })
+        .map(|line| line.set_material(material))
+        .collect()
+}
+
+impl World {
+    // adds axis arrows and a ground grid sized to the scene. they are plain
+    // objects until hide_guides takes them out again
+    pub fn show_guides(&mut self) {
+        if !self.guides.is_empty() {
+            return;
+        }
+        let mut bounds = BoundingBox::empty();
+        bounds.add_point(Point::new(0.0, 0.0, 0.0));
+        bounds.merge(&self.bounds());
+        let size = (bounds.max - bounds.min).magnitude().max(1.0);
+        // a power of ten giving ten to a hundred lines either side
+        let spacing = 10f64.powf(size.log10().floor() - 1.0);
+        let mut guides = axis_arrows(size / 4.0);
+        guides.extend(ground_grid(size, spacing));
+        self.guides = guides.iter().map(|g| g.id()).collect();
+        self.objects.extend(guides);
+    }
+
+    pub fn hide_guides(&mut self) {
+        let guides = std::mem::take(&mut self.guides);
+        self.objects.retain(|o| !guides.contains(&o.id()));
+    }
+
+    pub fn guides_shown(&self) -> bool {
+        !self.guides.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::tuple::Vector;
+    use crate::world::default_world;
+
+    #[test]
+    fn arrows_point_along_their_axes() {
+        let arrows = axis_arrows(2.0);
+        assert_eq!(arrows.len(), 6);
+        let tips = [arrows[1].bounds(), arrows[3].bounds(), arrows[5].bounds()];
+        assert!((tips[0].max.0.x - 2.0).abs() < 1e-9);
+        assert!((tips[1].max.0.y - 2.0).abs() < 1e-9);
+        assert!((tips[2].max.0.z - 2.0).abs() < 1e-9);
+        assert_eq!(arrows[2].material.color, Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn grid_lines_cross_the_ground() {
+        let grid = ground_grid(2.0, 1.0);
+        assert_eq!(grid.len(), 10);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let on_line = Ray::new(Point::new(1.0, 1.0, 0.3), down);
+        let between = Ray::new(Point::new(0.5, 1.0, 0.5), down);
+        assert!(grid.iter().any(|g| g.intersect(on_line).hit().is_some()));
+        assert!(grid.iter().all(|g| g.intersect(between).hit().is_none()));
+    }
+
+    #[test]
+    fn guides_toggle_on_and_off() {
+        let mut w = default_world();
+        w.show_guides();
+        assert!(w.guides_shown());
+        let shown = w.objects.len();
+        assert!(shown > 2);
+        w.show_guides();
+        assert_eq!(w.objects.len(), shown);
+        // a copy of the world still knows which objects are guides
+        let mut copy = w.clone();
+        copy.hide_guides();
+        assert_eq!(copy.objects.len(), 2);
+        w.hide_guides();
+        assert!(!w.guides_shown());
+        assert_eq!(w.objects.len(), 2);
+    }
+}
@@ -1,24 +1,42 @@
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
 use crate::ray::Ray;
-use crate::sphere::Sphere;
-use crate::tuple::{Point, Vector};
+use crate::tuple::{Point, Vector, EPSILON};
+use core::cmp::Ordering;
+use core::ops::Deref;
+use smallvec::SmallVec;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Intersection<'a> {
     pub t: f64,
-    pub object: &'a Sphere,
+    pub object: &'a Object,
+    // where on the surface the ray hit, see shape::LocalHit
+    pub u: f64,
+    pub v: f64,
 }
 
 impl<'a> Intersection<'a> {
-    pub fn new(t: f64, object: &Sphere) -> Intersection {
-        Intersection { t, object }
+    pub fn new(t: f64, object: &'a Object) -> Intersection<'a> {
+        Intersection::with_uv(t, object, 0.0, 0.0)
     }
 
-    pub fn prepare_computations(&self, ray: Ray) -> Computations {
+    pub fn with_uv(t: f64, object: &'a Object, u: f64, v: f64) -> Intersection<'a> {
+        Intersection { t, object, u, v }
+    }
+
+    pub fn prepare_computations(&self, ray: Ray) -> Computations<'a> {
+        self.prepare_computations_with_bias(ray, EPSILON)
+    }
+
+    // bias is how far over_point is pushed off the surface, it should grow
+    // with the scale of the scene to avoid acne on large objects
+    pub fn prepare_computations_with_bias(&self, ray: Ray, bias: f64) -> Computations<'a> {
         let t = self.t;
         let point = ray.position(self.t);
         let object = self.object;
-        let eyev: Vector = (-ray.direction).try_into().unwrap();
-        let mut normal = self.object.normal_at(point);
+        let eyev: Vector = -ray.direction;
+        let mut normal = self.object.normal_at_hit(point, self);
         let mut inside = false;
 
         if normal.dot(eyev) < 0.0 {
@@ -32,52 +50,241 @@ impl<'a> Intersection<'a> {
             point,
             eyev,
             normal,
+            reflectv: ray.direction.reflect(normal),
             inside,
-            over_point: point + normal * crate::tuple::EPSILON,
+            over_point: point + normal * bias,
+            under_point: point + normal * -bias,
+            from: None,
+            to: None,
+            n1: 1.0,
+            n2: 1.0,
+        }
+    }
+
+    // same as prepare_computations_with_bias but also works out which
+    // objects the ray leaves and enters from all intersections along it
+    pub fn prepare_computations_in(
+        &self,
+        ray: Ray,
+        xs: &Intersections<'a>,
+        bias: f64,
+    ) -> Computations<'a> {
+        let mut comps = self.prepare_computations_with_bias(ray, bias);
+        let mut containers: Vec<&'a Object> = vec![];
+        let mut sorted = xs.list.iter().collect::<Vec<_>>();
+        if !xs.sorted {
+            sorted.sort_by(|a, b| a.t.total_cmp(&b.t));
         }
+        for i in sorted {
+            let is_hit = i.t == self.t && i.object.id() == self.object.id();
+            if is_hit {
+                comps.from = containers.last().copied();
+            }
+            match containers.iter().position(|o| o.id() == i.object.id()) {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => containers.push(i.object),
+            }
+            if is_hit {
+                comps.to = containers.last().copied();
+                break;
+            }
+        }
+        let index = |o: Option<&Object>| o.map_or(1.0, |o| o.material.refractive_index);
+        comps.n1 = index(comps.from);
+        comps.n2 = index(comps.to);
+        comps
     }
 }
 
 pub struct Computations<'a> {
-    pub object: &'a Sphere,
+    pub object: &'a Object,
     pub t: f64,
     pub point: Point,
     pub eyev: Vector,
     pub normal: Vector,
+    // the incoming ray mirrored about the normal
+    pub reflectv: Vector,
     pub inside: bool,
     pub over_point: Point,
+    // just below the surface, where refracted rays start
+    pub under_point: Point,
+    // objects the ray is inside of before and after crossing the surface,
+    // None is empty space
+    pub from: Option<&'a Object>,
+    pub to: Option<&'a Object>,
+    // their refractive indices
+    pub n1: f64,
+    pub n2: f64,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Intersections<'a>(pub Vec<Intersection<'a>>);
+// hits kept on the stack before the list spills to the heap, covers
+// most rays through simple scenes without allocating
+pub const INLINE_INTERSECTIONS: usize = 8;
+
+type List<'a> = SmallVec<[Intersection<'a>; INLINE_INTERSECTIONS]>;
+
+// order of hits at the same depth, the higher priority object first, then
+// the nearer hit and last the object made first, so every tie has one
+// answer
+fn tie_order(a: &Intersection, b: &Intersection) -> Ordering {
+    b.object
+        .priority
+        .cmp(&a.object.priority)
+        .then(a.t.total_cmp(&b.t))
+        .then(a.object.id().cmp(&b.object.id()))
+}
+
+// by t with nan after everything, whatever its sign. total_cmp alone puts
+// a negative nan first, where it would break the partition on t < 0
+fn nearest_first(a: &Intersection, b: &Intersection) -> Ordering {
+    let t = |x: &Intersection| if x.t.is_nan() { f64::INFINITY } else { x.t };
+    t(a).total_cmp(&t(b))
+        .then(a.t.is_nan().cmp(&b.t.is_nan()))
+        .then_with(|| tie_order(a, b))
+}
+
+#[derive(Debug, Clone)]
+pub struct Intersections<'a> {
+    list: List<'a>,
+    sorted: bool,
+}
+
+impl<'a> Default for Intersections<'a> {
+    fn default() -> Self {
+        Intersections::new()
+    }
+}
 
 impl<'a> Intersections<'a> {
     pub fn new() -> Intersections<'a> {
-        Intersections(Vec::new())
+        Intersections {
+            list: SmallVec::new(),
+            sorted: true,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // pushing and concatenating leave the list unsorted, call sort() once
+    // after everything is collected
     pub fn push(&mut self, intersection: Intersection<'a>) {
-        self.0.push(intersection);
-        self.0.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        self.list.push(intersection);
+        self.sorted = false;
+    }
+
+    pub fn concat(&mut self, other: Intersections<'a>) {
+        self.list.extend(other.list);
+        self.sorted = false;
+    }
+
+    pub fn sort(&mut self) {
+        if !self.sorted {
+            self.list.sort_by(nearest_first);
+            self.sorted = true;
+        }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
     }
 
-    pub fn concat(&mut self, mut other: Intersections<'a>) {
-        self.0.append(&mut other.0);
-        self.0.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    // the nearest non-negative hit, see hit_with_tolerance. uses the
+    // global EPSILON, worlds pass their own settings.epsilon
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
+        self.hit_with_tolerance(EPSILON)
     }
 
-    pub fn hit(&self) -> Option<Intersection> {
-        self.0
+    // coplanar surfaces meet the ray at the same t give or take rounding,
+    // hits within tolerance of the nearest are settled by tie_order
+    // instead of by whichever rounded nearer, so the surface that shows
+    // doesn't flicker from frame to frame. the tolerance should grow with
+    // the scale of the scene like the other epsilons
+    pub fn hit_with_tolerance(&self, tolerance: f64) -> Option<&Intersection<'a>> {
+        if self.sorted {
+            // first non-negative t in a sorted list
+            let index = self.list.partition_point(|x| x.t < 0.0);
+            let nearest = self.list.get(index)?.t;
+            return self.list[index..]
+                .iter()
+                .take_while(|x| x.t < nearest + tolerance)
+                .min_by(|a, b| tie_order(a, b));
+        }
+        let nearest = self
+            .list
             .iter()
             .filter(|x| x.t >= 0.0)
-            .reduce(|a, b| if a.t < b.t { a } else { b })
-            .map(|x| x.clone())
+            .map(|x| x.t)
+            .reduce(f64::min)?;
+        self.list
+            .iter()
+            .filter(|x| x.t >= nearest && x.t < nearest + tolerance)
+            .min_by(|a, b| tie_order(a, b))
+    }
+
+    // every intersection in front of the ray origin, nearest first
+    pub fn hits(&self) -> Vec<&Intersection<'a>> {
+        if self.sorted {
+            let index = self.list.partition_point(|x| x.t < 0.0);
+            return self.list[index..].iter().filter(|x| x.t >= 0.0).collect();
+        }
+        let mut hits = self.list.iter().filter(|x| x.t >= 0.0).collect::<Vec<_>>();
+        hits.sort_by(|a, b| nearest_first(a, b));
+        hits
+    }
+}
+
+impl<'a> PartialEq for Intersections<'a> {
+    fn eq(&self, other: &Intersections<'a>) -> bool {
+        self.list == other.list
+    }
+}
+
+impl<'a> Deref for Intersections<'a> {
+    type Target = [Intersection<'a>];
+    fn deref(&self) -> &Self::Target {
+        &self.list
+    }
+}
+
+impl<'a> IntoIterator for Intersections<'a> {
+    type Item = Intersection<'a>;
+    type IntoIter = smallvec::IntoIter<[Intersection<'a>; INLINE_INTERSECTIONS]>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Intersections<'a> {
+    type Item = &'b Intersection<'a>;
+    type IntoIter = core::slice::Iter<'b, Intersection<'a>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.iter()
     }
 }
 
 impl<'a> From<Vec<Intersection<'a>>> for Intersections<'a> {
-    fn from(x: Vec<Intersection<'a>>) -> Intersections {
-        Intersections(x)
+    fn from(list: Vec<Intersection<'a>>) -> Intersections<'a> {
+        Intersections {
+            list: SmallVec::from_vec(list),
+            sorted: false,
+        }
+    }
+}
+
+impl<'a, const N: usize> From<[Intersection<'a>; N]> for Intersections<'a> {
+    fn from(list: [Intersection<'a>; N]) -> Intersections<'a> {
+        Intersections {
+            list: list.into_iter().collect(),
+            sorted: false,
+        }
     }
 }
 
@@ -85,32 +292,32 @@ impl<'a> From<Vec<Intersection<'a>>> for Intersections<'a> {
 mod tests {
     use super::*;
     use crate::ray::Ray;
-    use crate::sphere::Sphere;
+    use crate::sphere::sphere;
     use crate::tuple::{Point, Vector};
     #[test]
     fn intersection_encapsulates_t_and_object() {
         let t = 3.5;
-        let s = Sphere::new();
+        let s = sphere();
         let intersection = Intersection::new(t, &s);
         assert_eq!(intersection.t, t);
         assert_eq!(intersection.object, &s);
     }
     #[test]
     fn aggregating_intersections() {
-        let s = Sphere::new();
+        let s = sphere();
         let i1 = Intersection::new(1.0, &s);
         let i2 = Intersection::new(2.0, &s);
         let xs: Intersections = vec![i1, i2].into();
 
-        assert_eq!(xs.0.len(), 2);
-        assert_eq!(xs.0[0].object, &s);
-        assert_eq!(xs.0[1].object, &s);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object, &s);
+        assert_eq!(xs[1].object, &s);
     }
     #[test]
     fn intersect_sets_the_object() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].object, &s);
@@ -119,27 +326,27 @@ mod tests {
 
     #[test]
     fn hit_all_positive_intersections() {
-        let s = Sphere::new();
+        let s = sphere();
         let i1 = Intersection::new(1.0, &s);
         let i2 = Intersection::new(2.0, &s);
         let xs: Intersections = vec![i1, i2].into();
         let hit = xs.hit();
-        assert_eq!(hit, Some(i1));
+        assert_eq!(hit, Some(&i1));
     }
 
     #[test]
     fn hit_some_negative_intersections() {
-        let s = Sphere::new();
+        let s = sphere();
         let i1 = Intersection::new(-1.0, &s);
         let i2 = Intersection::new(2.0, &s);
         let xs: Intersections = vec![i1, i2].into();
         let hit = xs.hit();
-        assert_eq!(hit, Some(i2));
+        assert_eq!(hit, Some(&i2));
     }
 
     #[test]
     fn hit_all_negative_intersections() {
-        let s = Sphere::new();
+        let s = sphere();
         let i1 = Intersection::new(-1.0, &s);
         let i2 = Intersection::new(-2.0, &s);
         let xs: Intersections = vec![i1, i2].into();
@@ -149,20 +356,129 @@ mod tests {
 
     #[test]
     fn hit_lowest_nonnegative_intersection() {
-        let s = Sphere::new();
+        let s = sphere();
         let i1 = Intersection::new(5.0, &s);
         let i2 = Intersection::new(7.0, &s);
         let i3 = Intersection::new(-3.0, &s);
         let i4 = Intersection::new(2.0, &s);
         let xs: Intersections = vec![i1, i2, i3, i4].into();
         let hit = xs.hit();
-        assert_eq!(hit, Some(i4));
+        assert_eq!(hit, Some(&i4));
+    }
+
+    #[test]
+    fn hit_on_sorted_intersections() {
+        let s = sphere();
+        let i1 = Intersection::new(5.0, &s);
+        let i2 = Intersection::new(7.0, &s);
+        let i3 = Intersection::new(-3.0, &s);
+        let i4 = Intersection::new(2.0, &s);
+        let mut xs: Intersections = vec![i1, i2, i3, i4].into();
+        xs.sort();
+        assert_eq!(xs.hit(), Some(&i4));
+
+        let mut xs: Intersections = vec![i3].into();
+        xs.sort();
+        assert_eq!(xs.hit(), None);
+    }
+
+    #[test]
+    fn ties_go_to_the_higher_priority() {
+        let floor = sphere();
+        let decal = sphere().set_priority(1);
+        // rounding puts the decal a hair behind the floor
+        let under = Intersection::new(4.0, &floor);
+        let over = Intersection::new(4.0 + 1e-9, &decal);
+        for list in [vec![under, over], vec![over, under]] {
+            let mut xs: Intersections = list.into();
+            assert_eq!(xs.hit(), Some(&over));
+            xs.sort();
+            assert_eq!(xs.hit(), Some(&over));
+        }
+        // clearly behind is behind, whatever the priority
+        let behind = Intersection::new(4.1, &decal);
+        let xs: Intersections = vec![behind, under].into();
+        assert_eq!(xs.hit(), Some(&under));
+        // unless the scene is big enough for that to be rounding
+        assert_eq!(xs.hit_with_tolerance(0.5), Some(&behind));
+        // equal priorities at exactly the same t go to the older object
+        let twin = sphere();
+        let xs: Intersections = vec![Intersection::new(4.0, &twin), under].into();
+        assert_eq!(xs.hit().unwrap().object.id(), floor.id());
+    }
+
+    #[test]
+    fn push_does_not_sort_until_asked() {
+        let s = sphere();
+        let mut xs = Intersections::new();
+        xs.push(Intersection::new(5.0, &s));
+        xs.push(Intersection::new(-1.0, &s));
+        xs.push(Intersection::new(2.0, &s));
+        assert_eq!(xs.len(), 3);
+        assert!(!xs.is_sorted());
+        assert_eq!(xs[0].t, 5.0);
+        assert_eq!(xs.hit().map(|i| i.t), Some(2.0));
+
+        xs.sort();
+        assert!(xs.is_sorted());
+        let ts: Vec<f64> = xs.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![-1.0, 2.0, 5.0]);
+        assert_eq!(xs.hit().map(|i| i.t), Some(2.0));
+        let mut xs = Intersections::new();
+        xs.push(Intersection::new(3.0, &s));
+        xs.push(Intersection::new(-f64::NAN, &s));
+        xs.push(Intersection::new(2.0, &s));
+        xs.sort();
+        assert_eq!(xs[0].t, 2.0);
+        assert_eq!(xs.hit().map(|i| i.t), Some(2.0));
+        assert_eq!(
+            xs.hits().iter().map(|i| i.t).collect::<Vec<_>>(),
+            vec![2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn nan_intersections_sort_last() {
+        let s = sphere();
+        let mut xs = Intersections::new();
+        xs.push(Intersection::new(f64::NAN, &s));
+        xs.push(Intersection::new(2.0, &s));
+        assert_eq!(xs.hits().len(), 1);
+        xs.sort();
+        assert_eq!(xs[0].t, 2.0);
+        assert_eq!(xs.hit().map(|i| i.t), Some(2.0));
+    }
+
+    #[test]
+    fn iterate_over_intersections() {
+        let s = sphere();
+        let xs: Intersections = vec![Intersection::new(1.0, &s), Intersection::new(2.0, &s)].into();
+        let mut total = 0.0;
+        for i in &xs {
+            total += i.t;
+        }
+        assert_eq!(total, 3.0);
+        assert_eq!(xs.into_iter().count(), 2);
+        assert!(Intersections::new().is_empty());
+    }
+
+    #[test]
+    fn hits_returns_all_nonnegative_in_order() {
+        let s = sphere();
+        let i1 = Intersection::new(5.0, &s);
+        let i2 = Intersection::new(7.0, &s);
+        let i3 = Intersection::new(-3.0, &s);
+        let i4 = Intersection::new(2.0, &s);
+        let mut xs: Intersections = vec![i1, i2, i3, i4].into();
+        assert_eq!(xs.hits(), vec![&i4, &i1, &i2]);
+        xs.sort();
+        assert_eq!(xs.hits(), vec![&i4, &i1, &i2]);
     }
 
     #[test]
     fn precompute_state_of_intersection() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
+        let s = sphere();
         let i = Intersection::new(4.0, &s);
         let comps = i.prepare_computations(r);
 
@@ -175,7 +491,7 @@ mod tests {
     #[test]
     fn test_hit_on_intersection_on_outside() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
+        let s = sphere();
         let i = Intersection::new(4.0, &s);
         let comps = i.prepare_computations(r);
 
@@ -184,7 +500,7 @@ mod tests {
     #[test]
     fn test_hit_on_intersection_on_inside() {
         let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
+        let s = sphere();
         let i = Intersection::new(1.0, &s);
         let comps = i.prepare_computations(r);
 
@@ -193,4 +509,98 @@ mod tests {
         assert_eq!(comps.normal, Vector::new(0.0, 0.0, -1.0));
         assert!(comps.inside)
     }
+
+    #[test]
+    fn precomputing_the_reflection_vector() {
+        let half = core::f64::consts::FRAC_1_SQRT_2;
+        let s = sphere().set_transform(crate::transformations::translation(0.0, -2.0, 0.0));
+        let r = Ray::new(Point::new(0.0, 1.0, -1.0), Vector::new(0.0, -half, half));
+        let i = Intersection::new(2f64.sqrt(), &s);
+        let comps = i.prepare_computations(r);
+        assert_eq!(comps.normal, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(comps.reflectv, Vector::new(0.0, half, half));
+    }
+
+    #[test]
+    fn over_point_uses_given_bias() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let s = sphere();
+        let i = Intersection::new(4.0, &s);
+        let comps = i.prepare_computations_with_bias(r, 0.01);
+
+        assert_eq!(comps.over_point, Point::new(0.0, 0.0, -1.01));
+    }
+
+    #[test]
+    fn few_intersections_stay_inline() {
+        let s = sphere();
+        let mut xs = Intersections::new();
+        for i in 0..INLINE_INTERSECTIONS {
+            xs.push(Intersection::new(i as f64, &s));
+        }
+        assert!(!xs.list.spilled());
+        xs.push(Intersection::new(-1.0, &s));
+        assert!(xs.list.spilled());
+        xs.sort();
+        assert_eq!(xs.hit().unwrap().t, 0.0);
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        use crate::material::Material;
+        use crate::transformations::{scaling, translation};
+        let glass = |n: f64| Material {
+            transparency: 1.0,
+            refractive_index: n,
+            ..Default::default()
+        };
+        let a = sphere()
+            .set_transform(scaling(2.0, 2.0, 2.0))
+            .set_material(glass(1.5));
+        let b = sphere()
+            .set_transform(translation(0.0, 0.0, -0.25))
+            .set_material(glass(2.0));
+        // copies are objects of their own, the ray is inside both at once
+        let c = b
+            .clone()
+            .set_transform(translation(0.0, 0.0, 0.25))
+            .set_material(glass(2.5));
+        assert_ne!(c.id(), b.id());
+        let r = Ray::new(Point::new(0.0, 0.0, -4.0), Vector::new(0.0, 0.0, 1.0));
+        let xs: Intersections = vec![
+            Intersection::new(2.0, &a),
+            Intersection::new(2.75, &b),
+            Intersection::new(3.25, &c),
+            Intersection::new(4.75, &b),
+            Intersection::new(5.25, &c),
+            Intersection::new(6.0, &a),
+        ]
+        .into();
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (i, (n1, n2)) in xs.iter().zip(expected) {
+            let comps = i.prepare_computations_in(r, &xs, EPSILON);
+            assert_eq!((comps.n1, comps.n2), (n1, n2));
+        }
+    }
+
+    #[test]
+    fn under_point_is_below_the_surface() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let s = crate::sphere::glass_sphere()
+            .set_transform(crate::transformations::translation(0.0, 0.0, 1.0));
+        let i = Intersection::new(5.0, &s);
+        let xs: Intersections = vec![i].into();
+        let comps = i.prepare_computations_in(r, &xs, EPSILON);
+        assert!(comps.under_point.0.z > EPSILON / 2.0);
+        assert!(comps.point.0.z < comps.under_point.0.z);
+        assert_eq!(comps.to.map(|o| o.id()), Some(s.id()));
+        assert!(comps.from.is_none());
+    }
 }
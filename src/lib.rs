@@ -1,12 +1,107 @@
+// the math and intersection core builds without std (with alloc), the
+// rest of the crate needs the `std` feature which is on by default
+#![cfg_attr(all(not(feature = "std"), not(test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+mod no_std;
+
+#[cfg(feature = "std")]
+pub mod animation;
+#[cfg(feature = "std")]
+pub mod aov;
+#[cfg(feature = "std")]
+pub mod asset;
+#[cfg(feature = "std")]
+pub mod bake;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod bounds;
+pub mod brdf;
+pub mod bvh;
+#[cfg(feature = "std")]
 pub mod camera;
 pub mod canvas;
 pub mod color;
+#[cfg(feature = "std")]
+pub mod compare;
+#[cfg(feature = "std")]
+pub mod contact_sheet;
+pub mod csg;
+pub mod cylinder;
+pub mod decal;
+#[cfg(feature = "exact")]
+pub mod exact;
+#[cfg(feature = "std")]
+pub mod exposure;
+#[cfg(feature = "std")]
+pub mod font;
+#[cfg(feature = "std")]
+pub mod generators;
+pub mod group;
+#[cfg(feature = "std")]
+pub mod guides;
 pub mod intersection;
 pub mod light;
+pub mod lod;
+#[cfg(feature = "std")]
+pub mod lpe;
 pub mod material;
 pub mod matrix;
+#[cfg(feature = "std")]
+pub mod matte;
+pub mod noise;
+#[cfg(feature = "std")]
+pub mod obj_parser;
+pub mod object;
+pub mod pattern;
+#[cfg(feature = "std")]
+pub mod plate;
+#[cfg(feature = "std")]
+pub mod png;
+#[cfg(feature = "std")]
+pub mod polarization;
+pub mod portal;
+#[cfg(feature = "std")]
+pub mod post;
+#[cfg(feature = "std")]
+pub mod prepared;
+mod profile;
+#[cfg(feature = "std")]
+pub mod progressive;
 pub mod ray;
+#[cfg(feature = "std")]
+pub mod renderer;
+#[cfg(feature = "std")]
+pub mod resample;
+#[cfg(feature = "std")]
+pub mod rng;
+#[cfg(feature = "std")]
+pub mod sampling;
+#[cfg(feature = "std")]
+pub mod scene;
+pub mod settings;
+#[cfg(feature = "std")]
+pub mod setups;
+pub mod shape;
+pub mod shutter;
+pub mod slab;
+#[cfg(feature = "std")]
+pub mod spectral;
 pub mod sphere;
+#[cfg(test)]
+mod strategies;
+pub mod tonemap;
 pub mod transformations;
+pub mod triangle;
 pub mod tuple;
+#[cfg(feature = "std")]
+pub mod visualize;
+pub mod water;
+#[cfg(feature = "std")]
+pub mod wireframe;
+#[cfg(feature = "std")]
 pub mod world;
+#[cfg(feature = "std")]
+pub mod yaml;
@@ -1,9 +1,352 @@
-use crate::{color::Color, tuple::Point};
+use crate::canvas::Texture;
+use crate::color::{Color, BLACK};
+use crate::decal::Projector;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::transformations;
+use crate::tuple::{Point, Vector};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+// lumens per watt of light at 555nm, where the eye is most sensitive
+pub const LUMINOUS_EFFICACY: f64 = 683.0;
+
+// lights have no falloff, so their intensity stands for the light reaching
+// the subject. in photometric terms an intensity of one is an irradiance of
+// one watt per square meter, LUMINOUS_EFFICACY lux
+pub fn lux_to_intensity(lux: f64) -> f64 {
+    lux / LUMINOUS_EFFICACY
+}
+
+// illuminance at distance meters straight in front of a source of the given
+// luminous intensity
+pub fn candela_to_lux(candela: f64, distance: f64) -> f64 {
+    candela / (distance * distance)
+}
+
+// luminous intensity of a bare source sending its flux equally everywhere
+pub fn lumens_to_candela(lumens: f64) -> f64 {
+    lumens / (4.0 * core::f64::consts::PI)
+}
+
+// texture mask in front of a light, outside of the mask no light gets through
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gobo {
+    pub projector: Projector,
+    pub mask: Texture,
+}
+
+impl Gobo {
+    pub fn transmittance(&self, point: Point) -> Color {
+        match self.projector.project(point) {
+            Some((u, v)) => {
+                let x = (u * self.mask.width as f64) as isize;
+                let y = (v * self.mask.height as f64) as isize;
+                self.mask.read_pixel(x, y).unwrap_or(BLACK)
+            }
+            None => BLACK,
+        }
+    }
+}
+
+// how far away directional lights are put when something needs a
+// position for them, e.g. to aim shadow rays. far past any scene but
+// still leaving plenty of precision for the direction
+pub const DIRECTIONAL_DISTANCE: f64 = 1e9;
+
+// every kind of light a world can hold
+#[derive(Debug, Clone, PartialEq)]
+pub enum Light {
+    Point(PointLight),
+    Spot(SpotLight),
+    Area(AreaLight),
+    Directional(DirectionalLight),
+}
+
+impl Light {
+    // the light's color and brightness before anything shapes it
+    pub fn intensity(&self) -> Color {
+        match self {
+            Light::Point(l) => l.intensity,
+            Light::Spot(l) => l.intensity,
+            Light::Area(l) => l.intensity,
+            Light::Directional(l) => l.intensity,
+        }
+    }
+
+    // what reaches point from each of the sample positions, before
+    // shadows and the surface take their part
+    pub fn intensity_at(&self, point: Point) -> Color {
+        match self {
+            Light::Point(l) => l.intensity_at(point),
+            Light::Spot(l) => l.intensity_at(point),
+            Light::Area(l) => l.intensity,
+            Light::Directional(l) => l.intensity,
+        }
+    }
+
+    // where the light reaching point comes from. shading averages over
+    // these, checking shadows for each, so lights with several of them
+    // cast soft shadows
+    pub fn sample_positions(&self, point: Point) -> Vec<Point> {
+        match self {
+            Light::Point(l) => vec![l.position],
+            Light::Spot(l) => vec![l.position],
+            Light::Area(l) => l.sample_positions(),
+            Light::Directional(l) => {
+                vec![point + -l.direction.normalize() * DIRECTIONAL_DISTANCE]
+            }
+        }
+    }
+
+    // see PointLight
+    pub fn diffuse(&self) -> bool {
+        match self {
+            Light::Point(l) => l.diffuse,
+            Light::Spot(l) => l.diffuse,
+            Light::Area(l) => l.diffuse,
+            Light::Directional(l) => l.diffuse,
+        }
+    }
+
+    pub fn specular(&self) -> bool {
+        match self {
+            Light::Point(l) => l.specular,
+            Light::Spot(l) => l.specular,
+            Light::Area(l) => l.specular,
+            Light::Directional(l) => l.specular,
+        }
+    }
+}
+
+impl From<PointLight> for Light {
+    fn from(light: PointLight) -> Light {
+        Light::Point(light)
+    }
+}
+
+impl From<SpotLight> for Light {
+    fn from(light: SpotLight) -> Light {
+        Light::Spot(light)
+    }
+}
+
+impl From<AreaLight> for Light {
+    fn from(light: AreaLight) -> Light {
+        Light::Area(light)
+    }
+}
+
+impl From<DirectionalLight> for Light {
+    fn from(light: DirectionalLight) -> Light {
+        Light::Directional(light)
+    }
+}
+
+// a light shining only into a cone, as on a stage. angles are measured
+// from the axis, so they are half the cone's opening. light is at full
+// strength inside the inner angle and fades smoothly to nothing at the
+// outer one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLight {
+    pub position: Point,
+    pub direction: Vector,
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    pub intensity: Color,
+    pub gobo: Option<Gobo>,
+    pub diffuse: bool,
+    pub specular: bool,
+}
+
+impl SpotLight {
+    pub fn new(
+        position: Point,
+        direction: Vector,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            intensity,
+            gobo: None,
+            diffuse: true,
+            specular: true,
+        }
+    }
+
+    pub fn set_diffuse(mut self, diffuse: bool) -> SpotLight {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn set_specular(mut self, specular: bool) -> SpotLight {
+        self.specular = specular;
+        self
+    }
+
+    // mask stretched over the outer cone, place and aim the light first
+    // since the projector starts out along its axis
+    pub fn set_gobo(mut self, mask: impl Into<Texture>) -> SpotLight {
+        self.gobo = Some(Gobo {
+            projector: Projector::new(self.position, self.direction, 2.0 * self.outer_angle),
+            mask: mask.into(),
+        });
+        self
+    }
+
+    // keeps the light where it is and points its cone at target, turning
+    // the gobo along with it
+    pub fn aim_at(&mut self, target: Point) {
+        self.direction = target - self.position;
+        if let Some(gobo) = &mut self.gobo {
+            gobo.projector = Projector::new(self.position, self.direction, 2.0 * self.outer_angle);
+        }
+    }
+
+    // the cone's falloff with the gobo's shadow on top
+    pub fn intensity_at(&self, point: Point) -> Color {
+        let lit = self.intensity * self.falloff(point);
+        match &self.gobo {
+            Some(gobo) => lit * gobo.transmittance(point),
+            None => lit,
+        }
+    }
+
+    // share of the light reaching point, 0 to 1
+    pub fn falloff(&self, point: Point) -> f64 {
+        let cos = (point - self.position)
+            .normalize()
+            .dot(self.direction.normalize());
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.max(self.inner_angle).cos();
+        if cos >= cos_inner {
+            return 1.0;
+        }
+        if cos <= cos_outer {
+            return 0.0;
+        }
+        // smoothstep, so the edge of the pool of light has no visible ring
+        let t = (cos - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+// a glowing rectangle from corner along the full edges uvec and vvec. it
+// is sampled at the centers of a usteps by vsteps grid of cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct AreaLight {
+    pub corner: Point,
+    pub uvec: Vector,
+    pub usteps: u32,
+    pub vvec: Vector,
+    pub vsteps: u32,
+    pub intensity: Color,
+    pub diffuse: bool,
+    pub specular: bool,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Point,
+        uvec: Vector,
+        usteps: u32,
+        vvec: Vector,
+        vsteps: u32,
+        intensity: Color,
+    ) -> AreaLight {
+        AreaLight {
+            corner,
+            uvec,
+            usteps: usteps.max(1),
+            vvec,
+            vsteps: vsteps.max(1),
+            intensity,
+            diffuse: true,
+            specular: true,
+        }
+    }
+
+    pub fn set_diffuse(mut self, diffuse: bool) -> AreaLight {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn set_specular(mut self, specular: bool) -> AreaLight {
+        self.specular = specular;
+        self
+    }
+
+    pub fn center(&self) -> Point {
+        self.corner + (self.uvec + self.vvec) * 0.5
+    }
+
+    // keeps the rectangle's center and size and turns it to face target,
+    // uvec ends up level and vvec upright with uvec x vvec towards target
+    pub fn aim_at(&mut self, target: Point) {
+        let center = self.center();
+        let orientation = transformations::look_at(center, target);
+        let along = |v: Vector| Vector::try_from(&orientation * v).unwrap();
+        self.uvec = along(Vector::new(-self.uvec.magnitude(), 0.0, 0.0));
+        self.vvec = along(Vector::new(0.0, self.vvec.magnitude(), 0.0));
+        self.corner = center + -(self.uvec + self.vvec) * 0.5;
+    }
+
+    pub fn sample_positions(&self) -> Vec<Point> {
+        let (du, dv) = (
+            self.uvec * (1.0 / self.usteps as f64),
+            self.vvec * (1.0 / self.vsteps as f64),
+        );
+        (0..self.vsteps)
+            .flat_map(|v| (0..self.usteps).map(move |u| (u, v)))
+            .map(|(u, v)| self.corner + du * (u as f64 + 0.5) + dv * (v as f64 + 0.5))
+            .collect()
+    }
+}
+
+// light arriving in parallel from far away, like sunlight. direction is
+// the way the light travels
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vector,
+    pub intensity: Color,
+    pub diffuse: bool,
+    pub specular: bool,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction,
+            intensity,
+            diffuse: true,
+            specular: true,
+        }
+    }
+
+    pub fn set_diffuse(mut self, diffuse: bool) -> DirectionalLight {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn set_specular(mut self, specular: bool) -> DirectionalLight {
+        self.specular = specular;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PointLight {
     pub intensity: Color,
     pub position: Point,
+    pub gobo: Option<Gobo>,
+    // whether the light shows up in the diffuse and specular parts of
+    // shading, turning one off gives fill lights without highlights or
+    // lights that only add highlights
+    pub diffuse: bool,
+    pub specular: bool,
 }
 
 impl PointLight {
@@ -11,6 +354,59 @@ impl PointLight {
         PointLight {
             intensity,
             position,
+            gobo: None,
+            diffuse: true,
+            specular: true,
+        }
+    }
+
+    // a fixture rated in candela lighting a subject distance meters away.
+    // color only sets the hue, it is scaled to a luminance of one first.
+    // black has no hue to scale and gives a light that is off
+    pub fn from_candela(position: Point, candela: f64, color: Color, distance: f64) -> PointLight {
+        let luminance = color.luminance();
+        if luminance <= 0.0 {
+            return PointLight::new(position, BLACK);
+        }
+        let tint = color * (1.0 / luminance);
+        let intensity = lux_to_intensity(candela_to_lux(candela, distance));
+        PointLight::new(position, tint * intensity)
+    }
+
+    // same for a bare bulb rated by its total flux in lumens
+    pub fn from_lumens(position: Point, lumens: f64, color: Color, distance: f64) -> PointLight {
+        PointLight::from_candela(position, lumens_to_candela(lumens), color, distance)
+    }
+
+    pub fn set_diffuse(mut self, diffuse: bool) -> PointLight {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn set_specular(mut self, specular: bool) -> PointLight {
+        self.specular = specular;
+        self
+    }
+
+    // turns the light into a spot shining through mask, place the light
+    // before adding the gobo since the projector starts at its position
+    pub fn set_gobo(
+        mut self,
+        direction: Vector,
+        field_of_view: f64,
+        mask: impl Into<Texture>,
+    ) -> PointLight {
+        self.gobo = Some(Gobo {
+            projector: Projector::new(self.position, direction, field_of_view),
+            mask: mask.into(),
+        });
+        self
+    }
+
+    pub fn intensity_at(&self, point: Point) -> Color {
+        match &self.gobo {
+            Some(gobo) => self.intensity * gobo.transmittance(point),
+            None => self.intensity,
         }
     }
 }
@@ -18,6 +414,10 @@ impl PointLight {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::canvas::Canvas;
+    use static_assertions::assert_impl_all;
+    use std::f64::consts::PI;
+
     #[test]
     fn point_light_with_features() {
         let intensity = Color::new(0.5, 0.5, 0.5);
@@ -25,5 +425,178 @@ mod tests {
         let light = PointLight::new(position, intensity);
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
+        assert_eq!(light.intensity_at(Point::new(3.0, 2.0, 1.0)), intensity);
+    }
+
+    #[test]
+    fn photometric_lights() {
+        // a 100 candela lamp 2 meters away gives 25 lux
+        let warm = Color::new(1.0, 0.8, 0.6);
+        let lamp = PointLight::from_candela(Point::zero(), 100.0, warm, 2.0);
+        assert!((lamp.intensity.luminance() - 25.0 / LUMINOUS_EFFICACY).abs() < 1e-12);
+        let ratio = lamp.intensity.red / lamp.intensity.blue;
+        assert!((ratio - 1.0 / 0.6).abs() < 1e-9);
+        // an 800 lumen bulb is about 64 candela all around
+        let bulb = PointLight::from_lumens(Point::zero(), 800.0, warm, 2.0);
+        let same = PointLight::from_candela(Point::zero(), 800.0 / (4.0 * PI), warm, 2.0);
+        assert_eq!(bulb.intensity, same.intensity);
+        assert!((lumens_to_candela(800.0) - 63.66).abs() < 0.01);
+        assert_eq!(lux_to_intensity(LUMINOUS_EFFICACY), 1.0);
+        // black stays black instead of dividing by zero
+        let off = PointLight::from_lumens(Point::zero(), 800.0, BLACK, 2.0);
+        assert_eq!(off.intensity, BLACK);
+        assert!(!off.intensity.red.is_nan());
+    }
+
+    #[test]
+    fn gobo_masks_light_intensity() {
+        // vertical blinds, left half open and right half closed
+        let mut mask = Canvas::new(2, 1);
+        mask.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), Color::new(1.0, 0.5, 1.0))
+            .set_gobo(Vector::new(0.0, 0.0, 1.0), PI / 2.0, mask);
+        // looking down +z the projector's left is -x
+        assert_eq!(
+            light.intensity_at(Point::new(-1.0, 0.0, 0.0)),
+            Color::new(1.0, 0.5, 1.0)
+        );
+        assert_eq!(light.intensity_at(Point::new(1.0, 0.0, 0.0)), BLACK);
+        assert_eq!(light.intensity_at(Point::new(0.0, 0.0, -10.0)), BLACK);
+    }
+
+    #[test]
+    fn spot_lights_fade_between_their_cones() {
+        let light: Light = SpotLight::new(
+            Point::new(0.0, 10.0, 0.0),
+            Vector::new(0.0, -2.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into();
+        let at_angle = |a: f64| light.intensity_at(Point::new(10.0 * a.tan(), 0.0, 0.0));
+        assert_eq!(at_angle(0.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(at_angle(PI / 9.0), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(at_angle(PI / 3.9), BLACK);
+        // behind the light too
+        assert_eq!(light.intensity_at(Point::new(0.0, 20.0, 0.0)), BLACK);
+        // dimming steadily across the edge
+        let edge = (1..10)
+            .map(|i| at_angle(PI / 8.0 + PI / 8.0 * i as f64 / 10.0).red)
+            .collect::<Vec<_>>();
+        assert!(edge.windows(2).all(|w| w[1] < w[0]));
+        assert!(edge[0] > 0.9 && edge[8] < 0.1);
+        // a hard edged spot
+        let hard = SpotLight::new(Point::zero(), Vector::new(0.0, 0.0, 1.0), 0.3, 0.3, BLACK);
+        assert_eq!(hard.falloff(Point::new(0.0, 0.0, 1.0)), 1.0);
+        assert_eq!(hard.falloff(Point::new(1.0, 0.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn gobos_shade_spot_light_cones() {
+        let mut mask = Canvas::new(2, 1);
+        mask.write_pixel(0, 0, Color::new(1.0, 1.0, 1.0));
+        let spot = SpotLight::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .set_gobo(mask);
+        let light: Light = spot.clone().into();
+        // the open half is lit as the cone allows, the closed half is dark
+        assert_eq!(
+            light.intensity_at(Point::new(-0.5, 0.0, 0.0)),
+            Color::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(light.intensity_at(Point::new(0.5, 0.0, 0.0)), BLACK);
+        let edge = Point::new(-5.0 * (PI / 5.0).tan(), 0.0, 0.0);
+        let faded = light.intensity_at(edge).red;
+        assert!(faded > 0.0 && faded < 1.0);
+        assert_eq!(faded, spot.falloff(edge));
     }
+
+    #[test]
+    fn area_lights_sample_a_grid() {
+        let light: Light = AreaLight::new(
+            Point::new(-1.0, 2.0, 0.0),
+            Vector::new(2.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 0.0, 4.0),
+            4,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into();
+        let samples = light.sample_positions(Point::zero());
+        assert_eq!(samples.len(), 8);
+        assert_eq!(samples[0], Point::new(-0.5, 2.0, 0.5));
+        assert_eq!(samples[1], Point::new(0.5, 2.0, 0.5));
+        assert_eq!(samples[7], Point::new(0.5, 2.0, 3.5));
+        let Light::Area(area) = &light else {
+            unreachable!()
+        };
+        assert_eq!(area.center(), Point::new(0.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn aiming_lights_keeps_their_position() {
+        let mut spot = SpotLight::new(
+            Point::new(1.0, 2.0, 3.0),
+            Vector::new(0.0, 0.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .set_gobo(Canvas::new(1, 1));
+        spot.aim_at(Point::new(1.0, -8.0, 3.0));
+        assert_eq!(spot.position, Point::new(1.0, 2.0, 3.0));
+        assert_eq!(spot.falloff(Point::new(1.0, 0.0, 3.0)), 1.0);
+        assert_eq!(spot.falloff(Point::new(1.0, 2.0, 10.0)), 0.0);
+        let aimed = spot.clone().set_gobo(Canvas::new(1, 1));
+        assert_eq!(spot.gobo, aimed.gobo);
+
+        let mut area = AreaLight::new(
+            Point::new(-1.0, 5.0, -2.0),
+            Vector::new(2.0, 0.0, 0.0),
+            2,
+            Vector::new(0.0, 0.0, 4.0),
+            2,
+            Color::new(1.0, 1.0, 1.0),
+        );
+        area.aim_at(Point::new(10.0, 5.0, 0.0));
+        assert_eq!(area.center(), Point::new(0.0, 5.0, 0.0));
+        assert!((area.uvec.magnitude() - 2.0).abs() < 1e-9);
+        assert!((area.vvec.magnitude() - 4.0).abs() < 1e-9);
+        // the rectangle now stands upright facing +x
+        assert_eq!(
+            area.uvec.cross(area.vvec).normalize(),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(area.vvec, Vector::new(0.0, 4.0, 0.0));
+    }
+
+    #[test]
+    fn directional_lights_come_from_far_away() {
+        let sun: Light =
+            DirectionalLight::new(Vector::new(0.0, -2.0, 0.0), Color::new(1.0, 0.9, 0.8)).into();
+        for p in [Point::zero(), Point::new(50.0, -3.0, 7.0)] {
+            let [from] = sun.sample_positions(p)[..] else {
+                unreachable!()
+            };
+            assert_eq!((from - p).normalize(), Vector::new(0.0, 1.0, 0.0));
+            assert!((from - p).magnitude() >= DIRECTIONAL_DISTANCE * 0.99);
+            assert_eq!(sun.intensity_at(p), Color::new(1.0, 0.9, 0.8));
+        }
+        assert!(sun.diffuse() && sun.specular());
+        // a sun that only lights up highlights
+        let Light::Directional(sun) = sun else {
+            unreachable!()
+        };
+        let glint: Light = sun.set_diffuse(false).into();
+        assert!(!glint.diffuse() && glint.specular());
+    }
+
+    assert_impl_all!(PointLight: Send, Sync);
+    assert_impl_all!(Light: Send, Sync);
 }
@@ -0,0 +1,146 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersections;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
+use crate::ray::Ray;
+
+#[derive(Debug, Clone)]
+pub struct LodLevel {
+    // used for hits up to this far along the ray
+    pub max_distance: f64,
+    pub objects: Vec<Object>,
+}
+
+// one model at several levels of detail, finest first. a ray picks the
+// level by how far away it meets the group's bounds, beyond the last
+// threshold the coarsest level is used
+#[derive(Debug, Clone, Default)]
+pub struct LodGroup {
+    levels: Vec<LodLevel>,
+    bounds: BoundingBox,
+}
+
+impl LodGroup {
+    pub fn new() -> LodGroup {
+        LodGroup::default()
+    }
+
+    // levels have to be added from finest to coarsest
+    pub fn add_level(mut self, max_distance: f64, objects: Vec<Object>) -> LodGroup {
+        for object in &objects {
+            self.bounds.merge(&object.bounds());
+        }
+        self.levels.push(LodLevel {
+            max_distance,
+            objects,
+        });
+        self
+    }
+
+    // same as add_level but switching once the group is smaller than pixels
+    // wide, for a camera whose pixels cover pixel_size at unit distance
+    pub fn add_level_for_screen_size(
+        self,
+        pixels: f64,
+        pixel_size: f64,
+        objects: Vec<Object>,
+    ) -> LodGroup {
+        let mut bounds = self.bounds;
+        for object in &objects {
+            bounds.merge(&object.bounds());
+        }
+        let diameter = (bounds.max - bounds.min).magnitude();
+        self.add_level(diameter / (pixels * pixel_size), objects)
+    }
+
+    pub fn levels(&self) -> &[LodLevel] {
+        &self.levels
+    }
+
+    // see Group::objects_mut
+    pub fn levels_mut(&mut self) -> &mut [LodLevel] {
+        &mut self.levels
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    pub fn level_at(&self, distance: f64) -> Option<&LodLevel> {
+        self.levels
+            .iter()
+            .find(|level| distance <= level.max_distance)
+            .or(self.levels.last())
+    }
+
+    // appends the intersections with the level chosen by the distance to
+    // the group's bounds, unsorted
+    pub fn intersect<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        let Some((t_min, _)) = self.bounds.intersection(ray) else {
+            return;
+        };
+        let distance = t_min.max(0.0) * ray.direction.magnitude();
+        if let Some(level) = self.level_at(distance) {
+            for object in &level.objects {
+                out.concat(object.intersect(ray));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+    use crate::tuple::{Point, Vector};
+
+    fn group() -> LodGroup {
+        // a detailed pair of spheres that turns into one big sphere far away
+        let detailed = vec![
+            sphere().set_transform(translation(-1.0, 0.0, 0.0)),
+            sphere().set_transform(translation(1.0, 0.0, 0.0)),
+        ];
+        let coarse = vec![sphere().set_transform(scaling(2.0, 1.0, 1.0))];
+        LodGroup::new()
+            .add_level(10.0, detailed)
+            .add_level(100.0, coarse)
+    }
+
+    #[test]
+    fn picking_a_level_by_distance() {
+        let g = group();
+        assert_eq!(g.level_at(5.0).unwrap().objects.len(), 2);
+        assert_eq!(g.level_at(50.0).unwrap().objects.len(), 1);
+        assert_eq!(g.level_at(500.0).unwrap().objects.len(), 1);
+        assert!(LodGroup::new().level_at(1.0).is_none());
+        assert_eq!(g.bounds().min, Point::new(-2.0, -1.0, -1.0));
+    }
+
+    #[test]
+    fn near_rays_see_the_detailed_level() {
+        let g = group();
+        let ray = |z: f64| Ray::new(Point::new(0.0, 0.9, z), Vector::new(0.0, 0.0, 1.0));
+        // between the two detailed spheres, but inside the coarse one
+        let mut near = Intersections::new();
+        g.intersect(ray(-5.0), &mut near);
+        assert!(near.is_empty());
+        let mut far = Intersections::new();
+        g.intersect(ray(-50.0), &mut far);
+        assert_eq!(far.len(), 2);
+        assert!(far.hit().unwrap().t > 49.0);
+    }
+
+    #[test]
+    fn screen_size_thresholds_become_distances() {
+        let g = LodGroup::new().add_level_for_screen_size(
+            100.0,
+            0.01,
+            vec![sphere().set_transform(scaling(5.0, 5.0, 5.0))],
+        );
+        // 10 units across cover 100 pixels of 0.01 at distance 10 * sqrt(3)
+        let expected = 10.0 * 3f64.sqrt();
+        assert!((g.levels()[0].max_distance - expected).abs() < 1e-9);
+    }
+}
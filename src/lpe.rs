@@ -0,0 +1,186 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::{Color, BLACK};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::world::World;
+
+// the light seen along a ray split by the path it took, so the parts can
+// be weighed again after rendering. they add up to color_at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightPaths {
+    // straight from lights, scattered by the surface. sheen counts too
+    pub diffuse: Color,
+    // straight from lights, as highlights. models that don't scale their
+    // highlights by the material's specular leave them in diffuse
+    pub specular: Color,
+    // by way of reflection, refraction or a portal. shadow catchers go
+    // here whole, as their shadows only make sense with what's behind
+    pub indirect: Color,
+    // given off by the surface itself. there are no emissive materials,
+    // the ambient term is what stands in for light nothing casts
+    pub emission: Color,
+}
+
+impl LightPaths {
+    pub fn none() -> LightPaths {
+        LightPaths {
+            diffuse: BLACK,
+            specular: BLACK,
+            indirect: BLACK,
+            emission: BLACK,
+        }
+    }
+
+    fn indirect(color: Color) -> LightPaths {
+        LightPaths {
+            indirect: color,
+            ..LightPaths::none()
+        }
+    }
+
+    pub fn total(&self) -> Color {
+        self.diffuse + self.specular + self.indirect + self.emission
+    }
+}
+
+// a render as one canvas per kind of light path
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightPathPasses {
+    pub diffuse: Canvas,
+    pub specular: Canvas,
+    pub indirect: Canvas,
+    pub emission: Canvas,
+}
+
+impl LightPathPasses {
+    // the passes put back together, each scaled by its weight. all ones
+    // gives the render
+    pub fn combine(&self, diffuse: f64, specular: f64, indirect: f64, emission: f64) -> Canvas {
+        let mut out = Canvas::new(self.diffuse.width, self.diffuse.height);
+        for (i, p) in out.pixels.iter_mut().enumerate() {
+            *p = self.diffuse.pixels[i] * diffuse
+                + self.specular.pixels[i] * specular
+                + self.indirect.pixels[i] * indirect
+                + self.emission.pixels[i] * emission;
+        }
+        out
+    }
+
+    pub fn beauty(&self) -> Canvas {
+        self.combine(1.0, 1.0, 1.0, 1.0)
+    }
+}
+
+impl World {
+    // color_at taken apart, only the first hit is split up and whatever
+    // it sees in reflections and through itself is indirect
+    pub fn light_paths_at(&self, ray: Ray) -> LightPaths {
+        let inters = self.intersect(ray);
+        let hit = inters.hit_with_tolerance(self.settings.epsilon);
+        if let Some((t, _)) = self.nearest_portal(ray) {
+            if hit.is_none_or(|hit| t < hit.t) {
+                return LightPaths::indirect(self.color_at(ray));
+            }
+        }
+        let Some(hit) = hit else {
+            return LightPaths::none();
+        };
+        let comps = hit.prepare_computations_in(
+            ray,
+            &inters,
+            hit.object.surface_bias(self.settings.shadow_bias),
+        );
+        if hit.object.material.shadow_catcher {
+            return LightPaths::indirect(self.shadow_catch(&comps, self.settings.max_bounces).0);
+        }
+        let material = self.surface_material(&comps);
+        let matte = Material {
+            specular: 0.0,
+            ..material
+        };
+        let mut paths = LightPaths::none();
+        for light in &self.lights {
+            // the ambient term is the same lit or not and wherever on the
+            // light the sample is
+            let emission = material.color * light.intensity_at(comps.over_point) * material.ambient;
+            let with_diffuse = self.direct_light(matte, light, &comps, true);
+            let full = self.direct_light(material, light, &comps, true);
+            paths.emission = paths.emission + emission;
+            paths.diffuse = paths.diffuse + (with_diffuse - emission);
+            paths.specular = paths.specular + (full - with_diffuse);
+        }
+        paths.indirect = self.reflected_color(&comps, self.settings.max_bounces)
+            + self.refracted_color(&comps, self.settings.max_bounces);
+        paths
+    }
+}
+
+impl Camera {
+    // one ray through the middle of each pixel, like render with a single
+    // sample and no background plate
+    pub fn render_light_paths(&self, world: &World) -> LightPathPasses {
+        let paths = self.render_with(|ray| world.light_paths_at(ray));
+        let pass = |f: fn(&LightPaths) -> Color| {
+            let mut canvas = Canvas::new(self.hsize() as isize, self.vsize() as isize);
+            canvas.pixels = paths.iter().map(f).collect();
+            canvas
+        };
+        LightPathPasses {
+            diffuse: pass(|p| p.diffuse),
+            specular: pass(|p| p.specular),
+            indirect: pass(|p| p.indirect),
+            emission: pass(|p| p.emission),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(11, 11, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        camera
+    }
+
+    #[test]
+    fn passes_add_up_to_the_render() {
+        let mut w = default_world();
+        w.objects[0].material.transparency = 0.5;
+        w.objects[0].material.refractive_index = 1.5;
+        let passes = camera().render_light_paths(&w);
+        assert_eq!(passes.beauty(), camera().render(&w));
+        // the middle of the glass sphere is lit and shows the inner one
+        let middle = |c: &Canvas| c.read_pixel(5, 5).unwrap();
+        assert!(middle(&passes.diffuse).green > 0.1);
+        assert!(middle(&passes.emission).green > 0.0);
+        assert!(middle(&passes.indirect).green > 0.0);
+        // turning a pass off takes it out of the image
+        let dimmed = passes.combine(1.0, 0.0, 1.0, 1.0);
+        assert_eq!(
+            middle(&dimmed),
+            middle(&passes.beauty()) - middle(&passes.specular)
+        );
+    }
+
+    #[test]
+    fn misses_and_matte_surfaces() {
+        let w = default_world();
+        let sky = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(w.light_paths_at(sky), LightPaths::none());
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let paths = w.light_paths_at(r);
+        assert_eq!(paths.indirect, BLACK);
+        assert_eq!(paths.total(), w.color_at(r));
+    }
+}
@@ -0,0 +1,79 @@
+use ray_tracer::asset::AssetResolver;
+use ray_tracer::canvas::Canvas;
+use ray_tracer::compare::{diff_canvases, rmse, ssim};
+use ray_tracer::scene::{self, diff_scenes};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "usage: ray-tracer diff <a.yaml|a.ppm> <b.yaml|b.ppm> [--error out.ppm]";
+
+fn is_image(path: &str) -> bool {
+    path.ends_with(".ppm")
+}
+
+// exit code 0 when the inputs match, 1 when they differ, 2 on errors
+fn diff(args: &[String]) -> Result<bool, String> {
+    let (a, b, error_path) = match args {
+        [a, b] => (a, b, None),
+        [a, b, flag, out] if flag == "--error" => (a, b, Some(out)),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    // paths are relative to the working directory, absolute ones still work
+    let assets = AssetResolver::new().with_search_path(".");
+    if is_image(a) && is_image(b) {
+        let load = |path: &str| {
+            let src = assets
+                .read_to_string(path)
+                .map_err(|e| format!("{}: {:?}", path, e))?;
+            Canvas::from_ppm(&src).map_err(|e| format!("{}: {:?}", path, e))
+        };
+        let (ca, cb) = (load(a)?, load(b)?);
+        let d = diff_canvases(&ca, &cb).map_err(|e| format!("{:?}", e))?;
+        let rmse = rmse(&ca, &cb).map_err(|e| format!("{:?}", e))?;
+        let ssim = ssim(&ca, &cb).map_err(|e| format!("{:?}", e))?;
+        println!(
+            "{} differing pixels, max channel error {:.4}",
+            d.differing_pixels, d.max_error
+        );
+        println!(
+            "rmse r {:.4} g {:.4} b {:.4}, ssim {:.4}",
+            rmse.red, rmse.green, rmse.blue, ssim
+        );
+        if let Some(out) = error_path {
+            fs::write(out, d.error.to_ppm()).map_err(|e| format!("{}: {}", out, e))?;
+        }
+        Ok(d.is_identical())
+    } else if !is_image(a) && !is_image(b) {
+        if error_path.is_some() {
+            return Err("--error only applies to images".to_string());
+        }
+        let load =
+            |path: &str| scene::load(&assets, path).map_err(|e| format!("{}: {:?}", path, e));
+        let (wa, ca) = load(a)?;
+        let (wb, cb) = load(b)?;
+        let changes = diff_scenes((&wa, &ca), (&wb, &cb));
+        for change in &changes {
+            println!("{}", change);
+        }
+        Ok(changes.is_empty())
+    } else {
+        Err("cannot compare a scene with an image".to_string())
+    }
+}
+
+fn main() -> ExitCode {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.split_first() {
+        Some((command, rest)) if command == "diff" => diff(rest),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -1,41 +1,138 @@
-use crate::color::{Color, BLACK};
-use crate::light::PointLight;
+use crate::brdf::{Brdf, LightSample, ShadingModel, Sheen};
+use crate::color::Color;
+use crate::light::Light;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::pattern::Pattern;
 use crate::tuple::{Point, Vector};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Material {
     pub color: Color,
+    // paints the surface instead of the plain color
+    pub pattern: Option<Pattern>,
     pub ambient: f64,
     pub diffuse: f64,
     pub specular: f64,
     pub shininess: f64,
+    // share of the light mirrored off the surface, 1 is a perfect mirror
+    pub reflective: f64,
+    // share of the light coming through the surface from behind
+    pub transparency: f64,
+    pub refractive_index: f64,
+    // how strongly the index changes with wavelength, lower numbers split
+    // light more. infinite means no dispersion
+    pub abbe_number: f64,
+    pub model: ShadingModel,
+    // velvet like brightening where light grazes past the eye, added on
+    // top of the model. the tint blends its color from white towards the
+    // surface color
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    // shows only the shadows and reflections the surface receives, for
+    // compositing onto photographs, see World::shadow_catch
+    pub shadow_catcher: bool,
 }
 
 impl Default for Material {
     fn default() -> Self {
         Material {
             color: Color::new(1.0, 1.0, 1.0),
+            pattern: None,
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.0,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            abbe_number: f64::INFINITY,
+            model: ShadingModel::Phong,
+            sheen: 0.0,
+            sheen_tint: 0.0,
+            shadow_catcher: false,
         }
     }
 }
 
+// wavelengths in nanometers standing in for the red, green and blue
+// channels when an rgb render has to split light by wavelength
+pub const CHANNEL_WAVELENGTHS: [f64; 3] = [610.0, 550.0, 465.0];
+
+// fraunhofer d, f and c lines the abbe number is defined with
+const D_LINE: f64 = 587.6;
+const F_LINE: f64 = 486.1;
+const C_LINE: f64 = 656.3;
+
+impl Material {
+    // the most light the surface can send back for each unit falling on it
+    // from a single white light, with the diffuse and specular peaks lined
+    // up. above one it makes light out of nothing, which the default phong
+    // material does at its highlights
+    pub fn peak_response(&self) -> f64 {
+        self.ambient
+            + self.diffuse
+            + self.specular
+            + self.sheen
+            + self.reflective
+            + self.transparency
+    }
+
+    pub fn is_energy_conserving(&self) -> bool {
+        self.peak_response() <= 1.0
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.abbe_number.is_finite() && self.refractive_index != 1.0
+    }
+
+    // refractive index at a wavelength in nanometers from a two term cauchy
+    // fit through the d line index and the abbe number
+    pub fn refractive_index_at(&self, wavelength: f64) -> f64 {
+        if !self.is_dispersive() {
+            return self.refractive_index;
+        }
+        let n = self.refractive_index;
+        let b = (n - 1.0) / (self.abbe_number * (F_LINE.powi(-2) - C_LINE.powi(-2)));
+        let a = n - b / (D_LINE * D_LINE);
+        a + b / (wavelength * wavelength)
+    }
+}
+
+// the light's sample positions averaged, all in or out of shadow
+// together. the world shades with shadows checked for each of them
 pub fn lighting(
     material: Material,
-    light: PointLight,
+    light: &Light,
+    point: Point,
+    eyev: Vector,
+    normalv: Vector,
+    in_shadow: bool,
+) -> Color {
+    let samples = light.sample_positions(point);
+    let sum = samples
+        .iter()
+        .map(|p| lighting_from(material, light, *p, point, eyev, normalv, in_shadow))
+        .fold(Color::new(0.0, 0.0, 0.0), |acc, c| acc + c);
+    sum * (1.0 / samples.len() as f64)
+}
+
+// light arriving from one of the light's sample positions
+pub fn lighting_from(
+    material: Material,
+    light: &Light,
+    position: Point,
     point: Point,
     eyev: Vector,
     normalv: Vector,
     in_shadow: bool,
 ) -> Color {
     // combine surface color with light intensity
-    let effective_color = material.color * light.intensity;
+    let intensity = light.intensity_at(point);
+    let effective_color = material.color * intensity;
 
     // find direction of light source
-    let lightv: Vector = (light.position - point).normalize();
+    let lightv: Vector = (position - point).normalize();
 
     // compute ambient light
     let ambient = effective_color * material.ambient;
@@ -45,31 +142,29 @@ pub fn lighting(
 
     // light_dote represents cosine of angle between light and normal vector
     // negative means light is on other side of surface
-    let light_dot_normal = lightv.dot(normalv);
-    let (diffuse, specular) = if light_dot_normal < 0.0 {
-        (BLACK, BLACK)
-    } else {
-        let diffuse = effective_color * material.diffuse * light_dot_normal;
-
-        // reflect dot eye represents the cosine of the angle between the
-        // reflection and the eye
-        let reflectv = -lightv.reflect(normalv);
-        let reflect_dot_eye = reflectv.dot(eyev);
-        let specular = if reflect_dot_eye <= 0.0 {
-            BLACK
-        } else {
-            let factor = reflect_dot_eye.powf(material.shininess);
-            light.intensity * material.specular * factor
-        };
-        (diffuse, specular)
+    if lightv.dot(normalv) < 0.0 {
+        return ambient;
+    }
+    let sample = LightSample {
+        intensity,
+        lightv,
+        eyev,
+        normalv,
+        diffuse: light.diffuse(),
+        specular: light.specular(),
     };
-
-    ambient + diffuse + specular
+    let mut c = ambient + material.model.shade(&material, &sample);
+    if material.sheen > 0.0 {
+        c = c + Sheen.shade(&material, &sample);
+    }
+    c
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::light::PointLight;
+    use static_assertions::assert_impl_all;
 
     #[test]
     fn default_material() {
@@ -89,8 +184,8 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
-        let result = lighting(m, light, position, eyev, normalv, false);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let result = lighting(m, &light, position, eyev, normalv, false);
         assert_eq!(result, Color::new(1.9, 1.9, 1.9));
     }
 
@@ -99,8 +194,8 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, 2f64.sqrt() / 2.0, -2f64.sqrt() / 2.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0));
-        let result = lighting(m, light, position, eyev, normalv, false);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let result = lighting(m, &light, position, eyev, normalv, false);
         assert_eq!(result, Color::new(1.0, 1.0, 1.0));
     }
     #[test]
@@ -108,8 +203,8 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
-        let result = lighting(m, light, position, eyev, normalv, false);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let result = lighting(m, &light, position, eyev, normalv, false);
         assert_eq!(result, Color::new(0.7364, 0.7364, 0.7364));
     }
     #[test]
@@ -117,8 +212,8 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, -2f64.sqrt() / 2.0, -2f64.sqrt() / 2.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
-        let result = lighting(m, light, position, eyev, normalv, false);
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let result = lighting(m, &light, position, eyev, normalv, false);
         assert_eq!(result, Color::new(1.6364, 1.6364, 1.6364));
     }
     #[test]
@@ -126,8 +221,8 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0));
-        let result = lighting(m, light, position, eyev, normalv, false);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), Color::new(1.0, 1.0, 1.0)).into();
+        let result = lighting(m, &light, position, eyev, normalv, false);
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
     #[test]
@@ -135,10 +230,54 @@ mod tests {
         let (m, position) = background();
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
-        let light = PointLight::new(Point::new(0.0, 0.0, -1.0), Color::new(1.0, 1.0, 1.0));
+        let light = PointLight::new(Point::new(0.0, 0.0, -1.0), Color::new(1.0, 1.0, 1.0)).into();
         let in_shadow = true;
-        let result = lighting(m, light, position, eyev, normalv, in_shadow);
+        let result = lighting(m, &light, position, eyev, normalv, in_shadow);
 
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
+
+    assert_impl_all!(Material: Send, Sync, Copy);
+
+    #[test]
+    fn energy_budget() {
+        let default: Material = Default::default();
+        assert!((default.peak_response() - 1.9).abs() < 1e-12);
+        assert!(!default.is_energy_conserving());
+        let matte = Material {
+            ambient: 0.1,
+            diffuse: 0.7,
+            specular: 0.2,
+            ..Default::default()
+        };
+        assert!(matte.is_energy_conserving());
+        let glass = Material {
+            transparency: 0.5,
+            ..matte
+        };
+        assert!(!glass.is_energy_conserving());
+    }
+
+    #[test]
+    fn dispersion_from_the_abbe_number() {
+        let glass = Material {
+            refractive_index: 1.5168,
+            abbe_number: 64.17,
+            ..Default::default()
+        };
+        assert!(glass.is_dispersive());
+        assert!((glass.refractive_index_at(D_LINE) - 1.5168).abs() < 1e-12);
+        let spread = glass.refractive_index_at(F_LINE) - glass.refractive_index_at(C_LINE);
+        assert!((spread * 64.17 - 0.5168).abs() < 1e-12);
+        // blue bends more than red
+        let [red, _, blue] = CHANNEL_WAVELENGTHS.map(|l| glass.refractive_index_at(l));
+        assert!(blue > red);
+
+        let plain = Material {
+            refractive_index: 1.5,
+            ..Default::default()
+        };
+        assert!(!plain.is_dispersive());
+        assert_eq!(plain.refractive_index_at(400.0), 1.5);
+    }
 }
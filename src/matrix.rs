@@ -1,9 +1,11 @@
-use crate::tuple::Tuple;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::tuple::{Tuple, Vector};
 
 type MatrixData<const W: usize, const H: usize> = [[f64; W]; H];
 type MatrixVecData = Vec<Vec<f64>>;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Matrix<const W: usize, const H: usize> {
     data: MatrixVecData,
 }
@@ -49,6 +51,64 @@ impl<const D: usize> SquareMatrix<D> {
     }
 }
 
+impl Matrix4 {
+    pub fn from_rows(rows: [Tuple; 4]) -> Matrix4 {
+        Matrix::from_vec(rows.iter().map(|r| vec![r.x, r.y, r.z, r.w]).collect())
+    }
+
+    pub fn from_cols(cols: [Tuple; 4]) -> Matrix4 {
+        Matrix4::from_rows(cols).transpose()
+    }
+
+    pub fn from_translation(v: Vector) -> Matrix4 {
+        Matrix4::identity(4).with_translation(v)
+    }
+
+    pub fn from_scale(v: Vector) -> Matrix4 {
+        let mut m = Matrix4::identity(4);
+        m.data[0][0] = v.0.x;
+        m.data[1][1] = v.0.y;
+        m.data[2][2] = v.0.z;
+        m
+    }
+
+    // rodrigues rotation of angle radians around axis, right handed like
+    // rotation_x/y/z
+    pub fn from_axis_angle(axis: Vector, angle: f64) -> Matrix4 {
+        let Tuple { x, y, z, .. } = axis.normalize().0;
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+        Matrix4::from_rows([
+            Tuple::new(t * x * x + c, t * x * y - s * z, t * x * z + s * y, 0.0),
+            Tuple::new(t * x * y + s * z, t * y * y + c, t * y * z - s * x, 0.0),
+            Tuple::new(t * x * z - s * y, t * y * z + s * x, t * z * z + c, 0.0),
+            Tuple::new(0.0, 0.0, 0.0, 1.0),
+        ])
+    }
+
+    pub fn row(&self, y: usize) -> Tuple {
+        let r = &self.data[y];
+        Tuple::new(r[0], r[1], r[2], r[3])
+    }
+
+    pub fn col(&self, x: usize) -> Tuple {
+        let d = &self.data;
+        Tuple::new(d[0][x], d[1][x], d[2][x], d[3][x])
+    }
+
+    pub fn translation(&self) -> Vector {
+        let t = self.col(3);
+        Vector::new(t.x, t.y, t.z)
+    }
+
+    pub fn with_translation(mut self, v: Vector) -> Matrix4 {
+        self.data[0][3] = v.0.x;
+        self.data[1][3] = v.0.y;
+        self.data[2][3] = v.0.z;
+        self
+    }
+}
+
 impl SquareMatrix<2> {
     pub fn determinant(&self) -> f64 {
         self.data[0][0] * self.data[1][1] - self.data[0][1] * self.data[1][0]
@@ -146,11 +206,54 @@ impl<const W: usize, const H: usize> PartialEq for Matrix<W, H> {
                 }
             }
         }
-        return true;
+        true
     }
 }
 
-use std::ops::Index;
+use core::fmt;
+
+// compact single line form, e.g. Matrix2[[1.0, 0.0], [0.0, 1.0]]
+impl<const W: usize, const H: usize> fmt::Debug for Matrix<W, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if W == H {
+            write!(f, "Matrix{}", W)?;
+        } else {
+            write!(f, "Matrix{}x{}", W, H)?;
+        }
+        f.debug_list().entries(self.data.iter()).finish()
+    }
+}
+
+// one row per line with aligned columns, the precision defaults to 5
+// decimals and can be set with the usual {:.3} syntax
+impl<const W: usize, const H: usize> fmt::Display for Matrix<W, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(5);
+        let cells = self
+            .data
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|x| format!("{:.*}", precision, if *x == 0.0 { 0.0 } else { *x }))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(0);
+        for (i, row) in cells.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "|")?;
+            for cell in row {
+                write!(f, " {:>width$}", cell, width = width)?;
+            }
+            write!(f, " |")?;
+        }
+        Ok(())
+    }
+}
+
+use core::ops::Index;
 
 impl<const W: usize, const H: usize> Index<(usize, usize)> for Matrix<W, H> {
     type Output = f64;
@@ -160,7 +263,7 @@ impl<const W: usize, const H: usize> Index<(usize, usize)> for Matrix<W, H> {
     }
 }
 
-use std::ops::{Div, Mul};
+use core::ops::{Div, Mul};
 
 impl<const W: usize, const H: usize, const L: usize> Mul<Matrix<H, L>> for Matrix<W, H> {
     // TODO: make matrix use references
@@ -206,6 +309,7 @@ impl<const W: usize, const H: usize> Mul<f64> for Matrix<W, H> {
 
 impl<const W: usize, const H: usize> Div<f64> for Matrix<W, H> {
     type Output = Matrix<W, H>;
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn div(self, rhs: f64) -> Self::Output {
         self * rhs.recip()
     }
@@ -245,6 +349,60 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transformations::{rotation_x, rotation_y, rotation_z, scaling, translation};
+    use crate::tuple::Point;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn construct_from_rows_and_cols() {
+        let rows = [
+            Tuple::new(1., 2., 3., 4.),
+            Tuple::new(5., 6., 7., 8.),
+            Tuple::new(9., 10., 11., 12.),
+            Tuple::new(13., 14., 15., 16.),
+        ];
+        let m = Matrix4::from_rows(rows);
+        assert_eq!(
+            m,
+            matrix!(
+                [1, 2, 3, 4],
+                [5, 6, 7, 8],
+                [9, 10, 11, 12],
+                [13, 14, 15, 16]
+            )
+        );
+        assert_eq!(Matrix4::from_cols(rows), m.transpose());
+        assert_eq!(m.row(1), rows[1]);
+        assert_eq!(m.col(2), Tuple::new(3., 7., 11., 15.));
+    }
+
+    #[test]
+    fn construct_from_translation_and_scale() {
+        let v = Vector::new(5., -3., 2.);
+        assert_eq!(Matrix4::from_translation(v), translation(5., -3., 2.));
+        assert_eq!(Matrix4::from_scale(v), scaling(5., -3., 2.));
+        assert_eq!(Matrix4::from_translation(v).translation(), v);
+        let m = scaling(2., 2., 2.).with_translation(Vector::new(1., 0., 0.));
+        assert_eq!(m * Point::new(1., 1., 1.), Point::new(3., 2., 2.).0);
+    }
+
+    #[test]
+    fn axis_angle_matches_principal_rotations() {
+        let x = Matrix4::from_axis_angle(Vector::new(2., 0., 0.), FRAC_PI_2);
+        let y = Matrix4::from_axis_angle(Vector::new(0., 1., 0.), 0.3);
+        let z = Matrix4::from_axis_angle(Vector::new(0., 0., 1.), -1.2);
+        assert_eq!(x, rotation_x(FRAC_PI_2));
+        assert_eq!(y, rotation_y(0.3));
+        assert_eq!(z, rotation_z(-1.2));
+    }
+
+    #[test]
+    fn axis_angle_keeps_axis_fixed() {
+        let axis = Vector::new(1., 1., 1.);
+        let m = Matrix4::from_axis_angle(axis, 2.0 * std::f64::consts::PI / 3.0);
+        assert_eq!(&m * axis, axis.0);
+        assert_eq!(m * Vector::new(1., 0., 0.), Vector::new(0., 1., 0.).0);
+    }
     #[test]
     fn construct_4x4_matrix() {
         let m = matrix![
@@ -493,6 +651,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_matrix_with_aligned_columns() {
+        let a = matrix!([1, -2.5], [10, 0]);
+        assert_eq!(format!("{:.1}", a), "|  1.0 -2.5 |\n| 10.0  0.0 |");
+        assert_eq!(
+            format!("{}", Matrix4::identity(4)).lines().next(),
+            Some("| 1.00000 0.00000 0.00000 0.00000 |")
+        );
+    }
+
+    #[test]
+    fn debug_matrix_is_compact() {
+        let a = matrix!([1, 2], [3, 4]);
+        assert_eq!(format!("{:?}", a), "Matrix2[[1.0, 2.0], [3.0, 4.0]]");
+        let b = matrix!([1, 2, 3], [4, 5, 6]);
+        assert_eq!(
+            format!("{:?}", b),
+            "Matrix3x2[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]"
+        );
+    }
+
     #[test]
     fn multiple_matrix_by_inverse() {
         let a = matrix!([3, -9, 7, 3], [3, -8, 2, -9], [-4, 4, 4, 1], [-6, 5, -1, 1]);
@@ -503,4 +682,39 @@ mod tests {
 
         assert_eq!(c * b.inverse().unwrap(), a);
     }
+
+    mod properties {
+        use super::*;
+        use crate::strategies::{point, transform};
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn matrix_times_inverse_is_identity(m in transform()) {
+                let inv = m.inverse().unwrap();
+                prop_assert_eq!(m.clone() * inv.clone(), Matrix4::identity(4));
+                prop_assert_eq!(inv * m, Matrix4::identity(4));
+            }
+
+            #[test]
+            fn transpose_twice_is_identity(m in transform()) {
+                prop_assert_eq!(m.transpose().transpose(), m);
+            }
+
+            #[test]
+            fn transpose_of_product(a in transform(), b in transform()) {
+                prop_assert_eq!(
+                    (a.clone() * b.clone()).transpose(),
+                    b.transpose() * a.transpose()
+                );
+            }
+
+            #[test]
+            fn inverse_undoes_transform(m in transform(), p in point()) {
+                let moved = &m * p;
+                let back = &m.inverse().unwrap() * moved;
+                prop_assert!(back.approx_eq(&p.0, 1e-6));
+            }
+        }
+    }
 }
@@ -0,0 +1,161 @@
+use crate::camera::Camera;
+use crate::canvas::{Canvas, CanvasError};
+use crate::png;
+use crate::profile::span;
+use crate::world::World;
+
+// a render with coverage, for putting objects into photographs. colors
+// are premultiplied by their alpha
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matte {
+    pub canvas: Canvas,
+    pub alpha: Vec<f64>,
+}
+
+impl Matte {
+    pub fn alpha_at(&self, x: isize, y: isize) -> f64 {
+        self.alpha[(y * self.canvas.width + x) as usize]
+    }
+
+    // the render laid over a photograph of the same size
+    pub fn over(&self, plate: &Canvas) -> Result<Canvas, CanvasError> {
+        if (plate.width, plate.height) != (self.canvas.width, self.canvas.height) {
+            return Err(CanvasError::SizeMismatch);
+        }
+        let mut out = plate.clone();
+        for (i, p) in out.pixels.iter_mut().enumerate() {
+            *p = self.canvas.pixels[i] + *p * (1.0 - self.alpha[i]);
+        }
+        Ok(out)
+    }
+
+    // 8 bit rgba png, which stores straight alpha so the colors are
+    // divided back out
+    pub fn to_png(&self) -> Vec<u8> {
+        let _span = span!(INFO, "encode", format = "png");
+        let samples = self
+            .canvas
+            .pixels
+            .iter()
+            .zip(&self.alpha)
+            .flat_map(|(c, a)| {
+                let [r, g, b] = if *a > 0.0 {
+                    (*c * (1.0 / a)).to_rgb8()
+                } else {
+                    [0, 0, 0]
+                };
+                [r, g, b, (a.clamp(0.0, 1.0) * 255.0).round() as u8]
+            })
+            .collect::<Vec<_>>();
+        png::encode(
+            self.canvas.width as u32,
+            self.canvas.height as u32,
+            png::ColorType::Rgba,
+            8,
+            &samples,
+        )
+    }
+}
+
+impl Camera {
+    pub fn render_matte(&self, world: &World) -> Matte {
+        let (colors, alpha) = self
+            .render_with(|ray| world.color_alpha_at(ray))
+            .into_iter()
+            .unzip();
+        let mut canvas = Canvas::new(self.hsize() as isize, self.vsize() as isize);
+        canvas.pixels = colors;
+        Matte { canvas, alpha }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::light::PointLight;
+    use crate::material::Material;
+    use crate::ray::Ray;
+    use crate::slab::slab;
+    use crate::sphere::sphere;
+    use crate::transformations::{translation, view_transform, Transformable};
+    use crate::tuple::{Point, Vector};
+    use std::f64::consts::PI;
+
+    // a ball resting above a catcher floor, lit from straight above
+    fn ball_on_catcher(reflective: f64) -> World {
+        let mut w = World::new();
+        w.objects
+            .push(sphere().set_transform(translation(0.0, 1.0, 0.0)));
+        w.objects.push(
+            slab(40.0, 1.0, 40.0)
+                .transformed(&translation(0.0, -1.5, 0.0))
+                .set_material(Material {
+                    shadow_catcher: true,
+                    reflective,
+                    ..Default::default()
+                }),
+        );
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        w
+    }
+
+    fn floor_ray(x: f64) -> Ray {
+        Ray::new(Point::new(x, -0.5, -5.0), Vector::new(0.0, -0.5, 5.0))
+    }
+
+    #[test]
+    fn catchers_show_only_their_shadows() {
+        let w = ball_on_catcher(0.0);
+        let (color, alpha) = w.color_alpha_at(floor_ray(0.0));
+        assert_eq!(color, Color::new(0.0, 0.0, 0.0));
+        assert!((alpha - 1.0).abs() < 1e-9);
+        assert_eq!(w.color_alpha_at(floor_ray(8.0)).1, 0.0);
+        // the ball itself is opaque and the sky empty
+        let ball = Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (color, alpha) = w.color_alpha_at(ball);
+        assert_eq!((color, alpha), (w.color_at(ball), 1.0));
+        let sky = Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(w.color_alpha_at(sky).1, 0.0);
+    }
+
+    #[test]
+    fn catchers_show_reflections() {
+        // looking at the floor in front of the ball, where it mirrors it
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, -1.0, 2.5));
+        let (matte, _) = ball_on_catcher(0.0).color_alpha_at(r);
+        let (mirror, alpha) = ball_on_catcher(0.5).color_alpha_at(r);
+        assert_eq!(matte, Color::new(0.0, 0.0, 0.0));
+        assert!(mirror.red > 0.0);
+        assert!(alpha > 0.0 && alpha < 1.0);
+    }
+
+    #[test]
+    fn mattes_composite_over_plates() {
+        let mut camera = Camera::new(21, 21, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 3.0, -6.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let matte = camera.render_matte(&ball_on_catcher(0.0));
+        let mut plate = Canvas::new(21, 21);
+        plate.fill(Color::new(0.5, 0.6, 0.7));
+        let out = matte.over(&plate).unwrap();
+        // far corners of the floor are untouched, the ball covers the middle
+        assert_eq!(out.read_pixel(0, 20).unwrap(), Color::new(0.5, 0.6, 0.7));
+        assert_eq!(matte.alpha_at(10, 10), 1.0);
+        assert_eq!(
+            out.read_pixel(10, 10).unwrap(),
+            matte.canvas.read_pixel(10, 10).unwrap()
+        );
+        assert!(matches!(
+            matte.over(&Canvas::new(2, 2)),
+            Err(CanvasError::SizeMismatch)
+        ));
+        let png = matte.to_png();
+        // rgba color type in the header
+        assert_eq!(png[25], 6);
+    }
+}
@@ -0,0 +1,57 @@
+// stand ins for what std provides to the core modules, pulled in with
+// `use crate::no_std::*` when building without std
+
+pub use alloc::boxed::Box;
+pub use alloc::format;
+pub use alloc::string::{String, ToString};
+pub use alloc::vec;
+pub use alloc::vec::Vec;
+
+// float functions that live in std, backed by libm
+pub trait FloatExt {
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn sin_cos(self) -> (Self, Self)
+    where
+        Self: Sized;
+    fn acos(self) -> Self;
+    fn atan2(self, other: Self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn floor(self) -> Self;
+}
+
+impl FloatExt for f64 {
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+    fn tan(self) -> f64 {
+        libm::tan(self)
+    }
+    fn sin_cos(self) -> (f64, f64) {
+        libm::sincos(self)
+    }
+    fn acos(self) -> f64 {
+        libm::acos(self)
+    }
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::tuple::Point;
+
+// ken perlin's permutation from his improved noise reference code
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn perm(i: usize) -> usize {
+    PERMUTATION[i & 255] as usize
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// dot product of the offset with one of twelve edge directions of a cube
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+// improved perlin noise, smooth and repeating every 256 units. roughly in
+// -1..1 and zero at every whole number point
+pub fn perlin(p: Point) -> f64 {
+    let (x, y, z) = (p.0.x, p.0.y, p.0.z);
+    let (fx, fy, fz) = (x.floor(), y.floor(), z.floor());
+    let (xi, yi, zi) = (
+        (fx as i64 & 255) as usize,
+        (fy as i64 & 255) as usize,
+        (fz as i64 & 255) as usize,
+    );
+    let (x, y, z) = (x - fx, y - fy, z - fz);
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+    let a = perm(xi) + yi;
+    let (aa, ab) = (perm(a) + zi, perm(a + 1) + zi);
+    let b = perm(xi + 1) + yi;
+    let (ba, bb) = (perm(b) + zi, perm(b + 1) + zi);
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(perm(aa), x, y, z), grad(perm(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(perm(ab), x, y - 1.0, z),
+                grad(perm(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(perm(aa + 1), x, y, z - 1.0),
+                grad(perm(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(perm(ab + 1), x, y - 1.0, z - 1.0),
+                grad(perm(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+// sum of the absolute noise of octaves, each at twice the frequency and
+// half the weight of the last. positive and streaky, the usual base for
+// marble veins
+pub fn turbulence(p: Point, octaves: u32) -> f64 {
+    let mut sum = 0.0;
+    let mut scale = 1.0;
+    for _ in 0..octaves {
+        let q = Point::new(p.0.x * scale, p.0.y * scale, p.0.z * scale);
+        sum += perlin(q).abs() / scale;
+        scale *= 2.0;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_is_zero_on_the_lattice() {
+        for p in [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(3.0, -7.0, 12.0),
+            Point::new(255.0, 1.0, -1.0),
+        ] {
+            assert_eq!(perlin(p), 0.0);
+        }
+    }
+
+    #[test]
+    fn noise_is_smooth_bounded_and_repeats() {
+        let mut max = 0.0f64;
+        for i in 0..500 {
+            let p = Point::new(
+                i as f64 * 0.137,
+                i as f64 * 0.071 + 0.3,
+                i as f64 * 0.053 - 4.1,
+            );
+            let n = perlin(p);
+            max = max.max(n.abs());
+            assert!(n.abs() <= 1.1);
+            let nearby = perlin(Point::new(p.0.x + 1e-4, p.0.y, p.0.z));
+            assert!((n - nearby).abs() < 1e-2);
+            let wrapped = perlin(Point::new(p.0.x + 256.0, p.0.y, p.0.z));
+            assert!((n - wrapped).abs() < 1e-9);
+        }
+        // and actually varies
+        assert!(max > 0.3);
+        let p = Point::new(0.5, 0.25, 0.75);
+        assert!(turbulence(p, 4) >= perlin(p).abs());
+        assert_eq!(turbulence(p, 1), perlin(p).abs());
+    }
+}
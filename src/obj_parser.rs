@@ -0,0 +1,292 @@
+use crate::asset::{AssetError, AssetResolver};
+use crate::group::Group;
+use crate::triangle::{smooth_triangle, triangle};
+use crate::tuple::{Point, Vector};
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::ErrorKind),
+    Asset(AssetError),
+    // the line number, counted from one, and what was wrong with it
+    Invalid(usize, String),
+}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> Self {
+        ObjError::Io(e.kind())
+    }
+}
+
+impl From<AssetError> for ObjError {
+    fn from(e: AssetError) -> Self {
+        ObjError::Asset(e)
+    }
+}
+
+// what a wavefront obj file holds. vertices and normals are numbered from
+// one in the file, index zero here is the first of them. faces outside any
+// named group go to the default group, each `g` line puts the faces after
+// it into a group of that name below the default one
+#[derive(Debug, Clone, Default)]
+pub struct ObjFile {
+    pub vertices: Vec<Point>,
+    pub normals: Vec<Vector>,
+    pub default_group: Group,
+    // lines skipped because they hold something other than vertices,
+    // normals, faces or groups, and triangles of faces with no area
+    pub ignored: usize,
+}
+
+impl ObjFile {
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.default_group.groups().iter().find(|g| g.name == name)
+    }
+
+    // everything in the file as one group, ready for World::add_group
+    pub fn to_group(self) -> Group {
+        self.default_group
+    }
+}
+
+// reads a mesh found through resolver, like scene::load
+pub fn load(resolver: &AssetResolver, name: &str) -> Result<ObjFile, ObjError> {
+    parse_obj_str(&resolver.read_to_string(name)?)
+}
+
+pub fn parse_obj_file(path: impl AsRef<Path>) -> Result<ObjFile, ObjError> {
+    parse_obj_str(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse_obj_str(source: &str) -> Result<ObjFile, ObjError> {
+    let mut obj = ObjFile::default();
+    // the named groups, added below the default group at the end. faces
+    // go to the one at current, or the default group while that's None
+    let mut named: Vec<Group> = vec![];
+    let mut current: Option<usize> = None;
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let invalid = |message: &str| ObjError::Invalid(line_number, message.into());
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let [x, y, z] = coordinates(&mut words).ok_or_else(|| invalid("bad vertex"))?;
+                obj.vertices.push(Point::new(x, y, z));
+            }
+            Some("vn") => {
+                let [x, y, z] = coordinates(&mut words).ok_or_else(|| invalid("bad normal"))?;
+                obj.normals.push(Vector::new(x, y, z));
+            }
+            Some("f") => {
+                let corners = words
+                    .map(|w| corner(w, &obj))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("bad face index"))?;
+                if corners.len() < 3 {
+                    return Err(invalid("faces need at least three vertices"));
+                }
+                let group = match current {
+                    Some(g) => &mut named[g],
+                    None => &mut obj.default_group,
+                };
+                // polygons are split into a fan of triangles around the
+                // first vertex, which assumes they are convex
+                let (p1, n1) = corners[0];
+                for pair in corners[1..].windows(2) {
+                    let ((p2, n2), (p3, n3)) = (pair[0], pair[1]);
+                    // meshes found in the wild hold slivers with repeated
+                    // or collinear corners, which have no normal
+                    let area = (p2 - p1).cross(p3 - p1).magnitude();
+                    if !area.is_finite() || area < 1e-12 {
+                        obj.ignored += 1;
+                        continue;
+                    }
+                    group.push_child(match (n1, n2, n3) {
+                        (Some(n1), Some(n2), Some(n3)) => smooth_triangle(p1, p2, p3, n1, n2, n3),
+                        _ => triangle(p1, p2, p3),
+                    });
+                }
+            }
+            Some("g") => {
+                let name = words.collect::<Vec<_>>().join(" ");
+                current = Some(match named.iter().position(|g| g.name == name) {
+                    Some(g) => g,
+                    None => {
+                        named.push(Group::new(&name));
+                        named.len() - 1
+                    }
+                });
+            }
+            // blank lines don't count as skipped
+            None => {}
+            Some(_) => obj.ignored += 1,
+        }
+    }
+    for group in named {
+        obj.default_group.push_group(group);
+    }
+    Ok(obj)
+}
+
+// the first three numbers, anything after them (like a w) is ignored
+fn coordinates<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<[f64; 3]> {
+    let mut xyz = [0.0; 3];
+    for c in xyz.iter_mut() {
+        *c = words.next()?.parse().ok().filter(|c: &f64| c.is_finite())?;
+    }
+    Some(xyz)
+}
+
+// a face corner as `v`, `v/vt`, `v//vn` or `v/vt/vn`. negative indexes
+// count back from the last vertex or normal read so far
+fn corner(word: &str, obj: &ObjFile) -> Option<(Point, Option<Vector>)> {
+    fn lookup<T: Copy>(index: &str, list: &[T]) -> Option<T> {
+        let index: i64 = index.parse().ok()?;
+        let at = if index < 0 {
+            list.len() as i64 + index
+        } else {
+            index - 1
+        };
+        list.get(usize::try_from(at).ok()?).copied()
+    }
+    let mut parts = word.split('/');
+    let point = lookup(parts.next()?, &obj.vertices)?;
+    let normal = match parts.nth(1) {
+        Some(n) if !n.is_empty() => Some(lookup(n, &obj.normals)?),
+        _ => None,
+    };
+    Some((point, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Geometry, Object};
+    use crate::triangle::{SmoothTriangle, Triangle};
+
+    fn corners(object: &Object) -> Triangle {
+        match object.geometry {
+            Geometry::Triangle(t) => t,
+            Geometry::SmoothTriangle(t) => t.triangle,
+            _ => panic!("not a triangle"),
+        }
+    }
+
+    #[test]
+    fn unknown_lines_are_skipped() {
+        let gibberish = "There was a young lady named Bright\n\
+                         who traveled much faster than light.\n\
+                         She set out one day\n\
+                         in a relative way,\n\
+                         and came back the previous night.\n";
+        let obj = parse_obj_str(gibberish).unwrap();
+        assert_eq!(obj.ignored, 5);
+        assert!(obj.default_group.is_empty());
+    }
+
+    #[test]
+    fn vertex_records() {
+        let obj = parse_obj_str("v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n").unwrap();
+        assert_eq!(obj.vertices[0], Point::new(-1.0, 1.0, 0.0));
+        assert_eq!(obj.vertices[1], Point::new(-1.0, 0.5, 0.0));
+        assert_eq!(obj.vertices[3], Point::new(1.0, 1.0, 0.0));
+        assert!(matches!(
+            parse_obj_str("v 1 x 0"),
+            Err(ObjError::Invalid(1, _))
+        ));
+    }
+
+    #[test]
+    fn polygons_are_triangulated() {
+        let obj = parse_obj_str("v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5\n")
+            .unwrap();
+        let g = &obj.default_group;
+        assert_eq!(g.children().len(), 3);
+        let v = &obj.vertices;
+        for (i, object) in g.children().iter().enumerate() {
+            let t = corners(object);
+            assert_eq!((t.p1, t.p2, t.p3), (v[0], v[i + 1], v[i + 2]));
+        }
+        assert!(matches!(
+            parse_obj_str("v 0 0 0\nf 1 2 3"),
+            Err(ObjError::Invalid(2, _))
+        ));
+    }
+
+    #[test]
+    fn faces_without_area_are_skipped() {
+        let obj = parse_obj_str("v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nf 1 2 3\nf 1 1 4\nf 1 2 4\n")
+            .unwrap();
+        assert_eq!(obj.ignored, 2);
+        assert_eq!(obj.default_group.children().len(), 1);
+        assert!(matches!(
+            parse_obj_str("v 0 0 0\nv nan 0 0\nv inf 1 0"),
+            Err(ObjError::Invalid(2, _))
+        ));
+    }
+
+    #[test]
+    fn triangles_in_named_groups() {
+        let obj = parse_obj_str(
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\
+             g FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n",
+        )
+        .unwrap();
+        let first = corners(&obj.group("FirstGroup").unwrap().children()[0]);
+        let second = corners(&obj.group("SecondGroup").unwrap().children()[0]);
+        assert_eq!(first.p3, obj.vertices[2]);
+        assert_eq!(second.p3, obj.vertices[3]);
+        let group = obj.to_group();
+        assert_eq!(group.groups().len(), 2);
+        assert_eq!(group.objects().len(), 2);
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let obj = parse_obj_str(
+            "v 0 1 0\nv -1 0 0\nv 1 0 0\n\
+             vn -1 0 0\nvn 1 0 0\nvn 0 1 0\n\
+             f 1//3 2//1 3//2\nf 1/0/3 2/102/1 3/14/2\nf -3 -2 -1\n",
+        )
+        .unwrap();
+        assert_eq!(obj.normals[2], Vector::new(0.0, 1.0, 0.0));
+        let g = obj.default_group.children();
+        let expected = book_smooth_triangle(&obj);
+        assert_eq!(g[0].geometry, expected);
+        assert_eq!(g[1].geometry, expected);
+        // without normals the triangle is flat
+        assert!(matches!(g[2].geometry, Geometry::Triangle(_)));
+    }
+
+    fn book_smooth_triangle(obj: &ObjFile) -> Geometry {
+        let (v, n) = (&obj.vertices, &obj.normals);
+        Geometry::SmoothTriangle(SmoothTriangle::new(v[0], v[1], v[2], n[2], n[0], n[1]))
+    }
+
+    #[test]
+    fn reading_a_file() {
+        let path = std::env::temp_dir().join("ray_tracer_obj_parser_test.obj");
+        std::fs::write(&path, "# a triangle\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let obj = parse_obj_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(obj.ignored, 1);
+        assert_eq!(obj.default_group.children().len(), 1);
+        assert!(matches!(
+            parse_obj_file(&path),
+            Err(ObjError::Io(io::ErrorKind::NotFound))
+        ));
+    }
+
+    #[test]
+    fn loading_through_a_resolver() {
+        let resolver = AssetResolver::new()
+            .with_asset("models/tri.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+        let obj = load(&resolver, "models/tri.obj").unwrap();
+        assert_eq!(obj.default_group.children().len(), 1);
+        assert!(matches!(
+            load(&resolver, "models/missing.obj"),
+            Err(ObjError::Asset(AssetError::NotFound(_)))
+        ));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::bounds::{BoundingBox, BoundingSphere};
+use crate::cylinder;
+use crate::intersection::{Intersection, Intersections};
+use crate::material::Material;
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::ray::Ray;
+use crate::shape::{hits_within, LocalHit, LocalHits, Shape};
+use crate::slab;
+use crate::sphere;
+use crate::transformations::Transformable;
+use crate::triangle::{SmoothTriangle, Triangle};
+use crate::tuple::{Point, Vector};
+use crate::water::Waves;
+use alloc::sync::Arc;
+use core::f64::consts::PI;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+// the unit shape the transform is applied to
+#[derive(Debug, Clone, Default)]
+pub enum Geometry {
+    #[default]
+    Sphere,
+    // see slab
+    Slab,
+    // see cylinder, the ends may be infinite
+    Cylinder {
+        minimum: f64,
+        maximum: f64,
+        closed: bool,
+    },
+    // see triangle
+    Triangle(Triangle),
+    SmoothTriangle(SmoothTriangle),
+    // any other shape, see shape
+    Custom(Arc<dyn Shape>),
+}
+
+impl PartialEq for Geometry {
+    fn eq(&self, other: &Self) -> bool {
+        use Geometry::*;
+        match (self, other) {
+            (Sphere, Sphere) | (Slab, Slab) => true,
+            (
+                Cylinder {
+                    minimum: min1,
+                    maximum: max1,
+                    closed: c1,
+                },
+                Cylinder {
+                    minimum: min2,
+                    maximum: max2,
+                    closed: c2,
+                },
+            ) => min1 == min2 && max1 == max2 && c1 == c2,
+            (Triangle(a), Triangle(b)) => a == b,
+            (SmoothTriangle(a), SmoothTriangle(b)) => a == b,
+            (Custom(a), Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Shape for Geometry {
+    fn local_intersect(&self, ray: Ray) -> LocalHits {
+        match *self {
+            Geometry::Sphere => sphere::local_intersect(ray),
+            Geometry::Slab => match slab::local_intersect(ray) {
+                Some((t1, t2)) => LocalHits::from_slice(&[LocalHit::new(t1), LocalHit::new(t2)]),
+                None => LocalHits::new(),
+            },
+            Geometry::Cylinder {
+                minimum,
+                maximum,
+                closed,
+            } => cylinder::local_intersect(ray, minimum, maximum, closed),
+            Geometry::Triangle(ref triangle) => triangle.local_intersect(ray),
+            Geometry::SmoothTriangle(ref triangle) => triangle.local_intersect(ray),
+            Geometry::Custom(ref shape) => shape.local_intersect(ray),
+        }
+    }
+
+    fn local_intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> LocalHits {
+        match *self {
+            Geometry::Sphere => sphere::local_intersect_within(ray, t_min, t_max),
+            Geometry::Triangle(ref triangle) => triangle.local_intersect_within(ray, t_min, t_max),
+            Geometry::SmoothTriangle(ref triangle) => {
+                triangle.local_intersect_within(ray, t_min, t_max)
+            }
+            Geometry::Custom(ref shape) => shape.local_intersect_within(ray, t_min, t_max),
+            _ => hits_within(self.local_intersect(ray), t_min, t_max),
+        }
+    }
+
+    fn local_normal_at(&self, point: Point) -> Vector {
+        match *self {
+            Geometry::Sphere => point - Point::new(0.0, 0.0, 0.0),
+            Geometry::Slab => slab::local_normal_at(point),
+            Geometry::Cylinder {
+                minimum, maximum, ..
+            } => cylinder::local_normal_at(point, minimum, maximum),
+            Geometry::Triangle(ref triangle) => triangle.local_normal_at(point),
+            Geometry::SmoothTriangle(ref triangle) => triangle.local_normal_at(point),
+            Geometry::Custom(ref shape) => shape.local_normal_at(point),
+        }
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        match *self {
+            Geometry::Cylinder {
+                minimum, maximum, ..
+            } => BoundingBox::new(
+                Point::new(-1.0, minimum, -1.0),
+                Point::new(1.0, maximum, 1.0),
+            ),
+            Geometry::Triangle(ref triangle) => triangle.local_bounds(),
+            Geometry::SmoothTriangle(ref triangle) => triangle.local_bounds(),
+            Geometry::Custom(ref shape) => shape.local_bounds(),
+            _ => BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0)),
+        }
+    }
+
+    fn local_normal_at_uv(&self, point: Point, u: f64, v: f64) -> Vector {
+        match *self {
+            Geometry::SmoothTriangle(ref triangle) => triangle.local_normal_at_uv(point, u, v),
+            Geometry::Custom(ref shape) => shape.local_normal_at_uv(point, u, v),
+            _ => self.local_normal_at(point),
+        }
+    }
+}
+
+// anything placed in a world: a geometry put in place by the transform
+// and shaded with the material. a clone is a new object with its own id,
+// so copies placed in the same world never stand in for each other.
+// World's clone keeps the ids, see snapshot
+#[derive(Debug, PartialEq)]
+pub struct Object {
+    id: u32,
+    pub transform: Matrix4,
+    pub inv_transform: Matrix4,
+    pub material: Material,
+    pub geometry: Geometry,
+    // ripples tilting the shading normal, see water
+    pub waves: Option<Waves>,
+    // how far shading points are pushed off this surface, instead of the
+    // scene's bias scaled to the object, see surface_bias
+    pub bias: Option<f64>,
+    // which of several surfaces at the same depth shows, higher wins. for
+    // coplanar setups like a decal card lying on a floor
+    pub priority: i32,
+}
+
+fn next_id() -> u32 {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+    COUNT.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Clone for Object {
+    fn clone(&self) -> Object {
+        Object {
+            id: next_id(),
+            ..self.snapshot()
+        }
+    }
+}
+
+impl Default for Object {
+    fn default() -> Self {
+        Object::new(Geometry::default())
+    }
+}
+
+impl Transformable for Object {
+    fn transformed(self, m: &Matrix4) -> Object {
+        let transform = m.clone() * self.transform.clone();
+        self.set_transform(transform)
+    }
+}
+
+impl Object {
+    pub fn new(geometry: Geometry) -> Object {
+        Object {
+            id: next_id(),
+            transform: Matrix4::identity(4),
+            inv_transform: Matrix4::identity(4),
+            material: Default::default(),
+            geometry,
+            waves: None,
+            bias: None,
+            priority: 0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    // a copy that keeps the id, for a copy of the whole world where it
+    // still stands for this object
+    pub(crate) fn snapshot(&self) -> Object {
+        Object {
+            id: self.id,
+            transform: self.transform.clone(),
+            inv_transform: self.inv_transform.clone(),
+            material: self.material,
+            geometry: self.geometry.clone(),
+            waves: self.waves.clone(),
+            bias: self.bias,
+            priority: self.priority,
+        }
+    }
+
+    pub fn set_transform(mut self, transform: Matrix4) -> Object {
+        self.update_transform(transform);
+        self
+    }
+
+    pub fn update_transform(&mut self, transform: Matrix4) {
+        self.inv_transform = transform
+            .inverse()
+            .expect("Fail to inverse sphere transform");
+        self.transform = transform;
+    }
+
+    pub fn set_material(mut self, material: Material) -> Object {
+        self.material = material;
+        self
+    }
+
+    pub fn set_geometry(mut self, geometry: Geometry) -> Object {
+        self.geometry = geometry;
+        self
+    }
+
+    pub fn set_waves(mut self, waves: Waves) -> Object {
+        self.waves = Some(waves);
+        self
+    }
+
+    pub fn set_bias(mut self, bias: f64) -> Object {
+        self.bias = Some(bias);
+        self
+    }
+
+    pub fn set_priority(mut self, priority: i32) -> Object {
+        self.priority = priority;
+        self
+    }
+
+    // the most the transform stretches anything, the length of the longest
+    // of the transformed axes
+    pub fn scale(&self) -> f64 {
+        (0..3)
+            .map(|x| {
+                let axis = Vector::new(
+                    self.transform.get(0, x),
+                    self.transform.get(1, x),
+                    self.transform.get(2, x),
+                );
+                axis.magnitude()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    // the bias for shading points on this object. default is meant for
+    // objects around a unit in size and grows or shrinks with the object,
+    // so millimeter and kilometer sized objects in one scene both stay
+    // clear of acne without floating off their surfaces
+    pub fn surface_bias(&self, default: f64) -> f64 {
+        self.bias.unwrap_or_else(|| default * self.scale())
+    }
+
+    pub fn intersect(&self, ray: Ray) -> Intersections<'_> {
+        let ray = ray.transform(&self.inv_transform);
+        let mut xs = Intersections::new();
+        for hit in self.geometry.local_intersect(ray) {
+            xs.push(Intersection::with_uv(hit.t, self, hit.u, hit.v));
+        }
+        xs
+    }
+
+    // only the intersections with t_min <= t <= t_max, see
+    // Shape::local_intersect_within
+    pub fn intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> Intersections<'_> {
+        let ray = ray.transform(&self.inv_transform);
+        let mut xs = Intersections::new();
+        for hit in self.geometry.local_intersect_within(ray, t_min, t_max) {
+            xs.push(Intersection::with_uv(hit.t, self, hit.u, hit.v));
+        }
+        xs
+    }
+
+    pub fn normal_at(&self, world_p: Point) -> Vector {
+        let object_p = Point::try_from(&self.inv_transform * world_p).unwrap();
+        self.world_normal(world_p, self.geometry.local_normal_at(object_p))
+    }
+
+    // the normal where a ray hit, using the u and v the hit recorded
+    pub fn normal_at_hit(&self, world_p: Point, hit: &Intersection) -> Vector {
+        let object_p = Point::try_from(&self.inv_transform * world_p).unwrap();
+        let object_normal = self.geometry.local_normal_at_uv(object_p, hit.u, hit.v);
+        self.world_normal(world_p, object_normal)
+    }
+
+    fn world_normal(&self, world_p: Point, object_normal: Vector) -> Vector {
+        let mut world_normal = &self.inv_transform.transpose() * object_normal;
+        // something something about multiplying by the inverse
+        // of 3x3 submatrix of transform which can be skipped by
+        // setting w to 0.
+        world_normal.w = 0.0;
+        let normal = world_normal.normalize().try_into().unwrap();
+        match &self.waves {
+            Some(waves) => waves.perturb(world_p.0.x, world_p.0.z, normal),
+            None => normal,
+        }
+    }
+
+    pub fn local_bounds(&self) -> BoundingBox {
+        self.geometry.local_bounds()
+    }
+
+    // an endless cylinder reaches infinity, where transforming the corners
+    // would only give nans
+    fn is_bounded(&self) -> bool {
+        let b = self.local_bounds();
+        [b.min.0, b.max.0]
+            .iter()
+            .all(|c| c.x.is_finite() && c.y.is_finite() && c.z.is_finite())
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        match self.geometry {
+            Geometry::Sphere => BoundingBox::of_unit_sphere(&self.transform),
+            _ if !self.is_bounded() => BoundingBox::new(
+                Point::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+                Point::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            ),
+            // the corners of a box are its extremes, and the caps of a
+            // cylinder are as wide as its box
+            _ => self.local_bounds().transform(&self.transform),
+        }
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        let s = BoundingSphere::of_unit_sphere(&self.transform);
+        match self.geometry {
+            Geometry::Sphere => s,
+            Geometry::Slab => BoundingSphere::new(s.center, s.radius * 3f64.sqrt()),
+            _ if !self.is_bounded() => BoundingSphere::new(s.center, f64::INFINITY),
+            Geometry::Cylinder { .. }
+            | Geometry::Triangle(_)
+            | Geometry::SmoothTriangle(_)
+            | Geometry::Custom(_) => BoundingSphere::around(&self.bounds()),
+        }
+    }
+
+    // spherical texture coordinates of a point on the surface, both in 0..1.
+    // they only depend on the direction from the center, so slabs get the
+    // same mapping pushed out onto their faces
+    pub fn uv_at(&self, world_p: Point) -> (f64, f64) {
+        let p = Point::try_from(&self.inv_transform * world_p).unwrap().0;
+        let theta = p.x.atan2(p.z);
+        let radius = (p.x * p.x + p.y * p.y + p.z * p.z).sqrt();
+        let phi = (p.y / radius).acos();
+        let raw_u = theta / (2.0 * PI);
+        (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+    }
+
+    // inverse of uv_at, the world space point for a texture coordinate.
+    // triangles take u and v as barycentric weights instead, folded back
+    // over the long edge when they add up past one
+    pub fn point_at_uv(&self, u: f64, v: f64) -> Point {
+        let triangle = match self.geometry {
+            Geometry::Triangle(triangle) => Some(triangle),
+            Geometry::SmoothTriangle(smooth) => Some(smooth.triangle),
+            _ => None,
+        };
+        if let Some(triangle) = triangle {
+            let (u, v) = if u + v > 1.0 {
+                (1.0 - u, 1.0 - v)
+            } else {
+                (u, v)
+            };
+            return (&self.transform * triangle.point_at(u, v))
+                .try_into()
+                .unwrap();
+        }
+        let theta = (0.5 - u) * 2.0 * PI;
+        let phi = (1.0 - v) * PI;
+        let p = Point::new(phi.sin() * theta.sin(), phi.cos(), phi.sin() * theta.cos());
+        let p = match self.geometry {
+            Geometry::Sphere => p,
+            Geometry::Slab => slab::onto_surface(p),
+            // out from the axis onto the wall, the poles land on the caps
+            // or, for open ends, at the rim
+            Geometry::Cylinder {
+                minimum, maximum, ..
+            } => {
+                let r = (p.0.x * p.0.x + p.0.z * p.0.z).sqrt();
+                if r < 1e-9 {
+                    Point::new(0.0, p.0.y.clamp(minimum, maximum), 0.0)
+                } else {
+                    Point::new(p.0.x / r, (p.0.y / r).clamp(minimum, maximum), p.0.z / r)
+                }
+            }
+            // only the direction is known for other shapes
+            Geometry::Triangle(_) | Geometry::SmoothTriangle(_) | Geometry::Custom(_) => p,
+        };
+        (&self.transform * p).try_into().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+    use crate::triangle::triangle;
+    use static_assertions::assert_impl_all;
+
+    #[test]
+    fn intersecting_within_a_range() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let ts = |s: &Object, t_min, t_max| {
+            s.intersect_within(r, t_min, t_max)
+                .iter()
+                .map(|i| i.t)
+                .collect::<Vec<_>>()
+        };
+        let s = sphere().set_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(ts(&s, 0.0, 10.0), vec![3.0, 7.0]);
+        assert_eq!(ts(&s, 3.0, 5.0), vec![3.0]);
+        assert_eq!(ts(&s, 5.0, 7.0), vec![7.0]);
+        // wholly before and after the range
+        assert!(ts(&s, 7.5, 10.0).is_empty());
+        assert!(ts(&s, 0.0, 2.5).is_empty());
+        let slab = slab::slab(2.0, 2.0, 2.0);
+        assert_eq!(ts(&slab, 4.5, 10.0), vec![6.0]);
+        let t = triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, -1.0, 0.0),
+            Point::new(1.0, -1.0, 0.0),
+        );
+        assert_eq!(ts(&t, 0.0, 5.0), vec![5.0]);
+        assert!(ts(&t, 0.0, 4.9).is_empty());
+    }
+
+    #[test]
+    fn chained_transforms_apply_in_call_order() {
+        let s = sphere().scaled(2.0, 2.0, 2.0).translated(0.0, 1.0, 0.0);
+        assert_eq!(
+            s.transform,
+            translation(0.0, 1.0, 0.0) * scaling(2.0, 2.0, 2.0)
+        );
+        assert_eq!(
+            s.normal_at(Point::new(0.0, 3.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn bias_follows_the_object_scale() {
+        let s = sphere().scaled(1.0, 3.0, 0.5).rotated_z(PI / 3.0);
+        assert!((s.scale() - 3.0).abs() < 1e-12);
+        assert!((s.surface_bias(1e-5) - 3e-5).abs() < 1e-15);
+        let tiny = sphere().scaled(0.001, 0.001, 0.001);
+        assert!((tiny.surface_bias(1e-5) - 1e-8).abs() < 1e-18);
+        // an override ignores the scale
+        assert_eq!(tiny.set_bias(0.1).surface_bias(1e-5), 0.1);
+    }
+
+    assert_impl_all!(Object: Send, Sync);
+}
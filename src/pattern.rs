@@ -0,0 +1,271 @@
+use crate::color::Color;
+use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::noise::{perlin, turbulence};
+use crate::object::Object;
+use crate::tuple::{Point, Tuple};
+use core::f64::consts::PI;
+
+// what a pattern paints, as a function of the point in pattern space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternKind {
+    // a band of each color per unit along x
+    Stripe(Color, Color),
+    // a blend from the first to the second color over each unit along x
+    Gradient(Color, Color),
+    // unit wide rings around the y axis
+    Ring(Color, Color),
+    // unit cubes of alternating color
+    Checkers(Color, Color),
+    // wavy veins of the second color through the first, running across x
+    Marble(Color, Color),
+    // growth rings around the y axis, wobbled so no two look the same
+    Wood(Color, Color),
+    Perturbed(PerturbedPattern),
+}
+
+// another pattern looked up at points pushed around by noise, so its
+// straight lines turn organic. the pattern lives in a static, like custom
+// shading models, so materials stay Copy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerturbedPattern {
+    pub pattern: &'static PatternKind,
+    // how far points are pushed, in pattern units
+    pub scale: f64,
+    // noise bumps per unit, higher gives busier distortion
+    pub frequency: f64,
+}
+
+impl PerturbedPattern {
+    pub fn new(pattern: &'static PatternKind, scale: f64) -> PerturbedPattern {
+        PerturbedPattern {
+            pattern,
+            scale,
+            frequency: 1.0,
+        }
+    }
+}
+
+fn is_even(x: f64) -> bool {
+    (x.floor() as i64).rem_euclid(2) == 0
+}
+
+fn blend(a: Color, b: Color, t: f64) -> Color {
+    a + (b - a) * t
+}
+
+impl PatternKind {
+    // the same pattern with f applied to its colors. a perturbed pattern's
+    // inner pattern is leaked to give it the static it needs, so this is
+    // for one-off copies like a spectral render's
+    pub fn map_colors(&self, f: impl Fn(Color) -> Color + Copy) -> PatternKind {
+        match *self {
+            PatternKind::Stripe(a, b) => PatternKind::Stripe(f(a), f(b)),
+            PatternKind::Gradient(a, b) => PatternKind::Gradient(f(a), f(b)),
+            PatternKind::Ring(a, b) => PatternKind::Ring(f(a), f(b)),
+            PatternKind::Checkers(a, b) => PatternKind::Checkers(f(a), f(b)),
+            PatternKind::Marble(a, b) => PatternKind::Marble(f(a), f(b)),
+            PatternKind::Wood(a, b) => PatternKind::Wood(f(a), f(b)),
+            PatternKind::Perturbed(p) => PatternKind::Perturbed(PerturbedPattern {
+                pattern: Box::leak(Box::new(p.pattern.map_colors(f))),
+                ..p
+            }),
+        }
+    }
+
+    pub fn color_at(&self, p: Point) -> Color {
+        let (x, y, z) = (p.0.x, p.0.y, p.0.z);
+        match *self {
+            PatternKind::Stripe(a, b) => {
+                if is_even(x) {
+                    a
+                } else {
+                    b
+                }
+            }
+            PatternKind::Gradient(a, b) => blend(a, b, x - x.floor()),
+            PatternKind::Ring(a, b) => {
+                if is_even((x * x + z * z).sqrt()) {
+                    a
+                } else {
+                    b
+                }
+            }
+            PatternKind::Checkers(a, b) => {
+                if is_even(x.floor() + y.floor() + z.floor()) {
+                    a
+                } else {
+                    b
+                }
+            }
+            PatternKind::Marble(a, b) => {
+                let veins = (x * PI + 4.0 * turbulence(p, 5)).sin();
+                // narrow dark veins on a mostly light ground
+                blend(a, b, ((1.0 - veins.abs()).max(0.0)).powi(4))
+            }
+            PatternKind::Wood(a, b) => {
+                let r = (x * x + z * z).sqrt() + 0.25 * perlin(Point::new(x, y * 0.2, z));
+                let t = r - r.floor();
+                // dark late wood at the end of each ring
+                blend(a, b, t * t)
+            }
+            PatternKind::Perturbed(p) => {
+                let f = p.frequency;
+                let (fx, fy, fz) = (x * f, y * f, z * f);
+                // three decorrelated noise lookups, one per axis
+                let dx = perlin(Point::new(fx, fy, fz));
+                let dy = perlin(Point::new(fx + 31.4, fy - 12.7, fz + 7.3));
+                let dz = perlin(Point::new(fx - 5.9, fy + 17.1, fz - 23.3));
+                p.pattern.color_at(Point::new(
+                    x + dx * p.scale,
+                    y + dy * p.scale,
+                    z + dz * p.scale,
+                ))
+            }
+        }
+    }
+}
+
+// a pattern placed on an object. the transform moves the pattern within
+// the object's own space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    // top three rows of the inverse transform, a matrix would keep
+    // materials from being Copy and the last row of an affine transform
+    // is always 0 0 0 1
+    inverse: [Tuple; 3],
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind) -> Pattern {
+        Pattern {
+            kind,
+            inverse: [
+                Tuple::new(1.0, 0.0, 0.0, 0.0),
+                Tuple::new(0.0, 1.0, 0.0, 0.0),
+                Tuple::new(0.0, 0.0, 1.0, 0.0),
+            ],
+        }
+    }
+
+    pub fn set_transform(mut self, transform: Matrix4) -> Pattern {
+        let inverse = transform
+            .inverse()
+            .expect("Fail to inverse pattern transform");
+        self.inverse = [inverse.row(0), inverse.row(1), inverse.row(2)];
+        self
+    }
+
+    // the color at a point in the object's space
+    pub fn color_at(&self, object_point: Point) -> Color {
+        let p = Tuple::from(object_point);
+        let [r0, r1, r2] = self.inverse;
+        self.kind
+            .color_at(Point::new(r0.dot(p), r1.dot(p), r2.dot(p)))
+    }
+
+    pub fn color_at_object(&self, object: &Object, world_point: Point) -> Color {
+        let object_point = Point::try_from(&object.inv_transform * world_point).unwrap();
+        self.color_at(object_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::BLACK;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+
+    const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    fn at(x: f64, y: f64, z: f64) -> Point {
+        Point::new(x, y, z)
+    }
+
+    #[test]
+    fn stripes_alternate_along_x() {
+        let p = PatternKind::Stripe(WHITE, BLACK);
+        for y in [0.0, 1.0, 2.0] {
+            assert_eq!(p.color_at(at(0.0, y, y)), WHITE);
+        }
+        assert_eq!(p.color_at(at(0.9, 0.0, 0.0)), WHITE);
+        assert_eq!(p.color_at(at(1.0, 0.0, 0.0)), BLACK);
+        assert_eq!(p.color_at(at(-0.1, 0.0, 0.0)), BLACK);
+        assert_eq!(p.color_at(at(-1.1, 0.0, 0.0)), WHITE);
+    }
+
+    #[test]
+    fn gradients_rings_and_checkers() {
+        let g = PatternKind::Gradient(WHITE, BLACK);
+        assert_eq!(g.color_at(at(0.25, 0.0, 0.0)), Color::new(0.75, 0.75, 0.75));
+        assert_eq!(g.color_at(at(0.75, 0.0, 0.0)), Color::new(0.25, 0.25, 0.25));
+        let r = PatternKind::Ring(WHITE, BLACK);
+        assert_eq!(r.color_at(at(0.0, 0.0, 0.0)), WHITE);
+        assert_eq!(r.color_at(at(1.0, 0.0, 0.0)), BLACK);
+        assert_eq!(r.color_at(at(0.708, 0.0, 0.708)), BLACK);
+        let c = PatternKind::Checkers(WHITE, BLACK);
+        assert_eq!(c.color_at(at(0.99, 0.0, 0.0)), WHITE);
+        assert_eq!(c.color_at(at(1.01, 0.0, 0.0)), BLACK);
+        assert_eq!(c.color_at(at(0.0, 0.0, 1.01)), BLACK);
+    }
+
+    #[test]
+    fn patterns_follow_their_object_and_transform() {
+        let stripes = Pattern::new(PatternKind::Stripe(WHITE, BLACK));
+        let object = sphere().set_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(stripes.color_at_object(&object, at(1.5, 0.0, 0.0)), WHITE);
+        let moved = stripes.set_transform(translation(0.5, 0.0, 0.0));
+        assert_eq!(moved.color_at(at(2.5, 0.0, 0.0)), WHITE);
+        assert_eq!(moved.color_at(at(0.25, 0.0, 0.0)), BLACK);
+        let both = stripes.set_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(both.color_at_object(&object, at(2.5, 0.0, 0.0)), WHITE);
+        assert_eq!(both.color_at_object(&object, at(4.5, 0.0, 0.0)), BLACK);
+    }
+
+    static STRIPES: PatternKind = PatternKind::Stripe(WHITE, BLACK);
+
+    #[test]
+    fn perturbing_bends_stripe_edges() {
+        let plain = Pattern::new(STRIPES);
+        let wavy = Pattern::new(PatternKind::Perturbed(PerturbedPattern::new(&STRIPES, 0.3)));
+        // along a line across the stripes the edges move, but the colors
+        // stay the two of the pattern
+        let differ = (0..200)
+            .map(|i| at(i as f64 * 0.05, 0.37, 0.81))
+            .filter(|p| {
+                let c = wavy.color_at(*p);
+                assert!(c == WHITE || c == BLACK);
+                c != plain.color_at(*p)
+            })
+            .count();
+        assert!(differ > 5 && differ < 100);
+        let still = PerturbedPattern::new(&STRIPES, 0.0);
+        assert_eq!(
+            PatternKind::Perturbed(still).color_at(at(0.7, 0.2, 0.4)),
+            STRIPES.color_at(at(0.7, 0.2, 0.4))
+        );
+    }
+
+    #[test]
+    fn marble_and_wood_stay_between_their_colors() {
+        let dark = Color::new(0.2, 0.1, 0.0);
+        for kind in [
+            PatternKind::Marble(WHITE, dark),
+            PatternKind::Wood(WHITE, dark),
+        ] {
+            let mut seen_light = false;
+            let mut seen_dark = false;
+            for i in 0..400 {
+                let p = at(i as f64 * 0.031, i as f64 * 0.017, i as f64 * 0.023);
+                let c = kind.color_at(p);
+                assert!(c.green >= dark.green - 1e-9 && c.green <= 1.0 + 1e-9);
+                seen_light |= c.green > 0.8;
+                seen_dark |= c.green < 0.4;
+            }
+            assert!(seen_light && seen_dark);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::canvas::Texture;
+use crate::color::{Color, BLACK};
+
+// how a plate of another shape than the render is laid behind it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlateFit {
+    // scaled to cover the whole render, cropping the plate's longer side
+    #[default]
+    Fill,
+    // scaled to show the whole plate, the bars beside it are black
+    Fit,
+    // squashed to the render's shape
+    Stretch,
+}
+
+// a photograph shown where primary rays miss, see Camera::set_background.
+// shadow catchers let it show through their unshadowed parts
+#[derive(Debug, Clone)]
+pub struct Plate {
+    pub image: Texture,
+    pub fit: PlateFit,
+}
+
+impl Plate {
+    pub fn new(image: impl Into<Texture>) -> Plate {
+        Plate {
+            image: image.into(),
+            fit: PlateFit::default(),
+        }
+    }
+
+    pub fn with_fit(mut self, fit: PlateFit) -> Plate {
+        self.fit = fit;
+        self
+    }
+
+    // the plate behind the continuous pixel coordinate (x, y) of a width
+    // by height render, the plate's and render's centers line up
+    pub fn sample(&self, x: f64, y: f64, width: u32, height: u32) -> Color {
+        let (pw, ph) = (self.image.width as f64, self.image.height as f64);
+        let (w, h) = (width as f64, height as f64);
+        let (sx, sy) = match self.fit {
+            PlateFit::Fill => {
+                let s = (pw / w).min(ph / h);
+                (s, s)
+            }
+            PlateFit::Fit => {
+                let s = (pw / w).max(ph / h);
+                (s, s)
+            }
+            PlateFit::Stretch => (pw / w, ph / h),
+        };
+        let (px, py) = (pw / 2.0 + (x - w / 2.0) * sx, ph / 2.0 + (y - h / 2.0) * sy);
+        if px < 0.0 || py < 0.0 || px >= pw || py >= ph {
+            return BLACK;
+        }
+        self.image.sample_bilinear(px, py)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::canvas::Canvas;
+    use crate::light::PointLight;
+    use crate::material::Material;
+    use crate::slab::slab;
+    use crate::transformations::{translation, Transformable};
+    use crate::tuple::Point;
+    use crate::world::World;
+    use std::f64::consts::PI;
+
+    const RED: Color = Color::new(1.0, 0.0, 0.0);
+    const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+
+    // 4 x 2, red on the left half and blue on the right
+    fn wide_plate() -> Plate {
+        let mut image = Canvas::new(4, 2);
+        for y in 0..2 {
+            for x in 0..4 {
+                image.write_pixel(x, y, if x < 2 { RED } else { BLUE });
+            }
+        }
+        Plate::new(image)
+    }
+
+    #[test]
+    fn plates_keep_their_aspect() {
+        // filling a square crops a pixel off each side
+        let fill = wide_plate();
+        assert_eq!(fill.sample(0.25, 1.0, 2, 2), RED);
+        assert_eq!(fill.sample(1.75, 1.0, 2, 2), BLUE);
+        assert_eq!(
+            fill.sample(0.5, 1.0, 2, 2),
+            fill.image.sample_bilinear(1.5, 1.0)
+        );
+        // fitting it leaves bars above and below
+        let fit = wide_plate().with_fit(PlateFit::Fit);
+        assert_eq!(fit.sample(0.5, 0.5, 4, 4), BLACK);
+        assert_eq!(fit.sample(0.5, 1.5, 4, 4), RED);
+        assert_eq!(fit.sample(3.5, 2.5, 4, 4), BLUE);
+        assert_eq!(fit.sample(3.5, 3.5, 4, 4), BLACK);
+        // stretching reaches every corner
+        let stretch = wide_plate().with_fit(PlateFit::Stretch);
+        assert_eq!(stretch.sample(0.1, 0.1, 4, 4), RED);
+        assert_eq!(stretch.sample(3.9, 3.9, 4, 4), BLUE);
+    }
+
+    #[test]
+    fn misses_show_the_plate() {
+        let mut camera = Camera::new(8, 4, PI / 2.0);
+        camera.set_background(Some(wide_plate()));
+        let image = camera.render(&World::new());
+        assert_eq!(image.read_pixel(0, 0).unwrap(), RED);
+        assert_eq!(image.read_pixel(7, 3).unwrap(), BLUE);
+        // so do shadow catchers with nothing casting onto them
+        let mut world = World::new();
+        world
+            .lights
+            .push(PointLight::new(Point::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        world.objects.push(
+            slab(100.0, 100.0, 1.0)
+                .transformed(&translation(0.0, 0.0, -5.5))
+                .set_material(Material {
+                    shadow_catcher: true,
+                    ..Default::default()
+                }),
+        );
+        assert_eq!(camera.render(&world), image);
+    }
+}
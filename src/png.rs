@@ -0,0 +1,134 @@
+// minimal png encoder, pixels are stored with uncompressed deflate blocks
+// so no compression library is needed
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorType {
+    Gray,
+    Rgb,
+    // straight, not premultiplied, alpha
+    Rgba,
+}
+
+// samples are row major with no filter bytes, bit_depth is 8 or 16 and 16
+// bit samples are big endian as png requires
+pub fn encode(
+    width: u32,
+    height: u32,
+    color_type: ColorType,
+    bit_depth: u8,
+    samples: &[u8],
+) -> Vec<u8> {
+    let channels = match color_type {
+        ColorType::Gray => 1,
+        ColorType::Rgb => 3,
+        ColorType::Rgba => 4,
+    };
+    let stride = width as usize * channels * (bit_depth as usize / 8);
+    assert_eq!(samples.len(), stride * height as usize);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.push(bit_depth);
+    header.push(match color_type {
+        ColorType::Gray => 0,
+        ColorType::Rgb => 2,
+        ColorType::Rgba => 6,
+    });
+    header.extend_from_slice(&[0, 0, 0]);
+    write_chunk(&mut out, b"IHDR", &header);
+
+    let mut raw = Vec::with_capacity((stride + 1) * height as usize);
+    for row in samples.chunks(stride.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_of_known_string() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn adler32_of_known_string() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn encode_small_rgb_image() {
+        let png = encode(2, 1, ColorType::Rgb, 8, &[255, 0, 0, 0, 255, 0]);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..20], &2u32.to_be_bytes());
+        assert_eq!(&png[20..24], &1u32.to_be_bytes());
+        assert_eq!(png[24], 8);
+        assert_eq!(png[25], 2);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn large_images_span_several_deflate_blocks() {
+        let samples = vec![7u8; 300 * 300];
+        let z = zlib_stored(&samples);
+        // header, two stored blocks of 5 byte headers, data and checksum
+        assert_eq!(z.len(), 2 + 2 * 5 + samples.len() + 4);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::intersection::Computations;
+use crate::material::{lighting_from, Material};
+use crate::ray::Ray;
+use crate::tuple::Vector;
+use crate::world::World;
+use std::f64::consts::PI;
+use std::ops::{Add, Mul};
+
+// polarization state of light as intensity, linear horizontal/vertical,
+// linear diagonal and circular parts. only meaningful together with the
+// reference direction perpendicular to the light it was measured against
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stokes {
+    pub s0: f64,
+    pub s1: f64,
+    pub s2: f64,
+    pub s3: f64,
+}
+
+impl Stokes {
+    pub fn new(s0: f64, s1: f64, s2: f64, s3: f64) -> Stokes {
+        Stokes { s0, s1, s2, s3 }
+    }
+
+    pub fn unpolarized(intensity: f64) -> Stokes {
+        Stokes::new(intensity, 0.0, 0.0, 0.0)
+    }
+
+    pub fn degree(&self) -> f64 {
+        if self.s0 <= 0.0 {
+            return 0.0;
+        }
+        ((self.s1 * self.s1 + self.s2 * self.s2 + self.s3 * self.s3).sqrt() / self.s0).min(1.0)
+    }
+
+    // angle of the linear part from the reference direction, 0..pi
+    pub fn angle(&self) -> f64 {
+        (0.5 * self.s2.atan2(self.s1)).rem_euclid(PI)
+    }
+
+    // the same light described against a reference turned by angle
+    pub fn rotated(&self, angle: f64) -> Stokes {
+        let (sin, cos) = (2.0 * angle).sin_cos();
+        Stokes::new(
+            self.s0,
+            cos * self.s1 + sin * self.s2,
+            -sin * self.s1 + cos * self.s2,
+            self.s3,
+        )
+    }
+
+    // the same light against another reference, both perpendicular to the
+    // direction the light travels in
+    pub fn reframed(&self, from: Vector, to: Vector, direction: Vector) -> Stokes {
+        let y = direction.cross(from);
+        self.rotated(to.dot(y).atan2(to.dot(from)))
+    }
+}
+
+impl Add for Stokes {
+    type Output = Stokes;
+    fn add(self, o: Stokes) -> Stokes {
+        Stokes::new(
+            self.s0 + o.s0,
+            self.s1 + o.s1,
+            self.s2 + o.s2,
+            self.s3 + o.s3,
+        )
+    }
+}
+
+impl Mul<f64> for Stokes {
+    type Output = Stokes;
+    fn mul(self, f: f64) -> Stokes {
+        Stokes::new(self.s0 * f, self.s1 * f, self.s2 * f, self.s3 * f)
+    }
+}
+
+// linear optical element acting on stokes vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mueller(pub [[f64; 4]; 4]);
+
+// cosine of the refracted angle, None on total internal reflection
+fn cos_refracted(n1: f64, n2: f64, cos_i: f64) -> Option<f64> {
+    let sin2_t = (n1 / n2).powi(2) * (1.0 - cos_i * cos_i);
+    (sin2_t <= 1.0).then(|| (1.0 - sin2_t).sqrt())
+}
+
+impl Mueller {
+    // partial polarizer with s and p intensity factors and the cross term,
+    // the reference direction is s, perpendicular to the plane of incidence
+    fn diattenuator(s: f64, p: f64, cross: f64) -> Mueller {
+        Mueller([
+            [(s + p) / 2.0, (s - p) / 2.0, 0.0, 0.0],
+            [(s - p) / 2.0, (s + p) / 2.0, 0.0, 0.0],
+            [0.0, 0.0, cross, 0.0],
+            [0.0, 0.0, 0.0, cross],
+        ])
+    }
+
+    // fresnel reflection off a dielectric going from index n1 into n2
+    pub fn reflection(n1: f64, n2: f64, cos_i: f64) -> Mueller {
+        let Some(cos_t) = cos_refracted(n1, n2, cos_i) else {
+            return Mueller::diattenuator(1.0, 1.0, 1.0);
+        };
+        let rs = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+        let rp = (n2 * cos_i - n1 * cos_t) / (n2 * cos_i + n1 * cos_t);
+        Mueller::diattenuator(rs * rs, rp * rp, rs * rp)
+    }
+
+    // fresnel transmission from n1 into n2, whatever is not reflected
+    pub fn transmission(n1: f64, n2: f64, cos_i: f64) -> Mueller {
+        let Some(cos_t) = cos_refracted(n1, n2, cos_i) else {
+            return Mueller::diattenuator(0.0, 0.0, 0.0);
+        };
+        let ts = 2.0 * n1 * cos_i / (n1 * cos_i + n2 * cos_t);
+        let tp = 2.0 * n1 * cos_i / (n2 * cos_i + n1 * cos_t);
+        let factor = n2 * cos_t / (n1 * cos_i);
+        Mueller::diattenuator(factor * ts * ts, factor * tp * tp, factor * ts * tp)
+    }
+}
+
+impl Mul<Stokes> for Mueller {
+    type Output = Stokes;
+    fn mul(self, s: Stokes) -> Stokes {
+        let v = [s.s0, s.s1, s.s2, s.s3];
+        let row = |r: usize| (0..4).map(|c| self.0[r][c] * v[c]).sum::<f64>();
+        Stokes::new(row(0), row(1), row(2), row(3))
+    }
+}
+
+// some direction perpendicular to v
+fn perpendicular(v: Vector) -> Vector {
+    let helper = if v.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+    v.cross(helper).normalize()
+}
+
+// s direction of the plane holding normal and direction, None when they
+// are parallel and every direction is as good as another
+fn s_direction(normal: Vector, direction: Vector) -> Option<Vector> {
+    let s = normal.cross(direction);
+    (s.magnitude() > 1e-9).then(|| s.normalize())
+}
+
+// stokes vector of the light leaving a hit towards the eye, against the
+// returned reference. diffuse and ambient light is unpolarized, specular
+// highlights are polarized by fresnel reflection and light seen through
+// transparent objects by fresnel transmission. polarization of the light
+// sources themselves and portals are not taken into account
+fn shade(world: &World, comps: &Computations, remaining: usize) -> (Stokes, Vector) {
+    let m = comps.object.material;
+    let out = -comps.eyev;
+    let reference = perpendicular(out);
+    let mut stokes = Stokes::default();
+    let samples = world.lights.iter().flat_map(|light| {
+        let positions = light.sample_positions(comps.over_point);
+        let weight = 1.0 / positions.len() as f64;
+        positions.into_iter().map(move |p| (light, p, weight))
+    });
+    for (light, position, weight) in samples {
+        let shadowed = world.is_shadowed_from(position, comps.over_point);
+        let full = lighting_from(
+            m,
+            light,
+            position,
+            comps.over_point,
+            comps.eyev,
+            comps.normal,
+            shadowed,
+        );
+        let matte = Material { specular: 0.0, ..m };
+        let diffuse = lighting_from(
+            matte,
+            light,
+            position,
+            comps.over_point,
+            comps.eyev,
+            comps.normal,
+            shadowed,
+        );
+        stokes = stokes + Stokes::unpolarized(diffuse.luminance() * weight);
+        let specular = (full - diffuse).luminance() * weight;
+        if specular <= 0.0 {
+            continue;
+        }
+        // a highlight is a mirror bounce off the half vector
+        let lightv = (position - comps.over_point).normalize();
+        let half = (lightv + comps.eyev).normalize();
+        let polarized = match s_direction(half, comps.eyev) {
+            Some(s) if m.refractive_index != 1.0 => {
+                let fresnel = Mueller::reflection(1.0, m.refractive_index, half.dot(lightv));
+                let bounced = fresnel * Stokes::unpolarized(1.0);
+                (bounced * (specular / bounced.s0)).reframed(s, reference, out)
+            }
+            _ => Stokes::unpolarized(specular),
+        };
+        stokes = stokes + polarized;
+    }
+    if m.transparency > 0.0 && remaining > 0 {
+        let (behind, from) = refracted(world, comps, remaining);
+        stokes = stokes + behind.reframed(from, reference, out);
+    }
+    (stokes, reference)
+}
+
+// light reaching the eye through the surface from behind, with its reference
+fn refracted(world: &World, comps: &Computations, remaining: usize) -> (Stokes, Vector) {
+    let none = (Stokes::default(), perpendicular(-comps.eyev));
+    let (n1, n2) = (comps.n1, comps.n2);
+    let ratio = n1 / n2;
+    let cos_i = comps.eyev.dot(comps.normal);
+    let Some(cos_t) = cos_refracted(n1, n2, cos_i) else {
+        return none;
+    };
+    let direction = comps.normal * (ratio * cos_i - cos_t) - comps.eyev * ratio;
+    let (behind, reference) = trace(world, Ray::new(comps.under_point, direction), remaining - 1);
+    let transparency = comps.object.material.transparency;
+    // the light travels back along the refracted ray, from n2 into n1
+    let fresnel = Mueller::transmission(n2, n1, cos_t);
+    let Some(s) = s_direction(comps.normal, direction) else {
+        // head on s and p are the same and any reference will do
+        return (fresnel * behind * transparency, reference);
+    };
+    let behind = behind.reframed(reference, s, -direction);
+    (fresnel * behind * transparency, s)
+}
+
+// polarization of the light coming back along ray and its reference
+fn trace(world: &World, ray: Ray, remaining: usize) -> (Stokes, Vector) {
+    let xs = world.intersect(ray);
+    match xs.hit_with_tolerance(world.settings.epsilon) {
+        Some(hit) => {
+            let comps = hit.prepare_computations_in(
+                ray,
+                &xs,
+                hit.object.surface_bias(world.settings.shadow_bias),
+            );
+            shade(world, &comps, remaining)
+        }
+        None => (Stokes::default(), perpendicular(ray.direction)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StokesBuffer {
+    pub width: usize,
+    pub height: usize,
+    // measured against the camera's horizontal
+    pub data: Vec<Stokes>,
+}
+
+fn hsv(hue: f64, saturation: f64, value: f64) -> Color {
+    let h = hue.rem_euclid(1.0) * 6.0;
+    let f = |n: f64| {
+        let k = (n + h) % 6.0;
+        value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+    };
+    Color::new(f(5.0), f(3.0), f(1.0))
+}
+
+impl StokesBuffer {
+    pub fn get(&self, x: usize, y: usize) -> Stokes {
+        self.data[y * self.width + x]
+    }
+
+    // hue is the angle of polarization, saturation the degree and
+    // brightness the intensity, so unpolarized light is gray
+    pub fn to_false_color(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width as isize, self.height as isize);
+        canvas.pixels = self
+            .data
+            .iter()
+            .map(|s| hsv(s.angle() / PI, s.degree(), s.s0.min(1.0)))
+            .collect();
+        canvas
+    }
+}
+
+impl Camera {
+    // opt in pass tracking stokes vectors through transparent surfaces
+    // and specular highlights, depth is shared like in the color pass
+    pub fn render_polarization(&self, world: &World) -> StokesBuffer {
+        let view = self
+            .transform()
+            .inverse()
+            .expect("camera transform is invertible");
+        // camera space +x is to the left of the image
+        let right = Vector::try_from(&view * Vector::new(-1.0, 0.0, 0.0)).unwrap();
+        let data = self.render_with(|ray| {
+            let direction = ray.direction.normalize();
+            let (stokes, reference) = trace(world, ray, world.settings.max_bounces);
+            let horizontal = (right - direction * right.dot(direction)).normalize();
+            stokes.reframed(reference, horizontal, direction)
+        });
+        StokesBuffer {
+            width: self.hsize() as usize,
+            height: self.vsize() as usize,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::glass_sphere;
+    use crate::transformations::view_transform;
+    use crate::tuple::Point;
+    use crate::world::default_world;
+
+    #[test]
+    fn brewster_reflection_is_fully_polarized() {
+        let n = 1.5;
+        let brewster = f64::atan(n);
+        let reflected = Mueller::reflection(1.0, n, brewster.cos()) * Stokes::unpolarized(1.0);
+        assert!((reflected.degree() - 1.0).abs() < 1e-9);
+        // s polarized, along the reference
+        assert!(reflected.angle().abs() < 1e-9);
+
+        let head_on = Mueller::reflection(1.0, n, 1.0) * Stokes::unpolarized(1.0);
+        assert!(head_on.degree() < 1e-9);
+        assert!((head_on.s0 - 0.04).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fresnel_conserves_energy() {
+        for cos_i in [1.0, 0.8, 0.3, 0.05] {
+            let light = Stokes::unpolarized(1.0);
+            let r = Mueller::reflection(1.0, 1.5, cos_i) * light;
+            let t = Mueller::transmission(1.0, 1.5, cos_i) * light;
+            assert!((r.s0 + t.s0 - 1.0).abs() < 1e-9);
+        }
+        let trapped = Mueller::transmission(1.5, 1.0, 0.5) * Stokes::unpolarized(1.0);
+        assert_eq!(trapped.s0, 0.0);
+    }
+
+    #[test]
+    fn turning_the_reference() {
+        let horizontal = Stokes::new(1.0, 1.0, 0.0, 0.0);
+        let turned = horizontal.rotated(PI / 2.0);
+        assert!((turned.s1 + 1.0).abs() < 1e-9);
+        let x = Vector::new(1.0, 0.0, 0.0);
+        let y = Vector::new(0.0, 1.0, 0.0);
+        let z = Vector::new(0.0, 0.0, 1.0);
+        let reframed = horizontal.reframed(x, y, z);
+        assert!((reframed.s1 + 1.0).abs() < 1e-9);
+        assert!((reframed.angle() - PI / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn glass_polarizes_the_view() {
+        let mut world = default_world();
+        world.objects[0] = glass_sphere();
+        let mut camera = Camera::new(21, 21, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let buffer = camera.render_polarization(&world);
+        assert_eq!(buffer.data.len(), 21 * 21);
+        assert!(buffer.data.iter().all(|s| s.degree() <= 1.0 && s.s0 >= 0.0));
+        assert!(buffer.data.iter().any(|s| s.degree() > 0.01));
+        // nothing behind the corners, no light and no polarization
+        assert_eq!(buffer.get(0, 0), Stokes::default());
+        let image = buffer.to_false_color();
+        assert_eq!(image.pixels[0], Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn false_colors() {
+        assert_eq!(hsv(0.0, 1.0, 1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(hsv(1.0 / 3.0, 1.0, 1.0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(hsv(0.5, 0.0, 0.5), Color::new(0.5, 0.5, 0.5));
+    }
+}
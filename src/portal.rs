@@ -0,0 +1,99 @@
+use crate::matrix::Matrix4;
+use crate::object::Object;
+use crate::ray::Ray;
+use crate::tuple::EPSILON;
+
+// two linked spheres, a ray entering one from the outside comes out of
+// the other at the matching point with its direction carried along. the
+// spheres themselves are never drawn and rays leaving a portal from the
+// inside pass through untouched
+#[derive(Debug, Clone)]
+pub struct Portal {
+    pub a: Object,
+    pub b: Object,
+    a_to_b: Matrix4,
+    b_to_a: Matrix4,
+}
+
+impl Portal {
+    pub fn new(a: Object, b: Object) -> Portal {
+        Portal {
+            a_to_b: b.transform.clone() * a.inv_transform.clone(),
+            b_to_a: a.transform.clone() * b.inv_transform.clone(),
+            a,
+            b,
+        }
+    }
+
+    fn entry(side: &Object, ray: Ray) -> Option<f64> {
+        let xs = side.intersect(ray);
+        let t = xs.first()?.t;
+        if t > EPSILON {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    // distance to the closest portal mouth along ray and the ray coming out
+    // of the other side, starting on the exit sphere
+    pub fn teleport(&self, ray: Ray) -> Option<(f64, Ray)> {
+        let through_a = Portal::entry(&self.a, ray).map(|t| (t, &self.a_to_b));
+        let through_b = Portal::entry(&self.b, ray).map(|t| (t, &self.b_to_a));
+        let (t, m) = match (through_a, through_b) {
+            (Some(a), Some(b)) => {
+                if a.0 <= b.0 {
+                    a
+                } else {
+                    b
+                }
+            }
+            (a, b) => a.or(b)?,
+        };
+        let entry = Ray::new(ray.position(t), ray.direction);
+        Some((t, entry.transform(m)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation};
+    use crate::tuple::{Point, Vector};
+
+    #[test]
+    fn ray_entering_a_leaves_from_b() {
+        let portal = Portal::new(
+            sphere(),
+            sphere().set_transform(translation(5.0, 0.0, 10.0) * scaling(2.0, 2.0, 2.0)),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (t, out) = portal.teleport(r).unwrap();
+        assert_eq!(t, 4.0);
+        assert_eq!(out.origin, Point::new(5.0, 0.0, 8.0));
+        assert_eq!(out.direction, Vector::new(0.0, 0.0, 2.0));
+    }
+
+    #[test]
+    fn ray_inside_a_portal_is_not_teleported() {
+        let portal = Portal::new(
+            sphere(),
+            sphere().set_transform(translation(0.0, 0.0, 10.0)),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        assert!(portal.teleport(r).is_none());
+    }
+
+    #[test]
+    fn closest_mouth_is_used() {
+        let portal = Portal::new(
+            sphere().set_transform(translation(0.0, 0.0, 10.0)),
+            sphere(),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (t, out) = portal.teleport(r).unwrap();
+        assert_eq!(t, 4.0);
+        assert_eq!(out.origin, Point::new(0.0, 0.0, 9.0));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::resample::Filter;
+use crate::sampling::Sampling;
+
+// effects applied to a finished render, in hdr so they can run before tone
+// mapping. each takes the whole image and returns a new one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostEffect {
+    // radial lens distortion, positive bows straight lines outwards like a
+    // wide angle lens (barrel), negative pinches them in (pincushion). the
+    // image corners move by about this fraction of the half diagonal
+    Distortion(f64),
+    // lateral color fringing, red is scaled up and blue down around the
+    // center by this fraction, so edges split towards the corners
+    ChromaticAberration(f64),
+    // glow around highlights. the light above threshold luminance is
+    // blurred over `levels` halvings of the image, each one twice as wide
+    // as the last, and added back times intensity
+    Bloom {
+        threshold: f64,
+        intensity: f64,
+        levels: u32,
+    },
+    // monochrome noise scaling each pixel by up to amount either way. the
+    // same seed gives the same grain, so animations can pick whether it
+    // dances or stays put
+    Grain {
+        amount: f64,
+        seed: u64,
+        sampling: Sampling,
+    },
+    // fireflies, pixels more than this many times as bright as the
+    // brightest of their eight neighbours are replaced by the neighbours'
+    // average. real highlights are seldom a single pixel wide, so they
+    // survive
+    RejectOutliers(f64),
+}
+
+impl PostEffect {
+    pub fn apply(&self, image: &Canvas) -> Canvas {
+        match *self {
+            PostEffect::Distortion(k) => remap(image, |r| 1.0 + k * r * r),
+            PostEffect::ChromaticAberration(amount) => {
+                let red = remap(image, |_| 1.0 - amount);
+                let blue = remap(image, |_| 1.0 + amount);
+                let mut out = image.clone();
+                for (i, p) in out.pixels.iter_mut().enumerate() {
+                    *p = Color::new(red.pixels[i].red, p.green, blue.pixels[i].blue);
+                }
+                out
+            }
+            PostEffect::Bloom {
+                threshold,
+                intensity,
+                levels,
+            } => bloom(image, threshold, intensity, levels),
+            PostEffect::Grain {
+                amount,
+                seed,
+                sampling,
+            } => {
+                let mut out = image.clone();
+                for (i, p) in out.pixels.iter_mut().enumerate() {
+                    let (x, y) = (i as isize % image.width, i as isize / image.width);
+                    let n = sampling.sample(seed, x as u32, y as u32, 0) * 2.0 - 1.0;
+                    *p = *p * (1.0 + amount * n);
+                }
+                out
+            }
+            PostEffect::RejectOutliers(ratio) => reject_outliers(image, ratio),
+        }
+    }
+}
+
+fn reject_outliers(image: &Canvas, ratio: f64) -> Canvas {
+    let mut out = image.clone();
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let mut brightest = 0.0f64;
+            let mut sum = Color::new(0.0, 0.0, 0.0);
+            let mut count = 0;
+            for (dx, dy) in (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy))) {
+                let (nx, ny) = (x + dx, y + dy);
+                let inside = (0..image.width).contains(&nx) && (0..image.height).contains(&ny);
+                if (dx, dy) != (0, 0) && inside {
+                    let c = image.pixels[(ny * image.width + nx) as usize];
+                    brightest = brightest.max(c.luminance());
+                    sum = sum + c;
+                    count += 1;
+                }
+            }
+            let i = (y * image.width + x) as usize;
+            if count > 0 && image.pixels[i].luminance() > brightest * ratio {
+                out.pixels[i] = sum * (1.0 / count as f64);
+            }
+        }
+    }
+    out
+}
+
+fn bloom(image: &Canvas, threshold: f64, intensity: f64, levels: u32) -> Canvas {
+    let mut bright = image.clone();
+    for p in bright.pixels.iter_mut() {
+        let l = p.luminance();
+        *p = if l > threshold {
+            *p * ((l - threshold) / l)
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        };
+    }
+    let mut glow = Canvas::new(image.width, image.height);
+    let mut level = bright;
+    for _ in 0..levels {
+        if level.width < 2 || level.height < 2 {
+            break;
+        }
+        level = blurred(&level.resized(
+            level.width as usize / 2,
+            level.height as usize / 2,
+            Filter::Box,
+        ));
+        // back up to full size, a level pixel covers sx by sy image pixels
+        let sx = level.width as f64 / image.width as f64;
+        let sy = level.height as f64 / image.height as f64;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let i = (y * image.width + x) as usize;
+                glow.pixels[i] = glow.pixels[i]
+                    + level.sample_bilinear((x as f64 + 0.5) * sx, (y as f64 + 0.5) * sy);
+            }
+        }
+    }
+    let mut out = image.clone();
+    for (p, g) in out.pixels.iter_mut().zip(&glow.pixels) {
+        *p = *p + *g * intensity;
+    }
+    out
+}
+
+// five tap gaussian, one axis at a time, edges repeated
+fn blurred(image: &Canvas) -> Canvas {
+    const TAPS: [f64; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+    let pass = |src: &Canvas, dx: isize, dy: isize| {
+        let mut out = src.clone();
+        for y in 0..src.height {
+            for x in 0..src.width {
+                out.pixels[(y * src.width + x) as usize] =
+                    TAPS.iter()
+                        .enumerate()
+                        .fold(Color::new(0.0, 0.0, 0.0), |c, (k, w)| {
+                            let o = k as isize - 2;
+                            let sx = (x + o * dx).clamp(0, src.width - 1);
+                            let sy = (y + o * dy).clamp(0, src.height - 1);
+                            c + src.pixels[(sy * src.width + sx) as usize] * *w
+                        });
+            }
+        }
+        out
+    };
+    pass(&pass(image, 1, 0), 0, 1)
+}
+
+impl Canvas {
+    // runs the effects one after the other
+    pub fn post_processed(&self, effects: &[PostEffect]) -> Canvas {
+        effects
+            .iter()
+            .fold(self.clone(), |image, effect| effect.apply(&image))
+    }
+
+    // bilinear lookup at pixel coordinates, pixel centers sit at .5 and
+    // reads past the border repeat the edge
+    pub fn sample_bilinear(&self, x: f64, y: f64) -> Color {
+        let clamp = |v: f64, size: isize| v.clamp(0.0, (size - 1) as f64);
+        let (x, y) = (clamp(x - 0.5, self.width), clamp(y - 0.5, self.height));
+        let (x0, y0) = (x.floor() as isize, y.floor() as isize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+        let at = |x: isize, y: isize| self.pixels[(y * self.width + x) as usize];
+        let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+        let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+// every output pixel reads the source at its offset from the center times
+// scale(r), r being the distance from the center over the half diagonal
+fn remap(image: &Canvas, scale: impl Fn(f64) -> f64) -> Canvas {
+    let (cx, cy) = (image.width as f64 / 2.0, image.height as f64 / 2.0);
+    let half_diagonal = (cx * cx + cy * cy).sqrt().max(f64::EPSILON);
+    let mut out = image.clone();
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let (dx, dy) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+            let s = scale((dx * dx + dy * dy).sqrt() / half_diagonal);
+            out.pixels[(y * image.width + x) as usize] =
+                image.sample_bilinear(cx + dx * s, cy + dy * s);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a white frame one pixel in from the edge of a black image
+    fn frame() -> Canvas {
+        let mut c = Canvas::new(21, 21);
+        for i in 1..20 {
+            for (x, y) in [(i, 1), (i, 19), (1, i), (19, i)] {
+                c.write_pixel(x, y, Color::new(1.0, 1.0, 1.0));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn bilinear_samples() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, Color::new(1.0, 0.5, 0.0));
+        assert_eq!(c.sample_bilinear(1.0, 0.5), Color::new(0.5, 0.25, 0.0));
+        assert_eq!(c.sample_bilinear(1.5, 0.5), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(c.sample_bilinear(-3.0, 9.0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn distortion_leaves_the_center_and_bends_the_edges() {
+        let image = frame();
+        assert_eq!(PostEffect::Distortion(0.0).apply(&image), image);
+        let barrel = PostEffect::Distortion(0.3).apply(&image);
+        let pincushion = PostEffect::Distortion(-0.3).apply(&image);
+        // the middle of the top edge moves towards the center for barrel,
+        // away from it for pincushion
+        let edge = |c: &Canvas| (0..10).find(|&y| c.read_pixel(10, y).unwrap().red > 0.5);
+        assert_eq!(edge(&image), Some(1));
+        assert!(edge(&barrel).unwrap() > 1);
+        assert_eq!(edge(&pincushion), Some(0));
+        assert_eq!(
+            barrel.read_pixel(10, 10).unwrap(),
+            image.read_pixel(10, 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn chromatic_aberration_splits_channels_towards_the_edges() {
+        let image = frame();
+        let split = image.post_processed(&[PostEffect::ChromaticAberration(0.1)]);
+        // green is untouched
+        for (a, b) in image.pixels.iter().zip(&split.pixels) {
+            assert_eq!(a.green, b.green);
+        }
+        let near_corner = split.read_pixel(2, 2).unwrap();
+        assert!(near_corner.red != near_corner.blue);
+        assert_eq!(image.post_processed(&[]), image);
+    }
+
+    #[test]
+    fn bloom_spreads_only_highlights() {
+        let mut c = Canvas::new(16, 16);
+        c.fill(Color::new(0.5, 0.5, 0.5));
+        c.write_pixel(8, 8, Color::new(20.0, 20.0, 20.0));
+        let bloom = PostEffect::Bloom {
+            threshold: 1.0,
+            intensity: 0.5,
+            levels: 3,
+        };
+        let out = bloom.apply(&c);
+        // neighbours of the hot pixel glow, far corners barely change
+        let near = out.read_pixel(10, 8).unwrap().red;
+        let far = out.read_pixel(0, 0).unwrap().red;
+        assert!(near > 0.7);
+        assert!(far < 0.6 && far > 0.5);
+        assert!(out.read_pixel(8, 8).unwrap().red > 20.0);
+        // nothing above the threshold, nothing to do
+        let mut flat = Canvas::new(16, 16);
+        flat.fill(Color::new(0.5, 0.5, 0.5));
+        assert_eq!(bloom.apply(&flat), flat);
+    }
+
+    #[test]
+    fn lone_bright_pixels_are_rejected() {
+        let mut c = Canvas::new(8, 8);
+        c.fill(Color::new(0.2, 0.2, 0.2));
+        c.write_pixel(2, 2, Color::new(50.0, 50.0, 50.0));
+        // a highlight two pixels wide stays
+        c.write_pixel(5, 5, Color::new(3.0, 3.0, 3.0));
+        c.write_pixel(6, 5, Color::new(3.0, 3.0, 3.0));
+        let out = PostEffect::RejectOutliers(4.0).apply(&c);
+        assert_eq!(out.read_pixel(2, 2).unwrap(), Color::new(0.2, 0.2, 0.2));
+        assert_eq!(out.read_pixel(5, 5).unwrap(), Color::new(3.0, 3.0, 3.0));
+        assert_eq!(out.read_pixel(0, 0).unwrap(), Color::new(0.2, 0.2, 0.2));
+    }
+
+    #[test]
+    fn grain_is_seeded() {
+        let mut c = Canvas::new(8, 8);
+        c.fill(Color::new(0.5, 0.5, 0.5));
+        for sampling in [Sampling::White, Sampling::BlueNoise] {
+            let grain = |seed| {
+                PostEffect::Grain {
+                    amount: 0.1,
+                    seed,
+                    sampling,
+                }
+                .apply(&c)
+            };
+            assert_eq!(grain(1), grain(1));
+            assert_ne!(grain(1), grain(2));
+            for p in &grain(1).pixels {
+                assert!((0.45..=0.55).contains(&p.red));
+                assert_eq!(p.red, p.blue);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::bounds::BoundingBox;
+use crate::profile::span;
+use crate::world::World;
+use std::ops::Deref;
+
+// groups holding more objects than this are divided when prepared
+pub const GROUP_THRESHOLD: usize = 8;
+
+// a world made ready for rendering by World::prepare. it reads like the
+// world it came from but can't be changed, edit hands the world back for
+// that and it has to be prepared again afterwards
+#[derive(Debug, Clone)]
+pub struct PreparedWorld {
+    world: World,
+    bounds: BoundingBox,
+}
+
+impl World {
+    // does the work rays would otherwise repeat or miss out on: moved
+    // objects are refit, the bvh is built and big groups are divided.
+    // objects keep their inverse transforms and triangles their edges
+    // from the start, so those are ready already
+    pub fn prepare(mut self) -> PreparedWorld {
+        let _span = span!(INFO, "prepare", objects = self.objects.len());
+        self.refit();
+        if !self
+            .bvh
+            .as_ref()
+            .is_some_and(|bvh| bvh.matches(&self.objects))
+        {
+            self.build_bvh();
+        }
+        if self
+            .sphere_batch
+            .as_ref()
+            .is_some_and(|batch| !batch.matches(&self.objects))
+        {
+            self.build_sphere_batch();
+        }
+        self.groups = std::mem::take(&mut self.groups)
+            .into_iter()
+            .map(|g| g.divide(GROUP_THRESHOLD))
+            .collect();
+        let bounds = self.bounds();
+        PreparedWorld {
+            world: self,
+            bounds,
+        }
+    }
+}
+
+impl PreparedWorld {
+    pub fn edit(self) -> World {
+        self.world
+    }
+
+    // the world's bounds, worked out once when prepared
+    pub fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+}
+
+impl Deref for PreparedWorld {
+    type Target = World;
+    fn deref(&self) -> &World {
+        &self.world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::camera::Camera;
+    use crate::generators::sphere_grid;
+    use crate::group::Group;
+    use crate::ray::Ray;
+    use crate::sphere::sphere;
+    use crate::transformations::{translation, view_transform};
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(11, 11, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        camera
+    }
+
+    #[test]
+    fn preparing_renders_the_same_world() {
+        let mut w = default_world();
+        let mut row = Group::new("row");
+        for i in 0..20 {
+            row.push_child(sphere().set_transform(translation(i as f64 - 10.0, -3.0, 0.0)));
+        }
+        w.add_group(row);
+        let image = camera().render(&w);
+        let prepared = w.clone().prepare();
+        assert!(prepared.bvh.is_some());
+        assert!(!prepared.groups[0].groups().is_empty());
+        assert_eq!(prepared.bounds(), w.bounds());
+        assert_eq!(camera().render(&prepared), image);
+    }
+
+    #[test]
+    fn editing_and_preparing_again() {
+        let mut w = sphere_grid(3, 3.0).prepare().edit();
+        w.set_object_transform(0, translation(0.0, 50.0, 0.0));
+        assert_eq!(w.changed_objects(), &[0]);
+        let prepared = w.prepare();
+        assert!(prepared.changed_objects().is_empty());
+        assert_eq!(prepared.bounds().max.0.y, 51.0);
+        let r = Ray::new(Point::new(0.0, 50.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(prepared.intersect(r).len(), 2);
+        // objects swapped in place are caught too, not only moved ones
+        let mut w = prepared.edit();
+        w.build_sphere_batch();
+        w.objects[1] = sphere().set_transform(translation(0.0, -50.0, 0.0));
+        let prepared = w.prepare();
+        let r = Ray::new(Point::new(0.0, -50.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(prepared.intersect(r).len(), 2);
+        assert!(prepared
+            .sphere_batch
+            .as_ref()
+            .unwrap()
+            .matches(&prepared.objects));
+    }
+}
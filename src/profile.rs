@@ -0,0 +1,26 @@
+// spans around the expensive phases of a render, only recorded with the
+// `tracing` feature so the default build has no extra dependency or cost.
+// levels: INFO for whole frames, builds and encodes, DEBUG per row and
+// TRACE for per hit shading
+//
+//     let _span = span!(INFO, "render", width = 640);
+
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $($rest:tt)*) => {
+        tracing::span!(tracing::Level::$level, $($rest)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($level:ident, $($rest:tt)*) => {
+        $crate::profile::NoSpan
+    };
+}
+
+pub(crate) use span;
+
+// stands in for an entered span when tracing is disabled
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
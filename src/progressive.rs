@@ -0,0 +1,279 @@
+use crate::camera::{Camera, Rect};
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::sampling::Sampling;
+use crate::tonemap::ToneMapper;
+use crate::world::World;
+use std::time::{Duration, Instant};
+
+// rows rendered between looks at the clock in render_for
+const BAND_HEIGHT: u32 = 8;
+
+// renders an image one sample per pixel at a time so it can be shown
+// while it sharpens. the samples are summed in hdr and only tone mapped
+// when an image is asked for, so the last preview is the final image
+#[derive(Debug, Clone)]
+pub struct Progressive {
+    width: u32,
+    height: u32,
+    sum: Vec<Color>,
+    passes: u32,
+    pub tone: ToneMapper,
+    pub seed: u64,
+    // where the jitter comes from
+    pub sampling: Sampling,
+    // samples brighter than this luminance are scaled down to it before
+    // they are summed, so one lucky sample can't light up a pixel for
+    // good. a little energy is lost in return
+    pub max_radiance: Option<f64>,
+}
+
+impl Progressive {
+    pub fn new(camera: &Camera, tone: ToneMapper) -> Progressive {
+        Progressive {
+            width: camera.hsize(),
+            height: camera.vsize(),
+            sum: vec![Color::new(0.0, 0.0, 0.0); (camera.hsize() * camera.vsize()) as usize],
+            passes: 0,
+            tone,
+            seed: 0,
+            sampling: Sampling::White,
+            max_radiance: None,
+        }
+    }
+
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    // adds one sample to every pixel. the first pass goes through pixel
+    // centers, later ones are jittered across the pixel
+    pub fn render_pass(&mut self, camera: &Camera, world: &World) {
+        let rect = Rect::new(0, 0, self.width, self.height);
+        let samples = self.samples(camera, world, rect);
+        self.add_pass(samples);
+    }
+
+    // render_pass in bands of rows, given up on if the deadline passes
+    // before the last band is done. returns whether the pass was added
+    fn render_pass_before(&mut self, camera: &Camera, world: &World, deadline: Instant) -> bool {
+        let mut samples = Vec::with_capacity(self.sum.len());
+        for y in (0..self.height).step_by(BAND_HEIGHT as usize) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            let band = Rect::new(0, y, self.width, BAND_HEIGHT);
+            samples.extend(self.samples(camera, world, band));
+        }
+        self.add_pass(samples);
+        true
+    }
+
+    fn samples(&self, camera: &Camera, world: &World, rect: Rect) -> Vec<Color> {
+        assert_eq!((camera.hsize(), camera.vsize()), (self.width, self.height));
+        let pass = self.passes;
+        let (seed, sampling) = (self.seed, self.sampling);
+        camera.render_region_with(rect, |x, y| {
+            if pass == 0 {
+                return camera.subpixel_color(world, x, y, 0.0, 0.0);
+            }
+            let dx = sampling.sample(seed, x, y, pass * 2) - 0.5;
+            let dy = sampling.sample(seed, x, y, pass * 2 + 1) - 0.5;
+            camera.subpixel_color(world, x, y, dx, dy)
+        })
+    }
+
+    fn add_pass(&mut self, samples: Vec<Color>) {
+        for (sum, sample) in self.sum.iter_mut().zip(samples) {
+            *sum = *sum + clamp_radiance(sample, self.max_radiance);
+        }
+        self.passes += 1;
+    }
+
+    // the average so far, unmapped
+    pub fn hdr(&self) -> Canvas {
+        let scale = 1.0 / self.passes.max(1) as f64;
+        Canvas {
+            width: self.width as isize,
+            height: self.height as isize,
+            pixels: self.sum.iter().map(|c| *c * scale).collect(),
+        }
+    }
+
+    // the average so far through the tone mapper, for display and export
+    pub fn image(&self) -> Canvas {
+        self.hdr().tone_mapped(self.tone)
+    }
+
+    // runs passes until there are `passes` of them, handing each preview
+    // to `preview` as it comes in
+    pub fn render(
+        &mut self,
+        camera: &Camera,
+        world: &World,
+        passes: u32,
+        mut preview: impl FnMut(&Canvas),
+    ) -> Canvas {
+        while self.passes < passes {
+            self.render_pass(camera, world);
+            preview(&self.image());
+        }
+        self.image()
+    }
+
+    // runs passes for as long as budget allows. the first pass is always
+    // finished so there is an image, a later one the time runs out on is
+    // dropped so every pixel keeps the same number of samples
+    pub fn render_for(&mut self, camera: &Camera, world: &World, budget: Duration) -> Canvas {
+        let deadline = Instant::now() + budget;
+        if self.passes == 0 {
+            self.render_pass(camera, world);
+        }
+        while self.render_pass_before(camera, world, deadline) {}
+        self.image()
+    }
+}
+
+impl Camera {
+    // the best hdr image budget allows, passes of jittered samples take
+    // the place of set_samples. what render does with a time budget set
+    pub fn render_for(&self, world: &World, budget: Duration) -> Canvas {
+        let mut progressive = Progressive::new(self, ToneMapper::Clamp);
+        progressive.render_for(self, world, budget);
+        progressive.hdr()
+    }
+}
+
+fn clamp_radiance(c: Color, max: Option<f64>) -> Color {
+    match max {
+        Some(max) if c.luminance() > max => c * (max / c.luminance()),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::post::PostEffect;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn previews_are_tone_mapped_from_hdr() {
+        let mut w = default_world();
+        w.lights =
+            vec![PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(4.0, 4.0, 4.0)).into()];
+        let mut camera = Camera::new(8, 8, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let mut progressive = Progressive::new(&camera, ToneMapper::Reinhard);
+        let mut previews = vec![];
+        let last = progressive.render(&camera, &w, 3, |p| previews.push(p.clone()));
+        assert_eq!(progressive.passes(), 3);
+        assert_eq!(previews.len(), 3);
+        assert_eq!(previews[2], last);
+        // the sum kept the light that a clipped buffer would have lost
+        let hdr = progressive.hdr();
+        assert!(hdr.pixels.iter().any(|p| p.red > 1.0));
+        assert_eq!(last, hdr.tone_mapped(ToneMapper::Reinhard));
+        assert!(last.pixels.iter().all(|p| p.red < 1.0));
+        // the first pass is the plain render
+        let plain = camera.render(&w).tone_mapped(ToneMapper::Reinhard);
+        assert_eq!(previews[0], plain);
+    }
+
+    #[test]
+    fn jitter_can_come_from_blue_noise() {
+        let w = default_world();
+        let mut camera = Camera::new(8, 8, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let render = |sampling| {
+            let mut p = Progressive::new(&camera, ToneMapper::Clamp);
+            p.sampling = sampling;
+            p.render(&camera, &w, 3, |_| {})
+        };
+        assert_eq!(render(Sampling::BlueNoise), render(Sampling::BlueNoise));
+        assert_ne!(render(Sampling::BlueNoise), render(Sampling::White));
+    }
+
+    #[test]
+    fn renders_do_not_depend_on_thread_count() {
+        let w = default_world();
+        let mut camera = Camera::new(16, 12, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let render = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                [Sampling::White, Sampling::BlueNoise].map(|sampling| {
+                    let mut p = Progressive::new(&camera, ToneMapper::Clamp);
+                    p.seed = 5;
+                    p.sampling = sampling;
+                    p.render(&camera, &w, 4, |_| {})
+                        .post_processed(&[PostEffect::Grain {
+                            amount: 0.1,
+                            seed: 5,
+                            sampling,
+                        }])
+                })
+            })
+        };
+        let bits = |images: [Canvas; 2]| {
+            images
+                .iter()
+                .flat_map(|image| &image.pixels)
+                .flat_map(|c| [c.red.to_bits(), c.green.to_bits(), c.blue.to_bits()])
+                .collect::<Vec<_>>()
+        };
+        let single = bits(render(1));
+        assert_eq!(single, bits(render(4)));
+        assert_eq!(single, bits(render(7)));
+    }
+
+    #[test]
+    fn budgets_stop_after_whole_passes() {
+        let camera = Camera::new(6, 6, PI / 3.0);
+        let mut w = default_world();
+        let plain = camera.render(&w);
+        // with no time at all there is still the first pass
+        w.settings = w.settings.with_time_budget(Duration::ZERO);
+        assert_eq!(camera.render(&w), plain);
+        let mut p = Progressive::new(&camera, ToneMapper::Clamp);
+        p.render_for(&camera, &w, Duration::from_millis(50));
+        assert!(p.passes() > 1);
+    }
+
+    #[test]
+    fn clamping_bright_samples() {
+        let mut w = default_world();
+        w.lights[0] =
+            PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(10.0, 10.0, 10.0)).into();
+        let camera = Camera::new(6, 6, PI / 3.0);
+        let mut p = Progressive::new(&camera, ToneMapper::Clamp);
+        p.max_radiance = Some(0.5);
+        p.render_pass(&camera, &w);
+        assert!(p.hdr().pixels.iter().all(|c| c.luminance() <= 0.5 + 1e-9));
+        let c = Color::new(4.0, 2.0, 0.0);
+        assert_eq!(clamp_radiance(c, None), c);
+        let clamped = clamp_radiance(c, Some(1.0));
+        assert!((clamped.luminance() - 1.0).abs() < 1e-9);
+        // the hue stays
+        assert!((clamped.red / clamped.green - 2.0).abs() < 1e-9);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::matrix::Matrix4;
+use crate::transformations::Transformable;
 use crate::tuple::{Point, Tuple, Vector};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -26,6 +27,12 @@ impl Ray {
     }
 }
 
+impl Transformable for Ray {
+    fn transformed(self, m: &Matrix4) -> Ray {
+        self.transform(m)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,4 +74,12 @@ mod tests {
         assert_eq!(r2.origin, Point::new(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, Vector::new(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn chaining_transforms_on_a_ray() {
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
+        let r2 = r.scaled(2.0, 3.0, 4.0).translated(3.0, 4.0, 5.0);
+        assert_eq!(r2.origin, Point::new(5.0, 10.0, 17.0));
+        assert_eq!(r2.direction, Vector::new(0.0, 3.0, 0.0));
+    }
 }
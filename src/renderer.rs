@@ -0,0 +1,163 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::prepared::PreparedWorld;
+use crate::profile::span;
+use crate::ray::Ray;
+use crate::world::World;
+use rayon::prelude::*;
+
+// a prepared copy of a world, for apps that keep editing their own world
+// and render it every frame. prepare again whenever the edits should show
+// up, see World::prepare for what preparing does
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    world: PreparedWorld,
+}
+
+impl Renderer {
+    pub fn prepare(world: &World) -> Renderer {
+        Renderer {
+            world: world.clone().prepare(),
+        }
+    }
+
+    pub fn world(&self) -> &PreparedWorld {
+        &self.world
+    }
+
+    pub fn color_at(&self, ray: Ray) -> Color {
+        self.world.color_at(ray)
+    }
+
+    pub fn render(&self, camera: &Camera) -> Canvas {
+        camera.render(&self.world)
+    }
+
+    // renders every camera in one parallel batch, rows of all views are
+    // shared out together so small views don't leave threads idle. with a
+    // time budget set the views are rendered one by one, each given the
+    // whole budget as render would
+    pub fn render_views(&self, cameras: &[Camera]) -> Vec<Canvas> {
+        let _span = span!(INFO, "render_views", views = cameras.len());
+        if self.world.settings.time_budget.is_some() {
+            return cameras.iter().map(|camera| self.render(camera)).collect();
+        }
+        let rows = cameras
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| (0..c.vsize()).map(move |y| (i, y)))
+            .collect::<Vec<_>>();
+        let mut rendered = rows
+            .par_iter()
+            .map(|&(i, y)| {
+                let camera = &cameras[i];
+                (0..camera.hsize())
+                    .map(|x| camera.pixel_color(&self.world, x, y))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+        cameras
+            .iter()
+            .map(|camera| {
+                let mut image = Canvas::new(camera.hsize() as isize, camera.vsize() as isize);
+                image.pixels = rendered
+                    .by_ref()
+                    .take(camera.vsize() as usize)
+                    .flatten()
+                    .collect();
+                image
+            })
+            .collect()
+    }
+}
+
+// several views of one world, e.g. front, side and detail shots of a
+// product. the world is prepared once for all of them
+pub fn render_views(world: &World, cameras: &[Camera]) -> Vec<Canvas> {
+    Renderer::prepare(world).render_views(cameras)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generators;
+    use crate::plate::Plate;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use static_assertions::assert_impl_all;
+    use std::f64::consts::PI;
+    use std::time::Duration;
+
+    fn camera() -> Camera {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        c
+    }
+
+    #[test]
+    fn prepared_render_matches_world_render() {
+        let world = default_world();
+        let renderer = Renderer::prepare(&world);
+        assert!(renderer.world().bvh.is_some());
+        assert_eq!(renderer.render(&camera()), camera().render(&world));
+
+        let grid = generators::sphere_grid(4, 1.5);
+        assert_eq!(
+            Renderer::prepare(&grid).render(&camera()),
+            camera().render(&grid)
+        );
+    }
+
+    #[test]
+    fn edits_after_prepare_are_not_seen() {
+        let mut world = default_world();
+        let renderer = Renderer::prepare(&world);
+        let before = renderer.render(&camera());
+        world.objects.clear();
+        assert_eq!(renderer.render(&camera()), before);
+        assert_ne!(Renderer::prepare(&world).render(&camera()), before);
+    }
+
+    #[test]
+    fn rendering_several_views_at_once() {
+        let world = default_world();
+        let side = || {
+            let mut c = Camera::new(7, 5, PI / 3.0);
+            c.set_transform(view_transform(
+                Point::new(5.0, 1.0, 0.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            ));
+            // views keep their own plate and samples
+            let mut plate = Canvas::new(1, 1);
+            plate.write_pixel(0, 0, Color::new(0.2, 0.4, 0.6));
+            c.set_background(Some(Plate::new(plate)));
+            c.set_samples(3);
+            c
+        };
+        let views = render_views(&world, &[camera(), side()]);
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[0], camera().render(&world));
+        assert_eq!(views[1], side().render(&world));
+        assert_eq!(
+            views[1].read_pixel(0, 0).unwrap(),
+            Color::new(0.2, 0.4, 0.6)
+        );
+        assert!(render_views(&world, &[]).is_empty());
+        // a time budget is kept to like render does
+        let mut world = world;
+        world.settings = world.settings.with_time_budget(Duration::ZERO);
+        let views = render_views(&world, &[camera(), side()]);
+        assert_eq!(views[1], side().render(&world));
+        assert_ne!(views[1], side().render(&default_world()));
+    }
+
+    assert_impl_all!(Renderer: Send, Sync);
+}
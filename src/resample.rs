@@ -0,0 +1,213 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::world::World;
+use std::f64::consts::PI;
+
+// reconstruction filters for resizing a canvas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    // plain average of the pixels each new pixel covers, exact for whole
+    // number factors
+    Box,
+    // windowed sinc over three lobes, sharper but may ring a little
+    // around hard edges
+    Lanczos3,
+}
+
+impl Filter {
+    fn radius(&self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(&self, x: f64) -> f64 {
+        match self {
+            Filter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos3 => {
+                let sinc = |x: f64| {
+                    if x.abs() < 1e-9 {
+                        1.0
+                    } else {
+                        (PI * x).sin() / (PI * x)
+                    }
+                };
+                if x.abs() < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+// for each target pixel along one axis, the source pixels it reads and
+// their normalized weights
+fn contributions(filter: Filter, from: usize, to: usize) -> Vec<Vec<(usize, f64)>> {
+    let scale = from as f64 / to as f64;
+    // shrinking stretches the filter over the source pixels a target
+    // pixel covers, enlarging keeps it at one pixel
+    let stretch = scale.max(1.0);
+    let radius = filter.radius() * stretch;
+    (0..to)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * scale;
+            let first = (center - radius).floor().max(0.0) as usize;
+            let last = ((center + radius).ceil() as usize).min(from);
+            let mut taps = (first..last)
+                .map(|j| (j, filter.weight((j as f64 + 0.5 - center) / stretch)))
+                .filter(|(_, w)| *w != 0.0)
+                .collect::<Vec<_>>();
+            let total: f64 = taps.iter().map(|(_, w)| w).sum();
+            for (_, w) in taps.iter_mut() {
+                *w /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+impl Canvas {
+    // resizes to width x height, one axis at a time. lanczos can ring
+    // below zero, which is cut off
+    pub fn resized(&self, width: usize, height: usize, filter: Filter) -> Canvas {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let black = Color::new(0.0, 0.0, 0.0);
+        let columns = contributions(filter, w, width);
+        let mut wide = vec![black; width * h];
+        for y in 0..h {
+            for (x, taps) in columns.iter().enumerate() {
+                wide[y * width + x] = taps
+                    .iter()
+                    .fold(black, |c, (j, wt)| c + self.pixels[y * w + j] * *wt);
+            }
+        }
+        let rows = contributions(filter, h, height);
+        let mut pixels = vec![black; width * height];
+        for (y, taps) in rows.iter().enumerate() {
+            for x in 0..width {
+                let c = taps
+                    .iter()
+                    .fold(black, |c, (j, wt)| c + wide[j * width + x] * *wt);
+                pixels[y * width + x] =
+                    Color::new(c.red.max(0.0), c.green.max(0.0), c.blue.max(0.0));
+            }
+        }
+        Canvas {
+            width: width as isize,
+            height: height as isize,
+            pixels,
+        }
+    }
+
+    // scaled down with lanczos so its longer side is at most max_side,
+    // keeping the aspect ratio. smaller images are returned as they are
+    pub fn thumbnail(&self, max_side: usize) -> Canvas {
+        let longest = self.width.max(self.height) as usize;
+        if longest <= max_side {
+            return self.clone();
+        }
+        let scale = max_side as f64 / longest as f64;
+        let size = |n: isize| ((n as f64 * scale).round() as usize).max(1);
+        self.resized(size(self.width), size(self.height), Filter::Lanczos3)
+    }
+}
+
+impl Camera {
+    // renders at factor times the size and boxes it back down, so every
+    // pixel averages factor x factor evenly spread samples
+    pub fn render_supersampled(&self, world: &World, factor: u32) -> Canvas {
+        let factor = factor.max(1);
+        let mut large = Camera::new(
+            self.hsize() * factor,
+            self.vsize() * factor,
+            self.field_of_view(),
+        );
+        large.set_transform(self.transform().clone());
+        large
+            .render(world)
+            .resized(self.hsize() as usize, self.vsize() as usize, Filter::Box)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+
+    fn stripes() -> Canvas {
+        let mut c = Canvas::new(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                let v = if x % 2 == 0 { 1.0 } else { 0.0 };
+                c.write_pixel(x, y, Color::new(v, v, v));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn box_filter_averages_blocks() {
+        let small = stripes().resized(4, 2, Filter::Box);
+        assert_eq!((small.width, small.height), (4, 2));
+        for p in &small.pixels {
+            assert_eq!(*p, Color::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn filters_keep_flat_areas_flat() {
+        let mut c = Canvas::new(9, 7);
+        c.fill(Color::new(0.2, 0.4, 0.6));
+        for filter in [Filter::Box, Filter::Lanczos3] {
+            for p in &c.resized(4, 3, filter).pixels {
+                assert_eq!(*p, Color::new(0.2, 0.4, 0.6));
+            }
+        }
+        // and enlarging works too
+        assert_eq!(
+            c.resized(20, 15, Filter::Lanczos3).pixels[37],
+            Color::new(0.2, 0.4, 0.6)
+        );
+    }
+
+    #[test]
+    fn thumbnails_keep_the_aspect_ratio() {
+        let t = stripes().thumbnail(4);
+        assert_eq!((t.width, t.height), (4, 2));
+        assert_eq!(stripes().thumbnail(100), stripes());
+    }
+
+    #[test]
+    fn supersampling_smooths_edges() {
+        let mut camera = Camera::new(9, 9, std::f64::consts::PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let w = default_world();
+        let plain = camera.render(&w);
+        let smooth = camera.render_supersampled(&w, 3);
+        assert_eq!((smooth.width, smooth.height), (9, 9));
+        // the middle of the sphere barely changes, its rim does
+        let (a, b) = (
+            plain.read_pixel(4, 4).unwrap(),
+            smooth.read_pixel(4, 4).unwrap(),
+        );
+        assert!((a.red - b.red).abs() < 0.05);
+        assert_ne!(plain, smooth);
+        assert_eq!(camera.render_supersampled(&w, 1), plain);
+    }
+}
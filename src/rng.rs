@@ -0,0 +1,71 @@
+// small seedable generator (splitmix64), good enough for scene layout and
+// sampling and reproducible across platforms
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    // a stream of its own for one pixel. parallel renders draw from these
+    // instead of one generator per thread, so a pixel gets the same
+    // numbers whichever thread renders it and however many there are
+    pub fn for_pixel(seed: u64, x: u32, y: u32) -> Rng {
+        let pixel = ((y as u64) << 20) ^ x as u64;
+        Rng::new(seed ^ pixel.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in 0..1
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        for _ in 0..10 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn floats_stay_in_range() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            let x = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn pixels_get_their_own_streams() {
+        let first = Rng::for_pixel(9, 3, 4).next_u64();
+        assert_eq!(first, Rng::for_pixel(9, 3, 4).next_u64());
+        for (x, y) in [(4, 3), (3, 5), (2, 4)] {
+            assert_ne!(first, Rng::for_pixel(9, x, y).next_u64());
+        }
+        assert_ne!(first, Rng::for_pixel(10, 3, 4).next_u64());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::rng::Rng;
+
+// side of the tiled blue noise mask
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+// a 64 x 64 void and cluster mask, every byte value appears 16 times and
+// neighbouring pixels hold far apart values, so thresholds of it spread
+// points evenly with no low frequency clumps
+static BLUE_NOISE: &[u8; 4096] = include_bytes!("blue_noise.bin");
+
+// where the random numbers of stochastic effects come from. white noise
+// is independent per pixel and clumps at low sample counts, blue noise
+// pushes the error of neighbouring pixels apart so it reads as fine,
+// even grain instead of blotches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sampling {
+    #[default]
+    White,
+    BlueNoise,
+}
+
+impl Sampling {
+    // a number in 0..1 for one pixel. dimension tells apart the numbers
+    // a pixel needs, e.g. the x and y jitter of each pass, and seed
+    // renders. blue noise values of different dimensions come from shifted
+    // copies of the mask, so each one is blue across the image on its own
+    pub fn sample(&self, seed: u64, x: u32, y: u32, dimension: u32) -> f64 {
+        let key = seed ^ ((dimension as u64) << 40);
+        match self {
+            Sampling::White => Rng::for_pixel(key, x, y).next_f64(),
+            Sampling::BlueNoise => {
+                let shift = Rng::new(key).next_u64();
+                let mx = (x as u64 + (shift & 0xffff)) % BLUE_NOISE_SIZE as u64;
+                let my = (y as u64 + (shift >> 16 & 0xffff)) % BLUE_NOISE_SIZE as u64;
+                let v = BLUE_NOISE[(my * BLUE_NOISE_SIZE as u64 + mx) as usize];
+                // the middle of the byte's range, never exactly 0 or 1
+                (v as f64 + 0.5) / 256.0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // variance of the means of 4 x 4 blocks, high when values clump
+    fn clumping(sampling: Sampling, dimension: u32) -> f64 {
+        let means = (0..256)
+            .map(|b| {
+                let (bx, by) = (b % 16 * 4, b / 16 * 4);
+                let mut sum = 0.0;
+                for y in 0..4 {
+                    for x in 0..4 {
+                        sum += sampling.sample(3, bx + x, by + y, dimension);
+                    }
+                }
+                sum / 16.0
+            })
+            .collect::<Vec<_>>();
+        let mean = means.iter().sum::<f64>() / 256.0;
+        means.iter().map(|m| (m - mean) * (m - mean)).sum::<f64>() / 256.0
+    }
+
+    #[test]
+    fn the_mask_holds_every_level_equally() {
+        let mut counts = [0; 256];
+        for v in BLUE_NOISE.iter() {
+            counts[*v as usize] += 1;
+        }
+        assert!(counts.iter().all(|c| *c == 16));
+    }
+
+    #[test]
+    fn blue_noise_clumps_less_than_white_noise() {
+        for dimension in 0..4 {
+            let blue = clumping(Sampling::BlueNoise, dimension);
+            let white = clumping(Sampling::White, dimension);
+            assert!(blue * 2.0 < white);
+        }
+    }
+
+    #[test]
+    fn samples_are_repeatable_and_vary_by_dimension() {
+        for s in [Sampling::White, Sampling::BlueNoise] {
+            let v = s.sample(1, 5, 7, 0);
+            assert!((0.0..1.0).contains(&v));
+            assert_eq!(v, s.sample(1, 5, 7, 0));
+            let others = (1..8).map(|d| s.sample(1, 5, 7, d));
+            assert!(others.into_iter().any(|o| o != v));
+        }
+    }
+}
@@ -0,0 +1,1398 @@
+use crate::asset::{AssetError, AssetResolver};
+use crate::brdf::ShadingModel;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::csg::{Csg, CsgOp, CsgOperand};
+use crate::light::{
+    AreaLight, DirectionalLight, Light, PointLight, SpotLight, DIRECTIONAL_DISTANCE,
+};
+use crate::material::Material;
+use crate::matrix::Matrix4;
+use crate::object::{Geometry, Object};
+use crate::transformations;
+use crate::triangle::{SmoothTriangle, Triangle};
+use crate::tuple::{Point, Tuple, Vector};
+use crate::world::World;
+use crate::yaml::{self, Value, YamlError};
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    Syntax(YamlError),
+    Invalid(String),
+    Asset(AssetError),
+}
+
+impl From<YamlError> for SceneError {
+    fn from(e: YamlError) -> Self {
+        SceneError::Syntax(e)
+    }
+}
+
+impl From<AssetError> for SceneError {
+    fn from(e: AssetError) -> Self {
+        SceneError::Asset(e)
+    }
+}
+
+// writes worlds in the yaml scene format from the book so scenes built
+// in code can be edited by hand. object transforms are written as a full
+// row major matrix, decals, portals, gobos and patterns have no yaml form
+// yet and are left out. neither do groups, lods and csgs, their objects
+// are written as items of their own: all of a group, the finest level of
+// a lod and both sides of a csg, which reads back as a plain union
+
+fn triple(x: f64, y: f64, z: f64) -> String {
+    format!("[{}, {}, {}]", x, y, z)
+}
+
+fn point(p: Point) -> String {
+    triple(p.0.x, p.0.y, p.0.z)
+}
+
+fn vector(v: Vector) -> String {
+    triple(v.0.x, v.0.y, v.0.z)
+}
+
+fn color(c: Color) -> String {
+    triple(c.red, c.green, c.blue)
+}
+
+fn matrix(m: &Matrix4) -> String {
+    let values = (0..4)
+        .flat_map(|y| (0..4).map(move |x| (y, x)))
+        .map(|(y, x)| m.get(y, x).to_string())
+        .collect::<Vec<_>>();
+    format!("[ matrix, {} ]", values.join(", "))
+}
+
+fn material(out: &mut String, m: &Material) {
+    let d = Material::default();
+    writeln!(out, "  material:").unwrap();
+    writeln!(out, "    color: {}", color(m.color)).unwrap();
+    for (name, value, default) in [
+        ("ambient", m.ambient, d.ambient),
+        ("diffuse", m.diffuse, d.diffuse),
+        ("specular", m.specular, d.specular),
+        ("shininess", m.shininess, d.shininess),
+        ("reflective", m.reflective, d.reflective),
+        ("transparency", m.transparency, d.transparency),
+        ("refractive-index", m.refractive_index, d.refractive_index),
+        ("abbe-number", m.abbe_number, d.abbe_number),
+        ("sheen", m.sheen, d.sheen),
+        ("sheen-tint", m.sheen_tint, d.sheen_tint),
+    ] {
+        if value != default {
+            writeln!(out, "    {}: {}", name, value).unwrap();
+        }
+    }
+    if m.shadow_catcher {
+        writeln!(out, "    shadow-catcher: true").unwrap();
+    }
+    // custom models live in code and cannot be written out
+    match m.model {
+        ShadingModel::Phong | ShadingModel::Custom(_) => {}
+        ShadingModel::BlinnPhong => writeln!(out, "    shading: blinn-phong").unwrap(),
+        ShadingModel::Lambert => writeln!(out, "    shading: lambert").unwrap(),
+        ShadingModel::Pbr {
+            roughness,
+            metallic,
+        } => {
+            writeln!(out, "    shading: pbr").unwrap();
+            writeln!(out, "    roughness: {}", roughness).unwrap();
+            writeln!(out, "    metallic: {}", metallic).unwrap();
+        }
+    }
+}
+
+// from, to and up that view_transform turns back into the camera transform.
+// view_transform does not normalize its left vector, so up keeps a share
+// along the view direction to reproduce that length
+fn camera_vectors(camera: &Camera) -> (Point, Point, Vector) {
+    let m = camera.transform();
+    let left = Vector::new(m.get(0, 0), m.get(0, 1), m.get(0, 2));
+    let forward = -Vector::new(m.get(2, 0), m.get(2, 1), m.get(2, 2));
+    let from = camera.position();
+    let to = Point::try_from(from.0 + forward.0).unwrap();
+    let along = (1.0 - left.dot(left)).max(0.0).sqrt();
+    let up = Vector::try_from(left.cross(forward).0 + forward.0 * along).unwrap();
+    (from, to, up)
+}
+
+fn kind(object: &Object) -> &'static str {
+    match object.geometry {
+        Geometry::Sphere => "sphere",
+        Geometry::Slab => "slab",
+        Geometry::Cylinder { .. } => "cylinder",
+        Geometry::Triangle(_) => "triangle",
+        Geometry::SmoothTriangle(_) => "smooth-triangle",
+        // written out but cannot be read back
+        Geometry::Custom(_) => "custom",
+    }
+}
+
+// what a light is added as and its keys in the order they are written,
+// shared by to_yaml and diff_scenes
+fn light_fields(light: &Light) -> (&'static str, Vec<(&'static str, String)>) {
+    match light {
+        Light::Point(l) => (
+            "light",
+            vec![("at", point(l.position)), ("intensity", color(l.intensity))],
+        ),
+        Light::Spot(l) => (
+            "spot-light",
+            vec![
+                ("at", point(l.position)),
+                ("direction", vector(l.direction)),
+                ("inner-angle", l.inner_angle.to_string()),
+                ("outer-angle", l.outer_angle.to_string()),
+                ("intensity", color(l.intensity)),
+            ],
+        ),
+        Light::Area(l) => (
+            "area-light",
+            vec![
+                ("corner", point(l.corner)),
+                ("uvec", vector(l.uvec)),
+                ("usteps", l.usteps.to_string()),
+                ("vvec", vector(l.vvec)),
+                ("vsteps", l.vsteps.to_string()),
+                ("intensity", color(l.intensity)),
+            ],
+        ),
+        Light::Directional(l) => (
+            "directional-light",
+            vec![
+                ("direction", vector(l.direction)),
+                ("intensity", color(l.intensity)),
+            ],
+        ),
+    }
+}
+
+fn triangle_corners(out: &mut String, t: &Triangle) {
+    writeln!(out, "  p1: {}", point(t.p1)).unwrap();
+    writeln!(out, "  p2: {}", point(t.p2)).unwrap();
+    writeln!(out, "  p3: {}", point(t.p3)).unwrap();
+}
+
+fn yaml_object(out: &mut String, object: &Object) {
+    writeln!(out).unwrap();
+    writeln!(out, "- add: {}", kind(object)).unwrap();
+    if let Geometry::Cylinder {
+        minimum,
+        maximum,
+        closed,
+    } = object.geometry
+    {
+        writeln!(out, "  min: {}", minimum).unwrap();
+        writeln!(out, "  max: {}", maximum).unwrap();
+        writeln!(out, "  closed: {}", closed).unwrap();
+    }
+    match object.geometry {
+        Geometry::Triangle(t) => triangle_corners(out, &t),
+        Geometry::SmoothTriangle(smooth) => {
+            triangle_corners(out, &smooth.triangle);
+            writeln!(out, "  n1: {}", vector(smooth.n1)).unwrap();
+            writeln!(out, "  n2: {}", vector(smooth.n2)).unwrap();
+            writeln!(out, "  n3: {}", vector(smooth.n3)).unwrap();
+        }
+        _ => {}
+    }
+    material(out, &object.material);
+    if object.transform != Matrix4::identity(4) {
+        writeln!(out, "  transform:").unwrap();
+        writeln!(out, "    - {}", matrix(&object.transform)).unwrap();
+    }
+}
+
+fn csg_op(op: CsgOp) -> &'static str {
+    match op {
+        CsgOp::Union => "union",
+        CsgOp::Intersection => "intersection",
+        CsgOp::Difference => "difference",
+    }
+}
+
+pub fn to_yaml(world: &World, camera: &Camera) -> String {
+    let mut out = String::new();
+
+    let (from, to, up) = camera_vectors(camera);
+    writeln!(out, "- add: camera").unwrap();
+    writeln!(out, "  width: {}", camera.hsize()).unwrap();
+    writeln!(out, "  height: {}", camera.vsize()).unwrap();
+    writeln!(out, "  field-of-view: {}", camera.field_of_view()).unwrap();
+    writeln!(out, "  from: {}", point(from)).unwrap();
+    writeln!(out, "  to: {}", point(to)).unwrap();
+    writeln!(out, "  up: {}", vector(up)).unwrap();
+
+    for light in &world.lights {
+        let (kind, fields) = light_fields(light);
+        writeln!(out).unwrap();
+        writeln!(out, "- add: {}", kind).unwrap();
+        for (key, value) in fields {
+            writeln!(out, "  {}: {}", key, value).unwrap();
+        }
+        for (name, on) in [("diffuse", light.diffuse()), ("specular", light.specular())] {
+            if !on {
+                writeln!(out, "  {}: false", name).unwrap();
+            }
+        }
+    }
+
+    for object in &world.objects {
+        yaml_object(&mut out, object);
+    }
+    for object in world.groups.iter().flat_map(|g| g.objects()) {
+        yaml_object(&mut out, object);
+    }
+    for lod in &world.lods {
+        for object in lod.levels().iter().take(1).flat_map(|l| &l.objects) {
+            yaml_object(&mut out, object);
+        }
+    }
+    for csg in &world.csgs {
+        writeln!(out).unwrap();
+        writeln!(out, "# the parts of a csg {}", csg_op(csg.op)).unwrap();
+        for object in csg.objects() {
+            yaml_object(&mut out, object);
+        }
+    }
+
+    out
+}
+
+fn invalid<T>(message: impl Into<String>) -> Result<T, SceneError> {
+    Err(SceneError::Invalid(message.into()))
+}
+
+fn number(v: &Value, what: &str) -> Result<f64, SceneError> {
+    match v.as_f64() {
+        Some(x) => Ok(x),
+        None => invalid(format!("{} should be a number", what)),
+    }
+}
+
+fn numbers(v: &Value, what: &str) -> Result<Vec<f64>, SceneError> {
+    match v.as_list() {
+        Some(items) => items.iter().map(|x| number(x, what)).collect(),
+        None => invalid(format!("{} should be a list of numbers", what)),
+    }
+}
+
+fn xyz(v: &Value, what: &str) -> Result<(f64, f64, f64), SceneError> {
+    match numbers(v, what)?[..] {
+        [x, y, z] => Ok((x, y, z)),
+        _ => invalid(format!("{} should have 3 components", what)),
+    }
+}
+
+fn required<'a>(item: &'a Value, key: &str) -> Result<&'a Value, SceneError> {
+    match item.get(key) {
+        Some(v) => Ok(v),
+        None => invalid(format!("missing `{}`", key)),
+    }
+}
+
+// either a plain intensity or a fixture's rating in candela or lumens with
+// the distance in meters it lights the scene from, one by default
+fn parse_light(item: &Value, at: Point) -> Result<PointLight, SceneError> {
+    let rated = match (item.get("candela"), item.get("lumens")) {
+        (Some(c), None) => Some((number(c, "candela")?, false)),
+        (None, Some(l)) => Some((number(l, "lumens")?, true)),
+        (None, None) => None,
+        _ => return invalid("light should have candela or lumens, not both"),
+    };
+    let Some((amount, lumens)) = rated else {
+        let (r, g, b) = xyz(required(item, "intensity")?, "intensity")?;
+        return Ok(PointLight::new(at, Color::new(r, g, b)));
+    };
+    let color = match item.get("color") {
+        Some(c) => {
+            let (r, g, b) = xyz(c, "color")?;
+            Color::new(r, g, b)
+        }
+        None => Color::new(1.0, 1.0, 1.0),
+    };
+    let distance = match item.get("distance") {
+        Some(d) => number(d, "distance")?,
+        None => 1.0,
+    };
+    let positive = |x: f64| x.is_finite() && x > 0.0;
+    let usable =
+        positive(color.luminance()) && positive(distance) && amount.is_finite() && amount >= 0.0;
+    if !usable {
+        return invalid("light rating, color and distance should be positive");
+    }
+    Ok(if lumens {
+        PointLight::from_lumens(at, amount, color, distance)
+    } else {
+        PointLight::from_candela(at, amount, color, distance)
+    })
+}
+
+fn required_point(item: &Value, key: &str) -> Result<Point, SceneError> {
+    let (x, y, z) = xyz(required(item, key)?, key)?;
+    if !(x.is_finite() && y.is_finite() && z.is_finite()) {
+        return invalid(format!("{} should be finite", key));
+    }
+    Ok(Point::new(x, y, z))
+}
+
+fn required_vector(item: &Value, key: &str) -> Result<Vector, SceneError> {
+    let p = required_point(item, key)?;
+    Ok(Vector::new(p.0.x, p.0.y, p.0.z))
+}
+
+fn required_color(item: &Value, key: &str) -> Result<Color, SceneError> {
+    let (r, g, b) = xyz(required(item, key)?, key)?;
+    Ok(Color::new(r, g, b))
+}
+
+fn direction(item: &Value) -> Result<Vector, SceneError> {
+    let v = required_vector(item, "direction")?;
+    if v.magnitude() < 1e-12 {
+        return invalid("direction should not be zero");
+    }
+    Ok(v)
+}
+
+// most cells an area light's side is split into
+const MAX_LIGHT_STEPS: f64 = 256.0;
+
+fn steps(item: &Value, key: &str) -> Result<u32, SceneError> {
+    let n = number(required(item, key)?, key)?;
+    if n.fract() != 0.0 || !(1.0..=MAX_LIGHT_STEPS).contains(&n) {
+        return invalid(format!(
+            "{} should be a whole number from 1 to {}",
+            key, MAX_LIGHT_STEPS
+        ));
+    }
+    Ok(n as u32)
+}
+
+// angles are in radians from the axis, like the camera's field of view
+fn parse_spot_light(item: &Value) -> Result<SpotLight, SceneError> {
+    let angle = |key: &str| -> Result<f64, SceneError> {
+        let a = number(required(item, key)?, key)?;
+        if !(0.0..=PI).contains(&a) {
+            return invalid(format!("{} should be between 0 and pi", key));
+        }
+        Ok(a)
+    };
+    Ok(SpotLight::new(
+        required_point(item, "at")?,
+        direction(item)?,
+        angle("inner-angle")?,
+        angle("outer-angle")?,
+        required_color(item, "intensity")?,
+    ))
+}
+
+fn parse_area_light(item: &Value) -> Result<AreaLight, SceneError> {
+    Ok(AreaLight::new(
+        required_point(item, "corner")?,
+        required_vector(item, "uvec")?,
+        steps(item, "usteps")?,
+        required_vector(item, "vvec")?,
+        steps(item, "vsteps")?,
+        required_color(item, "intensity")?,
+    ))
+}
+
+fn flag(item: &Value, key: &str, default: bool) -> Result<bool, SceneError> {
+    match item.get(key).map(|v| v.as_str()) {
+        None => Ok(default),
+        Some(Some("true")) => Ok(true),
+        Some(Some("false")) => Ok(false),
+        Some(_) => invalid(format!("{} should be true or false", key)),
+    }
+}
+
+// the ends default to infinity, as in the book
+fn parse_cylinder(item: &Value) -> Result<Geometry, SceneError> {
+    let end = |key: &str, default: f64| match item.get(key) {
+        Some(x) => number(x, key),
+        None => Ok(default),
+    };
+    let (minimum, maximum) = (end("min", f64::NEG_INFINITY)?, end("max", f64::INFINITY)?);
+    if minimum.is_nan() || maximum.is_nan() || minimum > maximum {
+        return invalid("cylinder min should not be above max");
+    }
+    let closed = flag(item, "closed", false)?;
+    Ok(Geometry::Cylinder {
+        minimum,
+        maximum,
+        closed,
+    })
+}
+
+fn parse_triangle(item: &Value) -> Result<Triangle, SceneError> {
+    let corner = |key: &str| -> Result<Point, SceneError> {
+        let (x, y, z) = xyz(required(item, key)?, key)?;
+        Ok(Point::new(x, y, z))
+    };
+    let (p1, p2, p3) = (corner("p1")?, corner("p2")?, corner("p3")?);
+    let area = (p2 - p1).cross(p3 - p1).magnitude();
+    if !area.is_finite() || area < 1e-12 {
+        return invalid("triangle corners should be finite and not lie on one line");
+    }
+    Ok(Triangle::new(p1, p2, p3))
+}
+
+fn parse_material(v: &Value) -> Result<Material, SceneError> {
+    let mut m = Material::default();
+    if let Some(c) = v.get("color") {
+        let (r, g, b) = xyz(c, "color")?;
+        m.color = Color::new(r, g, b);
+    }
+    for (key, field) in [
+        ("ambient", &mut m.ambient),
+        ("diffuse", &mut m.diffuse),
+        ("specular", &mut m.specular),
+        ("shininess", &mut m.shininess),
+        ("reflective", &mut m.reflective),
+        ("transparency", &mut m.transparency),
+        ("refractive-index", &mut m.refractive_index),
+        ("abbe-number", &mut m.abbe_number),
+        ("sheen", &mut m.sheen),
+        ("sheen-tint", &mut m.sheen_tint),
+    ] {
+        if let Some(x) = v.get(key) {
+            *field = number(x, key)?;
+        }
+    }
+    m.shadow_catcher = match v.get("shadow-catcher").map(|s| s.as_str()) {
+        None | Some(Some("false")) => false,
+        Some(Some("true")) => true,
+        Some(_) => return invalid("shadow-catcher should be true or false"),
+    };
+    let param = |key: &str, default: f64| match v.get(key) {
+        Some(x) => number(x, key),
+        None => Ok(default),
+    };
+    m.model = match v.get("shading").map(|s| s.as_str()) {
+        None | Some(Some("phong")) => ShadingModel::Phong,
+        Some(Some("blinn-phong")) => ShadingModel::BlinnPhong,
+        Some(Some("lambert")) => ShadingModel::Lambert,
+        Some(Some("pbr")) => ShadingModel::Pbr {
+            roughness: param("roughness", 0.5)?,
+            metallic: param("metallic", 0.0)?,
+        },
+        Some(_) => return invalid("shading should be phong, blinn-phong, lambert or pbr"),
+    };
+    Ok(m)
+}
+
+// transforms are listed in the order they apply to the object
+fn parse_transform(v: &Value) -> Result<Matrix4, SceneError> {
+    let steps = match v.as_list() {
+        Some(steps) => steps,
+        None => return invalid("transform should be a list"),
+    };
+    let mut m = Matrix4::identity(4);
+    for step in steps {
+        let (op, args) = match step.as_list() {
+            Some([op, args @ ..]) => (op.as_str().unwrap_or(""), args),
+            _ => return invalid("transform steps should be `[ op, args... ]`"),
+        };
+        let args = args
+            .iter()
+            .map(|a| number(a, op))
+            .collect::<Result<Vec<_>, _>>()?;
+        let t = match (op, &args[..]) {
+            ("translate", &[x, y, z]) => transformations::translation(x, y, z),
+            ("scale", &[x, y, z]) => transformations::scaling(x, y, z),
+            ("rotate-x", &[r]) => transformations::rotation_x(r),
+            ("rotate-y", &[r]) => transformations::rotation_y(r),
+            ("rotate-z", &[r]) => transformations::rotation_z(r),
+            ("shear", &[a, b, c, d, e, f]) => transformations::shearing(a, b, c, d, e, f),
+            ("matrix", values) if values.len() == 16 => Matrix4::from_rows([
+                Tuple::new(values[0], values[1], values[2], values[3]),
+                Tuple::new(values[4], values[5], values[6], values[7]),
+                Tuple::new(values[8], values[9], values[10], values[11]),
+                Tuple::new(values[12], values[13], values[14], values[15]),
+            ]),
+            _ => {
+                return invalid(format!(
+                    "bad transform `{}` with {} arguments",
+                    op,
+                    args.len()
+                ))
+            }
+        };
+        m = t * m;
+    }
+    Ok(m)
+}
+
+// largest image side accepted from a scene file
+const MAX_CAMERA_SIZE: f64 = 16384.0;
+
+// reads a scene file found through resolver
+pub fn load(resolver: &AssetResolver, name: &str) -> Result<(World, Camera), SceneError> {
+    from_yaml(&resolver.read_to_string(name)?)
+}
+
+// for scenes embedded with include_bytes!
+pub fn from_yaml_bytes(bytes: &[u8]) -> Result<(World, Camera), SceneError> {
+    match std::str::from_utf8(bytes) {
+        Ok(src) => from_yaml(src),
+        Err(_) => invalid("scene is not valid utf-8"),
+    }
+}
+
+// reads the subset of the book's scene format written by to_yaml,
+// define and extend are not supported
+pub fn from_yaml(src: &str) -> Result<(World, Camera), SceneError> {
+    let doc = yaml::parse(src)?;
+    let items = match doc.as_list() {
+        Some(items) => items,
+        None => return invalid("scene should be a list of `add` items"),
+    };
+    let mut world = World::new();
+    let mut camera = None;
+    for item in items {
+        match required(item, "add")?.as_str() {
+            Some("camera") => {
+                let width = number(required(item, "width")?, "width")?;
+                let height = number(required(item, "height")?, "height")?;
+                let fov = number(required(item, "field-of-view")?, "field-of-view")?;
+                let (fx, fy, fz) = xyz(required(item, "from")?, "from")?;
+                let (tx, ty, tz) = xyz(required(item, "to")?, "to")?;
+                let (ux, uy, uz) = xyz(required(item, "up")?, "up")?;
+                if !(1.0..=MAX_CAMERA_SIZE).contains(&width)
+                    || !(1.0..=MAX_CAMERA_SIZE).contains(&height)
+                {
+                    return invalid("camera size out of range");
+                }
+                let (from, to) = (Point::new(fx, fy, fz), Point::new(tx, ty, tz));
+                let up = Vector::new(ux, uy, uz);
+                // view_transform normalizes both directions, so a zero or
+                // parallel pair has to be caught before calling it
+                let side = (to - from).cross(up).magnitude();
+                if !side.is_finite() || side < 1e-9 {
+                    return invalid("camera from, to and up do not define a view");
+                }
+                let view = transformations::view_transform(from, to, up);
+                let mut c = Camera::new(width as u32, height as u32, fov);
+                c.set_transform(view);
+                camera = Some(c);
+            }
+            Some("light") => {
+                let (x, y, z) = xyz(required(item, "at")?, "at")?;
+                world.lights.push(
+                    parse_light(item, Point::new(x, y, z))?
+                        .set_diffuse(flag(item, "diffuse", true)?)
+                        .set_specular(flag(item, "specular", true)?)
+                        .into(),
+                );
+            }
+            Some("spot-light") => {
+                world.lights.push(
+                    parse_spot_light(item)?
+                        .set_diffuse(flag(item, "diffuse", true)?)
+                        .set_specular(flag(item, "specular", true)?)
+                        .into(),
+                );
+            }
+            Some("area-light") => {
+                world.lights.push(
+                    parse_area_light(item)?
+                        .set_diffuse(flag(item, "diffuse", true)?)
+                        .set_specular(flag(item, "specular", true)?)
+                        .into(),
+                );
+            }
+            Some("directional-light") => {
+                world.lights.push(
+                    DirectionalLight::new(direction(item)?, required_color(item, "intensity")?)
+                        .set_diffuse(flag(item, "diffuse", true)?)
+                        .set_specular(flag(item, "specular", true)?)
+                        .into(),
+                );
+            }
+            Some(kind @ ("sphere" | "slab" | "cylinder" | "triangle" | "smooth-triangle")) => {
+                let geometry = match kind {
+                    "slab" => Geometry::Slab,
+                    "cylinder" => parse_cylinder(item)?,
+                    "triangle" => Geometry::Triangle(parse_triangle(item)?),
+                    "smooth-triangle" => {
+                        let normal = |key: &str| -> Result<Vector, SceneError> {
+                            let (x, y, z) = xyz(required(item, key)?, key)?;
+                            Ok(Vector::new(x, y, z))
+                        };
+                        Geometry::SmoothTriangle(SmoothTriangle {
+                            triangle: parse_triangle(item)?,
+                            n1: normal("n1")?,
+                            n2: normal("n2")?,
+                            n3: normal("n3")?,
+                        })
+                    }
+                    _ => Geometry::Sphere,
+                };
+                let mut object = Object::new(geometry);
+                if let Some(m) = item.get("material") {
+                    object = object.set_material(parse_material(m)?);
+                }
+                if let Some(t) = item.get("transform") {
+                    let t = parse_transform(t)?;
+                    if !t.invertible() {
+                        return invalid(format!("{} transform is not invertible", kind));
+                    }
+                    object = object.set_transform(t);
+                }
+                world.objects.push(object);
+            }
+            Some(other) => return invalid(format!("cannot add `{}`", other)),
+            None => return invalid("`add` should name what to add"),
+        }
+    }
+    match camera {
+        Some(camera) => Ok((world, camera)),
+        None => invalid("scene has no camera"),
+    }
+}
+
+fn compare(out: &mut Vec<String>, what: &str, a: String, b: String) {
+    if a != b {
+        out.push(format!("{}: {} -> {}", what, a, b));
+    }
+}
+
+fn compare_f64(out: &mut Vec<String>, what: &str, a: f64, b: f64) {
+    if (a - b).abs() > crate::tuple::EPSILON {
+        out.push(format!("{}: {} -> {}", what, a, b));
+    }
+}
+
+// human readable list of what changed going from scene a to scene b,
+// objects and lights are matched up by their position in the scene
+pub fn diff_scenes(a: (&World, &Camera), b: (&World, &Camera)) -> Vec<String> {
+    let (wa, ca) = a;
+    let (wb, cb) = b;
+    let mut out = vec![];
+
+    compare(
+        &mut out,
+        "camera size",
+        format!("{}x{}", ca.hsize(), ca.vsize()),
+        format!("{}x{}", cb.hsize(), cb.vsize()),
+    );
+    compare_f64(
+        &mut out,
+        "camera field-of-view",
+        ca.field_of_view(),
+        cb.field_of_view(),
+    );
+    if ca.transform() != cb.transform() {
+        out.push(format!(
+            "camera transform: {} -> {}",
+            matrix(ca.transform()),
+            matrix(cb.transform())
+        ));
+    }
+
+    for i in 0..wa.lights.len().max(wb.lights.len()) {
+        match (wa.lights.get(i), wb.lights.get(i)) {
+            (Some(la), Some(lb)) => {
+                let ((kind_a, fields_a), (kind_b, fields_b)) = (light_fields(la), light_fields(lb));
+                if kind_a != kind_b {
+                    out.push(format!("light {} kind: {} -> {}", i, kind_a, kind_b));
+                    continue;
+                }
+                for ((key, a), (_, b)) in fields_a.into_iter().zip(fields_b) {
+                    compare(&mut out, &format!("light {} {}", i, key), a, b);
+                }
+                for (name, a, b) in [
+                    ("diffuse", la.diffuse(), lb.diffuse()),
+                    ("specular", la.specular(), lb.specular()),
+                ] {
+                    if a != b {
+                        out.push(format!("light {} {}: {} -> {}", i, name, a, b));
+                    }
+                }
+            }
+            (Some(_), None) => out.push(format!("light {} removed", i)),
+            (None, Some(_)) => out.push(format!("light {} added", i)),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    for i in 0..wa.objects.len().max(wb.objects.len()) {
+        match (wa.objects.get(i), wb.objects.get(i)) {
+            (Some(oa), Some(ob)) => {
+                compare(
+                    &mut out,
+                    &format!("object {} kind", i),
+                    kind(oa).to_string(),
+                    kind(ob).to_string(),
+                );
+                if oa.geometry != ob.geometry && kind(oa) == kind(ob) {
+                    out.push(format!(
+                        "object {} geometry: {:?} -> {:?}",
+                        i, oa.geometry, ob.geometry
+                    ));
+                }
+                if oa.transform != ob.transform {
+                    out.push(format!(
+                        "object {} transform: {} -> {}",
+                        i,
+                        matrix(&oa.transform),
+                        matrix(&ob.transform)
+                    ));
+                }
+                let (ma, mb) = (oa.material, ob.material);
+                if ma.model != mb.model {
+                    out.push(format!(
+                        "object {} shading: {:?} -> {:?}",
+                        i, ma.model, mb.model
+                    ));
+                }
+                if ma.color != mb.color {
+                    compare(
+                        &mut out,
+                        &format!("object {} color", i),
+                        color(ma.color),
+                        color(mb.color),
+                    );
+                }
+                for (name, x, y) in [
+                    ("ambient", ma.ambient, mb.ambient),
+                    ("diffuse", ma.diffuse, mb.diffuse),
+                    ("specular", ma.specular, mb.specular),
+                    ("shininess", ma.shininess, mb.shininess),
+                    ("reflective", ma.reflective, mb.reflective),
+                    ("transparency", ma.transparency, mb.transparency),
+                    ("refractive-index", ma.refractive_index, mb.refractive_index),
+                    ("abbe-number", ma.abbe_number, mb.abbe_number),
+                    ("sheen", ma.sheen, mb.sheen),
+                    ("sheen-tint", ma.sheen_tint, mb.sheen_tint),
+                ] {
+                    compare_f64(&mut out, &format!("object {} {}", i, name), x, y);
+                }
+                if ma.shadow_catcher != mb.shadow_catcher {
+                    compare(
+                        &mut out,
+                        &format!("object {} shadow-catcher", i),
+                        ma.shadow_catcher.to_string(),
+                        mb.shadow_catcher.to_string(),
+                    );
+                }
+            }
+            (Some(_), None) => out.push(format!("object {} removed", i)),
+            (None, Some(_)) => out.push(format!("object {} added", i)),
+            (None, None) => unreachable!(),
+        }
+    }
+    out
+}
+
+fn pov_vector(x: f64, y: f64, z: f64) -> String {
+    format!("<{}, {}, {}>", x, y, z)
+}
+
+fn pov_point(p: Point) -> String {
+    pov_vector(p.0.x, p.0.y, p.0.z)
+}
+
+fn pov_normal(n: Vector) -> String {
+    pov_vector(n.0.x, n.0.y, n.0.z)
+}
+
+// pov-ray multiplies row vectors, so the 4x3 matrix it wants is the
+// transpose of the top three rows
+fn pov_matrix(m: &Matrix4) -> String {
+    let values = (0..4)
+        .flat_map(|x| (0..3).map(move |y| (y, x)))
+        .map(|(y, x)| m.get(y, x).to_string())
+        .collect::<Vec<_>>();
+    format!("matrix <{}>", values.join(", "))
+}
+
+fn pov_object(out: &mut String, object: &Object) {
+    let m = &object.material;
+    match object.geometry {
+        Geometry::Sphere => {
+            writeln!(out, "sphere {{").unwrap();
+            writeln!(out, "  <0, 0, 0>, 1").unwrap();
+        }
+        Geometry::Slab => {
+            writeln!(out, "box {{").unwrap();
+            writeln!(out, "  <-1, -1, -1>, <1, 1, 1>").unwrap();
+        }
+        // pov-ray has no endless cylinders, so those are cut off far
+        // away
+        Geometry::Cylinder {
+            minimum,
+            maximum,
+            closed,
+        } => {
+            let (lo, hi) = (minimum.max(-1e6), maximum.min(1e6));
+            writeln!(out, "cylinder {{").unwrap();
+            writeln!(out, "  <0, {}, 0>, <0, {}, 0>, 1", lo, hi).unwrap();
+            if !closed {
+                writeln!(out, "  open").unwrap();
+            }
+        }
+        Geometry::Triangle(t) => {
+            writeln!(out, "triangle {{").unwrap();
+            writeln!(
+                out,
+                "  {}, {}, {}",
+                pov_point(t.p1),
+                pov_point(t.p2),
+                pov_point(t.p3)
+            )
+            .unwrap();
+        }
+        Geometry::SmoothTriangle(smooth) => {
+            let (t, p, n) = (smooth.triangle, pov_point, pov_normal);
+            writeln!(out, "smooth_triangle {{").unwrap();
+            writeln!(
+                out,
+                "  {}, {}, {}, {}, {}, {}",
+                p(t.p1),
+                n(smooth.n1),
+                p(t.p2),
+                n(smooth.n2),
+                p(t.p3),
+                n(smooth.n3)
+            )
+            .unwrap();
+        }
+        // stands in with its box
+        Geometry::Custom(ref shape) => {
+            let b = shape.local_bounds();
+            writeln!(out, "box {{").unwrap();
+            writeln!(
+                out,
+                "  {}, {}",
+                pov_vector(b.min.0.x, b.min.0.y, b.min.0.z),
+                pov_vector(b.max.0.x, b.max.0.y, b.max.0.z)
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "  texture {{").unwrap();
+    if m.transparency > 0.0 {
+        writeln!(
+            out,
+            "    pigment {{ color rgbt <{}, {}, {}, {}> }}",
+            m.color.red, m.color.green, m.color.blue, m.transparency
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            out,
+            "    pigment {{ color rgb {} }}",
+            pov_vector(m.color.red, m.color.green, m.color.blue)
+        )
+        .unwrap();
+    }
+    let reflection = if m.reflective > 0.0 {
+        format!(" reflection {}", m.reflective)
+    } else {
+        String::new()
+    };
+    writeln!(
+        out,
+        "    finish {{ ambient {} diffuse {} phong {} phong_size {}{} }}",
+        m.ambient, m.diffuse, m.specular, m.shininess, reflection
+    )
+    .unwrap();
+    writeln!(out, "  }}").unwrap();
+    if m.refractive_index != 1.0 {
+        writeln!(out, "  interior {{ ior {} }}", m.refractive_index).unwrap();
+    }
+    writeln!(out, "  {}", pov_matrix(&object.transform)).unwrap();
+    writeln!(out, "}}").unwrap();
+}
+
+fn pov_csg(out: &mut String, csg: &Csg) {
+    writeln!(out, "{} {{", csg_op(csg.op)).unwrap();
+    for side in [csg.left(), csg.right()] {
+        match side {
+            CsgOperand::Object(object) => pov_object(out, object),
+            CsgOperand::Group(group) => {
+                writeln!(out, "union {{").unwrap();
+                for object in group.objects() {
+                    pov_object(out, object);
+                }
+                writeln!(out, "}}").unwrap();
+            }
+            CsgOperand::Csg(csg) => pov_csg(out, csg),
+        }
+    }
+    writeln!(out, "}}").unwrap();
+}
+
+// pov-ray scene for cross checking renders, both renderers use a left
+// handed y up world so coordinates carry over as is. groups are written as
+// their objects, lods as their finest level and csgs as pov-ray's own
+pub fn to_pov(world: &World, camera: &Camera) -> String {
+    let mut out = String::new();
+    writeln!(out, "global_settings {{ assumed_gamma 1.0 }}").unwrap();
+    writeln!(out, "background {{ color rgb <0, 0, 0> }}").unwrap();
+
+    let (from, to, up) = camera_vectors(camera);
+    let (to, up) = (to.0, up.0);
+    let aspect = camera.hsize() as f64 / camera.vsize() as f64;
+    // pov-ray's angle is always horizontal, ours spans the longer side
+    let angle = if aspect >= 1.0 {
+        camera.field_of_view()
+    } else {
+        2.0 * ((camera.field_of_view() / 2.0).tan() * aspect).atan()
+    };
+    writeln!(out).unwrap();
+    writeln!(out, "camera {{").unwrap();
+    writeln!(out, "  perspective").unwrap();
+    writeln!(
+        out,
+        "  location {}",
+        pov_vector(from.0.x, from.0.y, from.0.z)
+    )
+    .unwrap();
+    writeln!(out, "  right x*{}", aspect).unwrap();
+    writeln!(out, "  up y").unwrap();
+    writeln!(out, "  sky {}", pov_vector(up.x, up.y, up.z)).unwrap();
+    writeln!(out, "  angle {}", angle.to_degrees()).unwrap();
+    writeln!(out, "  look_at {}", pov_vector(to.x, to.y, to.z)).unwrap();
+    writeln!(out, "}}").unwrap();
+
+    for light in &world.lights {
+        let c = light.intensity();
+        let at = |p: Point| pov_vector(p.0.x, p.0.y, p.0.z);
+        let (position, shape) = match light {
+            Light::Point(l) => (l.position, String::new()),
+            Light::Spot(l) => (
+                l.position,
+                format!(
+                    " spotlight point_at {} radius {} falloff {}",
+                    at(l.position + l.direction),
+                    l.inner_angle.to_degrees(),
+                    l.outer_angle.max(l.inner_angle).to_degrees()
+                ),
+            ),
+            Light::Area(l) => {
+                let (u, v) = (l.uvec.0, l.vvec.0);
+                (
+                    l.center(),
+                    format!(
+                        " area_light {} {} {} {}",
+                        pov_vector(u.x, u.y, u.z),
+                        pov_vector(v.x, v.y, v.z),
+                        l.usteps,
+                        l.vsteps
+                    ),
+                )
+            }
+            Light::Directional(l) => {
+                let from = Point::zero() + -l.direction.normalize() * DIRECTIONAL_DISTANCE;
+                (from, " parallel point_at <0, 0, 0>".to_string())
+            }
+        };
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "light_source {{ {} color rgb {}{} }}",
+            at(position),
+            pov_vector(c.red, c.green, c.blue),
+            shape
+        )
+        .unwrap();
+    }
+
+    for object in &world.objects {
+        writeln!(out).unwrap();
+        pov_object(&mut out, object);
+    }
+    for object in world.groups.iter().flat_map(|g| g.objects()) {
+        writeln!(out).unwrap();
+        pov_object(&mut out, object);
+    }
+    for lod in &world.lods {
+        for object in lod.levels().iter().take(1).flat_map(|l| &l.objects) {
+            writeln!(out).unwrap();
+            pov_object(&mut out, object);
+        }
+    }
+    for csg in &world.csgs {
+        writeln!(out).unwrap();
+        pov_csg(&mut out, csg);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::BLACK;
+    use crate::group::Group;
+    use crate::lod::LodGroup;
+    use crate::sphere::sphere;
+    use crate::transformations::{translation, view_transform};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn export_default_world() {
+        let mut camera = Camera::new(100, 50, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.0, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let yaml = to_yaml(&default_world(), &camera);
+        let expected = "\
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 1.5707963267948966
+  from: [0, 1, -5]
+  to: [0, 1, -4]
+  up: [0, 1, 0]
+
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+
+- add: sphere
+  material:
+    color: [0.8, 1, 0.6]
+    diffuse: 0.7
+    specular: 0.2
+
+- add: sphere
+  material:
+    color: [1, 1, 1]
+  transform:
+    - [ matrix, 0.5, 0, 0, 0, 0, 0.5, 0, 0, 0, 0, 0.5, 0, 0, 0, 0, 1 ]
+";
+        assert_eq!(yaml, expected);
+    }
+
+    #[test]
+    fn loading_through_a_resolver() {
+        let mut camera = Camera::new(10, 10, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.0, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let yaml = to_yaml(&default_world(), &camera);
+        let resolver = AssetResolver::new().with_asset("scenes/default.yaml", yaml);
+        let (world, _) = load(&resolver, "scenes/default.yaml").unwrap();
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(
+            load(&resolver, "missing.yaml").unwrap_err(),
+            SceneError::Asset(AssetError::NotFound("missing.yaml".to_string()))
+        );
+    }
+
+    #[test]
+    fn loading_embedded_scenes() {
+        let (world, camera) =
+            from_yaml_bytes(include_bytes!("../examples/assets/scene.yaml")).unwrap();
+        assert_eq!(world.objects.len(), 2);
+        assert_eq!(camera.hsize(), 320);
+        assert_eq!(
+            from_yaml_bytes(&[0xff]).unwrap_err(),
+            SceneError::Invalid("scene is not valid utf-8".to_string())
+        );
+    }
+
+    #[test]
+    fn export_pov_scene() {
+        let mut camera = Camera::new(100, 50, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 1.0, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let pov = to_pov(&default_world(), &camera);
+        assert!(pov.contains("  location <0, 1, -5>\n  right x*2\n  up y\n  sky <0, 1, 0>\n"));
+        assert!(pov.contains("  angle 90\n  look_at <0, 1, -4>\n"));
+        assert!(pov.contains("light_source { <-10, 10, -10> color rgb <1, 1, 1> }"));
+        assert!(pov.contains("finish { ambient 0.1 diffuse 0.7 phong 0.2 phong_size 200 }"));
+        assert!(pov.contains("  matrix <0.5, 0, 0, 0, 0.5, 0, 0, 0, 0.5, 0, 0, 0>\n"));
+        assert_eq!(pov.matches("sphere {").count(), 2);
+    }
+
+    #[test]
+    fn models_are_written_as_their_objects() {
+        let camera = Camera::new(10, 10, PI / 2.0);
+        let mut world = World::new();
+        world.add_group(
+            Group::new("pair")
+                .add_child(sphere())
+                .add_group(Group::new("inner").add_child(Object::new(Geometry::Slab))),
+        );
+        world.lods.push(
+            LodGroup::new()
+                .add_level(10.0, vec![sphere(), sphere()])
+                .add_level(f64::INFINITY, vec![sphere()]),
+        );
+        let hole = sphere().set_transform(translation(0.5, 0.0, 0.0));
+        world.csgs.push(Csg::new(CsgOp::Difference, sphere(), hole));
+        let (w, _) = from_yaml(&to_yaml(&world, &camera)).unwrap();
+        assert_eq!(w.objects.len(), 6);
+        assert_eq!(w.objects[1].geometry, Geometry::Slab);
+        assert_eq!(w.objects[5].transform, translation(0.5, 0.0, 0.0));
+        let pov = to_pov(&world, &camera);
+        assert_eq!(pov.matches("sphere {").count(), 5);
+        assert!(pov.contains("difference {\nsphere {"));
+    }
+
+    #[test]
+    fn pov_matrix_is_transposed() {
+        assert_eq!(
+            pov_matrix(&translation(1.0, 2.0, 3.0)),
+            "matrix <1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 2, 3>"
+        );
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let mut camera = Camera::new(100, 50, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(1.0, 2.0, -5.0),
+            Point::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let mut world = default_world();
+        world.lights.push(
+            PointLight::new(Point::new(5.0, 5.0, -5.0), Color::new(0.3, 0.3, 0.3))
+                .set_specular(false)
+                .into(),
+        );
+        world.lights.push(
+            SpotLight::new(
+                Point::new(0.0, 8.0, 0.0),
+                Vector::new(0.0, -1.0, 0.5),
+                PI / 10.0,
+                PI / 6.0,
+                Color::new(1.0, 0.9, 0.7),
+            )
+            .into(),
+        );
+        let mut panel = AreaLight::new(
+            Point::new(-1.0, 4.0, -1.0),
+            Vector::new(2.0, 0.0, 0.0),
+            3,
+            Vector::new(0.0, 0.0, 2.0),
+            2,
+            Color::new(0.5, 0.5, 0.5),
+        );
+        panel.aim_at(Point::new(1.0, 0.0, 1.0));
+        world.lights.push(panel.into());
+        world.lights.push(
+            DirectionalLight::new(Vector::new(1.0, -1.0, 0.0), Color::new(0.2, 0.2, 0.3))
+                .set_diffuse(false)
+                .into(),
+        );
+        world
+            .objects
+            .push(crate::slab::slab(4.0, 0.5, 4.0).set_material(Material {
+                model: ShadingModel::Pbr {
+                    roughness: 0.3,
+                    metallic: 1.0,
+                },
+                ..Default::default()
+            }));
+        world
+            .objects
+            .push(crate::cylinder::cylinder(f64::NEG_INFINITY, 2.0, true));
+        world.objects.push(crate::triangle::triangle(
+            Point::new(0.0, 1.0, 0.5),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.25),
+        ));
+        world.objects.push(crate::triangle::smooth_triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        ));
+        let (w, c) = from_yaml(&to_yaml(&world, &camera)).unwrap();
+        assert_eq!(
+            diff_scenes((&world, &camera), (&w, &c)),
+            Vec::<String>::new()
+        );
+        assert_eq!(w.objects[0].material, world.objects[0].material);
+        assert_eq!(w.objects[1].transform, world.objects[1].transform);
+        assert_eq!(c.transform(), camera.transform());
+        assert_eq!(w.objects[2].geometry, Geometry::Slab);
+        assert_eq!(w.objects[2].material, world.objects[2].material);
+        assert_eq!(w.objects[3].geometry, world.objects[3].geometry);
+        assert_eq!(w.objects[4].geometry, world.objects[4].geometry);
+        assert_eq!(w.objects[5].geometry, world.objects[5].geometry);
+        assert!(w.lights[0].specular() && !w.lights[1].specular() && w.lights[1].diffuse());
+        assert_eq!(w.lights[2..], world.lights[2..]);
+        assert!(!w.lights[4].diffuse());
+        assert_eq!(
+            diff_scenes((&world, &camera), (&default_world(), &camera)),
+            vec![
+                "light 1 removed".to_string(),
+                "light 2 removed".to_string(),
+                "light 3 removed".to_string(),
+                "light 4 removed".to_string(),
+                "object 2 removed".to_string(),
+                "object 3 removed".to_string(),
+                "object 4 removed".to_string(),
+                "object 5 removed".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn transform_steps_apply_in_order() {
+        let src = "\
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: sphere
+  transform:
+    - [ scale, 2, 2, 2 ]
+    - [ translate, 0, 1, 0 ]
+";
+        let (w, _) = from_yaml(src).unwrap();
+        assert_eq!(
+            w.objects[0].transform,
+            translation(0.0, 1.0, 0.0) * crate::transformations::scaling(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn lights_in_photometric_units() {
+        let src = "\
+- add: camera
+  width: 10
+  height: 10
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: light
+  at: [0, 5, 0]
+  lumens: 800
+  color: [1, 0.9, 0.8]
+  distance: 2.5
+- add: light
+  at: [0, 5, 0]
+  candela: 683
+";
+        let (w, _) = from_yaml(src).unwrap();
+        let bulb = PointLight::from_lumens(
+            Point::new(0.0, 5.0, 0.0),
+            800.0,
+            Color::new(1.0, 0.9, 0.8),
+            2.5,
+        );
+        assert_eq!(w.lights[0], Light::from(bulb));
+        assert_eq!(w.lights[1].intensity(), Color::new(1.0, 1.0, 1.0));
+        assert!(from_yaml(&src.replace("candela: 683", "candela: 683\n  lumens: 1")).is_err());
+    }
+
+    #[test]
+    fn invalid_scenes_are_reported() {
+        assert_eq!(
+            from_yaml("- add: light\n  at: [0, 0, 0]\n").unwrap_err(),
+            SceneError::Invalid("missing `intensity`".to_string())
+        );
+        assert_eq!(
+            from_yaml("- add: cube\n").unwrap_err(),
+            SceneError::Invalid("cannot add `cube`".to_string())
+        );
+        assert!(matches!(
+            from_yaml("- add: [1\n"),
+            Err(SceneError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_scenes_never_panic() {
+        let camera = "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n";
+        for src in [
+            "",
+            "-",
+            "- -",
+            ":",
+            "- add:",
+            "- add: [camera]",
+            "a:\n- b\n  - c",
+            "- add: sphere\n  transform:\n    - [ scale, 0, 0, 0 ]",
+            "- add: sphere\n  transform:\n    - [ matrix, 1 ]",
+            "- add: sphere\n  transform:\n    - []",
+            "- add: sphere\n  material: [1, 2]",
+            "- add: light\n  at: [nan, inf, 1]\n  intensity: [1, 1]",
+            "- add: triangle\n  p1: [nan, 0, 0]\n  p2: [1, 0, 0]\n  p3: [0, 1, 0]",
+            "- add: directional-light\n  direction: [0, 0, 0]\n  intensity: [1, 1, 1]",
+            "- add: spot-light\n  at: [0, 0, 0]\n  direction: [0, -1, 0]\n  inner-angle: -1\n  outer-angle: 9\n  intensity: [1, 1, 1]",
+            "- add: area-light\n  corner: [0, 0, 0]\n  uvec: [1, 0, 0]\n  usteps: 0.5\n  vvec: [0, 0, 1]\n  vsteps: 1e9\n  intensity: [1, 1, 1]",
+            &format!("{}  from: [0, 0, 0]\n  to: [0, 0, 0]\n  up: [0, 1, 0]", camera),
+            &format!("{}  from: [0, 0, 0]\n  to: [0, 1, 0]\n  up: [0, 1, 0]", camera),
+            "- add: camera\n  width: 1e12\n  height: -3\n  field-of-view: 1\n  from: [0, 0, 0]\n  to: [0, 0, 1]\n  up: [0, 1, 0]",
+        ] {
+            assert!(from_yaml(src).is_err(), "{:?} should be rejected", src);
+        }
+    }
+
+    #[test]
+    fn diff_reports_changed_parameters() {
+        let camera = Camera::new(10, 10, PI / 2.0);
+        let a = default_world();
+        let mut b = default_world();
+        b.objects[0].material.diffuse = 0.5;
+        b.lights[0] =
+            PointLight::new(Point::new(0.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        b.objects.push(sphere());
+        assert_eq!(
+            diff_scenes((&a, &camera), (&b, &camera)),
+            vec![
+                "light 0 at: [-10, 10, -10] -> [0, 10, -10]",
+                "object 0 diffuse: 0.7 -> 0.5",
+                "object 2 added",
+            ]
+        );
+        let spot = |outer: f64| {
+            SpotLight::new(
+                Point::zero(),
+                Vector::new(0.0, -1.0, 0.0),
+                0.25,
+                outer,
+                BLACK,
+            )
+        };
+        let (mut c, mut d) = (default_world(), default_world());
+        c.lights[0] = spot(0.5).into();
+        d.lights[0] = spot(0.75).set_specular(false).into();
+        assert_eq!(
+            diff_scenes((&c, &camera), (&d, &camera)),
+            vec![
+                "light 0 outer-angle: 0.5 -> 0.75",
+                "light 0 specular: true -> false"
+            ]
+        );
+        assert_eq!(
+            diff_scenes((&a, &camera), (&c, &camera)),
+            vec!["light 0 kind: light -> spot-light"]
+        );
+    }
+
+    #[test]
+    fn matrix_is_written_row_major() {
+        assert_eq!(
+            matrix(&translation(1.0, 2.0, 3.0)),
+            "[ matrix, 1, 0, 0, 1, 0, 1, 0, 2, 0, 0, 1, 3, 0, 0, 0, 1 ]"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::brdf::ShadingModel;
+use crate::tuple::EPSILON;
+use core::time::Duration;
+
+// reflections, refractions and portals a camera ray goes through unless
+// set otherwise. the limit is also what keeps facing portals or mirrors
+// from recursing forever
+pub const DEFAULT_MAX_BOUNCES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSettings {
+    // offset applied along the normal before casting shadow rays, for an
+    // object at unit scale. see Object::surface_bias
+    pub shadow_bias: f64,
+    // tolerance used when comparing distances while rendering
+    pub epsilon: f64,
+    // skip the surface a shadow ray starts on instead of relying on the bias
+    pub exclude_origin: bool,
+    // transparent objects let light through to what they shadow, tinted by
+    // their color, instead of casting shadows as dark as opaque ones
+    pub transparent_shadows: bool,
+    // shades every material with this model instead of its own, e.g. to
+    // switch a whole scene to blinn-phong highlights
+    pub shading: Option<ShadingModel>,
+    // how many reflections, refractions or portals a camera ray may go
+    // through, 0 shades only what it hits first
+    pub max_bounces: usize,
+    // wall clock time Camera::render may take. it renders progressively
+    // and returns what it has when the time is up, for thumbnails and
+    // previews where an image now beats a clean one later
+    pub time_budget: Option<Duration>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            shadow_bias: EPSILON,
+            epsilon: EPSILON,
+            exclude_origin: false,
+            transparent_shadows: false,
+            shading: None,
+            max_bounces: DEFAULT_MAX_BOUNCES,
+            time_budget: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    // the defaults are tuned for scenes around 1 unit in size, scale them
+    // along with the scene so huge scenes don't get acne and tiny scenes
+    // don't get peter-panning
+    pub fn for_scene_scale(scale: f64) -> RenderSettings {
+        let defaults = RenderSettings::default();
+        RenderSettings {
+            shadow_bias: defaults.shadow_bias * scale,
+            epsilon: defaults.epsilon * scale,
+            ..defaults
+        }
+    }
+
+    pub fn with_shadow_bias(mut self, shadow_bias: f64) -> RenderSettings {
+        self.shadow_bias = shadow_bias;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> RenderSettings {
+        self.epsilon = epsilon;
+        self
+    }
+
+    pub fn with_exclude_origin(mut self, exclude_origin: bool) -> RenderSettings {
+        self.exclude_origin = exclude_origin;
+        self
+    }
+
+    pub fn with_transparent_shadows(mut self, transparent_shadows: bool) -> RenderSettings {
+        self.transparent_shadows = transparent_shadows;
+        self
+    }
+
+    pub fn with_shading(mut self, shading: ShadingModel) -> RenderSettings {
+        self.shading = Some(shading);
+        self
+    }
+
+    pub fn with_max_bounces(mut self, max_bounces: usize) -> RenderSettings {
+        self.max_bounces = max_bounces;
+        self
+    }
+
+    pub fn with_time_budget(mut self, time_budget: Duration) -> RenderSettings {
+        self.time_budget = Some(time_budget);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_settings_use_global_epsilon() {
+        let s = RenderSettings::default();
+        assert_eq!(s.shadow_bias, EPSILON);
+        assert_eq!(s.epsilon, EPSILON);
+    }
+
+    #[test]
+    fn settings_scale_with_scene() {
+        let s = RenderSettings::for_scene_scale(1000.0);
+        assert!((s.shadow_bias - EPSILON * 1000.0).abs() < 1e-12);
+        assert!((s.epsilon - EPSILON * 1000.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::light::PointLight;
+use crate::material::Material;
+use crate::object::Object;
+use crate::rng::Rng;
+use crate::sphere::{glass_sphere, sphere};
+use crate::transformations::{scaling, translation, view_transform};
+use crate::tuple::{Point, Vector};
+use crate::water::{water_surface, Wave, Waves};
+use crate::world::World;
+use std::f64::consts::PI;
+
+// small hand built scenes with refraction in them, used by the docs and
+// tests as known setups that are easy to get wrong
+
+const TILE_THICKNESS: f64 = 0.001;
+
+// a tiles x tiles checkerboard of unit squares centered at the origin
+// with its top at y = 0. each tile is a flat disc reaching the corners
+// of its square, and since a disc is highest at its center, wherever two
+// discs overlap the one whose square it is wins
+pub fn checkered_floor(tiles: usize, y: f64, material: Material) -> Vec<Object> {
+    let offset = (tiles as f64 - 1.0) / 2.0;
+    let radius = 0.5 * 2f64.sqrt() * 1.01;
+    let mut floor = Vec::with_capacity(tiles * tiles);
+    for i in 0..tiles {
+        for j in 0..tiles {
+            let color = if (i + j) % 2 == 0 {
+                Color::new(0.9, 0.9, 0.9)
+            } else {
+                Color::new(0.1, 0.1, 0.1)
+            };
+            floor.push(
+                sphere()
+                    .set_transform(
+                        translation(i as f64 - offset, y - TILE_THICKNESS, j as f64 - offset)
+                            * scaling(radius, TILE_THICKNESS, radius),
+                    )
+                    .set_material(Material { color, ..material }),
+            );
+        }
+    }
+    floor
+}
+
+fn camera(from: Point, to: Point) -> Camera {
+    let mut camera = Camera::new(320, 240, PI / 3.0);
+    camera.set_transform(view_transform(from, to, Vector::new(0.0, 1.0, 0.0)));
+    camera
+}
+
+// a unit glass sphere resting on a checkerboard, the checks seen through
+// the sphere come out flipped and squeezed towards its rim
+pub fn glass_on_checkers(tiles: usize) -> (World, Camera) {
+    let mut w = World::new();
+    w.lights
+        .push(PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+    w.objects.extend(checkered_floor(
+        tiles,
+        0.0,
+        Material {
+            specular: 0.0,
+            ..Default::default()
+        },
+    ));
+    w.objects
+        .push(glass_sphere().set_transform(translation(0.0, 1.0, 0.0)));
+    let camera = camera(Point::new(0.0, 2.5, -5.0), Point::new(0.0, 0.5, 0.0));
+    (w, camera)
+}
+
+// a checkerboard `depth` units under a still water surface at y = 0.
+// glass casts full shadows, so the bottom is lit by ambient light
+pub fn water_pool(tiles: usize, depth: f64) -> (World, Camera) {
+    let mut w = World::new();
+    w.lights
+        .push(PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+    w.objects.extend(checkered_floor(
+        tiles,
+        -depth,
+        Material {
+            ambient: 0.6,
+            diffuse: 0.3,
+            specular: 0.0,
+            ..Default::default()
+        },
+    ));
+    w.objects
+        .push(water_surface(tiles as f64, 2.0 * depth, Waves::new()));
+    let camera = camera(Point::new(0.0, 3.0, -5.0), Point::new(0.0, -depth, 0.0));
+    (w, camera)
+}
+
+// a few waves from random directions around the wind, short ones
+// smaller, which sums up to an irregular looking chop
+pub fn ripples(count: usize, wavelength: f64, amplitude: f64, seed: u64) -> Waves {
+    let mut rng = Rng::new(seed);
+    let wind = rng.range(0.0, 2.0 * PI);
+    (0..count).fold(Waves::new(), |waves, _| {
+        let scale = rng.range(0.3, 1.0);
+        waves.with(Wave::new(
+            wind + rng.range(-1.0, 1.0),
+            wavelength * scale,
+            amplitude * scale / count as f64,
+        ))
+    })
+}
+
+// the water pool with ripples on it, frozen at `time`
+pub fn rippled_pool(tiles: usize, depth: f64, time: f64, seed: u64) -> (World, Camera) {
+    let (mut w, camera) = water_pool(tiles, depth);
+    let water = w.objects.last_mut().unwrap();
+    water.waves = Some(ripples(8, 0.5, 0.02, seed).at_time(time));
+    (w, camera)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::water::WATER_INDEX;
+
+    fn color_below(w: &World, x: f64, z: f64) -> Color {
+        let r = Ray::new(Point::new(x, 5.0, z), Vector::new(0.0, -1.0, 0.0));
+        w.color_at(r)
+    }
+
+    #[test]
+    fn floor_tiles_alternate() {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        w.objects.extend(checkered_floor(
+            4,
+            0.0,
+            Material {
+                specular: 0.0,
+                ..Default::default()
+            },
+        ));
+        // near the corner of a square its own disc still wins
+        let a = color_below(&w, -0.55, -0.55);
+        let b = color_below(&w, 0.55, -0.55);
+        let c = color_below(&w, -0.05, -0.05);
+        assert!(a.red > 0.5 && b.red < 0.5 && c.red > 0.5);
+        let top = w.intersect(Ray::new(
+            Point::new(0.3, 5.0, 0.2),
+            Vector::new(0.0, -1.0, 0.0),
+        ));
+        assert!((top.hit().unwrap().t - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn glass_sphere_rests_on_the_floor() {
+        let (w, camera) = glass_on_checkers(8);
+        assert_eq!(w.objects.len(), 65);
+        let b = w.bounds();
+        assert!(b.min.0.y.abs() < 0.01);
+        assert!((b.max.0.y - 2.0).abs() < 1e-9);
+        let center = camera.ray_for_pixel(160, 120);
+        assert!(w.intersect(center).hit().is_some());
+    }
+
+    #[test]
+    fn bottom_shows_through_the_water() {
+        let (w, camera) = water_pool(6, 1.0);
+        let xs = w.intersect(camera.ray_for_pixel(160, 120));
+        let hit = xs.hit().unwrap();
+        assert_eq!(hit.object.material.refractive_index, WATER_INDEX);
+        // straight down there is no bending, the checks are where they are
+        let a = color_below(&w, 0.5, 0.5);
+        let b = color_below(&w, -0.5, 0.5);
+        assert!(a.red > b.red + 0.1 || b.red > a.red + 0.1);
+    }
+
+    #[test]
+    fn ripples_shift_the_bottom() {
+        let (calm, _) = water_pool(6, 1.0);
+        let (rippled, _) = rippled_pool(6, 1.0, 0.0, 3);
+        let moved = (0..20)
+            .filter(|i| {
+                let x = -2.0 + *i as f64 * 0.2;
+                color_below(&calm, x, 0.3) != color_below(&rippled, x, 0.3)
+            })
+            .count();
+        assert!(moved > 0);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::bounds::BoundingBox;
+use crate::ray::Ray;
+use crate::tuple::{Point, Vector};
+use core::fmt::Debug;
+use smallvec::SmallVec;
+
+// where an object space ray crosses a shape. u and v place the crossing
+// on the surface for shapes with such coordinates, the barycentric
+// weights of p2 and p3 for triangles, and are zero otherwise
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalHit {
+    pub t: f64,
+    pub u: f64,
+    pub v: f64,
+}
+
+impl LocalHit {
+    pub fn new(t: f64) -> LocalHit {
+        LocalHit { t, u: 0.0, v: 0.0 }
+    }
+
+    pub fn with_uv(t: f64, u: f64, v: f64) -> LocalHit {
+        LocalHit { t, u, v }
+    }
+}
+
+// every crossing of a ray, in any order
+pub type LocalHits = SmallVec<[LocalHit; 4]>;
+
+// the hits with t_min <= t <= t_max
+pub fn hits_within(mut hits: LocalHits, t_min: f64, t_max: f64) -> LocalHits {
+    hits.retain(|h| t_min <= h.t && h.t <= t_max);
+    hits
+}
+
+// the untransformed geometry of an object. the object holding it applies
+// its transform and material, so a shape only answers in object space.
+// the built in ones are variants of Geometry, anything else plugs in
+// through Geometry::Custom, shared between the objects using it
+pub trait Shape: Debug + Send + Sync {
+    fn local_intersect(&self, ray: Ray) -> LocalHits;
+    fn local_normal_at(&self, point: Point) -> Vector;
+    fn local_bounds(&self) -> BoundingBox;
+
+    // only the crossings with t_min <= t <= t_max. t is the same in object
+    // and world space, so callers pass their world range straight through.
+    // shapes able to rule out hits before finding them should override it,
+    // the default finds every hit and drops the rest
+    fn local_intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> LocalHits {
+        hits_within(self.local_intersect(ray), t_min, t_max)
+    }
+
+    // the normal at a hit, given the u and v the hit came with. shapes
+    // blending their normals over the surface read them from there
+    // instead of working them back out of the point
+    fn local_normal_at_uv(&self, point: Point, _u: f64, _v: f64) -> Vector {
+        self.local_normal_at(point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{Geometry, Object};
+    use crate::transformations::{rotation_z, scaling, translation};
+    use crate::world::default_world;
+    use core::f64::consts::{FRAC_1_SQRT_2, PI};
+    use std::sync::{Arc, Mutex};
+
+    // remembers the last ray it was asked about and reports the point as
+    // its normal
+    #[derive(Debug, Default)]
+    struct TestShape {
+        saved_ray: Mutex<Option<Ray>>,
+    }
+
+    impl TestShape {
+        fn saved_ray(&self) -> Ray {
+            self.saved_ray.lock().unwrap().unwrap()
+        }
+    }
+
+    impl Shape for TestShape {
+        fn local_intersect(&self, ray: Ray) -> LocalHits {
+            *self.saved_ray.lock().unwrap() = Some(ray);
+            LocalHits::new()
+        }
+
+        fn local_normal_at(&self, point: Point) -> Vector {
+            point - Point::new(0.0, 0.0, 0.0)
+        }
+
+        fn local_bounds(&self) -> BoundingBox {
+            BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+        }
+    }
+
+    fn test_shape() -> (Arc<TestShape>, Object) {
+        let shape = Arc::new(TestShape::default());
+        (shape.clone(), Object::new(Geometry::Custom(shape)))
+    }
+
+    #[test]
+    fn rays_reach_shapes_in_object_space() {
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (shape, s) = test_shape();
+        let s = s.set_transform(scaling(2.0, 2.0, 2.0));
+        assert!(s.intersect(r).is_empty());
+        assert_eq!(shape.saved_ray().origin, Point::new(0.0, 0.0, -2.5));
+        assert_eq!(shape.saved_ray().direction, Vector::new(0.0, 0.0, 0.5));
+
+        let (shape, s) = test_shape();
+        s.set_transform(translation(5.0, 0.0, 0.0)).intersect(r);
+        assert_eq!(shape.saved_ray().origin, Point::new(-5.0, 0.0, -5.0));
+        assert_eq!(shape.saved_ray().direction, Vector::new(0.0, 0.0, 1.0));
+
+        // and through a world, which knows nothing of the shape
+        let (shape, s) = test_shape();
+        let mut w = default_world();
+        w.objects.push(s);
+        assert_eq!(w.intersect(r).len(), 4);
+        assert_eq!(shape.saved_ray(), r);
+    }
+
+    #[test]
+    fn normals_of_transformed_shapes() {
+        let s = test_shape().1.set_transform(translation(0.0, 1.0, 0.0));
+        let n = s.normal_at(Point::new(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+        assert_eq!(n, Vector::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+
+        let s = test_shape()
+            .1
+            .set_transform(scaling(1.0, 0.5, 1.0) * rotation_z(PI / 5.0));
+        let n = s.normal_at(Point::new(0.0, 2f64.sqrt() / 2.0, -2f64.sqrt() / 2.0));
+        assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
+    }
+
+    #[test]
+    fn custom_shapes_intersect_within_a_range() {
+        // a slab standing in for a shape without its own range test
+        #[derive(Debug)]
+        struct Wall;
+        impl Shape for Wall {
+            fn local_intersect(&self, ray: Ray) -> LocalHits {
+                Geometry::Slab.local_intersect(ray)
+            }
+            fn local_normal_at(&self, point: Point) -> Vector {
+                Geometry::Slab.local_normal_at(point)
+            }
+            fn local_bounds(&self) -> BoundingBox {
+                Geometry::Slab.local_bounds()
+            }
+        }
+        let s = Object::new(Geometry::Custom(Arc::new(Wall)));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let ts = |t_min, t_max| {
+            s.intersect_within(r, t_min, t_max)
+                .iter()
+                .map(|i| i.t)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ts(0.0, 10.0), vec![4.0, 6.0]);
+        assert_eq!(ts(5.0, 10.0), vec![6.0]);
+        assert!(ts(0.0, 3.9).is_empty());
+    }
+
+    #[test]
+    fn custom_shapes_have_bounds_and_identity() {
+        let (shape, s) = test_shape();
+        let s = s.set_transform(translation(1.0, 0.0, 0.0));
+        assert_eq!(s.bounds().max, Point::new(2.0, 1.0, 1.0));
+        assert_eq!(s.geometry, Geometry::Custom(shape));
+        assert_ne!(s.geometry, Geometry::Custom(Arc::new(TestShape::default())));
+        assert_ne!(s.geometry, Geometry::Sphere);
+        // copies of the object share the shape
+        let copy = s.clone();
+        assert_eq!(copy.geometry, s.geometry);
+        assert_ne!(copy.id(), s.id());
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+
+// how far open the shutter is over the exposure, as a function of how far
+// through it u is from 0 to 1. real shutters take time to open and close,
+// which softens the ends of motion blur streaks
+#[derive(Debug, Clone, Copy, Default)]
+pub enum ShutterCurve {
+    // fully open the whole time, streaks end sharply
+    #[default]
+    Box,
+    // opening until halfway and closing after, streaks fade at both ends
+    Triangle,
+    // efficiency between 0 and 1 for u, e.g. measured from a camera
+    Custom(fn(f64) -> f64),
+}
+
+impl PartialEq for ShutterCurve {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ShutterCurve::Box, ShutterCurve::Box) => true,
+            (ShutterCurve::Triangle, ShutterCurve::Triangle) => true,
+            (ShutterCurve::Custom(a), ShutterCurve::Custom(b)) => core::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl ShutterCurve {
+    pub fn at(&self, u: f64) -> f64 {
+        match self {
+            ShutterCurve::Box => 1.0,
+            ShutterCurve::Triangle => 1.0 - (2.0 * u - 1.0).abs(),
+            ShutterCurve::Custom(f) => f(u).clamp(0.0, 1.0),
+        }
+    }
+}
+
+// when the camera takes in light, in the time of whatever moves the
+// scene and relative to the start of the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+    pub curve: ShutterCurve,
+    // time the exposure takes to sweep from the top row to the bottom,
+    // like a phone's rolling shutter. 0 exposes every row at once
+    pub readout: f64,
+}
+
+impl Shutter {
+    pub fn new(open: f64, close: f64) -> Shutter {
+        Shutter {
+            open,
+            close,
+            curve: ShutterCurve::default(),
+            readout: 0.0,
+        }
+    }
+
+    pub fn with_curve(mut self, curve: ShutterCurve) -> Shutter {
+        self.curve = curve;
+        self
+    }
+
+    pub fn with_readout(mut self, readout: f64) -> Shutter {
+        self.readout = readout;
+        self
+    }
+
+    pub fn is_rolling(&self) -> bool {
+        self.readout > 0.0
+    }
+
+    // how much later than the top row row y of height is exposed
+    pub fn row_offset(&self, y: u32, height: u32) -> f64 {
+        self.readout * y as f64 / height as f64
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.close <= self.open
+    }
+
+    // how much light gets through at time t
+    pub fn efficiency(&self, t: f64) -> f64 {
+        if self.is_instant() {
+            return if t == self.open { 1.0 } else { 0.0 };
+        }
+        if t < self.open || t > self.close {
+            return 0.0;
+        }
+        self.curve.at((t - self.open) / (self.close - self.open))
+    }
+
+    // n times spread evenly over the exposure in order, each with its
+    // share of the light. the shares add up to 1 unless the curve never
+    // opens
+    pub fn time_samples(&self, n: u32) -> Vec<(f64, f64)> {
+        if self.is_instant() || n <= 1 {
+            let middle = self.open + (self.close - self.open).max(0.0) / 2.0;
+            return vec![(middle, 1.0)];
+        }
+        let times = (0..n)
+            .map(|i| self.open + (i as f64 + 0.5) / n as f64 * (self.close - self.open))
+            .collect::<Vec<_>>();
+        let total: f64 = times.iter().map(|t| self.efficiency(*t)).sum();
+        let scale = if total > 0.0 { 1.0 / total } else { 0.0 };
+        times
+            .into_iter()
+            .map(|t| (t, self.efficiency(t) * scale))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_weigh_time_samples() {
+        let open = Shutter::new(0.0, 1.0);
+        for (_, w) in open.time_samples(4) {
+            assert!((w - 0.25).abs() < 1e-12);
+        }
+        let soft = open.with_curve(ShutterCurve::Triangle);
+        let samples = soft.time_samples(4);
+        assert_eq!(samples[0].0, 0.125);
+        assert!((samples[0].1 - 0.125).abs() < 1e-12);
+        assert!((samples[1].1 - 0.375).abs() < 1e-12);
+        assert!((samples.iter().map(|s| s.1).sum::<f64>() - 1.0).abs() < 1e-12);
+        // only open in its second half
+        let late = open.with_curve(ShutterCurve::Custom(|u| if u < 0.5 { 0.0 } else { 1.0 }));
+        assert_eq!(late.efficiency(0.25), 0.0);
+        assert_eq!(late.time_samples(4)[3].1, 0.5);
+        assert_eq!(late.efficiency(2.0), 0.0);
+        // the default takes everything in at once
+        assert_eq!(Shutter::default().time_samples(8), vec![(0.0, 1.0)]);
+        let rolling = Shutter::default().with_readout(0.5);
+        assert!(rolling.is_rolling());
+        assert_eq!(rolling.row_offset(0, 10), 0.0);
+        assert_eq!(rolling.row_offset(5, 10), 0.25);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::object::{Geometry, Object};
+use crate::ray::Ray;
+use crate::transformations::scaling;
+use crate::tuple::{Point, Vector};
+
+// a box from -1 to 1 on every axis. flattened it makes walls and floors
+// with straight edges and real side faces, which a flattened sphere
+// thins out towards its rim
+
+// width along x, thickness along y and depth along z, centered on the
+// origin. place it with `transformed`
+pub fn slab(width: f64, thickness: f64, depth: f64) -> Object {
+    Object::new(Geometry::Slab).set_transform(scaling(width / 2.0, thickness / 2.0, depth / 2.0))
+}
+
+// entry and exit t of an object space ray, behind the origin included
+pub(crate) fn local_intersect(ray: Ray) -> Option<(f64, f64)> {
+    let (o, d) = (ray.origin.0, ray.direction.0);
+    let mut t_min = f64::NEG_INFINITY;
+    let mut t_max = f64::INFINITY;
+    for (o, d) in [(o.x, d.x), (o.y, d.y), (o.z, d.z)] {
+        let (t0, t1) = ((-1.0 - o) / d, (1.0 - o) / d);
+        t_min = t_min.max(t0.min(t1));
+        t_max = t_max.min(t0.max(t1));
+    }
+    if t_min <= t_max && t_min.is_finite() && t_max.is_finite() {
+        Some((t_min, t_max))
+    } else {
+        None
+    }
+}
+
+// normal of the face the point is closest to
+pub(crate) fn local_normal_at(p: Point) -> Vector {
+    let (x, y, z) = (p.0.x, p.0.y, p.0.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+    if ax >= ay && ax >= az {
+        Vector::new(x.signum(), 0.0, 0.0)
+    } else if ay >= az {
+        Vector::new(0.0, y.signum(), 0.0)
+    } else {
+        Vector::new(0.0, 0.0, z.signum())
+    }
+}
+
+// moves a point along its direction from the center onto the surface
+pub(crate) fn onto_surface(p: Point) -> Point {
+    let m = p.0.x.abs().max(p.0.y.abs()).max(p.0.z.abs());
+    Point::new(p.0.x / m, p.0.y / m, p.0.z / m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::{translation, Transformable};
+
+    #[test]
+    fn rays_hit_every_face() {
+        let cases = [
+            (
+                Point::new(5.0, 0.5, 0.0),
+                Vector::new(-1.0, 0.0, 0.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(-5.0, 0.5, 0.0),
+                Vector::new(1.0, 0.0, 0.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(0.5, 5.0, 0.0),
+                Vector::new(0.0, -1.0, 0.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(0.5, 0.0, -5.0),
+                Vector::new(0.0, 0.0, 1.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Point::new(0.0, 0.5, 0.0),
+                Vector::new(0.0, 0.0, 1.0),
+                -1.0,
+                1.0,
+            ),
+        ];
+        for (origin, direction, t1, t2) in cases {
+            assert_eq!(local_intersect(Ray::new(origin, direction)), Some((t1, t2)));
+        }
+        let miss = Ray::new(Point::new(2.0, 0.0, 2.0), Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(local_intersect(miss), None);
+        let diagonal = Ray::new(
+            Point::new(-2.0, 0.0, 0.0),
+            Vector::new(0.2673, 0.5345, 0.8018),
+        );
+        assert_eq!(local_intersect(diagonal), None);
+    }
+
+    #[test]
+    fn normals_point_out_of_the_nearest_face() {
+        assert_eq!(
+            local_normal_at(Point::new(1.0, 0.5, -0.8)),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            local_normal_at(Point::new(-0.4, 0.3, -1.0)),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            local_normal_at(Point::new(0.3, -1.0, -0.7)),
+            Vector::new(0.0, -1.0, 0.0)
+        );
+        assert_eq!(
+            local_normal_at(Point::new(1.0, 1.0, 1.0)),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn slab_has_side_faces() {
+        let floor = slab(10.0, 0.2, 4.0).transformed(&translation(0.0, -0.1, 0.0));
+        let b = floor.bounds();
+        assert_eq!(b.min, Point::new(-5.0, -0.2, -2.0));
+        assert_eq!(b.max, Point::new(5.0, 0.0, 2.0));
+        let top = Ray::new(Point::new(4.9, 1.0, 1.9), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(floor.intersect(top)[0].t, 1.0);
+        let side = Ray::new(Point::new(9.0, -0.1, 0.0), Vector::new(-1.0, 0.0, 0.0));
+        let xs = floor.intersect(side);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(
+            floor.normal_at(Point::new(5.0, -0.1, 0.0)),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+        let (u, v) = floor.uv_at(Point::new(5.0, -0.15, 1.0));
+        let p = floor.point_at_uv(u, v);
+        assert!((p - Point::new(5.0, -0.15, 1.0)).magnitude() < 1e-9);
+    }
+}
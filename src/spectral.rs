@@ -0,0 +1,319 @@
+use crate::camera::Camera;
+use crate::canvas::{Canvas, Texture};
+use crate::color::Color;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::SquareMatrix;
+use crate::profile::span;
+use crate::world::World;
+
+// visible range sampled by the spectral renderer, in nanometers
+pub const MIN_WAVELENGTH: f64 = 380.0;
+pub const MAX_WAVELENGTH: f64 = 730.0;
+
+// the fewest wavelengths a spectral render samples, one per band
+pub const MIN_SAMPLES: usize = 3;
+
+// piecewise gaussian used by the color matching fit below
+fn lobe(x: f64, mean: f64, left: f64, right: f64) -> f64 {
+    let sigma = if x < mean { left } else { right };
+    (-0.5 * ((x - mean) / sigma).powi(2)).exp()
+}
+
+// cie 1931 standard observer, multi lobe fit by wyman, sloan and shirley
+pub fn cie_xyz(wavelength: f64) -> (f64, f64, f64) {
+    let l = wavelength;
+    let x = 1.056 * lobe(l, 599.8, 37.9, 31.0) + 0.362 * lobe(l, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(l, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(l, 568.8, 46.9, 40.5) + 0.286 * lobe(l, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(l, 437.0, 11.8, 36.0) + 0.681 * lobe(l, 459.0, 26.0, 13.8);
+    (x, y, z)
+}
+
+// linear srgb primaries
+pub fn xyz_to_rgb(x: f64, y: f64, z: f64) -> Color {
+    Color::new(
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.969266 * x + 1.8760108 * y + 0.041556 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    )
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// smooth blue, green and red bands that add up to one everywhere
+fn bands(wavelength: f64) -> [f64; 3] {
+    let blue = 1.0 - smoothstep(470.0, 510.0, wavelength);
+    let red = smoothstep(570.0, 610.0, wavelength);
+    [blue, 1.0 - blue - red, red]
+}
+
+// renders by tracing the scene once per sampled wavelength and adding the
+// results up through the color matching functions. rgb colors in the scene
+// are turned into smooth spectra calibrated so that a color seen on its own
+// comes back unchanged and white stays a flat spectrum, products of colors
+// are where spectral and rgb rendering start to differ
+#[derive(Debug, Clone)]
+pub struct Spectral {
+    wavelengths: Vec<f64>,
+    // rgb contribution of a unit value at each wavelength
+    response: Vec<Color>,
+    // rgb color to band weights
+    upsample: SquareMatrix<3>,
+}
+
+impl Spectral {
+    // at least one sample has to fall in each band for colors to come back
+    // as they went in, fewer samples are raised to MIN_SAMPLES
+    pub fn new(samples: usize) -> Spectral {
+        let samples = samples.max(MIN_SAMPLES);
+        let step = (MAX_WAVELENGTH - MIN_WAVELENGTH) / samples as f64;
+        let wavelengths = (0..samples)
+            .map(|i| MIN_WAVELENGTH + (i as f64 + 0.5) * step)
+            .collect::<Vec<_>>();
+        let raw = wavelengths
+            .iter()
+            .map(|&l| {
+                let (x, y, z) = cie_xyz(l);
+                xyz_to_rgb(x, y, z) * step
+            })
+            .collect::<Vec<_>>();
+        // white balance so a flat spectrum is white
+        let white = raw.iter().fold(Color::new(0.0, 0.0, 0.0), |a, &b| a + b);
+        let response = raw
+            .iter()
+            .map(|c| {
+                Color::new(
+                    c.red / white.red,
+                    c.green / white.green,
+                    c.blue / white.blue,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // column j is the color of band j, inverting it gives the band
+        // weights that reproduce any color
+        let mut columns = [[0.0; 3]; 3];
+        for (&l, c) in wavelengths.iter().zip(&response) {
+            for (j, weight) in bands(l).into_iter().enumerate() {
+                columns[j][0] += weight * c.red;
+                columns[j][1] += weight * c.green;
+                columns[j][2] += weight * c.blue;
+            }
+        }
+        let to_rgb = SquareMatrix::<3>::new([
+            [columns[0][0], columns[1][0], columns[2][0]],
+            [columns[0][1], columns[1][1], columns[2][1]],
+            [columns[0][2], columns[1][2], columns[2][2]],
+        ]);
+        Spectral {
+            wavelengths,
+            response,
+            upsample: to_rgb.inverse().expect("spectral bands are independent"),
+        }
+    }
+
+    pub fn wavelengths(&self) -> &[f64] {
+        &self.wavelengths
+    }
+
+    // value of the spectrum of color at the i-th sampled wavelength
+    pub fn value_at(&self, color: Color, i: usize) -> f64 {
+        let m = &self.upsample;
+        let rgb = [color.red, color.green, color.blue];
+        let weight = |row: usize| (0..3).map(|k| m.get(row, k) * rgb[k]).sum::<f64>();
+        let band = bands(self.wavelengths[i]);
+        (0..3).map(|j| band[j] * weight(j)).sum()
+    }
+
+    // color of a spectrum given by its values at every sampled wavelength
+    pub fn to_rgb(&self, values: &[f64]) -> Color {
+        values
+            .iter()
+            .zip(&self.response)
+            .fold(Color::new(0.0, 0.0, 0.0), |sum, (&v, &c)| sum + c * v)
+    }
+
+    // copy of world in which every color is the gray value of its spectrum
+    // at the i-th wavelength, and dispersive materials take their
+    // refractive index at that wavelength
+    pub fn monochrome(&self, world: &World, i: usize) -> World {
+        let gray = |c: Color| {
+            let v = self.value_at(c, i);
+            Color::new(v, v, v)
+        };
+        let material = |m: &mut Material| {
+            m.color = gray(m.color);
+            if let Some(pattern) = &mut m.pattern {
+                pattern.kind = pattern.kind.map_colors(gray);
+            }
+            m.refractive_index = m.refractive_index_at(self.wavelengths[i]);
+            m.abbe_number = f64::INFINITY;
+        };
+        let texture = |t: &Texture| {
+            let mut canvas = Canvas::clone(t);
+            canvas.pixels.iter_mut().for_each(|p| *p = gray(*p));
+            Texture::new(canvas)
+        };
+        let mut world = world.clone();
+        for object in world.objects.iter_mut() {
+            material(&mut object.material);
+        }
+        for lod in world.lods.iter_mut() {
+            for level in lod.levels_mut() {
+                for object in level.objects.iter_mut() {
+                    material(&mut object.material);
+                }
+            }
+        }
+        for group in world.groups.iter_mut() {
+            for object in group.objects_mut() {
+                material(&mut object.material);
+            }
+        }
+        for csg in world.csgs.iter_mut() {
+            for object in csg.objects_mut() {
+                material(&mut object.material);
+            }
+        }
+        for light in world.lights.iter_mut() {
+            match light {
+                Light::Point(l) => {
+                    l.intensity = gray(l.intensity);
+                    if let Some(gobo) = &mut l.gobo {
+                        gobo.mask = texture(&gobo.mask);
+                    }
+                }
+                Light::Spot(l) => {
+                    l.intensity = gray(l.intensity);
+                    if let Some(gobo) = &mut l.gobo {
+                        gobo.mask = texture(&gobo.mask);
+                    }
+                }
+                Light::Area(l) => l.intensity = gray(l.intensity),
+                Light::Directional(l) => l.intensity = gray(l.intensity),
+            }
+        }
+        for decal in world.decals.iter_mut() {
+            decal.image = texture(&decal.image);
+        }
+        world
+    }
+
+    pub fn render(&self, camera: &Camera, world: &World) -> Canvas {
+        let _span = span!(INFO, "render_spectral", samples = self.wavelengths.len());
+        let mut image = Canvas::new(camera.hsize() as isize, camera.vsize() as isize);
+        image.pixels.fill(Color::new(0.0, 0.0, 0.0));
+        for (i, &response) in self.response.iter().enumerate() {
+            let layer = camera.render(&self.monochrome(world, i));
+            for (pixel, value) in image.pixels.iter_mut().zip(&layer.pixels) {
+                *pixel = *pixel + response * value.red;
+            }
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::{Pattern, PatternKind, PerturbedPattern};
+    use crate::transformations::view_transform;
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    fn camera() -> Camera {
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        c
+    }
+
+    #[test]
+    fn color_matching_peaks() {
+        let (_, y, _) = cie_xyz(555.0);
+        assert!((y - 1.0).abs() < 0.01);
+        let (x, _, _) = cie_xyz(600.0);
+        assert!((x - 1.06).abs() < 0.01);
+        let (_, _, z) = cie_xyz(445.0);
+        assert!(z > 1.7);
+    }
+
+    #[test]
+    fn colors_survive_the_round_trip() {
+        let s = Spectral::new(16);
+        for c in [
+            Color::new(1.0, 1.0, 1.0),
+            Color::new(0.8, 1.0, 0.6),
+            Color::new(0.1, 0.2, 0.9),
+        ] {
+            let values = (0..16).map(|i| s.value_at(c, i)).collect::<Vec<_>>();
+            assert!(s.to_rgb(&values).approx_eq(&c, 1e-9));
+        }
+        // white is flat
+        assert!((0..16).all(|i| (s.value_at(Color::new(1.0, 1.0, 1.0), i) - 1.0).abs() < 1e-9));
+        // too few samples to cover the bands are raised to enough
+        assert_eq!(Spectral::new(0).wavelengths().len(), MIN_SAMPLES);
+        for n in 1..=64 {
+            let s = Spectral::new(n);
+            let c = Color::new(0.8, 1.0, 0.6);
+            let values = (0..s.wavelengths().len())
+                .map(|i| s.value_at(c, i))
+                .collect::<Vec<_>>();
+            assert!(s.to_rgb(&values).approx_eq(&c, 1e-9));
+        }
+    }
+
+    #[test]
+    fn gray_scenes_render_like_rgb() {
+        let mut world = default_world();
+        world.objects[0].material = Material::default();
+        let expected = camera().render(&world);
+        let spectral = Spectral::new(8).render(&camera(), &world);
+        assert!(spectral
+            .pixels
+            .iter()
+            .zip(&expected.pixels)
+            .all(|(a, b)| a.approx_eq(b, 1e-9)));
+    }
+
+    #[test]
+    fn colored_scenes_keep_their_hue() {
+        let mut world = default_world();
+        world.objects[0].material.color = Color::new(0.9, 0.1, 0.1);
+        let pixel = Spectral::new(16).render(&camera(), &world).pixels[5 * 11 + 5];
+        assert!(pixel.red > 2.0 * pixel.green && pixel.red > 2.0 * pixel.blue);
+        // patterns are split into spectra too, perturbed ones included
+        static GREEN: PatternKind =
+            PatternKind::Stripe(Color::new(0.1, 0.9, 0.1), Color::new(0.1, 0.9, 0.1));
+        for kind in [
+            GREEN,
+            PatternKind::Perturbed(PerturbedPattern::new(&GREEN, 0.3)),
+        ] {
+            world.objects[0].material.pattern = Some(Pattern::new(kind));
+            let pixel = Spectral::new(16).render(&camera(), &world).pixels[5 * 11 + 5];
+            assert!(pixel.green > 2.0 * pixel.red && pixel.green > 2.0 * pixel.blue);
+        }
+    }
+
+    #[test]
+    fn each_wavelength_sees_its_own_index() {
+        let mut world = default_world();
+        world.objects[0].material.refractive_index = 1.5;
+        world.objects[0].material.abbe_number = 30.0;
+        let s = Spectral::new(4);
+        let indices = (0..4)
+            .map(|i| s.monochrome(&world, i).objects[0].material)
+            .inspect(|m| assert!(!m.is_dispersive()))
+            .map(|m| m.refractive_index)
+            .collect::<Vec<_>>();
+        assert!(indices.windows(2).all(|w| w[0] > w[1]));
+    }
+}
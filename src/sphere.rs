@@ -1,91 +1,74 @@
-use crate::intersection::{Intersection, Intersections};
 use crate::material::Material;
-use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::{Geometry, Object};
 use crate::ray::Ray;
-use crate::tuple::{Point, Vector};
-use std::sync::atomic::{AtomicU32, Ordering};
-
-#[derive(Debug, PartialEq)]
-pub struct Sphere {
-    id: u32,
-    pub transform: Matrix4,
-    pub inv_transform: Matrix4,
-    pub material: Material,
-}
-
-impl Sphere {
-    pub fn new() -> Sphere {
-        static COUNT: AtomicU32 = AtomicU32::new(0);
-        Sphere {
-            id: COUNT.fetch_add(1, Ordering::Relaxed),
-            transform: Matrix4::identity(4),
-            inv_transform: Matrix4::identity(4),
-            material: Default::default(),
-        }
-    }
-
-    pub fn set_transform(mut self, transform: Matrix4) -> Sphere {
-        self.inv_transform = transform
-            .inverse()
-            .expect("Fail to inverse sphere transform");
-        self.transform = transform;
-        self
-    }
+use crate::shape::{hits_within, LocalHit, LocalHits};
+use crate::tuple::Point;
 
-    pub fn set_material(mut self, material: Material) -> Sphere {
-        self.material = material;
-        self
-    }
-
-    pub fn intersect(&self, ray: Ray) -> Intersections {
-        let ray = ray.transform(&self.inv_transform);
-        let origin = ray.origin;
-        let direction = ray.direction;
-
-        let sphere_to_ray = origin - Point::new(0.0, 0.0, 0.0);
+// a unit sphere around the origin, the shape the book starts out with.
+// place it with `transformed`
+pub fn sphere() -> Object {
+    Object::new(Geometry::Sphere)
+}
 
-        let a = direction.dot(direction);
-        let b = 2.0 * direction.dot(sphere_to_ray);
-        let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
+// clear glass ball, as used throughout the refraction chapter
+pub fn glass_sphere() -> Object {
+    sphere().set_material(Material {
+        transparency: 1.0,
+        refractive_index: 1.5,
+        ..Default::default()
+    })
+}
 
-        let discriminant = b * b - 4.0 * a * c;
-        if discriminant < 0.0 {
-            return Intersections::new();
-        }
+pub(crate) fn local_intersect(ray: Ray) -> LocalHits {
+    let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
 
-        let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
-        let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+    let a = ray.direction.dot(ray.direction);
+    let b = 2.0 * ray.direction.dot(sphere_to_ray);
+    let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
 
-        let i1 = Intersection::new(t1, self);
-        let i2 = Intersection::new(t2, self);
-        vec![i1, i2].into()
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return LocalHits::new();
     }
 
-    pub fn normal_at(&self, world_p: Point) -> Vector {
-        let object_p = &self.inv_transform * world_p;
-
-        let object_normal = Point::try_from(object_p).unwrap() - Point::new(0.0, 0.0, 0.0);
+    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+    LocalHits::from_slice(&[LocalHit::new(t1), LocalHit::new(t2)])
+}
 
-        let mut world_normal = &self.inv_transform.transpose() * object_normal;
-        // something something about multiplying by the inverse
-        // of 3x3 submatrix of transform which can be skipped by
-        // setting w to 0.
-        world_normal.w = 0.0;
-        world_normal.normalize().try_into().unwrap()
-    }
+pub(crate) fn local_intersect_within(ray: Ray, t_min: f64, t_max: f64) -> LocalHits {
+    let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
+    let a = ray.direction.dot(ray.direction);
+    let half_b = ray.direction.dot(sphere_to_ray);
+    let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
+    // the sphere lies wholly before or after the range when the ray's
+    // closest approach does and the end of the range is already outside,
+    // checked without the square root
+    let closest = -half_b / a;
+    let outside = |t: f64| {
+        let d = t - closest;
+        a * d * d > half_b * half_b / a - c
+    };
+    if (closest < t_min && outside(t_min)) || (closest > t_max && outside(t_max)) {
+        return LocalHits::new();
+    }
+    hits_within(local_intersect(ray), t_min, t_max)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrix::Matrix4;
     use crate::transformations::{self, scaling, translation};
     use crate::tuple::Vector;
 
     #[test]
     fn ray_intersects_sphere_at_2_points() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 4.0);
         assert_eq!(xs[1].t, 6.0);
@@ -93,8 +76,8 @@ mod tests {
     #[test]
     fn ray_intersects_sphere_at_tangent() {
         let r = Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 5.0);
         assert_eq!(xs[1].t, 5.0);
@@ -102,15 +85,15 @@ mod tests {
     #[test]
     fn ray_misses_sphere() {
         let r = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
         assert_eq!(xs.len(), 0);
     }
     #[test]
     fn ray_originates_inside_sphere() {
         let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, -1.0);
         assert_eq!(xs[1].t, 1.0);
@@ -118,8 +101,8 @@ mod tests {
     #[test]
     fn sphere_is_behind_ray() {
         let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new();
-        let xs = s.intersect(r).0;
+        let s = sphere();
+        let xs = s.intersect(r);
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, -6.0);
         assert_eq!(xs[1].t, -4.0);
@@ -127,22 +110,22 @@ mod tests {
 
     #[test]
     fn sphere_default_transformation() {
-        let s = Sphere::new();
+        let s = sphere();
         assert_eq!(s.transform, Matrix4::identity(4));
     }
 
     #[test]
     fn change_sphere_transform() {
         let t = translation(2.0, 3.0, 4.0);
-        let s = Sphere::new().set_transform(t.clone());
+        let s = sphere().set_transform(t.clone());
         assert_eq!(s.transform, t);
     }
 
     #[test]
     fn intersecting_scaled_sphere_with_ray() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new().set_transform(scaling(2.0, 2.0, 2.0));
-        let xs = s.intersect(r).0;
+        let s = sphere().set_transform(scaling(2.0, 2.0, 2.0));
+        let xs = s.intersect(r);
 
         assert_eq!(xs.len(), 2);
         assert_eq!(xs[0].t, 3.0);
@@ -152,36 +135,36 @@ mod tests {
     #[test]
     fn intersecting_translated_sphere_with_ray() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let s = Sphere::new().set_transform(translation(5.0, 0.0, 0.0));
-        let xs = s.intersect(r).0;
+        let s = sphere().set_transform(translation(5.0, 0.0, 0.0));
+        let xs = s.intersect(r);
 
         assert_eq!(xs.len(), 0);
     }
 
     #[test]
     fn normal_on_sphere_x_axis() {
-        let s = Sphere::new();
+        let s = sphere();
         let n = s.normal_at(Point::new(1.0, 0.0, 0.0));
         assert_eq!(n, Vector::new(1.0, 0.0, 0.0));
     }
 
     #[test]
     fn normal_on_sphere_y_axis() {
-        let s = Sphere::new();
+        let s = sphere();
         let n = s.normal_at(Point::new(0.0, 1.0, 0.0));
         assert_eq!(n, Vector::new(0.0, 1.0, 0.0));
     }
 
     #[test]
     fn normal_on_sphere_z_axis() {
-        let s = Sphere::new();
+        let s = sphere();
         let n = s.normal_at(Point::new(0.0, 0.0, 1.0));
         assert_eq!(n, Vector::new(0.0, 0.0, 1.0));
     }
 
     #[test]
     fn normal_on_sphere_not_at_axis() {
-        let s = Sphere::new();
+        let s = sphere();
         let n = s.normal_at(Point::new(
             3f64.sqrt() / 3.0,
             3f64.sqrt() / 3.0,
@@ -194,8 +177,9 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_on_translated_sphere() {
-        let s = Sphere::new().set_transform(translation(0.0, 1.0, 0.0));
+        let s = sphere().set_transform(translation(0.0, 1.0, 0.0));
         let n = s.normal_at(Point::new(0.0, 1.70711, -0.70711));
         assert_eq!(n, Vector::new(0.0, 0.70711, -0.70711));
     }
@@ -203,14 +187,51 @@ mod tests {
     #[test]
     fn normal_on_transformed_sphere() {
         let m = scaling(1.0, 0.5, 1.0) * transformations::rotation_z(std::f64::consts::PI / 5.0);
-        let s = Sphere::new().set_transform(m);
+        let s = sphere().set_transform(m);
         let n = s.normal_at(Point::new(0.0, 2f64.sqrt() / 2.0, -2f64.sqrt() / 2.0));
         assert_eq!(n, Vector::new(0.0, 0.97014, -0.24254));
     }
 
+    #[test]
+    fn bounds_of_transformed_sphere() {
+        let s = sphere().set_transform(translation(1.0, 2.0, 3.0) * scaling(2.0, 1.0, 1.0));
+        let b = s.bounds();
+        assert_eq!(b.min, Point::new(-1.0, 1.0, 2.0));
+        assert_eq!(b.max, Point::new(3.0, 3.0, 4.0));
+        // turning a sphere leaves its box alone, unlike its local box
+        let turned = sphere().set_transform(transformations::rotation_y(0.5));
+        assert!((turned.bounds().max.0.x - 1.0).abs() < 1e-9);
+        assert!(turned.local_bounds().transform(&turned.transform).max.0.x > 1.3);
+    }
+
+    #[test]
+    fn uv_mapping_on_sphere() {
+        let s = sphere();
+        let cases = [
+            (Point::new(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Point::new(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Point::new(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Point::new(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Point::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Point::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+        ];
+        for (p, (u, v)) in cases {
+            let (pu, pv) = s.uv_at(p);
+            assert!((pu - u).abs() < 1e-5 && (pv - v).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn point_at_uv_inverts_uv_at() {
+        let s = sphere().set_transform(translation(1.0, 2.0, 3.0) * scaling(2.0, 1.0, 1.0));
+        let p = s.point_at_uv(0.3, 0.6);
+        let (u, v) = s.uv_at(p);
+        assert!((u - 0.3).abs() < 1e-5 && (v - 0.6).abs() < 1e-5);
+    }
+
     #[test]
     fn sphere_has_default_material() {
-        let s = Sphere::new();
+        let s = sphere();
         assert_eq!(s.material, Default::default());
     }
 
@@ -220,7 +241,7 @@ mod tests {
             ambient: 1.0,
             ..Default::default()
         };
-        let s = Sphere::new().set_material(m);
+        let s = sphere().set_material(m);
         assert_eq!(s.material, m);
     }
 }
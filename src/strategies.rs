@@ -0,0 +1,51 @@
+// proptest strategies shared by the math tests
+use crate::matrix::Matrix4;
+use crate::transformations::{rotation_x, rotation_y, rotation_z, scaling, shearing, translation};
+use crate::tuple::{Point, Vector};
+use proptest::prelude::*;
+use std::f64::consts::PI;
+
+pub fn coordinate() -> impl Strategy<Value = f64> {
+    -100.0..100.0
+}
+
+pub fn point() -> impl Strategy<Value = Point> {
+    (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Point::new(x, y, z))
+}
+
+pub fn vector() -> impl Strategy<Value = Vector> {
+    (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| Vector::new(x, y, z))
+}
+
+pub fn unit_vector() -> impl Strategy<Value = Vector> {
+    vector()
+        .prop_filter("needs a direction", |v| v.magnitude() > 1e-3)
+        .prop_map(|v| v.normalize())
+}
+
+pub fn angle() -> impl Strategy<Value = f64> {
+    -PI..PI
+}
+
+// scale factors kept away from zero so transforms stay well conditioned
+fn scale() -> impl Strategy<Value = f64> {
+    (0.1..10.0, any::<bool>()).prop_map(|(s, flip): (f64, bool)| if flip { -s } else { s })
+}
+
+fn step() -> impl Strategy<Value = Matrix4> {
+    prop_oneof![
+        (coordinate(), coordinate(), coordinate()).prop_map(|(x, y, z)| translation(x, y, z)),
+        (scale(), scale(), scale()).prop_map(|(x, y, z)| scaling(x, y, z)),
+        angle().prop_map(rotation_x),
+        angle().prop_map(rotation_y),
+        angle().prop_map(rotation_z),
+        (-0.5..0.5, -0.5..0.5, -0.5..0.5).prop_map(|(a, b, c)| shearing(a, 0.0, 0.0, b, c, 0.0)),
+    ]
+}
+
+// invertible transform built from up to four translate, scale, rotate
+// and shear steps
+pub fn transform() -> impl Strategy<Value = Matrix4> {
+    prop::collection::vec(step(), 1..4)
+        .prop_map(|steps| steps.into_iter().fold(Matrix4::identity(4), |m, s| s * m))
+}
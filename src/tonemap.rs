@@ -0,0 +1,68 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+
+// squeezes rendered light levels, which are open ended, into the 0..1 an
+// image can show. renders stay in hdr and are only mapped for display or
+// export, so every preview of the same render looks like the final image
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ToneMapper {
+    // cuts everything above one, what writing a ppm does anyway
+    #[default]
+    Clamp,
+    // c / (1 + c), never clips but greys out bright colors
+    Reinhard,
+    // narkowicz's fit of the aces filmic curve, with a toe and a soft
+    // shoulder
+    Aces,
+}
+
+impl ToneMapper {
+    pub fn map(&self, c: Color) -> Color {
+        let f = |x: f64| {
+            let x = x.max(0.0);
+            match self {
+                ToneMapper::Clamp => x.min(1.0),
+                ToneMapper::Reinhard => x / (1.0 + x),
+                ToneMapper::Aces => {
+                    ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+                }
+            }
+        };
+        Color::new(f(c.red), f(c.green), f(c.blue))
+    }
+}
+
+impl Canvas {
+    pub fn tone_mapped(&self, mapper: ToneMapper) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|p| mapper.map(*p)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mappers_keep_colors_displayable() {
+        let bright = Color::new(4.0, 1.0, 0.25);
+        for mapper in [ToneMapper::Clamp, ToneMapper::Reinhard, ToneMapper::Aces] {
+            let c = mapper.map(bright);
+            for x in [c.red, c.green, c.blue] {
+                assert!((0.0..=1.0).contains(&x));
+            }
+            // brighter stays brighter
+            assert!(c.red >= c.green && c.green > c.blue);
+            assert_eq!(
+                mapper.map(Color::new(0.0, 0.0, 0.0)),
+                Color::new(0.0, 0.0, 0.0)
+            );
+        }
+        assert_eq!(ToneMapper::Clamp.map(bright), Color::new(1.0, 1.0, 0.25));
+        assert_eq!(ToneMapper::Reinhard.map(bright), Color::new(0.8, 0.5, 0.2));
+        assert!(ToneMapper::Aces.map(bright).red < 1.0);
+    }
+}
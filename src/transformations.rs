@@ -1,5 +1,7 @@
 use crate::matrix;
 use crate::matrix::Matrix4;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
 use crate::tuple::{Point, Vector};
 
 pub fn translation(x: f64, y: f64, z: f64) -> Matrix4 {
@@ -37,6 +39,31 @@ pub fn rotation_z(r: f64) -> Matrix4 {
     )
 }
 
+pub fn rotation_about_axis(axis: Vector, r: f64) -> Matrix4 {
+    Matrix4::from_axis_angle(axis, r)
+}
+
+// shortest rotation taking the direction of a onto the direction of b
+pub fn rotation_from_to(a: Vector, b: Vector) -> Matrix4 {
+    let a = a.normalize();
+    let b = b.normalize();
+    let cos = a.dot(b).clamp(-1.0, 1.0);
+    let axis = a.cross(b);
+    if axis.magnitude() < 1e-9 {
+        if cos > 0.0 {
+            return Matrix4::identity(4);
+        }
+        // opposite directions, any axis perpendicular to a works
+        let other = if a.0.x.abs() < 0.9 {
+            Vector::new(1.0, 0.0, 0.0)
+        } else {
+            Vector::new(0.0, 1.0, 0.0)
+        };
+        return rotation_about_axis(a.cross(other), core::f64::consts::PI);
+    }
+    rotation_about_axis(axis, cos.acos())
+}
+
 pub fn shearing(x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Matrix4 {
     matrix!(
         [1.0, x_y, x_z, 0.0],
@@ -60,6 +87,61 @@ pub fn view_transform(from: Point, to: Point, up: Vector) -> Matrix4 {
 
     orientation * translation(-from.0.x, -from.0.y, -from.0.z)
 }
+// picks an up vector that is not parallel to the forward direction
+fn up_for(forward: Vector) -> Vector {
+    let forward = forward.normalize();
+    if forward.dot(Vector::new(0.0, 1.0, 0.0)).abs() > 1.0 - 1e-6 {
+        Vector::new(0.0, 0.0, 1.0)
+    } else {
+        Vector::new(0.0, 1.0, 0.0)
+    }
+}
+
+// view transform from `from` towards `to` without having to pick an up
+// vector, falls back to +z when looking straight up or down
+pub fn aim(from: Point, to: Point) -> Matrix4 {
+    view_transform(from, to, up_for(to - from))
+}
+
+// object placement at `from` whose local -z axis points at `to`, the
+// inverse of aim, for orienting lights and instanced geometry
+pub fn look_at(from: Point, to: Point) -> Matrix4 {
+    aim(from, to)
+        .inverse()
+        .expect("Fail to inverse look at matrix")
+}
+
+// chaining helpers, each call applies its transform after the ones already
+// on the value, so sphere.scaled(2., 2., 2.).translated(0., 1., 0.) scales
+// first and then moves the scaled sphere
+pub trait Transformable: Sized {
+    fn transformed(self, m: &Matrix4) -> Self;
+
+    fn translated(self, x: f64, y: f64, z: f64) -> Self {
+        self.transformed(&translation(x, y, z))
+    }
+
+    fn scaled(self, x: f64, y: f64, z: f64) -> Self {
+        self.transformed(&scaling(x, y, z))
+    }
+
+    fn rotated_x(self, r: f64) -> Self {
+        self.transformed(&rotation_x(r))
+    }
+
+    fn rotated_y(self, r: f64) -> Self {
+        self.transformed(&rotation_y(r))
+    }
+
+    fn rotated_z(self, r: f64) -> Self {
+        self.transformed(&rotation_z(r))
+    }
+
+    fn sheared(self, x_y: f64, x_z: f64, y_x: f64, y_z: f64, z_x: f64, z_y: f64) -> Self {
+        self.transformed(&shearing(x_y, x_z, y_x, y_z, z_x, z_y))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::f64::consts::PI;
@@ -319,4 +401,106 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn rotating_about_an_arbitrary_axis() {
+        let m = rotation_about_axis(Vector::new(0.0, 0.0, 3.0), PI / 2.0);
+        assert_eq!(m, rotation_z(PI / 2.0));
+        let m = rotation_about_axis(Vector::new(1.0, 1.0, 0.0), PI);
+        assert_eq!(m * Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0).0);
+    }
+
+    #[test]
+    fn rotation_from_one_vector_to_another() {
+        let a = Vector::new(1.0, 2.0, 3.0);
+        let b = Vector::new(-4.0, 0.5, 2.0);
+        let m = rotation_from_to(a, b);
+        assert_eq!(&m * a.normalize(), b.normalize().0);
+        assert_eq!(rotation_from_to(a, a * 2.0), Matrix4::identity(4));
+    }
+
+    #[test]
+    fn rotation_between_opposite_vectors() {
+        for a in [Vector::new(1.0, 0.0, 0.0), Vector::new(0.0, -2.0, 1.0)] {
+            let m = rotation_from_to(a, -a);
+            assert_eq!(&m * a.normalize(), (-a.normalize()).0);
+        }
+    }
+
+    #[test]
+    fn look_at_points_negative_z_at_target() {
+        let from = Point::new(1.0, 2.0, 3.0);
+        let to = Point::new(4.0, -2.0, 3.0);
+        let m = look_at(from, to);
+        assert_eq!(&m * Point::new(0.0, 0.0, 0.0), from.0);
+        assert_eq!(&m * Point::new(0.0, 0.0, -5.0), to.0);
+    }
+
+    #[test]
+    fn aim_straight_down_picks_another_up() {
+        let t = aim(Point::new(0.0, 5.0, 0.0), Point::new(0.0, 0.0, 0.0));
+        assert!(t.invertible());
+        assert_eq!(&t * Point::new(0.0, 0.0, 0.0), Point::new(0.0, 0.0, -5.0).0);
+    }
+
+    #[test]
+    fn transformable_defaults_compose_left_to_right() {
+        let r = crate::ray::Ray::new(Point::new(1.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0));
+        let r = r.rotated_z(PI / 2.0).translated(0.0, 0.0, 1.0);
+        assert_eq!(r.origin, Point::new(0.0, 1.0, 1.0));
+        assert_eq!(r.direction, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    mod properties {
+        use super::*;
+        use crate::strategies::{angle, point, unit_vector};
+        use proptest::prelude::*;
+
+        fn row(m: &Matrix4, y: usize) -> Vector {
+            Vector::new(m.get(y, 0), m.get(y, 1), m.get(y, 2))
+        }
+
+        proptest! {
+            #[test]
+            fn view_transform_rows_are_orthogonal(
+                from in point(),
+                forward in unit_vector(),
+                up in unit_vector(),
+            ) {
+                prop_assume!(forward.cross(up).magnitude() > 1e-3);
+                let to = Point::try_from(from.0 + forward.0).unwrap();
+                let m = view_transform(from, to, up);
+                let (left, true_up, back) = (row(&m, 0), row(&m, 1), row(&m, 2));
+                prop_assert!(left.dot(true_up).abs() < 1e-9);
+                prop_assert!(left.dot(back).abs() < 1e-9);
+                prop_assert!(true_up.dot(back).abs() < 1e-9);
+                prop_assert!((back.magnitude() - 1.0).abs() < 1e-9);
+                // left keeps the length of forward x up like the book, so
+                // the basis is only orthonormal for a perpendicular up
+                let along = up.dot(forward);
+                prop_assert!((left.magnitude() - (1.0 - along * along).sqrt()).abs() < 1e-9);
+            }
+
+            #[test]
+            fn aim_is_orthonormal(from in point(), forward in unit_vector()) {
+                let to = Point::try_from(from.0 + forward.0).unwrap();
+                let m = aim(from, to);
+                prop_assert_eq!(&m * forward, Vector::new(0.0, 0.0, -1.0).0);
+                prop_assert!((m.get(3, 3) - 1.0).abs() < 1e-12);
+            }
+
+            #[test]
+            fn axis_rotation_is_orthonormal(axis in unit_vector(), r in angle()) {
+                let m = rotation_about_axis(axis, r);
+                prop_assert_eq!(m.clone() * m.transpose(), Matrix4::identity(4));
+                prop_assert!((m.determinant() - 1.0).abs() < 1e-9);
+                prop_assert_eq!(&m * axis, axis.0);
+            }
+
+            #[test]
+            fn rotation_from_to_maps_directions(a in unit_vector(), b in unit_vector()) {
+                prop_assert_eq!(&rotation_from_to(a, b) * a, b.0);
+            }
+        }
+    }
 }
@@ -0,0 +1,284 @@
+use crate::bounds::BoundingBox;
+use crate::object::{Geometry, Object};
+use crate::ray::Ray;
+use crate::shape::{LocalHit, LocalHits, Shape};
+use crate::tuple::{Point, Vector, EPSILON};
+
+// a flat triangle, the building block of meshes. the edges and normal are
+// worked out once up front since every ray needs them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+    pub e1: Vector,
+    pub e2: Vector,
+    pub normal: Vector,
+}
+
+impl Triangle {
+    pub fn new(p1: Point, p2: Point, p3: Point) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).normalize(),
+        }
+    }
+
+    // the point at barycentric weights u for p2 and v for p3
+    pub fn point_at(&self, u: f64, v: f64) -> Point {
+        self.p1 + self.e1 * u + self.e2 * v
+    }
+
+    // the weights of a point in the triangle's plane, undoing point_at
+    pub fn barycentric(&self, p: Point) -> (f64, f64) {
+        let d = p - self.p1;
+        let (d11, d12, d22) = (
+            self.e1.dot(self.e1),
+            self.e1.dot(self.e2),
+            self.e2.dot(self.e2),
+        );
+        let (dp1, dp2) = (d.dot(self.e1), d.dot(self.e2));
+        let denom = d11 * d22 - d12 * d12;
+        (
+            (d22 * dp1 - d12 * dp2) / denom,
+            (d11 * dp2 - d12 * dp1) / denom,
+        )
+    }
+}
+
+// a triangle with a normal given at each corner, blended across the face
+// so a mesh of them shades like the curved surface it stands for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothTriangle {
+    pub triangle: Triangle,
+    pub n1: Vector,
+    pub n2: Vector,
+    pub n3: Vector,
+}
+
+impl SmoothTriangle {
+    pub fn new(
+        p1: Point,
+        p2: Point,
+        p3: Point,
+        n1: Vector,
+        n2: Vector,
+        n3: Vector,
+    ) -> SmoothTriangle {
+        SmoothTriangle {
+            triangle: Triangle::new(p1, p2, p3),
+            n1,
+            n2,
+            n3,
+        }
+    }
+}
+
+// the corners are given in object space, move it with `transformed`
+pub fn triangle(p1: Point, p2: Point, p3: Point) -> Object {
+    Object::new(Geometry::Triangle(Triangle::new(p1, p2, p3)))
+}
+
+pub fn smooth_triangle(
+    p1: Point,
+    p2: Point,
+    p3: Point,
+    n1: Vector,
+    n2: Vector,
+    n3: Vector,
+) -> Object {
+    Object::new(Geometry::SmoothTriangle(SmoothTriangle::new(
+        p1, p2, p3, n1, n2, n3,
+    )))
+}
+
+impl Shape for Triangle {
+    fn local_intersect(&self, ray: Ray) -> LocalHits {
+        self.local_intersect_within(ray, f64::NEG_INFINITY, f64::INFINITY)
+    }
+
+    // moller-trumbore, solving for t and the barycentric u and v at once.
+    // t is known before v, so hits out of range skip the last test
+    fn local_intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> LocalHits {
+        let dir_cross_e2 = ray.direction.cross(self.e2);
+        let det = self.e1.dot(dir_cross_e2);
+        // the ray runs parallel to the plane. det grows with the edges and
+        // the ray direction, so it's measured against them, squared to
+        // save the square roots, or small triangles would never be hit
+        let scale = self.e1.dot(self.e1) * self.e2.dot(self.e2) * ray.direction.dot(ray.direction);
+        if det * det < EPSILON * EPSILON * scale {
+            return LocalHits::new();
+        }
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin - self.p1;
+        let u = f * p1_to_origin.dot(dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return LocalHits::new();
+        }
+        let origin_cross_e1 = p1_to_origin.cross(self.e1);
+        let t = f * self.e2.dot(origin_cross_e1);
+        if t < t_min || t > t_max {
+            return LocalHits::new();
+        }
+        let v = f * ray.direction.dot(origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return LocalHits::new();
+        }
+        LocalHits::from_slice(&[LocalHit::with_uv(t, u, v)])
+    }
+
+    fn local_normal_at(&self, _: Point) -> Vector {
+        self.normal
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        let mut b = BoundingBox::empty();
+        for p in [self.p1, self.p2, self.p3] {
+            b.add_point(p);
+        }
+        b
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn local_intersect(&self, ray: Ray) -> LocalHits {
+        self.triangle.local_intersect(ray)
+    }
+
+    fn local_intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> LocalHits {
+        self.triangle.local_intersect_within(ray, t_min, t_max)
+    }
+
+    fn local_normal_at(&self, point: Point) -> Vector {
+        let (u, v) = self.triangle.barycentric(point);
+        self.local_normal_at_uv(point, u, v)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        self.triangle.local_bounds()
+    }
+
+    fn local_normal_at_uv(&self, _: Point, u: f64, v: f64) -> Vector {
+        self.n2 * u + self.n3 * v + self.n1 * (1.0 - u - v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book_triangle() -> Triangle {
+        Triangle::new(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn constructing_a_triangle() {
+        let t = book_triangle();
+        assert_eq!(t.e1, Vector::new(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, Vector::new(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, Vector::new(0.0, 0.0, -1.0));
+        for p in [
+            Point::new(0.0, 0.5, 0.0),
+            Point::new(-0.5, 0.75, 0.0),
+            Point::new(0.5, 0.25, 0.0),
+        ] {
+            assert_eq!(t.local_normal_at(p), t.normal);
+        }
+    }
+
+    #[test]
+    fn rays_missing_a_triangle() {
+        let t = book_triangle();
+        let forward = Vector::new(0.0, 0.0, 1.0);
+        for ray in [
+            // parallel to the triangle
+            Ray::new(Point::new(0.0, -1.0, -2.0), Vector::new(0.0, 1.0, 0.0)),
+            // past each edge
+            Ray::new(Point::new(1.0, 1.0, -2.0), forward),
+            Ray::new(Point::new(-1.0, 1.0, -2.0), forward),
+            Ray::new(Point::new(0.0, -1.0, -2.0), forward),
+        ] {
+            assert!(t.local_intersect(ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_a_triangle() {
+        let t = book_triangle();
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 2.0);
+        // fine mesh detail is hit as well
+        let tiny = Triangle::new(
+            Point::new(0.0, 1e-3, 0.0),
+            Point::new(-1e-3, 0.0, 0.0),
+            Point::new(1e-3, 0.0, 0.0),
+        );
+        let r = Ray::new(Point::new(0.0, 5e-4, -2.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(tiny.local_intersect(r).len(), 1);
+    }
+
+    fn book_smooth_triangle() -> Object {
+        smooth_triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(-1.0, 0.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn intersections_with_a_smooth_triangle_store_uv() {
+        let tri = book_smooth_triangle();
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = tri.intersect(r);
+        assert!((xs[0].u - 0.45).abs() < EPSILON);
+        assert!((xs[0].v - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn smooth_triangles_interpolate_the_normal() {
+        let tri = book_smooth_triangle();
+        let i = crate::intersection::Intersection::with_uv(1.0, &tri, 0.45, 0.25);
+        let n = Vector::new(-0.5547, 0.83205, 0.0);
+        assert_eq!(tri.normal_at_hit(Point::new(0.0, 0.0, 0.0), &i), n);
+        let r = Ray::new(Point::new(-0.2, 0.3, -2.0), Vector::new(0.0, 0.0, 1.0));
+        let comps = i.prepare_computations(r);
+        assert_eq!(comps.normal, n);
+        // without a hit the weights are worked out from the point
+        assert_eq!(tri.normal_at(Point::new(-0.2, 0.3, 0.0)), n);
+    }
+
+    #[test]
+    fn triangles_work_as_objects() {
+        let t = triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        );
+        let r = Ray::new(Point::new(0.0, 0.5, -2.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(t.intersect(r).hit().unwrap().t, 2.0);
+        assert_eq!(
+            t.normal_at(Point::new(0.0, 0.5, 0.0)),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+        let b = t.bounds();
+        assert_eq!(b.min, Point::new(-1.0, 0.0, 0.0));
+        assert_eq!(b.max, Point::new(1.0, 1.0, 0.0));
+        let p = t.point_at_uv(0.25, 0.5);
+        assert_eq!(p, Point::new(0.25, 0.25, 0.0));
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Tuple {
     pub x: f64,
@@ -44,18 +47,41 @@ impl Tuple {
             self.x * other.y - self.y * other.x,
         )
     }
+
+    pub fn approx_eq(&self, other: &Tuple, epsilon: f64) -> bool {
+        (self.x - other.x).abs() < epsilon
+            && (self.y - other.y).abs() < epsilon
+            && (self.z - other.z).abs() < epsilon
+            && (self.w - other.w).abs() < epsilon
+    }
 }
 
 impl PartialEq for Tuple {
     fn eq(&self, other: &Tuple) -> bool {
-        (self.x - other.x).abs() < EPSILON
-            && (self.y - other.y).abs() < EPSILON
-            && (self.z - other.z).abs() < EPSILON
-            && (self.w - other.w).abs() < EPSILON
+        self.approx_eq(other, EPSILON)
     }
 }
 
-use std::ops::{Add, Div, Mul, Neg, Sub};
+use core::fmt;
+use core::ops::{Add, Div, Mul, Neg, Sub};
+
+fn write_components(f: &mut fmt::Formatter<'_>, components: &[f64]) -> fmt::Result {
+    let precision = f.precision().unwrap_or(5);
+    write!(f, "(")?;
+    for (i, c) in components.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{:.*}", precision, c)?;
+    }
+    write!(f, ")")
+}
+
+impl fmt::Display for Tuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_components(f, &[self.x, self.y, self.z, self.w])
+    }
+}
 
 impl Add for Tuple {
     type Output = Self;
@@ -126,7 +152,7 @@ impl Vector {
 
     pub fn reflect(self, normal: Vector) -> Vector {
         let inv = self;
-        (inv - normal * 2.0 * inv.dot(normal)).try_into().unwrap()
+        inv - normal * 2.0 * inv.dot(normal)
     }
 
     pub fn normalize(self) -> Vector {
@@ -134,11 +160,11 @@ impl Vector {
     }
 
     pub fn cross(self, rhs: Vector) -> Vector {
-        Tuple::from(self).cross(rhs.into()).try_into().unwrap()
+        Tuple::from(self).cross(rhs.into())
     }
 
     pub fn dot(self, rhs: Vector) -> f64 {
-        Tuple::from(self).dot(rhs.into()).try_into().unwrap()
+        Tuple::from(self).dot(rhs.into())
     }
 
     pub fn magnitude(self) -> f64 {
@@ -169,6 +195,13 @@ impl Neg for Vector {
     }
 }
 
+impl fmt::Display for Vector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vector")?;
+        write_components(f, &[self.0.x, self.0.y, self.0.z])
+    }
+}
+
 impl From<Vector> for Tuple {
     fn from(vector: Vector) -> Self {
         vector.0
@@ -199,6 +232,13 @@ impl Point {
     }
 }
 
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "point")?;
+        write_components(f, &[self.0.x, self.0.y, self.0.z])
+    }
+}
+
 impl From<Point> for Tuple {
     fn from(point: Point) -> Self {
         point.0
@@ -273,6 +313,7 @@ mod tests {
         assert_eq!(a - b, diff);
     }
     #[test]
+    #[allow(clippy::useless_conversion)]
     fn sub_vector_from_point() {
         let p = Tuple::from(Point::new(3., 2., 1.));
         let v = Tuple::from(Vector::new(5., 6., 7.));
@@ -355,4 +396,58 @@ mod tests {
         let r = v.reflect(n);
         assert_eq!(r, Vector::new(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn display_tuples() {
+        let t = Tuple::new(1.0, -2.5, 0.0, 1.0);
+        assert_eq!(format!("{:.2}", t), "(1.00, -2.50, 0.00, 1.00)");
+        assert_eq!(
+            format!("{:.1}", Point::new(1.0, 2.0, 3.0)),
+            "point(1.0, 2.0, 3.0)"
+        );
+        assert_eq!(
+            format!("{}", Vector::new(0.0, 1.0, 0.0)),
+            "vector(0.00000, 1.00000, 0.00000)"
+        );
+    }
+
+    #[test]
+    fn compare_tuples_with_custom_epsilon() {
+        let a = Tuple::new(1.0, 2.0, 3.0, 1.0);
+        let b = Tuple::new(1.001, 2.0, 3.0, 1.0);
+        assert_ne!(a, b);
+        assert!(a.approx_eq(&b, 0.01));
+        assert!(!a.approx_eq(&b, 0.0001));
+    }
+
+    mod properties {
+        use super::*;
+        use crate::strategies::{point, unit_vector, vector};
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn reflect_preserves_magnitude(v in vector(), n in unit_vector()) {
+                let r = v.reflect(n);
+                prop_assert!((r.magnitude() - v.magnitude()).abs() < 1e-9 * v.magnitude().max(1.0));
+            }
+
+            #[test]
+            fn cross_is_orthogonal(a in unit_vector(), b in unit_vector()) {
+                let c = a.cross(b);
+                prop_assert!(c.dot(a).abs() < 1e-9);
+                prop_assert!(c.dot(b).abs() < 1e-9);
+            }
+
+            #[test]
+            fn normalize_gives_unit_length(v in unit_vector()) {
+                prop_assert!((v.normalize().magnitude() - 1.0).abs() < 1e-12);
+            }
+
+            #[test]
+            fn point_minus_point_plus_point(a in point(), b in point()) {
+                prop_assert_eq!(Point::try_from(b.0 + (a - b).0).unwrap(), a);
+            }
+        }
+    }
 }
@@ -0,0 +1,204 @@
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::{Color, BLACK};
+use crate::world::World;
+
+const SCALE: [Color; 5] = [
+    Color::new(0.0, 0.0, 1.0),
+    Color::new(0.0, 1.0, 1.0),
+    Color::new(0.0, 1.0, 0.0),
+    Color::new(1.0, 1.0, 0.0),
+    Color::new(1.0, 0.0, 0.0),
+];
+
+// blue through cyan, green and yellow to red as t goes from 0 to 1
+pub fn false_color(t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0) * (SCALE.len() - 1) as f64;
+    let i = (t.floor() as usize).min(SCALE.len() - 2);
+    let f = t - i as f64;
+    SCALE[i] * (1.0 - f) + SCALE[i + 1] * f
+}
+
+// maps luminance in 0..max_luminance onto the false color scale, with
+// isolines drawn in black between each of the given number of bands
+pub fn false_color_canvas(canvas: &Canvas, max_luminance: f64, isolines: Option<usize>) -> Canvas {
+    let mut result = canvas.clone();
+    let levels = canvas
+        .pixels
+        .iter()
+        .map(|c| c.luminance() / max_luminance)
+        .collect::<Vec<_>>();
+    for (pixel, level) in result.pixels.iter_mut().zip(&levels) {
+        *pixel = false_color(*level);
+    }
+
+    if let Some(bands) = isolines {
+        let band = |i: usize| (levels[i].clamp(0.0, 1.0) * bands as f64).floor() as usize;
+        let (width, height) = (canvas.width as usize, canvas.height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let crosses = (x + 1 < width && band(i + 1) != band(i))
+                    || (y + 1 < height && band(i + width) != band(i));
+                if crosses {
+                    result.pixels[i] = Color::new(0.0, 0.0, 0.0);
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn render_false_color(
+    world: &World,
+    camera: &Camera,
+    max_luminance: f64,
+    isolines: Option<usize>,
+) -> Canvas {
+    false_color_canvas(&camera.render(world), max_luminance, isolines)
+}
+
+pub const FLIPPED: Color = Color::new(1.0, 0.0, 1.0);
+
+// diagnostic view for inverted geometry: surfaces seen from behind, where
+// the normal points away from the eye, are drawn in FLIPPED and the rest
+// in grey by how squarely they face the camera
+pub fn render_normal_flips(world: &World, camera: &Camera) -> Canvas {
+    let mut canvas = Canvas::new(camera.hsize() as isize, camera.vsize() as isize);
+    canvas.pixels = camera.render_with(|ray| {
+        match world
+            .intersect(ray)
+            .hit_with_tolerance(world.settings.epsilon)
+        {
+            Some(hit) => {
+                let comps = hit.prepare_computations(ray);
+                if comps.inside {
+                    FLIPPED
+                } else {
+                    let facing = comps.normal.dot(comps.eyev);
+                    Color::new(facing, facing, facing) * 0.8 + Color::new(0.2, 0.2, 0.2)
+                }
+            }
+            None => BLACK,
+        }
+    });
+    canvas
+}
+
+pub const OVER_BRIGHT: Color = Color::new(1.0, 0.0, 1.0);
+
+// indices of the objects whose material can reflect more light than it
+// receives, see Material::peak_response
+pub fn audit_materials(world: &World) -> Vec<usize> {
+    world
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(_, o)| !o.material.is_energy_conserving())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// validation view for tuning materials: the normal render with every
+// pixel brighter than 1 in any channel replaced by OVER_BRIGHT
+pub fn render_energy_audit(world: &World, camera: &Camera) -> Canvas {
+    let mut canvas = camera.render(world);
+    for c in &mut canvas.pixels {
+        if c.red > 1.0 || c.green > 1.0 || c.blue > 1.0 {
+            *c = OVER_BRIGHT;
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::PointLight;
+    use crate::material::Material;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, view_transform};
+    use crate::tuple::{Point, Vector};
+    use crate::world::default_world;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn false_color_scale_endpoints() {
+        assert_eq!(false_color(0.0), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(false_color(0.5), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(false_color(1.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(false_color(7.0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(false_color(0.125), Color::new(0.0, 0.5, 1.0));
+    }
+
+    #[test]
+    fn canvas_mapped_by_luminance() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(1, 0, Color::new(2.0, 2.0, 2.0));
+        let fc = false_color_canvas(&c, 2.0, None);
+        assert_eq!(fc.read_pixel(0, 0).unwrap(), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(fc.read_pixel(1, 0).unwrap(), Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn isolines_between_bands() {
+        let mut c = Canvas::new(4, 1);
+        for x in 0..4 {
+            let l = x as f64 / 4.0;
+            c.write_pixel(x, 0, Color::new(l, l, l));
+        }
+        let fc = false_color_canvas(&c, 1.0, Some(2));
+        let black = Color::new(0.0, 0.0, 0.0);
+        assert_ne!(fc.read_pixel(0, 0).unwrap(), black);
+        assert_eq!(fc.read_pixel(1, 0).unwrap(), black);
+        assert_ne!(fc.read_pixel(2, 0).unwrap(), black);
+    }
+
+    #[test]
+    fn inside_hits_are_flagged() {
+        let mut camera = Camera::new(11, 11, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let mut w = World::new();
+        w.objects.push(sphere());
+        let outside = render_normal_flips(&w, &camera);
+        assert!((outside.read_pixel(5, 5).unwrap().red - 1.0).abs() < 1e-9);
+        assert_eq!(outside.read_pixel(0, 0).unwrap(), BLACK);
+        // the eye sits inside the big sphere, so every pixel sees its back
+        w.objects[0] = sphere().set_transform(scaling(10.0, 10.0, 10.0));
+        let inside = render_normal_flips(&w, &camera);
+        assert!(inside.pixels.iter().all(|c| *c == FLIPPED));
+    }
+
+    #[test]
+    fn over_bright_highlights_are_flagged() {
+        let mut camera = Camera::new(21, 21, PI / 3.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        let mut w = default_world();
+        w.lights[0] =
+            PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        w.objects[0].material = Material::default();
+        assert_eq!(audit_materials(&w), vec![0, 1]);
+        let audit = render_energy_audit(&w, &camera);
+        // the highlight faces the camera in the middle
+        assert_eq!(audit.read_pixel(10, 10).unwrap(), OVER_BRIGHT);
+        assert_ne!(audit.read_pixel(10, 2).unwrap(), OVER_BRIGHT);
+
+        w.objects[0].material = Material {
+            diffuse: 0.6,
+            specular: 0.3,
+            ..Default::default()
+        };
+        w.objects.truncate(1);
+        assert!(audit_materials(&w).is_empty());
+        let audit = render_energy_audit(&w, &camera);
+        assert!(!audit.pixels.contains(&OVER_BRIGHT));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::color::Color;
+use crate::material::Material;
+#[cfg(not(feature = "std"))]
+use crate::no_std::*;
+use crate::object::Object;
+use crate::sphere::sphere;
+use crate::transformations::{scaling, translation};
+use crate::tuple::Vector;
+use core::f64::consts::PI;
+
+// ripples are faked in shading only: the surface stays where it is and
+// just its normal is tilted by the slope of a sum of travelling sines
+// laid out over world x and z
+
+const GRAVITY: f64 = 9.81;
+pub const WATER_INDEX: f64 = 1.33;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wave {
+    // unit direction of travel in the xz plane
+    pub direction: (f64, f64),
+    pub wavelength: f64,
+    pub amplitude: f64,
+}
+
+impl Wave {
+    // angle is measured in the xz plane from +x towards +z
+    pub fn new(angle: f64, wavelength: f64, amplitude: f64) -> Wave {
+        let (sin, cos) = angle.sin_cos();
+        Wave {
+            direction: (cos, sin),
+            wavelength,
+            amplitude,
+        }
+    }
+
+    fn number(&self) -> f64 {
+        2.0 * PI / self.wavelength
+    }
+
+    // deep water waves, longer ones travel faster
+    fn frequency(&self) -> f64 {
+        (GRAVITY * self.number()).sqrt()
+    }
+
+    fn phase(&self, x: f64, z: f64, time: f64) -> f64 {
+        let (dx, dz) = self.direction;
+        self.number() * (dx * x + dz * z) - self.frequency() * time
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Waves {
+    pub waves: Vec<Wave>,
+    pub time: f64,
+}
+
+impl Waves {
+    pub fn new() -> Waves {
+        Waves::default()
+    }
+
+    pub fn with(mut self, wave: Wave) -> Waves {
+        self.waves.push(wave);
+        self
+    }
+
+    pub fn at_time(mut self, time: f64) -> Waves {
+        self.time = time;
+        self
+    }
+
+    pub fn height(&self, x: f64, z: f64) -> f64 {
+        self.waves
+            .iter()
+            .map(|w| w.amplitude * w.phase(x, z, self.time).sin())
+            .sum()
+    }
+
+    // partial derivatives of height along x and z
+    pub fn slope(&self, x: f64, z: f64) -> (f64, f64) {
+        self.waves.iter().fold((0.0, 0.0), |(sx, sz), w| {
+            let d = w.amplitude * w.number() * w.phase(x, z, self.time).cos();
+            (sx + d * w.direction.0, sz + d * w.direction.1)
+        })
+    }
+
+    // tilts a world space normal by the slope at its point, scaled by how
+    // much the normal faces up or down so the sides of a slab are left
+    // alone and its underside matches its top
+    pub fn perturb(&self, x: f64, z: f64, normal: Vector) -> Vector {
+        let (sx, sz) = self.slope(x, z);
+        let up = normal.0.y;
+        (normal + Vector::new(-sx * up, 0.0, -sz * up)).normalize()
+    }
+}
+
+// a wide flat slab of water whose top is at y = 0 over the `width` square
+// around the origin, `depth` deep in the middle
+pub fn water_surface(width: f64, depth: f64, waves: Waves) -> Object {
+    // the ellipsoid is made a hundred times wider than asked for so its
+    // top is still flat at the edges
+    let radius = 100.0 * width;
+    sphere()
+        .set_transform(translation(0.0, -depth, 0.0) * scaling(radius, depth, radius))
+        .set_material(Material {
+            color: Color::new(0.1, 0.3, 0.4),
+            ambient: 0.0,
+            diffuse: 0.1,
+            transparency: 0.9,
+            refractive_index: WATER_INDEX,
+            ..Default::default()
+        })
+        .set_waves(waves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tuple::Point;
+
+    #[test]
+    fn calm_water_keeps_its_normal() {
+        let s = water_surface(10.0, 2.0, Waves::new());
+        let n = s.normal_at(Point::new(1.0, 0.0, 3.0));
+        assert_eq!(n, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn slope_is_derivative_of_height() {
+        let waves = Waves::new()
+            .with(Wave::new(0.3, 2.0, 0.05))
+            .with(Wave::new(2.0, 0.7, 0.02))
+            .at_time(1.5);
+        let (x, z, h) = (0.4, -1.3, 1e-6);
+        let (sx, sz) = waves.slope(x, z);
+        let dx = (waves.height(x + h, z) - waves.height(x - h, z)) / (2.0 * h);
+        let dz = (waves.height(x, z + h) - waves.height(x, z - h)) / (2.0 * h);
+        assert!((sx - dx).abs() < 1e-6);
+        assert!((sz - dz).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ripples_move_with_time() {
+        let waves = Waves::new().with(Wave::new(0.0, 1.0, 0.1));
+        let s = water_surface(10.0, 2.0, waves.clone());
+        let later = water_surface(10.0, 2.0, waves.at_time(0.1));
+        let p = Point::new(0.1, 0.0, 0.0);
+        let n = s.normal_at(p);
+        assert!((n.magnitude() - 1.0).abs() < 1e-9);
+        assert!(n.0.x.abs() > 0.1);
+        assert_ne!(n, later.normal_at(p));
+        // the underside tilts the other way, as the flip side of the same
+        // surface
+        let under = s.normal_at(Point::new(0.1, -4.0, 0.0));
+        assert!(under.0.y < 0.0 && (under.0.x + n.0.x).abs() < 1e-3);
+    }
+}
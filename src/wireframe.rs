@@ -0,0 +1,601 @@
+use crate::bounds::BoundingBox;
+use crate::camera::Camera;
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::object::{Geometry, Object};
+use crate::shape::Shape;
+use crate::tuple::{Point, Vector};
+use crate::visualize::false_color;
+use crate::world::World;
+use std::f64::consts::PI;
+use std::fmt::Write;
+
+const SEGMENTS: usize = 64;
+
+// points on a circle in object space given its center and two orthogonal
+// radius vectors, closed so the last point repeats the first
+fn circle(center: Point, a: Vector, b: Vector) -> Vec<Point> {
+    (0..=SEGMENTS)
+        .map(|i| {
+            let angle = i as f64 / SEGMENTS as f64 * 2.0 * PI;
+            center + a * angle.cos() + b * angle.sin()
+        })
+        .collect()
+}
+
+// the silhouette of a unit sphere seen from eye (in object space) is the
+// circle of tangent points p where p.e = 1
+fn silhouette(eye: Point) -> Option<Vec<Point>> {
+    let e = eye - Point::zero();
+    let d2 = e.dot(e);
+    if d2 <= 1.0 {
+        return None;
+    }
+    let axis = e.normalize();
+    let helper = if axis.0.x.abs() > 0.9 {
+        Vector::new(0.0, 1.0, 0.0)
+    } else {
+        Vector::new(1.0, 0.0, 0.0)
+    };
+    let radius = (1.0 - 1.0 / d2).sqrt();
+    let a = helper.cross(axis).normalize() * radius;
+    let b = axis.cross(a);
+    Some(circle(Point::zero() + e / d2, a, b))
+}
+
+// the two lines along the side of a unit cylinder facing eye (in object
+// space), where the rays from eye graze it
+fn cylinder_silhouette(eye: Point, minimum: f64, maximum: f64) -> Vec<Vec<Point>> {
+    let d2 = eye.0.x * eye.0.x + eye.0.z * eye.0.z;
+    if d2 <= 1.0 {
+        return vec![];
+    }
+    let (minimum, maximum) = (minimum.max(-FAR), maximum.min(FAR));
+    let along = (1.0 - 1.0 / d2).sqrt() / d2.sqrt();
+    [1.0, -1.0]
+        .map(|side| {
+            let x = eye.0.x / d2 - side * eye.0.z * along;
+            let z = eye.0.z / d2 + side * eye.0.x * along;
+            vec![Point::new(x, minimum, z), Point::new(x, maximum, z)]
+        })
+        .to_vec()
+}
+
+// how far the open ends of infinite cylinders are drawn
+const FAR: f64 = 1e4;
+
+fn box_edges(bounds: &BoundingBox) -> Vec<Vec<Point>> {
+    bounds.edges().iter().map(|(a, b)| vec![*a, *b]).collect()
+}
+
+// object space lines that show an object's shape and how it is turned
+// and stretched: great circles around each axis of a sphere, the edges of
+// boxes and triangles and the rims of cylinders. shapes from outside the
+// crate are drawn as their bounding box
+fn feature_lines(object: &Object) -> Vec<Vec<Point>> {
+    let x = Vector::new(1.0, 0.0, 0.0);
+    let y = Vector::new(0.0, 1.0, 0.0);
+    let z = Vector::new(0.0, 0.0, 1.0);
+    match object.geometry {
+        Geometry::Sphere => vec![
+            circle(Point::zero(), x, z),
+            circle(Point::zero(), x, y),
+            circle(Point::zero(), y, z),
+        ],
+        Geometry::Cylinder {
+            minimum, maximum, ..
+        } => [minimum, maximum]
+            .into_iter()
+            .filter(|y| y.is_finite())
+            .map(|y| circle(Point::new(0.0, y, 0.0), x, z))
+            .collect(),
+        Geometry::Triangle(t) => vec![vec![t.p1, t.p2, t.p3, t.p1]],
+        Geometry::SmoothTriangle(t) => {
+            let t = t.triangle;
+            vec![vec![t.p1, t.p2, t.p3, t.p1]]
+        }
+        Geometry::Slab | Geometry::Custom(_) => box_edges(&object.geometry.local_bounds()),
+    }
+}
+
+// object space outline of where the surface turns away from eye, for
+// curved shapes. flat ones have it on their edges already
+fn silhouettes(object: &Object, eye: Point) -> Vec<Vec<Point>> {
+    let local_eye = Point::try_from(&object.inv_transform * eye).unwrap();
+    match object.geometry {
+        Geometry::Sphere => silhouette(local_eye).into_iter().collect(),
+        Geometry::Cylinder {
+            minimum, maximum, ..
+        } => cylinder_silhouette(local_eye, minimum, maximum),
+        _ => vec![],
+    }
+}
+
+fn to_world(object: &Object, lines: Vec<Vec<Point>>) -> Vec<Vec<Point>> {
+    lines
+        .into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|p| Point::try_from(&object.transform * p).unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+// world space outlines of an object seen from eye, its feature lines
+// followed by its silhouette
+pub fn outlines(object: &Object, eye: Point) -> Vec<Vec<Point>> {
+    let mut lines = feature_lines(object);
+    lines.extend(silhouettes(object, eye));
+    to_world(object, lines)
+}
+
+// every object a wireframe shows: loose ones, those in groups and csgs,
+// and the level of detail picked for eye
+fn drawn_objects(world: &World, eye: Point) -> Vec<&Object> {
+    let mut objects = world.objects.iter().collect::<Vec<_>>();
+    for group in &world.groups {
+        objects.extend(group.objects());
+    }
+    for csg in &world.csgs {
+        objects.extend(csg.objects());
+    }
+    for lod in &world.lods {
+        let b = lod.bounds();
+        let nearest = Point::new(
+            eye.0.x.clamp(b.min.0.x, b.max.0.x),
+            eye.0.y.clamp(b.min.0.y, b.max.0.y),
+            eye.0.z.clamp(b.min.0.z, b.max.0.z),
+        );
+        if let Some(level) = lod.level_at((eye - nearest).magnitude()) {
+            objects.extend(&level.objects);
+        }
+    }
+    objects
+}
+
+// splits a world space polyline into projected runs, breaking wherever a
+// point falls behind the camera
+pub fn project_polyline(camera: &Camera, points: &[Point]) -> Vec<Vec<(f64, f64)>> {
+    let mut runs = vec![];
+    let mut current = vec![];
+    for p in points {
+        match camera.project(*p) {
+            Some(xy) => current.push(xy),
+            None => {
+                if current.len() > 1 {
+                    runs.push(current);
+                }
+                current = vec![];
+            }
+        }
+    }
+    if current.len() > 1 {
+        runs.push(current);
+    }
+    runs
+}
+
+fn svg_color(c: Color) -> String {
+    let [r, g, b] = c.to_rgb8();
+    format!("rgb({},{},{})", r, g, b)
+}
+
+pub fn to_svg(world: &World, camera: &Camera) -> String {
+    let eye = camera.position();
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = camera.hsize(),
+        h = camera.vsize()
+    )
+    .unwrap();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+    for object in drawn_objects(world, eye) {
+        let stroke = svg_color(object.material.color * 0.8);
+        for outline in outlines(object, eye) {
+            for run in project_polyline(camera, &outline) {
+                let points = run
+                    .iter()
+                    .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                writeln!(
+                    svg,
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1\"/>",
+                    points, stroke
+                )
+                .unwrap();
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// id and distance of the first surface behind every pixel
+fn visibility_buffer(world: &World, camera: &Camera) -> Vec<Option<(u32, f64)>> {
+    camera.render_with(|ray| {
+        world
+            .intersect(ray)
+            .hit_with_tolerance(world.settings.epsilon)
+            .map(|hit| (hit.object.id(), hit.t * ray.direction.magnitude()))
+    })
+}
+
+// technical illustration style render: silhouettes come from changes in the
+// visible object between neighboring pixels, and the outline curves are only
+// drawn where they are the nearest surface
+pub fn render_hidden_line(
+    world: &World,
+    camera: &Camera,
+    line: Color,
+    background: Color,
+) -> Canvas {
+    let width = camera.hsize() as usize;
+    let height = camera.vsize() as usize;
+    let buffer = visibility_buffer(world, camera);
+    let id_at = |x: usize, y: usize| buffer[y * width + x].map(|(id, _)| id);
+
+    let mut canvas = Canvas::new(width as isize, height as isize);
+    canvas.fill(background);
+
+    for y in 0..height {
+        for x in 0..width {
+            let id = id_at(x, y);
+            let edge = (x + 1 < width && id_at(x + 1, y) != id)
+                || (y + 1 < height && id_at(x, y + 1) != id);
+            if edge {
+                canvas.write_pixel(x as isize, y as isize, line);
+            }
+        }
+    }
+
+    let eye = camera.position();
+    for object in drawn_objects(world, eye) {
+        // the silhouette is already covered by the id edges
+        for outline in &to_world(object, feature_lines(object)) {
+            for segment in outline.windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                let steps = match (camera.project(a), camera.project(b)) {
+                    (Some(pa), Some(pb)) => {
+                        ((pb.0 - pa.0).hypot(pb.1 - pa.1) * 2.0).ceil().max(1.0)
+                    }
+                    _ => continue,
+                };
+                for i in 0..=steps as usize {
+                    let p = a + (b - a) * (i as f64 / steps);
+                    let Some((px, py)) = camera.project(p) else {
+                        continue;
+                    };
+                    if px < 0.0 || py < 0.0 || px >= width as f64 || py >= height as f64 {
+                        continue;
+                    }
+                    let (x, y) = (px as usize, py as usize);
+                    let depth = (p - eye).magnitude();
+                    if let Some((id, visible)) = buffer[y * width + x] {
+                        if id == object.id() && depth <= visible * 1.01 + 1e-3 {
+                            canvas.write_pixel(x as isize, y as isize, line);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    canvas
+}
+
+// draws a world space line, cut off where it goes behind the camera
+pub fn draw_line(canvas: &mut Canvas, camera: &Camera, a: Point, b: Point, color: Color) {
+    let (a, b) = match (camera.project(a), camera.project(b)) {
+        (Some(_), Some(_)) => (a, b),
+        (None, None) => return,
+        // walk the hidden end towards the visible one until it shows
+        (Some(_), None) => (a, visible_end(camera, a, b)),
+        (None, Some(_)) => (visible_end(camera, b, a), b),
+    };
+    let (Some(pa), Some(pb)) = (camera.project(a), camera.project(b)) else {
+        return;
+    };
+    // near the camera plane points project far off screen, so only walk
+    // the part of the line inside the canvas
+    let Some((pa, pb)) = clip_to_canvas(canvas, pa, pb) else {
+        return;
+    };
+    let steps = (pb.0 - pa.0).abs().max((pb.1 - pa.1).abs()).ceil().max(1.0);
+    for i in 0..=steps as usize {
+        let t = i as f64 / steps;
+        let x = pa.0 + (pb.0 - pa.0) * t;
+        let y = pa.1 + (pb.1 - pa.1) * t;
+        canvas.write_pixel(x.floor() as isize, y.floor() as isize, color);
+    }
+}
+
+// liang-barsky against the canvas rectangle
+fn clip_to_canvas(
+    canvas: &Canvas,
+    a: (f64, f64),
+    b: (f64, f64),
+) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, a.0),
+        (dx, canvas.width as f64 - a.0),
+        (-dy, a.1),
+        (dy, canvas.height as f64 - a.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let r = q / p;
+        if p < 0.0 {
+            t0 = t0.max(r);
+        } else {
+            t1 = t1.min(r);
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    Some((
+        (a.0 + dx * t0, a.1 + dy * t0),
+        (a.0 + dx * t1, a.1 + dy * t1),
+    ))
+}
+
+// the point furthest from `shown` towards `hidden` that still projects
+fn visible_end(camera: &Camera, shown: Point, hidden: Point) -> Point {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..32 {
+        let mid = (lo + hi) / 2.0;
+        if camera.project(shown + (hidden - shown) * mid).is_some() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    shown + (hidden - shown) * lo
+}
+
+pub fn draw_box(canvas: &mut Canvas, camera: &Camera, bounds: &BoundingBox, color: Color) {
+    if bounds.is_empty() {
+        return;
+    }
+    for (a, b) in bounds.edges() {
+        draw_line(canvas, camera, a, b, color);
+    }
+}
+
+// debug overlay on a finished render: every object's box in `color`, and
+// when the world has a bvh, its nodes colored from blue at the root to red
+// at the deepest leaves
+pub fn overlay_bounds(canvas: &mut Canvas, world: &World, camera: &Camera, color: Color) {
+    if let Some(bvh) = &world.bvh {
+        let nodes = bvh.node_bounds();
+        let deepest = nodes.iter().map(|(_, d)| *d).max().unwrap_or(0).max(1);
+        for (bounds, depth) in nodes {
+            draw_box(
+                canvas,
+                camera,
+                &bounds,
+                false_color(depth as f64 / deepest as f64),
+            );
+        }
+    }
+    for object in &world.objects {
+        draw_box(canvas, camera, &object.bounds(), color);
+    }
+    for lod in &world.lods {
+        draw_box(canvas, camera, &lod.bounds(), color);
+    }
+    for group in &world.groups {
+        draw_box(canvas, camera, &group.bounds(), color);
+    }
+    for csg in &world.csgs {
+        draw_box(canvas, camera, &csg.bounds(), color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csg::{Csg, CsgOp};
+    use crate::cylinder::cylinder;
+    use crate::group::Group;
+    use crate::lod::LodGroup;
+    use crate::slab::slab;
+    use crate::sphere::sphere;
+    use crate::transformations::{scaling, translation, view_transform};
+    use crate::triangle::triangle;
+    use crate::world::default_world;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(100, 100, PI / 2.0);
+        camera.set_transform(view_transform(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        camera
+    }
+
+    #[test]
+    fn silhouette_points_are_tangent() {
+        let eye = Point::new(0.0, 0.0, -5.0);
+        let points = silhouette(eye).unwrap();
+        for p in points {
+            let on_sphere = p - Point::zero();
+            assert!((on_sphere.magnitude() - 1.0).abs() < 1e-9);
+            assert!(on_sphere.dot(eye - p).abs() < 1e-9);
+        }
+        assert!(silhouette(Point::new(0.0, 0.0, 0.5)).is_none());
+    }
+
+    #[test]
+    fn outlines_follow_sphere_transform() {
+        let s = sphere().set_transform(translation(1.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0));
+        let lines = outlines(&s, Point::new(0.0, 0.0, -10.0));
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0][0], Point::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn outlines_follow_the_geometry() {
+        let eye = Point::new(0.0, 0.0, -10.0);
+        assert_eq!(outlines(&slab(2.0, 2.0, 2.0), eye).len(), 12);
+        let t = triangle(
+            Point::new(0.0, 1.0, 0.0),
+            Point::new(-1.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+        );
+        assert_eq!(
+            outlines(&t, eye),
+            vec![vec![
+                Point::new(0.0, 1.0, 0.0),
+                Point::new(-1.0, 0.0, 0.0),
+                Point::new(1.0, 0.0, 0.0),
+                Point::new(0.0, 1.0, 0.0),
+            ]]
+        );
+        // two rims and the two sides grazed by rays from the eye
+        let lines = outlines(&cylinder(-1.0, 2.0, true), eye);
+        assert_eq!(lines.len(), 4);
+        for side in &lines[2..] {
+            let p = side[0];
+            assert!(((p - Point::new(0.0, p.0.y, 0.0)).magnitude() - 1.0).abs() < 1e-9);
+            let normal = Vector::new(p.0.x, 0.0, p.0.z);
+            assert!(normal.dot(eye - p).abs() < 1e-9);
+            assert_eq!((side[0].0.y, side[1].0.y), (-1.0, 2.0));
+        }
+        // open ends have no rim
+        assert_eq!(
+            outlines(&cylinder(f64::NEG_INFINITY, 2.0, false), eye).len(),
+            3
+        );
+    }
+
+    #[test]
+    fn wireframes_include_groups_csgs_and_lods() {
+        let mut w = World::new();
+        w.add_group(
+            Group::new("pair")
+                .add_child(sphere())
+                .add_child(slab(1.0, 1.0, 1.0)),
+        );
+        w.csgs
+            .push(Csg::new(CsgOp::Difference, sphere(), slab(1.0, 1.0, 1.0)));
+        w.lods.push(
+            LodGroup::new()
+                .add_level(1.0, vec![sphere(), sphere()])
+                .add_level(f64::INFINITY, vec![slab(1.0, 1.0, 1.0)]),
+        );
+        // a sphere has four outlines and a box twelve edges, the lod is far
+        // enough away to be the single box
+        let svg = to_svg(&w, &camera());
+        assert_eq!(svg.matches("<polyline").count(), 2 * (4 + 12) + 12);
+    }
+
+    #[test]
+    fn polylines_break_behind_camera() {
+        let c = camera();
+        let points = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -10.0),
+            Point::new(1.0, 0.0, 1.0),
+            Point::new(2.0, 0.0, 1.0),
+        ];
+        let runs = project_polyline(&c, &points);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0][0], (50.0, 50.0));
+    }
+
+    #[test]
+    fn hidden_line_render_draws_silhouettes_only_where_visible() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut w = World::new();
+        w.objects.push(sphere());
+        let image = render_hidden_line(&w, &camera(), black, white);
+        // the left and right silhouette of the sphere are on the center row
+        let row: Vec<Color> = (0..100).map(|x| image.read_pixel(x, 50).unwrap()).collect();
+        let first = row.iter().position(|c| *c == black).unwrap();
+        let last = row.iter().rposition(|c| *c == black).unwrap();
+        assert!((38..=42).contains(&first));
+        assert!((57..=61).contains(&last));
+        // corners stay empty
+        assert_eq!(image.read_pixel(0, 0).unwrap(), white);
+    }
+
+    #[test]
+    fn hidden_outline_is_not_drawn() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut w = World::new();
+        w.objects.push(sphere());
+        let image = render_hidden_line(&w, &camera(), black, white);
+        // the equator faces the camera in front, the point straight up above the
+        // middle of the sphere is on the y/z great circle, so the front half of it
+        // draws a vertical line through the center
+        assert_eq!(image.read_pixel(50, 45).unwrap(), black);
+
+        // a bigger sphere in front hides everything behind it
+        let front = || sphere().set_transform(translation(0.0, 0.0, -2.5) * scaling(1.5, 1.5, 1.5));
+        w.objects.push(front());
+        let both = render_hidden_line(&w, &camera(), black, white);
+        let mut alone = World::new();
+        alone.objects.push(front());
+        let expected = render_hidden_line(&alone, &camera(), black, white);
+        assert_eq!(both.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn svg_contains_a_polyline_per_outline() {
+        let svg = to_svg(&default_world(), &camera());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 8);
+    }
+
+    #[test]
+    fn bounds_overlay_outlines_boxes() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut w = World::new();
+        w.objects.push(sphere());
+        let mut canvas = Canvas::new(100, 100);
+        overlay_bounds(&mut canvas, &w, &camera(), red);
+        // front edges at x = -1 and 1, four units from the eye
+        let row: Vec<Color> = (0..100)
+            .map(|x| canvas.read_pixel(x, 50).unwrap())
+            .collect();
+        assert!((36..=38).any(|x| row[x] == red));
+        assert!((61..=63).any(|x| row[x] == red));
+        assert_eq!(row[50], Color::new(0.0, 0.0, 0.0));
+
+        // boxes around the eye are clipped rather than dropped, and bvh
+        // nodes are drawn too
+        w.objects
+            .push(sphere().set_transform(scaling(10.0, 10.0, 10.0)));
+        for i in 0..6 {
+            w.objects.push(
+                sphere()
+                    .set_transform(translation(i as f64 - 3.0, 2.0, 0.0) * scaling(0.2, 0.2, 0.2)),
+            );
+        }
+        w.build_bvh();
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut canvas = Canvas::new(100, 100);
+        overlay_bounds(&mut canvas, &w, &camera(), white);
+        // the leaves are the deepest nodes
+        assert!(canvas.pixels.contains(&red));
+        let border = (0..100).flat_map(|i| [(i, 0), (i, 99), (0, i), (99, i)]);
+        assert!(border
+            .map(|(x, y)| canvas.read_pixel(x, y).unwrap())
+            .any(|c| c == white));
+    }
+}
@@ -1,15 +1,81 @@
+use crate::batch::SphereBatch;
+use crate::bounds::BoundingBox;
+use crate::bvh::Bvh;
 use crate::color::{Color, BLACK};
+use crate::csg::Csg;
+use crate::decal::Decal;
+use crate::group::Group;
 use crate::intersection::{Computations, Intersections};
-use crate::light::PointLight;
-use crate::material::{lighting, Material};
+use crate::light::{Light, PointLight};
+use crate::lod::LodGroup;
+use crate::material::{lighting_from, Material, CHANNEL_WAVELENGTHS};
+use crate::matrix::Matrix4;
+use crate::object::Object;
+use crate::portal::Portal;
+use crate::profile::span;
 use crate::ray::Ray;
-use crate::sphere::Sphere;
+use crate::settings::RenderSettings;
+use crate::sphere::sphere;
 use crate::transformations;
-use crate::tuple::Point;
+use crate::tuple::{Point, Vector};
 
+#[derive(Debug, Clone, Copy)]
+pub struct RaycastHit<'a> {
+    pub point: Point,
+    pub normal: Vector,
+    pub distance: f64,
+    pub object: &'a Object,
+}
+
+#[derive(Debug)]
 pub struct World {
-    pub objects: Vec<Sphere>,
-    pub lights: Vec<PointLight>,
+    pub objects: Vec<Object>,
+    pub lights: Vec<Light>,
+    pub decals: Vec<Decal>,
+    pub portals: Vec<Portal>,
+    // models drawn at a level of detail picked per ray
+    pub lods: Vec<LodGroup>,
+    // models kept together, intersected through their own bounds
+    // hierarchy, see Group::divide
+    pub groups: Vec<Group>,
+    // shapes combined from objects, groups and other csgs
+    pub csgs: Vec<Csg>,
+    // optional fast path for many round spheres, see build_sphere_batch
+    pub sphere_batch: Option<SphereBatch>,
+    // optional hierarchy over objects, see build_bvh
+    pub bvh: Option<Bvh>,
+    pub settings: RenderSettings,
+    // objects moved through set_object_transform since the last refit
+    changed: Vec<usize>,
+    // ids of the debug objects added by show_guides
+    pub(crate) guides: Vec<u32>,
+}
+
+// a copy of the world is a snapshot of the same scene, its objects keep
+// their ids so hits in it can be matched up with the original
+impl Clone for World {
+    fn clone(&self) -> World {
+        World {
+            objects: self.objects.iter().map(|o| o.snapshot()).collect(),
+            lights: self.lights.clone(),
+            decals: self.decals.clone(),
+            portals: self.portals.clone(),
+            lods: self.lods.clone(),
+            groups: self.groups.clone(),
+            csgs: self.csgs.clone(),
+            sphere_batch: self.sphere_batch.clone(),
+            bvh: self.bvh.clone(),
+            settings: self.settings,
+            changed: self.changed.clone(),
+            guides: self.guides.clone(),
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
 }
 
 impl World {
@@ -17,52 +83,516 @@ impl World {
         World {
             objects: vec![],
             lights: vec![],
+            decals: vec![],
+            portals: vec![],
+            lods: vec![],
+            groups: vec![],
+            csgs: vec![],
+            sphere_batch: None,
+            bvh: None,
+            settings: Default::default(),
+            changed: vec![],
+            guides: vec![],
         }
     }
 
-    pub fn intersect(&self, ray: Ray) -> Intersections {
-        let mut intersections = Intersections::new();
+    // adds a model as a whole, divide big ones first so rays don't test
+    // every triangle
+    pub fn add_group(&mut self, group: Group) {
+        self.groups.push(group);
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        let mut bounds = BoundingBox::empty();
         for object in &self.objects {
-            intersections.concat(object.intersect(ray));
+            bounds.merge(&object.bounds());
+        }
+        for lod in &self.lods {
+            bounds.merge(&lod.bounds());
         }
+        for group in &self.groups {
+            bounds.merge(&group.bounds());
+        }
+        for csg in &self.csgs {
+            bounds.merge(&csg.bounds());
+        }
+        bounds
+    }
+
+    // packs round spheres into struct of arrays storage used by intersect,
+    // call again after adding or changing objects
+    pub fn build_sphere_batch(&mut self) {
+        let _span = span!(INFO, "build_sphere_batch", objects = self.objects.len());
+        self.sphere_batch = Some(SphereBatch::new(&self.objects));
+    }
+
+    pub fn build_bvh(&mut self) {
+        let _span = span!(INFO, "build_bvh", objects = self.objects.len());
+        self.bvh = Some(Bvh::new(&self.objects));
+    }
+
+    // moves an object and remembers it so refit can update the acceleration
+    // structures, until then intersect falls back to testing every object
+    pub fn set_object_transform(&mut self, index: usize, transform: Matrix4) {
+        self.objects[index].update_transform(transform);
+        if !self.changed.contains(&index) {
+            self.changed.push(index);
+        }
+    }
+
+    pub fn changed_objects(&self) -> &[usize] {
+        &self.changed
+    }
+
+    // brings the bvh and sphere batch up to date with moved objects, the
+    // bvh keeps its shape and only has its bounds recomputed
+    pub fn refit(&mut self) {
+        if self.changed.is_empty() {
+            return;
+        }
+        let _span = span!(INFO, "refit", changed = self.changed.len());
+        if let Some(bvh) = &mut self.bvh {
+            if bvh.matches(&self.objects) {
+                bvh.refit(&self.objects);
+            } else {
+                *bvh = Bvh::new(&self.objects);
+            }
+        }
+        if self.sphere_batch.is_some() {
+            self.sphere_batch = Some(SphereBatch::new(&self.objects));
+        }
+        self.changed.clear();
+    }
+
+    pub fn intersect(&self, ray: Ray) -> Intersections<'_> {
+        let mut intersections = Intersections::new();
+        self.intersect_objects(ray, &mut intersections);
+        self.intersect_models(ray, &mut intersections);
+        intersections.sort();
+        intersections
+    }
+
+    // appends the intersections with the objects, through the bvh or the
+    // sphere batch when one is up to date. prepare makes sure they were
+    // built from these objects, here only their number is checked
+    fn intersect_objects<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        let fresh = self.changed.is_empty();
+        match (&self.bvh, &self.sphere_batch) {
+            (Some(bvh), _) if fresh && bvh.object_count() == self.objects.len() => {
+                bvh.intersect(&self.objects, ray, out);
+            }
+            (_, Some(batch)) if fresh && batch.object_count() == self.objects.len() => {
+                batch.intersect(&self.objects, ray, out);
+                for &i in &batch.generic {
+                    out.concat(self.objects[i].intersect(ray));
+                }
+            }
+            _ => {
+                for object in &self.objects {
+                    out.concat(object.intersect(ray));
+                }
+            }
+        }
+    }
+
+    // appends the intersections with the lod groups, groups and csgs
+    fn intersect_models<'a>(&'a self, ray: Ray, out: &mut Intersections<'a>) {
+        for lod in &self.lods {
+            lod.intersect(ray, out);
+        }
+        for group in &self.groups {
+            group.intersect(ray, out);
+        }
+        for csg in &self.csgs {
+            csg.intersect(ray, out);
+        }
+    }
+
+    // only intersections with t_min <= t <= t_max, sorted. objects get
+    // the range passed down so they can skip work on hits outside it,
+    // models are intersected whole and filtered
+    pub fn intersect_within(&self, ray: Ray, t_min: f64, t_max: f64) -> Intersections<'_> {
+        let mut intersections = Intersections::new();
+        let within = |t: f64| t >= t_min && t <= t_max;
+        let fresh = self.changed.is_empty();
+        match (&self.bvh, &self.sphere_batch) {
+            (Some(bvh), _) if fresh && bvh.object_count() == self.objects.len() => {
+                bvh.intersect_within(&self.objects, ray, t_min, t_max, &mut intersections);
+            }
+            (_, Some(batch)) if fresh && batch.object_count() == self.objects.len() => {
+                let mut batched = Intersections::new();
+                batch.intersect(&self.objects, ray, &mut batched);
+                for i in batched.into_iter().filter(|i| within(i.t)) {
+                    intersections.push(i);
+                }
+                for &i in &batch.generic {
+                    intersections.concat(self.objects[i].intersect_within(ray, t_min, t_max));
+                }
+            }
+            _ => {
+                for object in &self.objects {
+                    intersections.concat(object.intersect_within(ray, t_min, t_max));
+                }
+            }
+        }
+        let mut models = Intersections::new();
+        self.intersect_models(ray, &mut models);
+        for i in models.into_iter().filter(|i| within(i.t)) {
+            intersections.push(i);
+        }
+        intersections.sort();
+        intersections
+    }
+
+    pub fn intersect_excluding(&self, ray: Ray, excluded: &Object) -> Intersections<'_> {
+        let mut all = Intersections::new();
+        self.intersect_objects(ray, &mut all);
+        self.intersect_models(ray, &mut all);
+        let mut intersections = Intersections::new();
+        for i in all.into_iter().filter(|i| i.object.id() != excluded.id()) {
+            intersections.push(i);
+        }
+        intersections.sort();
         intersections
     }
 
     pub fn shade_hit(&self, comp: Computations) -> Color {
+        self.shade_hit_depth(comp, self.settings.max_bounces)
+    }
+
+    // remaining is how many more portals, reflections or refractions the
+    // light may pass
+    pub fn shade_hit_depth(&self, comp: Computations, remaining: usize) -> Color {
+        let _span = span!(TRACE, "shade_hit");
+        if comp.object.material.shadow_catcher {
+            return self.shadow_catch(&comp, remaining).0;
+        }
+        let material = self.surface_material(&comp);
         let mut c = Color::new(0.0, 0.0, 0.0);
         for light in &self.lights {
-            c = c + lighting(
-                comp.object.material,
-                *light,
-                comp.over_point,
-                comp.eyev,
-                comp.normal,
-                self.is_shadowed(light, comp.over_point),
-            )
+            c = c + self.direct_light(material, light, &comp, true);
+        }
+        c + self.reflected_color(&comp, remaining) + self.refracted_color(&comp, remaining)
+    }
+
+    // the hit object's material as lit, with the shading override, its
+    // pattern and any decals applied
+    pub(crate) fn surface_material(&self, comp: &Computations) -> Material {
+        let mut material = comp.object.material;
+        if let Some(model) = self.settings.shading {
+            material.model = model;
+        }
+        if let Some(pattern) = material.pattern {
+            material.color = pattern.color_at_object(comp.object, comp.point);
         }
-        c
+        for decal in &self.decals {
+            material.color = decal.apply(material.color, comp.point, comp.normal);
+        }
+        material
+    }
+
+    // light reaching the hit straight from light, averaged over its sample
+    // positions with each checked for shadow on its own
+    pub(crate) fn direct_light(
+        &self,
+        material: Material,
+        light: &Light,
+        comp: &Computations,
+        shadows: bool,
+    ) -> Color {
+        let samples = light.sample_positions(comp.over_point);
+        let sum = samples.iter().fold(BLACK, |sum, position| {
+            let light_at = |in_shadow| {
+                lighting_from(
+                    material,
+                    light,
+                    *position,
+                    comp.over_point,
+                    comp.eyev,
+                    comp.normal,
+                    in_shadow,
+                )
+            };
+            sum + if !shadows {
+                light_at(false)
+            } else if self.settings.transparent_shadows {
+                // what gets through the occluders lights the point partly
+                let (lit, shadowed) = (light_at(false), light_at(true));
+                shadowed + (lit - shadowed) * self.transmittance_at(*position, comp)
+            } else {
+                light_at(self.is_shadowed_at(*position, comp))
+            }
+        });
+        sum * (1.0 / samples.len() as f64)
+    }
+
+    fn transmittance_at(&self, position: Point, comp: &Computations) -> Color {
+        let origin = self.settings.exclude_origin.then_some(comp.object);
+        self.transmittance_from(position, comp.over_point, origin)
+    }
+
+    // share of the light from position reaching point per channel. opaque
+    // occluders block it all, transparent ones pass their transparency
+    // tinted by their color where the shadow ray first meets them, once
+    // per object however often the ray crosses it. origin is left out
+    // when given, see is_shadowed_from_excluding
+    pub fn transmittance_from(
+        &self,
+        position: Point,
+        point: Point,
+        origin: Option<&Object>,
+    ) -> Color {
+        let v = position - point;
+        let r = Ray::new(point, v.normalize());
+        let xs = self.intersect_within(r, 0.0, v.magnitude() - self.settings.epsilon);
+        let mut seen: Vec<u32> = vec![];
+        let mut through = Color::new(1.0, 1.0, 1.0);
+        for i in &xs {
+            let object = i.object;
+            if origin.is_some_and(|o| o.id() == object.id()) || seen.contains(&object.id()) {
+                continue;
+            }
+            let m = object.material;
+            if m.transparency <= 0.0 {
+                return BLACK;
+            }
+            let color = match m.pattern {
+                Some(pattern) => pattern.color_at_object(object, r.position(i.t)),
+                None => m.color,
+            };
+            through = through * color * m.transparency;
+            seen.push(object.id());
+        }
+        through
+    }
+
+    fn is_shadowed_at(&self, position: Point, comp: &Computations) -> bool {
+        if self.settings.exclude_origin {
+            self.is_shadowed_from_excluding(position, comp.over_point, comp.object)
+        } else {
+            self.is_shadowed_from(position, comp.over_point)
+        }
+    }
+
+    // what a shadow catcher receives, as a color premultiplied by an
+    // alpha. the alpha is how much of the background the shadows darken
+    // and the reflections cover, so it composites onto a photo as
+    // color + photo * (1 - alpha)
+    pub fn shadow_catch(&self, comp: &Computations, remaining: usize) -> (Color, f64) {
+        // shadows are the share of direct light blocked on the way, the
+        // catcher's own color and ambient light play no part
+        let material = Material {
+            color: Color::new(1.0, 1.0, 1.0),
+            ambient: 0.0,
+            ..comp.object.material
+        };
+        let (mut lit, mut received) = (0.0, 0.0);
+        for light in &self.lights {
+            lit += self.direct_light(material, light, comp, false).luminance();
+            received += self.direct_light(material, light, comp, true).luminance();
+        }
+        let shadow = if lit > 0.0 { 1.0 - received / lit } else { 0.0 };
+        let reflected = self.reflected_color(comp, remaining);
+        let cover = reflected.luminance().clamp(0.0, 1.0);
+        (reflected, shadow + (1.0 - shadow) * cover)
+    }
+
+    // light mirrored off a reflective surface
+    pub fn reflected_color(&self, comp: &Computations, remaining: usize) -> Color {
+        let reflective = comp.object.material.reflective;
+        if reflective == 0.0 || remaining == 0 {
+            return BLACK;
+        }
+        let ray = Ray::new(comp.over_point, comp.reflectv);
+        self.color_at_depth(ray, remaining - 1) * reflective
+    }
+
+    // light coming through a transparent surface, dispersive materials are
+    // traced once per channel at that channel's wavelength
+    pub fn refracted_color(&self, comp: &Computations, remaining: usize) -> Color {
+        let transparency = comp.object.material.transparency;
+        if transparency == 0.0 || remaining == 0 {
+            return BLACK;
+        }
+        let dispersive = [comp.from, comp.to]
+            .iter()
+            .flatten()
+            .any(|o| o.material.is_dispersive());
+        if !dispersive {
+            return self.refract(comp, comp.n1, comp.n2, remaining) * transparency;
+        }
+        let index =
+            |o: Option<&Object>, l: f64| o.map_or(1.0, |o| o.material.refractive_index_at(l));
+        let [r, g, b] = CHANNEL_WAVELENGTHS
+            .map(|l| self.refract(comp, index(comp.from, l), index(comp.to, l), remaining));
+        Color::new(r.red, g.green, b.blue) * transparency
+    }
+
+    // snell's law from n1 into n2, black on total internal reflection
+    fn refract(&self, comp: &Computations, n1: f64, n2: f64, remaining: usize) -> Color {
+        let ratio = n1 / n2;
+        let cos_i = comp.eyev.dot(comp.normal);
+        let sin2_t = ratio * ratio * (1.0 - cos_i * cos_i);
+        if sin2_t > 1.0 {
+            return BLACK;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = comp.normal * (ratio * cos_i - cos_t) - comp.eyev * ratio;
+        self.color_at_depth(Ray::new(comp.under_point, direction), remaining - 1)
     }
 
     pub fn color_at(&self, ray: Ray) -> Color {
-        let inters = self.intersect(ray);
-        if let Some(hit) = inters.hit() {
-            let comps = hit.prepare_computations(ray);
-            self.shade_hit(comps)
+        self.color_at_depth(ray, self.settings.max_bounces)
+    }
+
+    // color_at with the alpha to composite it with, premultiplied like
+    // shadow_catch. misses are transparent, shadow catchers partly so and
+    // everything else opaque
+    pub fn color_alpha_at(&self, ray: Ray) -> (Color, f64) {
+        self.color_alpha_of(ray, self.intersect(ray))
+    }
+
+    fn color_alpha_of(&self, ray: Ray, inters: Intersections) -> (Color, f64) {
+        match inters.hit_with_tolerance(self.settings.epsilon) {
+            None => (BLACK, 0.0),
+            Some(hit) if hit.object.material.shadow_catcher => {
+                let comps = hit.prepare_computations_in(
+                    ray,
+                    &inters,
+                    hit.object.surface_bias(self.settings.shadow_bias),
+                );
+                self.shadow_catch(&comps, self.settings.max_bounces)
+            }
+            Some(_) => (
+                self.shade_first(ray, inters, self.settings.max_bounces),
+                1.0,
+            ),
+        }
+    }
+
+    // like color_at but the ray itself is only tested against the objects
+    // at the given indices, the lod groups, groups and csgs, anything it
+    // spawns sees the whole world
+    pub fn color_at_among(&self, ray: Ray, objects: &[usize]) -> Color {
+        self.shade_first(
+            ray,
+            self.intersect_among(ray, objects),
+            self.settings.max_bounces,
+        )
+    }
+
+    // color_alpha_at with the ray limited the same way
+    pub fn color_alpha_at_among(&self, ray: Ray, objects: &[usize]) -> (Color, f64) {
+        self.color_alpha_of(ray, self.intersect_among(ray, objects))
+    }
+
+    fn intersect_among(&self, ray: Ray, objects: &[usize]) -> Intersections<'_> {
+        let mut inters = Intersections::new();
+        for &i in objects {
+            inters.concat(self.objects[i].intersect(ray));
+        }
+        self.intersect_models(ray, &mut inters);
+        inters.sort();
+        inters
+    }
+
+    // remaining is the number of portals, reflections or refractions the
+    // ray may still pass through
+    pub fn color_at_depth(&self, ray: Ray, remaining: usize) -> Color {
+        self.shade_first(ray, self.intersect(ray), remaining)
+    }
+
+    // color of the first thing along ray, either a hit in inters or a
+    // portal in front of it
+    fn shade_first(&self, ray: Ray, inters: Intersections, remaining: usize) -> Color {
+        let hit = inters.hit_with_tolerance(self.settings.epsilon);
+        if let Some((t, exit)) = self.nearest_portal(ray) {
+            if hit.is_none_or(|hit| t < hit.t) {
+                if remaining == 0 {
+                    return BLACK;
+                }
+                let origin = exit.position(self.settings.shadow_bias / exit.direction.magnitude());
+                return self.color_at_depth(Ray::new(origin, exit.direction), remaining - 1);
+            }
+        }
+        if let Some(hit) = hit {
+            let comps = hit.prepare_computations_in(
+                ray,
+                &inters,
+                hit.object.surface_bias(self.settings.shadow_bias),
+            );
+            self.shade_hit_depth(comps, remaining)
         } else {
             BLACK
         }
     }
 
-    pub fn is_shadowed(&self, light: &PointLight, point: Point) -> bool {
-        let v = light.position - point;
+    // the closest portal ray enters, with where along it and the ray out
+    pub(crate) fn nearest_portal(&self, ray: Ray) -> Option<(f64, Ray)> {
+        self.portals
+            .iter()
+            .filter_map(|p| p.teleport(ray))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    pub fn raycast(&self, origin: Point, direction: Vector) -> Option<RaycastHit<'_>> {
+        let ray = Ray::new(origin, direction.normalize());
+        let intersections = self.intersect(ray);
+        let hit = intersections.hit_with_tolerance(self.settings.epsilon)?;
+        let comps = hit.prepare_computations(ray);
+        Some(RaycastHit {
+            point: comps.point,
+            normal: comps.normal,
+            distance: comps.t,
+            object: comps.object,
+        })
+    }
+
+    // whether point is cut off from every sample position of light
+    pub fn is_shadowed(&self, light: &Light, point: Point) -> bool {
+        light
+            .sample_positions(point)
+            .into_iter()
+            .all(|position| self.is_shadowed_from(position, point))
+    }
+
+    pub fn is_shadowed_excluding(&self, light: &Light, point: Point, origin: &Object) -> bool {
+        light
+            .sample_positions(point)
+            .into_iter()
+            .all(|position| self.is_shadowed_from_excluding(position, point, origin))
+    }
+
+    // whether something lies between point and a light at position
+    pub fn is_shadowed_from(&self, position: Point, point: Point) -> bool {
+        let v = position - point;
+        let r = Ray::new(point, v.normalize());
         let distance = v.magnitude();
-        let direction = v.normalize();
+        self.shadow_hit(self.intersect_within(r, 0.0, distance), distance)
+    }
 
-        let r = Ray::new(point, direction);
-        let intersections = self.intersect(r);
+    // for convex objects a surface can never shadow itself, so the shadow ray
+    // may safely ignore it no matter how far off the surface it starts
+    pub fn is_shadowed_from_excluding(
+        &self,
+        position: Point,
+        point: Point,
+        origin: &Object,
+    ) -> bool {
+        let v = position - point;
+        let r = Ray::new(point, v.normalize());
+        let distance = v.magnitude();
+        let others = self
+            .intersect_within(r, 0.0, distance)
+            .into_iter()
+            .filter(|i| i.object.id() != origin.id())
+            .collect::<Vec<_>>();
+        self.shadow_hit(others.into(), distance)
+    }
 
+    fn shadow_hit(&self, intersections: Intersections, distance: f64) -> bool {
         if let Some(hit) = intersections.hit() {
-            if hit.t < distance {
+            if hit.t < distance - self.settings.epsilon {
                 return true;
             }
         }
@@ -72,18 +602,16 @@ impl World {
 
 pub fn default_world() -> World {
     let mut w = World::new();
-    w.objects.push(Sphere::new().set_material(Material {
+    w.objects.push(sphere().set_material(Material {
         color: Color::new(0.8, 1.0, 0.6),
         diffuse: 0.7,
         specular: 0.2,
         ..Default::default()
     }));
     w.objects
-        .push(Sphere::new().set_transform(transformations::scaling(0.5, 0.5, 0.5)));
-    w.lights.push(PointLight::new(
-        Point::new(-10.0, 10.0, -10.0),
-        Color::new(1.0, 1.0, 1.0),
-    ));
+        .push(sphere().set_transform(transformations::scaling(0.5, 0.5, 0.5)));
+    w.lights
+        .push(PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
 
     w
 }
@@ -91,7 +619,16 @@ pub fn default_world() -> World {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::brdf::ShadingModel;
+    use crate::generators;
+    use crate::light::{AreaLight, SpotLight};
+    use crate::pattern::{Pattern, PatternKind};
+    use crate::slab::slab;
+    use crate::transformations::Transformable;
+    use crate::tuple::EPSILON;
     use crate::{intersection::Intersection, tuple::Vector};
+    use core::f64::consts::PI;
+    use static_assertions::assert_impl_all;
     #[test]
     fn creating_world() {
         let w = World::new();
@@ -106,16 +643,25 @@ mod tests {
         assert_eq!(w.objects.len(), 2);
     }
 
+    #[test]
+    fn bounds_of_the_default_world() {
+        let w = default_world();
+        let b = w.bounds();
+        assert_eq!(b.min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(b.max, Point::new(1.0, 1.0, 1.0));
+        assert!(World::new().bounds().is_empty());
+    }
+
     #[test]
     fn intersect_world_with_ray() {
         let w = default_world();
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let xs = w.intersect(r);
-        assert_eq!(xs.0.len(), 4);
-        assert_eq!(xs.0[0].t, 4.0);
-        assert_eq!(xs.0[1].t, 4.5);
-        assert_eq!(xs.0[2].t, 5.5);
-        assert_eq!(xs.0[3].t, 6.0);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].t, 4.0);
+        assert_eq!(xs[1].t, 4.5);
+        assert_eq!(xs[2].t, 5.5);
+        assert_eq!(xs[3].t, 6.0);
     }
 
     #[test]
@@ -131,10 +677,8 @@ mod tests {
     #[test]
     fn shading_an_intersection_from_inside() {
         let mut w = default_world();
-        w.lights = vec![PointLight::new(
-            Point::new(0.0, 0.25, 0.0),
-            Color::new(1.0, 1.0, 1.0),
-        )];
+        w.lights =
+            vec![PointLight::new(Point::new(0.0, 0.25, 0.0), Color::new(1.0, 1.0, 1.0)).into()];
         let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
         let shape = &w.objects[1];
         let i = Intersection::new(0.5, shape);
@@ -167,12 +711,14 @@ mod tests {
         assert_eq!(c, w.objects[1].material.color);
     }
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn no_shadow_when_no_object_collinear_with_point() {
         let w = default_world();
         let p = Point::new(0.0, 10.0, 0.0);
         assert_eq!(w.is_shadowed(&w.lights[0], p), false);
     }
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn shadow_when_object_between_light_and_point() {
         let w = default_world();
         let p = Point::new(10.0, -10.0, 10.0);
@@ -180,12 +726,14 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn no_shadow_when_object_behind_point() {
         let w = default_world();
         let p = Point::new(-20.0, 20.0, -20.0);
         assert_eq!(w.is_shadowed(&w.lights[0], p), false);
     }
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn no_shadow_when_object_behind_light() {
         let w = default_world();
         let p = Point::new(-2.0, 2.0, -2.0);
@@ -195,12 +743,10 @@ mod tests {
     #[test]
     fn shade_hit_is_given_intersection_in_shadow() {
         let mut w = World::new();
-        w.lights.push(PointLight::new(
-            Point::new(0.0, 0.0, -10.0),
-            Color::new(1.0, 1.0, 1.0),
-        ));
-        let s1 = Sphere::new();
-        let s2 = Sphere::new().set_transform(transformations::translation(0.0, 0.0, 10.0));
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+        let s1 = sphere();
+        let s2 = sphere().set_transform(transformations::translation(0.0, 0.0, 10.0));
         w.objects = vec![s1, s2];
         let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
         let i = Intersection::new(4.0, &w.objects[1]);
@@ -211,10 +757,546 @@ mod tests {
     #[test]
     fn hit_offset_the_point() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let shape = Sphere::new().set_transform(transformations::translation(0.0, 0.0, 1.0));
+        let shape = sphere().set_transform(transformations::translation(0.0, 0.0, 1.0));
         let i = Intersection::new(5.0, &shape);
         let comps = i.prepare_computations(r);
         assert!(comps.over_point.0.z < -crate::tuple::EPSILON / 2.0);
         assert!(comps.point.0.z > comps.over_point.0.z);
     }
+
+    #[test]
+    fn color_at_uses_configured_shadow_bias() {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+        let s1 = sphere();
+        let s2 = sphere().set_transform(transformations::translation(0.0, 0.0, 10.0));
+        w.objects = vec![s1, s2];
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(r), Color::new(0.1, 0.1, 0.1));
+
+        // an absurd bias pushes the shadow ray origin past the occluder
+        w.settings = RenderSettings::default().with_shadow_bias(12.0);
+        assert_ne!(w.color_at(r), Color::new(0.1, 0.1, 0.1));
+
+        // so does the same bias set on the object, or a scene bias grown
+        // along with the object
+        w.settings = RenderSettings::default();
+        w.objects[1] = w.objects[1].clone().set_bias(12.0);
+        assert_ne!(w.color_at(r), Color::new(0.1, 0.1, 0.1));
+        w.objects[1] = sphere().transformed(
+            &(transformations::translation(0.0, 0.0, 10.0)
+                * transformations::scaling(2.0, 2.0, 2.0)),
+        );
+        w.settings = RenderSettings::default().with_shadow_bias(6.0);
+        assert_ne!(w.color_at(r), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn intersect_world_within_range() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect_within(r, 4.2, 5.5);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.5);
+        assert_eq!(xs[1].t, 5.5);
+        assert!(w.intersect_within(r, 7.0, 100.0).is_empty());
+    }
+
+    #[test]
+    fn raycast_reports_nearest_hit() {
+        let w = default_world();
+        let hit = w
+            .raycast(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 10.0))
+            .unwrap();
+        assert_eq!(hit.point, Point::new(0.0, 0.0, -1.0));
+        assert_eq!(hit.normal, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(hit.distance, 4.0);
+        assert_eq!(hit.object, &w.objects[0]);
+    }
+
+    #[test]
+    fn raycast_misses() {
+        let w = default_world();
+        let hit = w.raycast(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn intersect_excluding_skips_object() {
+        let w = default_world();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect_excluding(r, &w.objects[0]);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].t, 4.5);
+        assert_eq!(xs[1].t, 5.5);
+    }
+
+    #[test]
+    fn excluded_origin_does_not_shadow_itself() {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+        w.objects.push(sphere());
+        // a point on the surface with no bias at all, rounding puts it
+        // slightly inside the sphere
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0 + 1e-9, &w.objects[0]);
+        let comps = i.prepare_computations_with_bias(r, 0.0);
+        assert!(w.is_shadowed(&w.lights[0], comps.over_point));
+        assert!(!w.is_shadowed_excluding(&w.lights[0], comps.over_point, &w.objects[0]));
+    }
+
+    #[test]
+    fn shade_hit_excludes_origin_when_enabled() {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into());
+        w.objects.push(sphere());
+        w.settings = RenderSettings::default()
+            .with_shadow_bias(0.0)
+            .with_exclude_origin(true);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0 + 1e-9, &w.objects[0]);
+        let comps = i.prepare_computations_with_bias(r, 0.0);
+        assert_eq!(w.shade_hit(comps), Color::new(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn shading_setting_overrides_materials() {
+        let mut w = default_world();
+        // just off the peak of the highlight the models disagree
+        let r = Ray::new(Point::new(-0.38, 0.3, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let phong = w.color_at(r);
+        w.settings = RenderSettings::default().with_shading(ShadingModel::BlinnPhong);
+        let blinn = w.color_at(r);
+        assert!((blinn.red - phong.red).abs() > 1e-3);
+        w.settings = RenderSettings::default().with_shading(ShadingModel::Lambert);
+        assert!(w.color_at(r).red < phong.red);
+    }
+
+    #[test]
+    fn shade_hit_applies_decals() {
+        let mut w = default_world();
+        let mut image = crate::canvas::Canvas::new(1, 1);
+        image.fill(Color::new(1.0, 0.0, 0.0));
+        let projector = crate::decal::Projector::new(
+            Point::new(0.0, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+            0.5,
+        );
+        w.decals.push(Decal::new(projector, image));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let c = w.color_at(r);
+        assert!(c.red > 0.3);
+        assert!(c.green < 1e-9 && c.blue < 1e-9);
+        // outside the projector frustum the surface keeps its own color
+        let r = Ray::new(Point::new(0.0, 0.9, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.color_at(r).green > 0.0);
+    }
+
+    #[test]
+    fn rays_travel_through_portals() {
+        let mut w = World::new();
+        w.lights.push(
+            PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into(),
+        );
+        w.objects
+            .push(sphere().set_transform(transformations::translation(5.0, 0.0, 20.0)));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(r), BLACK);
+
+        let seen_directly = w.color_at(Ray::new(
+            Point::new(5.0, 0.0, 9.0),
+            Vector::new(0.0, 0.0, 1.0),
+        ));
+        w.portals.push(Portal::new(
+            sphere(),
+            sphere().set_transform(transformations::translation(5.0, 0.0, 10.0)),
+        ));
+        assert_eq!(w.color_at(r), seen_directly);
+    }
+
+    #[test]
+    fn looping_portals_stop_at_max_depth() {
+        let mut w = default_world();
+        w.objects.clear();
+        w.portals.push(Portal::new(
+            sphere(),
+            sphere().set_transform(transformations::translation(0.0, 0.0, -10.0)),
+        ));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(r), BLACK);
+    }
+
+    #[test]
+    fn sphere_batch_renders_the_same() {
+        let mut w = default_world();
+        w.objects
+            .push(sphere().set_transform(transformations::scaling(3.0, 0.2, 3.0)));
+        let r = Ray::new(Point::new(0.2, 0.3, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let expected = w.color_at(r);
+        let ts = |w: &World| w.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>();
+        let plain = ts(&w);
+        w.build_sphere_batch();
+        assert_eq!(w.sphere_batch.as_ref().unwrap().len(), 2);
+        let batched = ts(&w);
+        assert_eq!(plain.len(), batched.len());
+        assert!(plain
+            .iter()
+            .zip(&batched)
+            .all(|(a, b)| (a - b).abs() < 1e-9));
+        assert_eq!(w.color_at(r), expected);
+        // a stale batch is ignored rather than missing objects
+        w.objects
+            .push(sphere().set_transform(transformations::translation(0.0, 0.0, 5.0)));
+        assert_eq!(ts(&w).len(), batched.len() + 2);
+    }
+
+    #[test]
+    fn moved_objects_are_tracked_until_refit() {
+        let mut w = generators::sphere_grid(5, 2.5);
+        w.build_bvh();
+        w.build_sphere_batch();
+        let r = Ray::new(Point::new(20.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let ts = |w: &World| w.intersect(r).iter().map(|i| i.t).collect::<Vec<_>>();
+        assert!(ts(&w).is_empty());
+
+        w.set_object_transform(3, transformations::translation(20.0, 0.0, 0.0));
+        w.set_object_transform(3, transformations::translation(20.0, 0.0, 1.0));
+        assert_eq!(w.changed_objects(), &[3]);
+        // stale structures are skipped, so the moved sphere is still found
+        assert_eq!(ts(&w), vec![5.0, 7.0]);
+        w.refit();
+        assert!(w.changed_objects().is_empty());
+        assert_eq!(ts(&w), vec![5.0, 7.0]);
+        w.sphere_batch = None;
+        assert_eq!(ts(&w), vec![5.0, 7.0]);
+    }
+
+    #[test]
+    fn lod_groups_are_part_of_the_world() {
+        let mut w = World::new();
+        w.lights.push(
+            PointLight::new(Point::new(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)).into(),
+        );
+        let red = Material {
+            color: Color::new(1.0, 0.0, 0.0),
+            ..Default::default()
+        };
+        w.lods.push(
+            LodGroup::new()
+                .add_level(20.0, vec![sphere()])
+                .add_level(f64::INFINITY, vec![sphere().set_material(red)]),
+        );
+        let ray = |z: f64| Ray::new(Point::new(0.0, 0.0, z), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.intersect(ray(-5.0)).len(), 2);
+        assert_eq!(w.intersect_within(ray(-5.0), 0.0, 4.5).len(), 1);
+        let near = w.color_at(ray(-5.0));
+        let far = w.color_at(ray(-50.0));
+        assert!(near.green > 0.0);
+        assert!(far.green.abs() < 1e-9);
+        assert!(far.red > 0.0);
+        assert_eq!(w.bounds().max, Point::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn groups_render_like_loose_objects() {
+        let loose = generators::sphere_grid(4, 1.5);
+        let mut grouped = World::new();
+        grouped.lights = loose.lights.clone();
+        let mut group = Group::new("grid");
+        for object in &loose.objects {
+            group.push_child(object.clone());
+        }
+        grouped.add_group(group.divide(2));
+        assert!(grouped.objects.is_empty());
+        assert_eq!(grouped.bounds(), loose.bounds());
+        for x in [-2.0, -0.7, 0.0, 1.1, 2.2] {
+            let r = Ray::new(Point::new(x, 0.3, -10.0), Vector::new(0.0, 0.1, 1.0));
+            assert_eq!(grouped.color_at(r), loose.color_at(r));
+            assert_eq!(grouped.intersect(r).len(), loose.intersect(r).len());
+        }
+    }
+
+    #[test]
+    fn refracted_color_of_opaque_surface_or_at_max_depth() {
+        let mut w = default_world();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        {
+            let xs = w.objects[0].intersect(r);
+            let comps = xs[0].prepare_computations_in(r, &xs, EPSILON);
+            assert_eq!(w.refracted_color(&comps, 5), BLACK);
+        }
+        w.objects[0].material.transparency = 1.0;
+        w.objects[0].material.refractive_index = 1.5;
+        let xs = w.objects[0].intersect(r);
+        let comps = xs[0].prepare_computations_in(r, &xs, EPSILON);
+        assert_eq!(w.refracted_color(&comps, 0), BLACK);
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let mut w = default_world();
+        w.objects[0].material.transparency = 1.0;
+        w.objects[0].material.refractive_index = 1.5;
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let r = Ray::new(Point::new(0.0, 0.0, half), Vector::new(0.0, 1.0, 0.0));
+        let xs = w.objects[0].intersect(r);
+        let comps = xs[1].prepare_computations_in(r, &xs, EPSILON);
+        assert_eq!(w.refracted_color(&comps, 5), BLACK);
+    }
+
+    fn with_mirror_floor(reflective: f64) -> World {
+        let mut w = default_world();
+        w.objects.push(
+            slab(20.0, 1.0, 20.0)
+                .transformed(&transformations::translation(0.0, -1.5, 0.0))
+                .set_material(Material {
+                    reflective,
+                    ..Default::default()
+                }),
+        );
+        w
+    }
+
+    #[test]
+    fn reflected_color_of_a_matte_surface_or_at_max_depth() {
+        let mut w = default_world();
+        w.objects[1].material.ambient = 1.0;
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r);
+        assert_eq!(w.reflected_color(&comps, 5), BLACK);
+
+        let w = with_mirror_floor(0.5);
+        let r = Ray::new(Point::new(0.0, 0.0, -3.0), Vector::new(0.0, -0.5, 1.0));
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r);
+        assert_eq!(comps.object.id(), w.objects[2].id());
+        assert_eq!(w.reflected_color(&comps, 0), BLACK);
+    }
+
+    #[test]
+    fn max_bounces_limits_reflections() {
+        let r = Ray::new(Point::new(0.0, 0.0, -3.0), Vector::new(0.0, -0.5, 1.0));
+        let mut w = with_mirror_floor(0.5);
+        let matte = with_mirror_floor(0.0).color_at(r);
+        assert_ne!(w.color_at(r), matte);
+        w.settings = w.settings.with_max_bounces(0);
+        assert_eq!(w.color_at(r), matte);
+        // camera rays start out with the bounces the settings allow
+        w.settings = w.settings.with_max_bounces(1);
+        let xs = w.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r);
+        assert_eq!(w.color_at(r), w.shade_hit_depth(comps, 1));
+    }
+
+    #[test]
+    fn spot_lights_only_light_their_cone() {
+        let spot = |direction| {
+            let mut w = default_world();
+            w.lights = vec![SpotLight::new(
+                Point::new(0.0, 0.0, -10.0),
+                direction,
+                PI / 16.0,
+                PI / 12.0,
+                Color::new(1.0, 1.0, 1.0),
+            )
+            .into()];
+            w
+        };
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let toward = spot(Vector::new(0.0, 0.0, 1.0));
+        let away = spot(Vector::new(0.0, 1.0, 0.0));
+        let mut point = default_world();
+        point.lights[0] =
+            PointLight::new(Point::new(0.0, 0.0, -10.0), Color::new(1.0, 1.0, 1.0)).into();
+        assert_eq!(toward.color_at(r), point.color_at(r));
+        // ambient light comes from the light too, so none is left
+        assert_eq!(away.color_at(r), BLACK);
+        // the cone doesn't change where shadows fall
+        let behind = Point::new(0.0, 0.0, 10.0);
+        assert!(toward.is_shadowed(&toward.lights[0], behind));
+    }
+
+    #[test]
+    fn area_lights_cast_soft_shadows() {
+        let mut w = World::new();
+        w.objects
+            .push(sphere().set_transform(transformations::translation(0.0, 2.0, 0.0)));
+        w.objects
+            .push(slab(40.0, 1.0, 40.0).transformed(&transformations::translation(0.0, -0.5, 0.0)));
+        w.lights = vec![AreaLight::new(
+            Point::new(-2.0, 10.0, -2.0),
+            Vector::new(4.0, 0.0, 0.0),
+            4,
+            Vector::new(0.0, 0.0, 4.0),
+            4,
+            Color::new(1.0, 1.0, 1.0),
+        )
+        .into()];
+        let floor = |x| {
+            let r = Ray::new(Point::new(x, 0.5, 0.0), Vector::new(0.0, -1.0, 0.0));
+            w.color_at(r).luminance()
+        };
+        // the floor brightens gradually from the umbra out
+        let (umbra, penumbra, lit) = (floor(0.0), floor(1.25), floor(6.0));
+        assert!(umbra < penumbra && penumbra < lit);
+        assert!(w.is_shadowed(&w.lights[0], Point::new(0.0, 1e-4, 0.0)));
+        assert!(!w.is_shadowed(&w.lights[0], Point::new(1.25, 1e-4, 0.0)));
+    }
+
+    #[test]
+    fn stained_glass_casts_tinted_shadows() {
+        let pane = |transparency, transparent_shadows| {
+            let mut w = World::new();
+            w.lights =
+                vec![PointLight::new(Point::new(0.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0)).into()];
+            w.objects.push(
+                slab(40.0, 1.0, 40.0).transformed(&transformations::translation(0.0, -0.5, 0.0)),
+            );
+            w.objects.push(
+                slab(4.0, 0.1, 4.0)
+                    .transformed(&transformations::translation(0.0, 2.0, 0.0))
+                    .set_material(Material {
+                        color: Color::new(1.0, 0.2, 0.2),
+                        transparency,
+                        ..Default::default()
+                    }),
+            );
+            w.settings = w.settings.with_transparent_shadows(transparent_shadows);
+            w
+        };
+        let floor = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let dark = pane(0.0, true).color_at(floor);
+        assert_eq!(pane(0.5, false).color_at(floor), dark);
+        let tinted = pane(0.5, true).color_at(floor);
+        assert!(tinted.red > dark.red && tinted.green > dark.green);
+        assert!(tinted.red > tinted.green);
+        // the pane is crossed twice but filters once
+        let through = pane(0.5, true).transmittance_from(
+            Point::new(0.0, 10.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+            None,
+        );
+        assert_eq!(through, Color::new(0.5, 0.1, 0.1));
+    }
+
+    #[test]
+    fn patterns_replace_the_material_color() {
+        let (red, blue) = (Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0));
+        let mut striped = default_world();
+        striped.objects[0].material.pattern = Some(Pattern::new(PatternKind::Stripe(red, blue)));
+        for (x, color) in [(0.2, red), (-0.5, blue)] {
+            let mut plain = default_world();
+            plain.objects[0].material.color = color;
+            let r = Ray::new(Point::new(x, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+            assert_eq!(striped.color_at(r), plain.color_at(r));
+        }
+    }
+
+    #[test]
+    fn mirror_floors_show_what_is_above_them() {
+        // looking down at the floor in front of the spheres, which it
+        // mirrors
+        let r = Ray::new(Point::new(0.0, 0.0, -3.0), Vector::new(0.0, -0.5, 1.0));
+        let matte = with_mirror_floor(0.0);
+        let mirror = with_mirror_floor(0.5);
+        let xs = mirror.intersect(r);
+        let comps = xs.hit().unwrap().prepare_computations(r);
+        let reflected = mirror.reflected_color(&comps, 5);
+        assert!(reflected.green > 0.0);
+        assert_eq!(mirror.color_at(r), matte.color_at(r) + reflected);
+    }
+
+    #[test]
+    fn facing_mirrors_stop_at_the_depth_limit() {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        let mirror = Material {
+            reflective: 1.0,
+            ..Default::default()
+        };
+        for y in [-1.5, 1.5] {
+            w.objects.push(
+                slab(10.0, 1.0, 10.0)
+                    .transformed(&transformations::translation(0.0, y, 0.0))
+                    .set_material(mirror),
+            );
+        }
+        let c = w.color_at(Ray::new(
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        ));
+        assert!(c.red > 0.0 && c.red.is_finite());
+    }
+
+    // a ray inside a glass ball surrounded by a white room, glowing where
+    // light gets out and black where it is trapped
+    fn glass_in_a_room(glass: Material) -> World {
+        let mut w = World::new();
+        w.lights
+            .push(PointLight::new(Point::new(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0)).into());
+        let matte = Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Default::default()
+        };
+        w.objects.push(sphere().set_material(glass));
+        w.objects.push(
+            sphere()
+                .set_transform(transformations::scaling(10.0, 10.0, 10.0))
+                .set_material(Material {
+                    ambient: 1.0,
+                    ..matte
+                }),
+        );
+        w
+    }
+
+    #[test]
+    fn light_passes_through_transparent_objects() {
+        let w = glass_in_a_room(Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Default::default()
+        });
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.color_at(r).approx_eq(&Color::new(1.0, 1.0, 1.0), 1e-9));
+    }
+
+    #[test]
+    fn dispersion_traps_some_channels() {
+        // at 45 degrees inside the ball only indices below sqrt(2) escape,
+        // red is just under it while green and blue are just over
+        let glass = Material {
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            transparency: 1.0,
+            refractive_index: 1.414,
+            abbe_number: 10.0,
+            ..Default::default()
+        };
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        let r = Ray::new(Point::new(0.0, 0.0, half), Vector::new(0.0, 1.0, 0.0));
+        let c = glass_in_a_room(glass).color_at(r);
+        assert!(c.approx_eq(&Color::new(1.0, 0.0, 0.0), 1e-9));
+
+        let clear = Material {
+            abbe_number: f64::INFINITY,
+            refractive_index: 1.409,
+            ..glass
+        };
+        let c = glass_in_a_room(clear).color_at(r);
+        assert!(c.approx_eq(&Color::new(1.0, 1.0, 1.0), 1e-9));
+    }
+
+    assert_impl_all!(World: Send, Sync);
 }
@@ -0,0 +1,290 @@
+// just enough yaml for scene files: block maps and lists by indentation,
+// flow lists in brackets and plain scalars. anchors, multi line strings
+// and flow maps are not supported
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct YamlError {
+    pub line: usize,
+    pub message: String,
+}
+
+type Result<T> = std::result::Result<T, YamlError>;
+
+// deeper documents are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+struct Line {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_str()?.parse().ok()
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T> {
+    Err(YamlError {
+        line,
+        message: message.into(),
+    })
+}
+
+fn strip_comment(s: &str) -> &str {
+    let mut prev = ' ';
+    for (i, c) in s.char_indices() {
+        if c == '#' && prev.is_whitespace() {
+            return &s[..i];
+        }
+        prev = c;
+    }
+    s
+}
+
+fn is_map_entry(text: &str) -> bool {
+    !text.starts_with('[') && (text.contains(": ") || text.ends_with(':'))
+}
+
+fn split_entry(line: &Line) -> Result<(String, String)> {
+    let (key, value) = if let Some(key) = line.text.strip_suffix(':') {
+        (key, "")
+    } else if let Some((key, value)) = line.text.split_once(": ") {
+        (key, value)
+    } else {
+        return error(
+            line.number,
+            format!("expected `key: value`, found `{}`", line.text),
+        );
+    };
+    Ok((key.trim().to_string(), value.trim().to_string()))
+}
+
+fn parse_flow(s: &str, line: usize, depth: usize) -> Result<Value> {
+    if depth > MAX_DEPTH {
+        return error(line, "nested too deeply");
+    }
+    let s = s.trim();
+    if let Some(inner) = s.strip_prefix('[') {
+        let inner = match inner.strip_suffix(']') {
+            Some(inner) => inner,
+            None => return error(line, "unclosed `[`"),
+        };
+        let mut items = vec![];
+        let mut nesting = 0;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '[' => nesting += 1,
+                ']' => nesting -= 1,
+                ',' if nesting == 0 => {
+                    items.push(parse_flow(&inner[start..i], line, depth + 1)?);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        if !inner[start..].trim().is_empty() {
+            items.push(parse_flow(&inner[start..], line, depth + 1)?);
+        }
+        Ok(Value::List(items))
+    } else {
+        let s = s
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .unwrap_or(s);
+        Ok(Value::Scalar(s.to_string()))
+    }
+}
+
+fn parse_block(lines: &mut [Line], pos: &mut usize, indent: usize, depth: usize) -> Result<Value> {
+    if depth > MAX_DEPTH {
+        return error(lines[*pos].number, "nested too deeply");
+    }
+    if lines[*pos].text.starts_with('-') {
+        parse_list(lines, pos, indent, depth)
+    } else {
+        parse_map(lines, pos, indent, depth)
+    }
+}
+
+fn parse_list(lines: &mut [Line], pos: &mut usize, indent: usize, depth: usize) -> Result<Value> {
+    let mut items = vec![];
+    while *pos < lines.len() && lines[*pos].indent == indent && lines[*pos].text.starts_with('-') {
+        let text = lines[*pos].text.clone();
+        let rest = text[1..].trim_start();
+        if rest.is_empty() {
+            *pos += 1;
+            if *pos >= lines.len() || lines[*pos].indent <= indent {
+                items.push(Value::Scalar(String::new()));
+            } else {
+                let inner = lines[*pos].indent;
+                items.push(parse_block(lines, pos, inner, depth + 1)?);
+            }
+        } else if is_map_entry(rest) {
+            // the first entry shares the line with the dash, the rest of the
+            // map lines up with it
+            let item_indent = indent + text.len() - rest.len();
+            lines[*pos].indent = item_indent;
+            lines[*pos].text = rest.to_string();
+            items.push(parse_map(lines, pos, item_indent, depth + 1)?);
+        } else {
+            items.push(parse_flow(rest, lines[*pos].number, depth + 1)?);
+            *pos += 1;
+        }
+    }
+    Ok(Value::List(items))
+}
+
+fn parse_map(lines: &mut [Line], pos: &mut usize, indent: usize, depth: usize) -> Result<Value> {
+    let mut entries = vec![];
+    while *pos < lines.len() && lines[*pos].indent == indent && !lines[*pos].text.starts_with('-') {
+        let (key, value) = split_entry(&lines[*pos])?;
+        *pos += 1;
+        let value = if !value.is_empty() {
+            parse_flow(&value, lines[*pos - 1].number, depth + 1)?
+        } else if *pos < lines.len()
+            && (lines[*pos].indent > indent
+                || (lines[*pos].indent == indent && lines[*pos].text.starts_with('-')))
+        {
+            let inner = lines[*pos].indent;
+            parse_block(lines, pos, inner, depth + 1)?
+        } else {
+            Value::Scalar(String::new())
+        };
+        entries.push((key, value));
+    }
+    Ok(Value::Map(entries))
+}
+
+pub fn parse(src: &str) -> Result<Value> {
+    let mut lines = src
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, strip_comment(l).trim_end()))
+        .filter(|(_, l)| !l.trim().is_empty() && l.trim() != "---")
+        .map(|(number, l)| Line {
+            number,
+            indent: l.len() - l.trim_start().len(),
+            text: l.trim_start().to_string(),
+        })
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return Ok(Value::List(vec![]));
+    }
+    let mut pos = 0;
+    let indent = lines[0].indent;
+    let value = parse_block(&mut lines, &mut pos, indent, 0)?;
+    if pos < lines.len() {
+        return error(lines[pos].number, "unexpected indentation");
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(v: &str) -> Value {
+        Value::Scalar(v.to_string())
+    }
+
+    #[test]
+    fn parse_scene_style_document() {
+        let src = "\
+# a comment
+- add: camera
+  width: 100
+  from: [0, 1.5, -5]
+
+- add: sphere
+  material:
+    color: [1, 0.2, 1] # trailing comment
+  transform:
+    - [ translate, 1, 2, 3 ]
+    - [ scale, 2, 2, 2 ]
+";
+        let doc = parse(src).unwrap();
+        let items = doc.as_list().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].get("add"), Some(&s("camera")));
+        assert_eq!(items[0].get("width").unwrap().as_f64(), Some(100.0));
+        assert_eq!(
+            items[0].get("from"),
+            Some(&Value::List(vec![s("0"), s("1.5"), s("-5")]))
+        );
+        let material = items[1].get("material").unwrap();
+        assert_eq!(material.get("color").unwrap().as_list().unwrap().len(), 3);
+        let transform = items[1].get("transform").unwrap().as_list().unwrap();
+        assert_eq!(transform.len(), 2);
+        assert_eq!(transform[1].as_list().unwrap()[0], s("scale"));
+    }
+
+    #[test]
+    fn lists_may_sit_at_the_key_indent() {
+        let doc = parse("transform:\n- [ scale, 1, 1, 1 ]\nname: x\n").unwrap();
+        assert_eq!(doc.get("transform").unwrap().as_list().unwrap().len(), 1);
+        assert_eq!(doc.get("name"), Some(&s("x")));
+    }
+
+    #[test]
+    fn nested_flow_lists() {
+        let doc = parse("a: [1, [2, 3], \"x\"]").unwrap();
+        assert_eq!(
+            doc.get("a"),
+            Some(&Value::List(vec![
+                s("1"),
+                Value::List(vec![s("2"), s("3")]),
+                s("x")
+            ]))
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let flow = format!("a: {}{}", "[".repeat(10_000), "]".repeat(10_000));
+        assert_eq!(parse(&flow).unwrap_err().message, "nested too deeply");
+        let block = (0..10_000)
+            .map(|i| format!("{}a:", " ".repeat(i)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(parse(&block).unwrap_err().message, "nested too deeply");
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        let err = parse("a: 1\nb [1, 2\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        let err = parse("a: [1, 2\n").unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+}